@@ -32,5 +32,7 @@ fn main() {
 
     cbindgen::generate(crate_dir)
         .expect("Unable to generate bindings")
-        .write_to_file(out_path);
+        .write_to_file(&out_path);
+    // For checking the header compiles in tests
+    println!("cargo:rustc-env=DBN_C_HEADER_PATH={}", out_path.display());
 }
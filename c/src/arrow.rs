@@ -0,0 +1,352 @@
+use std::ptr::null_mut;
+
+use arrow_array::{
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema},
+    ffi_stream::FFI_ArrowArrayStream,
+    Array, RecordBatch, StructArray,
+};
+use dbn::{
+    encode::arrow::{RecordBatchEncoder, RecordBatchEncoderBuilder, DEFAULT_BATCH_SIZE},
+    Metadata, RecordHeader, RecordRef,
+};
+
+use crate::error::{clear_error, set_dbn_error, set_error, ErrorCode, ErrorInfo};
+
+/// Options for converting records to Arrow record batches.
+#[repr(C)]
+pub struct ArrowOptions {
+    /// Whether to encode prices as fixed-point decimals with a scale of 9. If `false`,
+    /// they're encoded as the raw fixed-precision integers.
+    pub pretty_px: bool,
+    /// Whether to encode timestamps as nanosecond UTC Arrow timestamps. If `false`,
+    /// they're encoded as the raw UNIX nanoseconds.
+    pub pretty_ts: bool,
+    /// Whether to add a dictionary-encoded `symbol` column using the symbol mappings in
+    /// the metadata.
+    pub map_symbols: bool,
+    /// The maximum number of rows in each record batch.
+    pub batch_size: libc::size_t,
+}
+
+/// Converts records of a single schema to Arrow record batches, which are exported
+/// through the Arrow C data interface.
+pub struct ArrowEncoder {
+    encoder: RecordBatchEncoder,
+    ts_out: bool,
+}
+
+/// Returns the default Arrow options: decimal prices, Arrow timestamps, no symbol column,
+/// and batches of up to 8192 rows.
+#[no_mangle]
+pub extern "C" fn DbnArrowOptions_default() -> ArrowOptions {
+    ArrowOptions {
+        pretty_px: true,
+        pretty_ts: true,
+        map_symbols: false,
+        batch_size: DEFAULT_BATCH_SIZE,
+    }
+}
+
+/// Creates an Arrow encoder for the schema of `metadata`. Returns null in case of
+/// error, such as if the metadata has no schema, with the details written to `error`.
+///
+/// # Safety
+/// Verifies `metadata` and `options` are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnArrowEncoder_create(
+    metadata: *const Metadata,
+    options: *const ArrowOptions,
+    error: *mut ErrorInfo,
+) -> *mut ArrowEncoder {
+    let (Some(metadata), Some(options)) = (metadata.as_ref(), options.as_ref()) else {
+        set_error(error, ErrorCode::NullPointer, "metadata or options is null");
+        return null_mut();
+    };
+    match build_encoder(metadata, options) {
+        Ok(encoder) => {
+            clear_error(error);
+            Box::into_raw(Box::new(ArrowEncoder {
+                encoder,
+                ts_out: metadata.ts_out,
+            }))
+        }
+        Err(err) => {
+            set_dbn_error(error, &err);
+            null_mut()
+        }
+    }
+}
+
+/// Exports the Arrow schema of the record batches to `out`, which the caller must
+/// release. Returns 0 on success, otherwise a negative `DbnErrorCode` with the details
+/// written to `error`.
+///
+/// # Safety
+/// `out` must point to memory for a `struct ArrowSchema`. Verifies `encoder` and `out`
+/// are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnArrowEncoder_schema(
+    encoder: *const ArrowEncoder,
+    out: *mut FFI_ArrowSchema,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let (Some(encoder), false) = (encoder.as_ref(), out.is_null()) else {
+        return set_error(error, ErrorCode::NullPointer, "encoder or out is null") as libc::c_int;
+    };
+    match FFI_ArrowSchema::try_from(encoder.encoder.schema().as_ref()) {
+        Ok(schema) => {
+            out.write(schema);
+            clear_error(error);
+            0
+        }
+        Err(err) => set_error(error, ErrorCode::Conversion, err) as libc::c_int,
+    }
+}
+
+/// Encodes the record beginning with `record`. Once `batch_size` records have been
+/// encoded, the batch is exported to `out` as a struct array, which the caller must
+/// release. Returns 1 if a batch was exported, 0 if the record was buffered, otherwise
+/// a negative `DbnErrorCode` with the details written to `error`.
+///
+/// # Safety
+/// `record` must point to a complete record of the length given in its header and
+/// `out` must point to memory for a `struct ArrowArray`. Verifies `encoder`, `record`,
+/// and `out` are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnArrowEncoder_encode(
+    encoder: *mut ArrowEncoder,
+    record: *const RecordHeader,
+    out: *mut FFI_ArrowArray,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let (Some(encoder), Some(record), false) = (encoder.as_mut(), record.as_ref(), out.is_null())
+    else {
+        return set_error(
+            error,
+            ErrorCode::NullPointer,
+            "encoder, record, or out is null",
+        ) as libc::c_int;
+    };
+    let res = encoder
+        .encoder
+        .encode_record_ref_ts_out(RecordRef::unchecked_from_header(record), encoder.ts_out);
+    export_batch(res, out, error)
+}
+
+/// Exports any buffered records to `out` as a struct array, which the caller must
+/// release. Returns 1 if a batch was exported, 0 if there were no buffered records,
+/// otherwise a negative `DbnErrorCode` with the details written to `error`.
+///
+/// # Safety
+/// `out` must point to memory for a `struct ArrowArray`. Verifies `encoder` and `out`
+/// are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnArrowEncoder_flush(
+    encoder: *mut ArrowEncoder,
+    out: *mut FFI_ArrowArray,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let (Some(encoder), false) = (encoder.as_mut(), out.is_null()) else {
+        return set_error(error, ErrorCode::NullPointer, "encoder or out is null") as libc::c_int;
+    };
+    export_batch(encoder.encoder.flush(), out, error)
+}
+
+/// Frees memory associated with the Arrow encoder. Any buffered records are discarded.
+///
+/// # Safety
+/// Verifies `encoder` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnArrowEncoder_free(encoder: *mut ArrowEncoder) {
+    if let Some(encoder) = encoder.as_mut() {
+        drop(Box::from_raw(encoder));
+    }
+}
+
+/// Converts the remaining records of `decoder` to an Arrow C stream of record batches,
+/// written to `out`. The stream takes ownership of `decoder`, which must not be used or
+/// freed afterwards, even in case of error. Returns 0 on success, otherwise a negative
+/// `DbnErrorCode` with the details written to `error`.
+///
+/// # Safety
+/// `out` must point to memory for a `struct ArrowArrayStream`. Verifies `decoder`,
+/// `options`, and `out` are not null. `error` may be null.
+#[cfg(not(target_os = "windows"))]
+#[no_mangle]
+pub unsafe extern "C" fn DbnDecoder_to_arrow_stream(
+    decoder: *mut crate::decode::Decoder,
+    options: *const ArrowOptions,
+    out: *mut FFI_ArrowArrayStream,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    use dbn::{decode::DbnMetadata, encode::arrow::RecordBatchIter};
+
+    if decoder.is_null() {
+        return set_error(error, ErrorCode::NullPointer, "decoder is null") as libc::c_int;
+    }
+    let decoder = Box::from_raw(decoder);
+    let (Some(options), false) = (options.as_ref(), out.is_null()) else {
+        return set_error(error, ErrorCode::NullPointer, "options or out is null") as libc::c_int;
+    };
+    match build_encoder(decoder.metadata(), options) {
+        Ok(encoder) => {
+            out.write(FFI_ArrowArrayStream::new(Box::new(RecordBatchIter::new(
+                *decoder, encoder,
+            ))));
+            clear_error(error);
+            0
+        }
+        Err(err) => set_dbn_error(error, &err) as libc::c_int,
+    }
+}
+
+fn build_encoder(metadata: &Metadata, options: &ArrowOptions) -> dbn::Result<RecordBatchEncoder> {
+    let symbol_map = if options.map_symbols {
+        Some(metadata.symbol_map()?)
+    } else {
+        None
+    };
+    RecordBatchEncoderBuilder::from_metadata(metadata)?
+        .use_pretty_px(options.pretty_px)
+        .use_pretty_ts(options.pretty_ts)
+        .symbol_map(symbol_map)
+        .batch_size(options.batch_size)
+        .build()
+}
+
+/// # Safety
+/// `out` must be a valid pointer. `error` must be null or a valid pointer.
+unsafe fn export_batch(
+    res: dbn::Result<Option<RecordBatch>>,
+    out: *mut FFI_ArrowArray,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    match res {
+        Ok(Some(batch)) => {
+            out.write(FFI_ArrowArray::new(&StructArray::from(batch).into_data()));
+            clear_error(error);
+            1
+        }
+        Ok(None) => {
+            clear_error(error);
+            0
+        }
+        Err(err) => set_dbn_error(error, &err) as libc::c_int,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, ptr::null};
+
+    use arrow_array::{
+        cast::AsArray, ffi::from_ffi, ffi_stream::ArrowArrayStreamReader, types::Decimal128Type,
+        RecordBatchReader,
+    };
+    use dbn::{rtype, Compression, MboMsg, Record, Schema, UNDEF_TIMESTAMP};
+
+    use super::*;
+    use crate::metadata::{DbnMetadata_create, DbnMetadata_free};
+
+    const TS: u64 = 1_704_186_000_000_000_000;
+
+    #[test]
+    fn test_encode_batches() {
+        let dataset = CString::new("XNAS.ITCH").unwrap();
+        let rec = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 5482, TS),
+            ts_recv: TS + 10,
+            price: 100_250_000_000,
+            ..Default::default()
+        };
+        let options = ArrowOptions {
+            batch_size: 2,
+            ..DbnArrowOptions_default()
+        };
+        unsafe {
+            let metadata = DbnMetadata_create(
+                dbn::DBN_VERSION,
+                dataset.as_ptr(),
+                Schema::Mbo,
+                TS,
+                UNDEF_TIMESTAMP,
+                false,
+            );
+            let encoder = DbnArrowEncoder_create(metadata, &options, null_mut());
+            assert!(!encoder.is_null());
+
+            let mut schema = FFI_ArrowSchema::empty();
+            assert_eq!(DbnArrowEncoder_schema(encoder, &mut schema, null_mut()), 0);
+            let mut array = FFI_ArrowArray::empty();
+            assert_eq!(
+                DbnArrowEncoder_encode(encoder, rec.header(), &mut array, null_mut()),
+                0
+            );
+            assert_eq!(
+                DbnArrowEncoder_encode(encoder, rec.header(), &mut array, null_mut()),
+                1
+            );
+            let batch = RecordBatch::from(StructArray::from(from_ffi(array, &schema).unwrap()));
+            assert_eq!(batch.num_rows(), 2);
+            assert_eq!(
+                batch
+                    .column_by_name("price")
+                    .unwrap()
+                    .as_primitive::<Decimal128Type>()
+                    .value_as_string(0),
+                "100.250000000"
+            );
+
+            let mut array = FFI_ArrowArray::empty();
+            assert_eq!(DbnArrowEncoder_flush(encoder, &mut array, null_mut()), 0);
+            DbnArrowEncoder_free(encoder);
+            DbnMetadata_free(metadata);
+        }
+    }
+
+    #[test]
+    fn test_decoder_to_stream() {
+        use std::{fs::File, os::fd::IntoRawFd};
+
+        use crate::decode::DbnDecoder_create;
+
+        let file = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../tests/data/test_data.mbo.v3.dbn.zst"
+        ))
+        .unwrap();
+        let options = ArrowOptions {
+            map_symbols: true,
+            ..DbnArrowOptions_default()
+        };
+        unsafe {
+            let decoder = DbnDecoder_create(file.into_raw_fd(), Compression::Zstd);
+            let mut stream = FFI_ArrowArrayStream::empty();
+            assert_eq!(
+                DbnDecoder_to_arrow_stream(decoder, &options, &mut stream, null_mut()),
+                0
+            );
+            let reader = ArrowArrayStreamReader::try_new(stream).unwrap();
+            assert!(reader.schema().field_with_name("symbol").is_ok());
+            let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches[0].num_rows(), 2);
+        }
+    }
+
+    #[test]
+    fn test_null() {
+        let mut error = ErrorInfo {
+            code: ErrorCode::Ok,
+            message: [0; crate::error::ERROR_MESSAGE_LEN],
+        };
+        unsafe {
+            assert!(DbnArrowEncoder_create(null(), null(), &mut error).is_null());
+            assert_eq!(error.code, ErrorCode::NullPointer);
+            assert_eq!(
+                DbnArrowEncoder_flush(null_mut(), null_mut(), null_mut()),
+                ErrorCode::NullPointer as libc::c_int
+            );
+        }
+    }
+}
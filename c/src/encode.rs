@@ -0,0 +1,253 @@
+// RawFd isn't defined for windows
+#![cfg(not(target_os = "windows"))]
+
+use std::{
+    fs::File,
+    os::fd::{FromRawFd, RawFd},
+    ptr::null_mut,
+};
+
+use dbn::{
+    encode::{DbnEncoder, DynWriter, EncodeRecord, EncodeRecordRef},
+    Compression, Metadata, RecordHeader, RecordRef,
+};
+
+use crate::error::{clear_error, set_dbn_error, set_error, ErrorCode, ErrorInfo};
+
+/// Encodes DBN records to a file with optional Zstandard compression.
+pub struct Encoder(DbnEncoder<DynWriter<'static, File>>);
+
+/// Creates a DBN encoder, immediately writing `metadata` to `file`. Returns null in
+/// case of error, with the details written to `error`.
+///
+/// # Safety
+/// `file` must be a valid file descriptor. This function assumes ownership of `file`.
+/// Verifies `metadata` is not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnEncoder_create(
+    file: RawFd,
+    metadata: *const Metadata,
+    compression: Compression,
+    error: *mut ErrorInfo,
+) -> *mut Encoder {
+    let Some(metadata) = metadata.as_ref() else {
+        set_error(error, ErrorCode::NullPointer, "metadata is null");
+        return null_mut();
+    };
+    match DynWriter::new(File::from_raw_fd(file), compression)
+        .and_then(|writer| DbnEncoder::new(writer, metadata))
+    {
+        Ok(encoder) => {
+            clear_error(error);
+            Box::into_raw(Box::new(Encoder(encoder)))
+        }
+        Err(err) => {
+            set_dbn_error(error, &err);
+            null_mut()
+        }
+    }
+}
+
+/// Encodes the record beginning with `record`. The record must match the version and
+/// `ts_out` of the metadata passed to `DbnEncoder_create`. Returns 0 on success,
+/// otherwise a negative `DbnErrorCode` with the details written to `error`.
+///
+/// # Safety
+/// `record` must point to a complete record of the length given in its header. Verifies
+/// `encoder` and `record` are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnEncoder_encode(
+    encoder: *mut Encoder,
+    record: *const RecordHeader,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let (Some(encoder), Some(record)) = (encoder.as_mut(), record.as_ref()) else {
+        return set_error(error, ErrorCode::NullPointer, "encoder or record is null")
+            as libc::c_int;
+    };
+    match encoder
+        .0
+        .encode_record_ref(RecordRef::unchecked_from_header(record))
+    {
+        Ok(()) => {
+            clear_error(error);
+            0
+        }
+        Err(err) => set_dbn_error(error, &err) as libc::c_int,
+    }
+}
+
+/// Flushes any buffered output to the file. Returns 0 on success, otherwise a negative
+/// `DbnErrorCode` with the details written to `error`.
+///
+/// # Safety
+/// Verifies `encoder` is not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnEncoder_flush(
+    encoder: *mut Encoder,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let Some(encoder) = encoder.as_mut() else {
+        return set_error(error, ErrorCode::NullPointer, "encoder is null") as libc::c_int;
+    };
+    match EncodeRecord::flush(&mut encoder.0) {
+        Ok(()) => {
+            clear_error(error);
+            0
+        }
+        Err(err) => set_dbn_error(error, &err) as libc::c_int,
+    }
+}
+
+/// Finishes the output, including any compression, and frees memory associated with the
+/// DBN encoder. This closes the file.
+///
+/// # Safety
+/// Verifies `encoder` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnEncoder_free(encoder: *mut Encoder) {
+    if let Some(encoder) = encoder.as_mut() {
+        drop(Box::from_raw(encoder));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, fs, os::fd::IntoRawFd, ptr::null};
+
+    use dbn::{rtype, MboMsg, Record, RecordHeader, Schema, UNDEF_TIMESTAMP};
+
+    use super::*;
+    use crate::{
+        decode::{DbnDecoder_create, DbnDecoder_decode, DbnDecoder_free, DbnDecoder_metadata},
+        error::ERROR_MESSAGE_LEN,
+        metadata::{DbnMetadata_create, DbnMetadata_free},
+    };
+
+    const TS: u64 = 1_704_186_000_000_000_000;
+
+    #[test]
+    fn test_encode_round_trip() {
+        let dataset = CString::new("XNAS.ITCH").unwrap();
+        let records = [
+            MboMsg {
+                hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 5482, TS),
+                ts_recv: TS + 10,
+                order_id: 1,
+                price: 100_000_000_000,
+                size: 10,
+                ..Default::default()
+            },
+            MboMsg {
+                hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 5482, TS + 20),
+                ts_recv: TS + 30,
+                order_id: 2,
+                price: 101_000_000_000,
+                size: 5,
+                ..Default::default()
+            },
+        ];
+        for compression in [Compression::None, Compression::Zstd] {
+            let path = std::env::temp_dir().join(format!(
+                "dbn-c-test-encode-{}-{compression:?}.dbn",
+                std::process::id()
+            ));
+            unsafe {
+                let metadata = DbnMetadata_create(
+                    dbn::DBN_VERSION,
+                    dataset.as_ptr(),
+                    Schema::Mbo,
+                    TS,
+                    UNDEF_TIMESTAMP,
+                    false,
+                );
+                assert!(!metadata.is_null());
+                let encoder = DbnEncoder_create(
+                    File::create(&path).unwrap().into_raw_fd(),
+                    metadata,
+                    compression,
+                    null_mut(),
+                );
+                assert!(!encoder.is_null());
+                for record in records.iter() {
+                    assert_eq!(DbnEncoder_encode(encoder, record.header(), null_mut()), 0);
+                }
+                assert_eq!(DbnEncoder_flush(encoder, null_mut()), 0);
+                DbnEncoder_free(encoder);
+
+                let decoder =
+                    DbnDecoder_create(File::open(&path).unwrap().into_raw_fd(), compression);
+                assert!(!decoder.is_null());
+                assert_eq!(&*DbnDecoder_metadata(decoder), &*metadata);
+                for record in records.iter() {
+                    let decoded = DbnDecoder_decode(decoder);
+                    assert_eq!(
+                        RecordRef::unchecked_from_header(decoded).get::<MboMsg>(),
+                        Some(record)
+                    );
+                }
+                assert!(DbnDecoder_decode(decoder).is_null());
+                DbnDecoder_free(decoder);
+                DbnMetadata_free(metadata);
+            }
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_encode_null() {
+        let mut error = ErrorInfo {
+            code: ErrorCode::Ok,
+            message: [0; ERROR_MESSAGE_LEN],
+        };
+        unsafe {
+            assert!(DbnEncoder_create(-1, null(), Compression::None, &mut error).is_null());
+            assert_eq!(error.code, ErrorCode::NullPointer);
+            assert_eq!(
+                DbnEncoder_encode(null_mut(), null(), &mut error),
+                ErrorCode::NullPointer as libc::c_int
+            );
+            assert_eq!(
+                DbnEncoder_flush(null_mut(), null_mut()),
+                ErrorCode::NullPointer as libc::c_int
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_error() {
+        let dataset = CString::new("XNAS.ITCH").unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "dbn-c-test-encode-error-{}.dbn",
+            std::process::id()
+        ));
+        let mut error = ErrorInfo {
+            code: ErrorCode::Ok,
+            message: [0; ERROR_MESSAGE_LEN],
+        };
+        unsafe {
+            let metadata = DbnMetadata_create(
+                dbn::DBN_VERSION,
+                dataset.as_ptr(),
+                Schema::Mbo,
+                TS,
+                0,
+                false,
+            );
+            // Read-only so writing the metadata fails
+            File::create(&path).unwrap();
+            let encoder = DbnEncoder_create(
+                File::open(&path).unwrap().into_raw_fd(),
+                metadata,
+                Compression::None,
+                &mut error,
+            );
+            assert!(encoder.is_null());
+            assert_eq!(error.code, ErrorCode::Io);
+            let message = std::ffi::CStr::from_ptr(error.message.as_ptr());
+            assert!(!message.is_empty());
+            DbnMetadata_free(metadata);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
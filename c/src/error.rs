@@ -0,0 +1,79 @@
+use std::{ffi::c_char, fmt::Display};
+
+/// The maximum length of an error message, including the null terminator.
+pub const ERROR_MESSAGE_LEN: usize = 256;
+
+/// The category of an error.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    Ok = 0,
+    NullPointer = -1,
+    BadArgument = -2,
+    Io = -3,
+    Decode = -4,
+    Encode = -5,
+    Conversion = -6,
+    Utf8 = -7,
+    Other = -8,
+}
+
+/// Details of an error, including a null-terminated message. Messages longer than
+/// `ERROR_MESSAGE_LEN` are truncated.
+#[repr(C)]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub message: [c_char; ERROR_MESSAGE_LEN],
+}
+
+impl From<&dbn::Error> for ErrorCode {
+    fn from(error: &dbn::Error) -> Self {
+        match error {
+            dbn::Error::Io { .. } => Self::Io,
+            dbn::Error::Decode(_) => Self::Decode,
+            dbn::Error::Encode(_) => Self::Encode,
+            dbn::Error::Conversion { .. } => Self::Conversion,
+            dbn::Error::Utf8 { .. } => Self::Utf8,
+            dbn::Error::BadArgument { .. } => Self::BadArgument,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Writes `code` and `message` to `error` if it's not null. Returns `code`.
+///
+/// # Safety
+/// `error` must be null or a valid pointer.
+pub(crate) unsafe fn set_error(
+    error: *mut ErrorInfo,
+    code: ErrorCode,
+    message: impl Display,
+) -> ErrorCode {
+    if let Some(error) = error.as_mut() {
+        error.code = code;
+        let message = message.to_string();
+        // Leave room for the null terminator
+        let len = message.len().min(ERROR_MESSAGE_LEN - 1);
+        for (dst, src) in error.message.iter_mut().zip(&message.as_bytes()[..len]) {
+            *dst = *src as c_char;
+        }
+        error.message[len] = 0;
+    }
+    code
+}
+
+/// Writes the details of `dbn_error` to `error` if it's not null. Returns the error code.
+///
+/// # Safety
+/// `error` must be null or a valid pointer.
+pub(crate) unsafe fn set_dbn_error(error: *mut ErrorInfo, dbn_error: &dbn::Error) -> ErrorCode {
+    set_error(error, ErrorCode::from(dbn_error), dbn_error)
+}
+
+/// Resets `error` to `ErrorCode::Ok` with an empty message if it's not null.
+///
+/// # Safety
+/// `error` must be null or a valid pointer.
+pub(crate) unsafe fn clear_error(error: *mut ErrorInfo) {
+    set_error(error, ErrorCode::Ok, "");
+}
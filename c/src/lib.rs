@@ -1,5 +1,10 @@
+pub mod arrow;
 mod cfile;
 pub mod compat;
 pub mod decode;
+pub mod encode;
+pub mod error;
 pub mod metadata;
+pub mod push_decode;
+pub mod symbol_map;
 pub mod text_serialization;
@@ -1,12 +1,15 @@
 use std::{
     ffi::{c_char, CStr},
-    io, slice,
+    io,
+    num::NonZeroU64,
+    ptr::{self, null_mut},
+    slice,
 };
 
 use dbn::{
     encode::dbn::MetadataEncoder,
     enums::{SType, Schema},
-    MetadataBuilder,
+    Metadata, MetadataBuilder, UNDEF_TIMESTAMP,
 };
 
 /// The byte offset of the `start` field in DBN-encoded Metadata.
@@ -63,6 +66,430 @@ pub unsafe extern "C" fn encode_metadata(
     }
 }
 
+/// Creates DBN metadata for records of `schema` with instrument ID symbology, such as
+/// for passing to `DbnEncoder_create`. `end` is optional and should be
+/// `UNDEF_TIMESTAMP` if unknown. Returns null in case of error, including an invalid
+/// `version` or `dataset`.
+///
+/// # Safety
+/// This function assumes `dataset` is a valid pointer to a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_create(
+    version: u8,
+    dataset: *const c_char,
+    schema: Schema,
+    start: u64,
+    end: u64,
+    ts_out: bool,
+) -> *mut Metadata {
+    if dataset.is_null() || version == 0 || version > dbn::DBN_VERSION {
+        return null_mut();
+    }
+    let Ok(dataset) = CStr::from_ptr(dataset).to_str() else {
+        return null_mut();
+    };
+    let metadata = MetadataBuilder::new()
+        .version(version)
+        .dataset(dataset.to_owned())
+        .start(start)
+        .end(NonZeroU64::new(end).filter(|end| end.get() != UNDEF_TIMESTAMP))
+        .stype_in(Some(SType::InstrumentId))
+        .stype_out(SType::InstrumentId)
+        .schema(Some(schema))
+        .ts_out(ts_out)
+        .build();
+    Box::into_raw(Box::new(metadata))
+}
+
+/// Frees memory associated with metadata created with `DbnMetadata_create`.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_free(metadata: *mut Metadata) {
+    if let Some(metadata) = metadata.as_mut() {
+        drop(Box::from_raw(metadata));
+    }
+}
+
+#[repr(C)]
+pub enum MetadataError {
+    NullMetadata = -1,
+    NullBuffer = -2,
+    BufferTooSmall = -3,
+    IndexOutOfRange = -4,
+}
+
+/// Returns the DBN version of `metadata` or 0 if `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_version(metadata: *const Metadata) -> u8 {
+    metadata.as_ref().map_or(0, |metadata| metadata.version)
+}
+
+/// Copies the dataset code to `buffer` with a null terminator. Returns the length of
+/// the dataset code.
+///
+/// # Errors
+/// - Returns -1 if `metadata` is null.
+/// - Returns -2 if `buffer` is null.
+/// - Returns -3 if `buffer` is too small.
+///
+/// # Safety
+/// This function assumes `buffer` is of size `length`. Verifies `metadata` and `buffer`
+/// are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_dataset(
+    metadata: *const Metadata,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(metadata) = metadata.as_ref() else {
+        return MetadataError::NullMetadata as libc::c_int;
+    };
+    copy_str(&metadata.dataset, buffer, length)
+}
+
+/// Sets `schema` to the schema of the records and returns `true`, or returns `false` if
+/// the records are of mixed schemas or `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` and `schema` are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_schema(
+    metadata: *const Metadata,
+    schema: *mut Schema,
+) -> bool {
+    metadata
+        .as_ref()
+        .is_some_and(|metadata| set_option(metadata.schema, schema))
+}
+
+/// Returns the start of the query range in UNIX epoch nanoseconds or `UNDEF_TIMESTAMP`
+/// if `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_start(metadata: *const Metadata) -> u64 {
+    metadata
+        .as_ref()
+        .map_or(UNDEF_TIMESTAMP, |metadata| metadata.start)
+}
+
+/// Returns the end of the query range in UNIX epoch nanoseconds or `UNDEF_TIMESTAMP` if
+/// there's no end or `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_end(metadata: *const Metadata) -> u64 {
+    metadata
+        .as_ref()
+        .and_then(|metadata| metadata.end)
+        .map_or(UNDEF_TIMESTAMP, NonZeroU64::get)
+}
+
+/// Returns the maximum number of records for the query or 0 if there's no limit or
+/// `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_limit(metadata: *const Metadata) -> u64 {
+    metadata
+        .as_ref()
+        .and_then(|metadata| metadata.limit)
+        .map_or(0, NonZeroU64::get)
+}
+
+/// Sets `stype_in` to the input symbology type and returns `true`, or returns `false`
+/// if the input symbols are of mixed symbology types or `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` and `stype_in` are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_stype_in(
+    metadata: *const Metadata,
+    stype_in: *mut SType,
+) -> bool {
+    metadata
+        .as_ref()
+        .is_some_and(|metadata| set_option(metadata.stype_in, stype_in))
+}
+
+/// Sets `stype_out` to the output symbology type and returns `true`, or returns
+/// `false` if `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` and `stype_out` are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_stype_out(
+    metadata: *const Metadata,
+    stype_out: *mut SType,
+) -> bool {
+    metadata
+        .as_ref()
+        .is_some_and(|metadata| set_option(Some(metadata.stype_out), stype_out))
+}
+
+/// Returns `true` if each record is followed by a `ts_out` send timestamp, or `false`
+/// if not or `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_ts_out(metadata: *const Metadata) -> bool {
+    metadata.as_ref().is_some_and(|metadata| metadata.ts_out)
+}
+
+/// Returns the length in bytes of fixed-length symbol strings, including a null
+/// terminator byte, or 0 if `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_symbol_cstr_len(metadata: *const Metadata) -> libc::size_t {
+    metadata
+        .as_ref()
+        .map_or(0, |metadata| metadata.symbol_cstr_len)
+}
+
+/// Returns the number of requested symbols or 0 if `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_symbols_len(metadata: *const Metadata) -> libc::size_t {
+    metadata
+        .as_ref()
+        .map_or(0, |metadata| metadata.symbols.len())
+}
+
+/// Copies the requested symbol at `index` to `buffer` with a null terminator. Returns
+/// the length of the symbol.
+///
+/// # Errors
+/// - Returns -1 if `metadata` is null.
+/// - Returns -2 if `buffer` is null.
+/// - Returns -3 if `buffer` is too small.
+/// - Returns -4 if `index` is out of range.
+///
+/// # Safety
+/// This function assumes `buffer` is of size `length`. Verifies `metadata` and `buffer`
+/// are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_symbol(
+    metadata: *const Metadata,
+    index: libc::size_t,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(metadata) = metadata.as_ref() else {
+        return MetadataError::NullMetadata as libc::c_int;
+    };
+    copy_list_item(&metadata.symbols, index, buffer, length)
+}
+
+/// Returns the number of symbols that didn't resolve for at least one day of the query
+/// or 0 if `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_partial_len(metadata: *const Metadata) -> libc::size_t {
+    metadata
+        .as_ref()
+        .map_or(0, |metadata| metadata.partial.len())
+}
+
+/// Copies the partially-resolved symbol at `index` to `buffer` with a null terminator.
+/// Returns the length of the symbol. See `DbnMetadata_symbol` for the error codes.
+///
+/// # Safety
+/// This function assumes `buffer` is of size `length`. Verifies `metadata` and `buffer`
+/// are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_partial(
+    metadata: *const Metadata,
+    index: libc::size_t,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(metadata) = metadata.as_ref() else {
+        return MetadataError::NullMetadata as libc::c_int;
+    };
+    copy_list_item(&metadata.partial, index, buffer, length)
+}
+
+/// Returns the number of symbols that didn't resolve for any day of the query or 0 if
+/// `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_not_found_len(metadata: *const Metadata) -> libc::size_t {
+    metadata
+        .as_ref()
+        .map_or(0, |metadata| metadata.not_found.len())
+}
+
+/// Copies the unresolved symbol at `index` to `buffer` with a null terminator. Returns
+/// the length of the symbol. See `DbnMetadata_symbol` for the error codes.
+///
+/// # Safety
+/// This function assumes `buffer` is of size `length`. Verifies `metadata` and `buffer`
+/// are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_not_found(
+    metadata: *const Metadata,
+    index: libc::size_t,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(metadata) = metadata.as_ref() else {
+        return MetadataError::NullMetadata as libc::c_int;
+    };
+    copy_list_item(&metadata.not_found, index, buffer, length)
+}
+
+/// Returns the number of symbol mappings, one per raw symbol, or 0 if `metadata` is
+/// null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_mappings_len(metadata: *const Metadata) -> libc::size_t {
+    metadata
+        .as_ref()
+        .map_or(0, |metadata| metadata.mappings.len())
+}
+
+/// Copies the raw symbol of the mapping at `index` to `buffer` with a null terminator.
+/// Returns the length of the raw symbol. See `DbnMetadata_symbol` for the error codes.
+///
+/// # Safety
+/// This function assumes `buffer` is of size `length`. Verifies `metadata` and `buffer`
+/// are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_mapping_raw_symbol(
+    metadata: *const Metadata,
+    index: libc::size_t,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(metadata) = metadata.as_ref() else {
+        return MetadataError::NullMetadata as libc::c_int;
+    };
+    let Some(mapping) = metadata.mappings.get(index) else {
+        return MetadataError::IndexOutOfRange as libc::c_int;
+    };
+    copy_str(&mapping.raw_symbol, buffer, length)
+}
+
+/// Returns the number of intervals of the mapping at `index` or 0 if `index` is out
+/// of range or `metadata` is null.
+///
+/// # Safety
+/// Verifies `metadata` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_mapping_intervals_len(
+    metadata: *const Metadata,
+    index: libc::size_t,
+) -> libc::size_t {
+    metadata
+        .as_ref()
+        .and_then(|metadata| metadata.mappings.get(index))
+        .map_or(0, |mapping| mapping.intervals.len())
+}
+
+/// Gets the interval at `interval_index` of the mapping at `index`, setting
+/// `start_date` and `end_date` to YYYYMMDD integers and copying the symbol to `buffer`
+/// with a null terminator. The end date is exclusive. Returns the length of the
+/// symbol. See `DbnMetadata_symbol` for the error codes.
+///
+/// # Safety
+/// This function assumes `buffer` is of size `length`. Verifies `metadata` and `buffer`
+/// are not null. `start_date` and `end_date` are ignored if null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnMetadata_mapping_interval(
+    metadata: *const Metadata,
+    index: libc::size_t,
+    interval_index: libc::size_t,
+    start_date: *mut u32,
+    end_date: *mut u32,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(metadata) = metadata.as_ref() else {
+        return MetadataError::NullMetadata as libc::c_int;
+    };
+    let Some(interval) = metadata
+        .mappings
+        .get(index)
+        .and_then(|mapping| mapping.intervals.get(interval_index))
+    else {
+        return MetadataError::IndexOutOfRange as libc::c_int;
+    };
+    if let Some(start_date) = start_date.as_mut() {
+        *start_date = date_to_yyyymmdd(interval.start_date);
+    }
+    if let Some(end_date) = end_date.as_mut() {
+        *end_date = date_to_yyyymmdd(interval.end_date);
+    }
+    copy_str(&interval.symbol, buffer, length)
+}
+
+/// Converts `date` to an integer of the form YYYYMMDD, as used in DBN.
+pub(crate) fn date_to_yyyymmdd(date: time::Date) -> u32 {
+    date.year() as u32 * 10_000 + date.month() as u32 * 100 + date.day() as u32
+}
+
+/// Converts an integer of the form YYYYMMDD to a date. Returns `None` if it's not a
+/// valid date.
+pub(crate) fn date_from_yyyymmdd(date: u32) -> Option<time::Date> {
+    let month = time::Month::try_from(u8::try_from(date / 100 % 100).ok()?).ok()?;
+    time::Date::from_calendar_date((date / 10_000) as i32, month, (date % 100) as u8).ok()
+}
+
+unsafe fn set_option<T>(value: Option<T>, out: *mut T) -> bool {
+    match (value, out.is_null()) {
+        (Some(value), false) => {
+            ptr::write(out, value);
+            true
+        }
+        _ => false,
+    }
+}
+
+unsafe fn copy_list_item(
+    list: &[String],
+    index: libc::size_t,
+    buffer: *mut c_char,
+    length: libc::size_t,
+) -> libc::c_int {
+    let Some(item) = list.get(index) else {
+        return MetadataError::IndexOutOfRange as libc::c_int;
+    };
+    copy_str(item, buffer, length)
+}
+
+/// Copies `s` to `buffer` with a null terminator.
+unsafe fn copy_str(s: &str, buffer: *mut c_char, length: libc::size_t) -> libc::c_int {
+    if buffer.is_null() {
+        return MetadataError::NullBuffer as libc::c_int;
+    }
+    if s.len() >= length {
+        return MetadataError::BufferTooSmall as libc::c_int;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer as *mut u8, length);
+    buffer[..s.len()].copy_from_slice(s.as_bytes());
+    buffer[s.len()] = 0;
+    s.len() as libc::c_int
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +506,106 @@ mod tests {
             MetadataEncoder::<Vec<u8>>::MIN_ENCODED_SIZE
         );
     }
+
+    #[test]
+    fn test_accessors() {
+        let metadata = MetadataBuilder::new()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(None)
+            .start(1)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .symbols(vec!["AAPL".to_owned(), "MSFT".to_owned()])
+            .mappings(vec![dbn::SymbolMapping {
+                raw_symbol: "AAPL".to_owned(),
+                intervals: vec![dbn::MappingInterval {
+                    start_date: date_from_yyyymmdd(20230703).unwrap(),
+                    end_date: date_from_yyyymmdd(20230704).unwrap(),
+                    symbol: "10".to_owned(),
+                }],
+            }])
+            .build();
+        let mut buffer = [0 as c_char; 16];
+        let buffer_str = |buffer: &[c_char]| unsafe {
+            CStr::from_ptr(buffer.as_ptr()).to_str().unwrap().to_owned()
+        };
+        unsafe {
+            assert_eq!(DbnMetadata_version(&metadata), dbn::DBN_VERSION);
+            assert_eq!(
+                DbnMetadata_dataset(&metadata, buffer.as_mut_ptr(), buffer.len()),
+                9
+            );
+            assert_eq!(buffer_str(&buffer), "XNAS.ITCH");
+            assert_eq!(
+                DbnMetadata_dataset(&metadata, buffer.as_mut_ptr(), 9),
+                MetadataError::BufferTooSmall as libc::c_int
+            );
+            let mut schema = Schema::Mbo;
+            assert!(!DbnMetadata_schema(&metadata, &mut schema));
+            let mut stype_in = SType::Parent;
+            assert!(DbnMetadata_stype_in(&metadata, &mut stype_in));
+            assert_eq!(stype_in, SType::RawSymbol);
+            let mut stype_out = SType::Parent;
+            assert!(DbnMetadata_stype_out(&metadata, &mut stype_out));
+            assert_eq!(stype_out, SType::InstrumentId);
+            assert_eq!(DbnMetadata_end(&metadata), UNDEF_TIMESTAMP);
+            assert_eq!(DbnMetadata_symbols_len(&metadata), 2);
+            assert_eq!(
+                DbnMetadata_symbol(&metadata, 1, buffer.as_mut_ptr(), buffer.len()),
+                4
+            );
+            assert_eq!(buffer_str(&buffer), "MSFT");
+            assert_eq!(
+                DbnMetadata_symbol(&metadata, 2, buffer.as_mut_ptr(), buffer.len()),
+                MetadataError::IndexOutOfRange as libc::c_int
+            );
+            assert_eq!(DbnMetadata_mappings_len(&metadata), 1);
+            assert_eq!(DbnMetadata_mapping_intervals_len(&metadata, 0), 1);
+            let (mut start_date, mut end_date) = (0, 0);
+            assert_eq!(
+                DbnMetadata_mapping_interval(
+                    &metadata,
+                    0,
+                    0,
+                    &mut start_date,
+                    &mut end_date,
+                    buffer.as_mut_ptr(),
+                    buffer.len()
+                ),
+                2
+            );
+            assert_eq!((start_date, end_date), (20230703, 20230704));
+            assert_eq!(buffer_str(&buffer), "10");
+        }
+    }
+
+    #[test]
+    fn test_accessors_null() {
+        let metadata = ptr::null();
+        let mut buffer = [0 as c_char; 16];
+        let mut schema = Schema::Mbo;
+        let mut stype = SType::Parent;
+        unsafe {
+            assert_eq!(DbnMetadata_version(metadata), 0);
+            assert_eq!(
+                DbnMetadata_dataset(metadata, buffer.as_mut_ptr(), buffer.len()),
+                MetadataError::NullMetadata as libc::c_int
+            );
+            assert!(!DbnMetadata_schema(metadata, &mut schema));
+            assert_eq!(DbnMetadata_start(metadata), UNDEF_TIMESTAMP);
+            assert_eq!(DbnMetadata_end(metadata), UNDEF_TIMESTAMP);
+            assert_eq!(DbnMetadata_limit(metadata), 0);
+            assert!(!DbnMetadata_stype_in(metadata, &mut stype));
+            assert!(!DbnMetadata_stype_out(metadata, &mut stype));
+            assert!(!DbnMetadata_ts_out(metadata));
+            assert_eq!(DbnMetadata_symbol_cstr_len(metadata), 0);
+            assert_eq!(DbnMetadata_symbols_len(metadata), 0);
+            assert_eq!(DbnMetadata_partial_len(metadata), 0);
+            assert_eq!(DbnMetadata_not_found_len(metadata), 0);
+            assert_eq!(DbnMetadata_mappings_len(metadata), 0);
+            assert_eq!(DbnMetadata_mapping_intervals_len(metadata, 0), 0);
+        }
+        assert_eq!(schema, Schema::Mbo);
+        assert_eq!(stype, SType::Parent);
+    }
 }
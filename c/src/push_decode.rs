@@ -0,0 +1,497 @@
+use std::{
+    ffi::c_void,
+    ptr::{null, null_mut},
+    slice,
+};
+
+use dbn::{
+    decode::dbn::fsm::{DbnFsm, ProcessResult},
+    Metadata, Record, RecordHeader, VersionUpgradePolicy,
+};
+
+use crate::error::{clear_error, set_dbn_error, set_error, ErrorCode, ErrorInfo};
+
+/// The size of the stack buffer used when reading from a `FILE` or callback.
+const READ_BUF_SIZE: usize = 8 * (1 << 10);
+
+/// A callback for reading up to `length` bytes into `buffer`. Should return the number
+/// of bytes read, 0 at the end of the input, or a negative number on error.
+pub type ReadCallback =
+    unsafe extern "C" fn(context: *mut c_void, buffer: *mut u8, length: libc::size_t) -> isize;
+
+/// Options for creating a push decoder.
+#[repr(C)]
+pub struct DecoderOptions {
+    /// Whether the input begins with DBN metadata. If `false`, the input is a fragment
+    /// of records.
+    pub has_metadata: bool,
+    /// Whether each record is followed by a `ts_out` send timestamp. Only applicable to
+    /// fragments, otherwise it's read from the metadata.
+    pub ts_out: bool,
+    /// The DBN version of a fragment, or 0 to detect it. Only applicable to fragments,
+    /// otherwise it's read from the metadata.
+    pub input_version: u8,
+    /// How to decode data from previous DBN versions.
+    pub upgrade_policy: VersionUpgradePolicy,
+}
+
+/// The result of `DbnPushDecoder_decode`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeStatus {
+    /// More bytes must be written to the decoder before anything else can be decoded.
+    ReadMore = 0,
+    /// Decoded the metadata, which is available with `DbnPushDecoder_metadata`.
+    Metadata = 1,
+    /// Decoded a record.
+    Record = 2,
+    /// Failed to decode. The details are in the `error` argument.
+    Error = -1,
+}
+
+/// A decoder that's passed bytes as they're received, such as from a network transport,
+/// rather than reading them itself.
+pub struct PushDecoder {
+    fsm: DbnFsm,
+    metadata: Option<Metadata>,
+}
+
+/// Returns the default decoder options: expecting metadata and upgrading records to the
+/// current DBN version.
+#[no_mangle]
+pub extern "C" fn DbnDecoderOptions_default() -> DecoderOptions {
+    DecoderOptions {
+        has_metadata: true,
+        ts_out: false,
+        input_version: 0,
+        upgrade_policy: VersionUpgradePolicy::default(),
+    }
+}
+
+/// Creates a push decoder with `options`. Returns null in case of error, with the
+/// details written to `error`.
+///
+/// # Safety
+/// Verifies `options` is not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_create(
+    options: *const DecoderOptions,
+    error: *mut ErrorInfo,
+) -> *mut PushDecoder {
+    let Some(options) = options.as_ref() else {
+        set_error(error, ErrorCode::NullPointer, "options is null");
+        return null_mut();
+    };
+    let fsm = DbnFsm::builder()
+        .ts_out(options.ts_out)
+        .input_dbn_version((options.input_version != 0).then_some(options.input_version))
+        .and_then(|builder| {
+            builder
+                .upgrade_policy(options.upgrade_policy)
+                .skip_metadata(!options.has_metadata)
+                .compat_size(if options.upgrade_policy == VersionUpgradePolicy::AsIs {
+                    0
+                } else {
+                    DbnFsm::DEFAULT_BUF_SIZE
+                })
+                .build()
+        });
+    match fsm {
+        Ok(fsm) => {
+            clear_error(error);
+            Box::into_raw(Box::new(PushDecoder {
+                fsm,
+                metadata: None,
+            }))
+        }
+        Err(err) => {
+            set_dbn_error(error, &err);
+            null_mut()
+        }
+    }
+}
+
+/// Passes `length` bytes from `bytes` to the decoder. Returns 0 on success, otherwise a
+/// negative `DbnErrorCode` with the details written to `error`.
+///
+/// # Safety
+/// This function assumes `bytes` is of size `length`. Verifies `decoder` and `bytes`
+/// are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_write(
+    decoder: *mut PushDecoder,
+    bytes: *const u8,
+    length: libc::size_t,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let (Some(decoder), false) = (decoder.as_mut(), bytes.is_null()) else {
+        return set_error(error, ErrorCode::NullPointer, "decoder or bytes is null") as libc::c_int;
+    };
+    clear_error(error);
+    decoder.fsm.write_all(slice::from_raw_parts(bytes, length));
+    0
+}
+
+/// Reads up to 8 KiB from `file` and passes it to the decoder. Returns the number of
+/// bytes read, which is 0 at the end of the file, or a negative `DbnErrorCode` on
+/// error, with the details written to `error`.
+///
+/// # Safety
+/// Verifies `decoder` and `file` are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_write_from_file(
+    decoder: *mut PushDecoder,
+    file: *mut libc::FILE,
+    error: *mut ErrorInfo,
+) -> isize {
+    let (Some(decoder), false) = (decoder.as_mut(), file.is_null()) else {
+        return set_error(error, ErrorCode::NullPointer, "decoder or file is null") as isize;
+    };
+    let mut buffer = [0; READ_BUF_SIZE];
+    let read = libc::fread(buffer.as_mut_ptr() as *mut c_void, 1, buffer.len(), file);
+    if read == 0 && libc::ferror(file) != 0 {
+        return set_error(
+            error,
+            ErrorCode::Io,
+            format!("reading from file: {}", std::io::Error::last_os_error()),
+        ) as isize;
+    }
+    clear_error(error);
+    decoder.fsm.write_all(&buffer[..read]);
+    read as isize
+}
+
+/// Calls `read` once to read up to 8 KiB and passes the bytes to the decoder. `context`
+/// is passed through to `read`. Returns the number of bytes read, which is 0 at the end
+/// of the input, or a negative `DbnErrorCode` on error, with the details written to
+/// `error`. It's an error for `read` to return a negative value or one greater than
+/// `length`, in which case no bytes are passed to the decoder.
+///
+/// # Safety
+/// `read` must write no more than `length` bytes to `buffer`. Verifies `decoder` is not
+/// null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_write_from_callback(
+    decoder: *mut PushDecoder,
+    read: ReadCallback,
+    context: *mut c_void,
+    error: *mut ErrorInfo,
+) -> isize {
+    let Some(decoder) = decoder.as_mut() else {
+        return set_error(error, ErrorCode::NullPointer, "decoder is null") as isize;
+    };
+    let mut buffer = [0; READ_BUF_SIZE];
+    let read = read(context, buffer.as_mut_ptr(), buffer.len());
+    let Ok(read) = usize::try_from(read) else {
+        return set_error(
+            error,
+            ErrorCode::Io,
+            format!("read callback returned {read}"),
+        ) as isize;
+    };
+    if read > buffer.len() {
+        return set_error(
+            error,
+            ErrorCode::BadArgument,
+            format!(
+                "read callback returned {read}, more than the buffer length of {}",
+                buffer.len()
+            ),
+        ) as isize;
+    }
+    clear_error(error);
+    decoder.fsm.write_all(&buffer[..read]);
+    read as isize
+}
+
+/// Decodes the next metadata or record from the bytes passed to the decoder. Should be
+/// called repeatedly until it returns `DbnDecodeStatus_ReadMore`. When a record is
+/// decoded, `record` is set to point to it until the next call to a
+/// `DbnPushDecoder` function.
+///
+/// # Safety
+/// Verifies `decoder` and `record` are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_decode(
+    decoder: *mut PushDecoder,
+    record: *mut *const RecordHeader,
+    error: *mut ErrorInfo,
+) -> DecodeStatus {
+    let (Some(decoder), Some(record)) = (decoder.as_mut(), record.as_mut()) else {
+        set_error(error, ErrorCode::NullPointer, "decoder or record is null");
+        return DecodeStatus::Error;
+    };
+    *record = null();
+    clear_error(error);
+    match decoder.fsm.process() {
+        ProcessResult::ReadMore(_) => DecodeStatus::ReadMore,
+        ProcessResult::Metadata(metadata) => {
+            decoder.metadata = Some(metadata);
+            DecodeStatus::Metadata
+        }
+        ProcessResult::Record(()) => {
+            let Some(rec) = decoder.fsm.last_record() else {
+                set_error(error, ErrorCode::Decode, "missing decoded record");
+                return DecodeStatus::Error;
+            };
+            *record = rec.header();
+            DecodeStatus::Record
+        }
+        ProcessResult::Err(err) => {
+            set_dbn_error(error, &err);
+            DecodeStatus::Error
+        }
+    }
+}
+
+/// Returns a pointer to the decoded DBN metadata, or null if the metadata hasn't been
+/// decoded yet or the input is a fragment.
+///
+/// # Safety
+/// Verifies `decoder` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_metadata(decoder: *const PushDecoder) -> *const Metadata {
+    decoder
+        .as_ref()
+        .and_then(|d| d.metadata.as_ref())
+        .map_or(null(), |metadata| metadata as *const Metadata)
+}
+
+/// Resets the decoder to expect a new DBN stream beginning with metadata, discarding
+/// any bytes that haven't been decoded.
+///
+/// # Safety
+/// Verifies `decoder` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_reset(decoder: *mut PushDecoder) {
+    if let Some(decoder) = decoder.as_mut() {
+        decoder.fsm.reset();
+        decoder.metadata = None;
+    }
+}
+
+/// Frees memory associated with the push decoder.
+///
+/// # Safety
+/// Verifies `decoder` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPushDecoder_free(decoder: *mut PushDecoder) {
+    if let Some(decoder) = decoder.as_mut() {
+        drop(Box::from_raw(decoder));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use std::ffi::CStr;
+
+    use dbn::{
+        encode::{DbnEncoder, EncodeRecord},
+        rtype, RecordRef, SType, Schema, TradeMsg,
+    };
+
+    use super::*;
+
+    const TS: u64 = 1_704_186_000_000_000_000;
+
+    fn encode(ts: &[u64]) -> Vec<u8> {
+        let metadata = Metadata::builder()
+            .dataset("XNAS.ITCH")
+            .schema(Some(Schema::Trades))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(TS)
+            .build();
+        let mut encoder = DbnEncoder::new(Vec::new(), &metadata).unwrap();
+        for ts in ts {
+            encoder
+                .encode_record(&TradeMsg {
+                    hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 5482, *ts),
+                    ts_recv: *ts + 1,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        encoder.get_ref().clone()
+    }
+
+    unsafe fn decode_all(decoder: *mut PushDecoder) -> (Vec<TradeMsg>, bool) {
+        let mut error = std::mem::zeroed::<ErrorInfo>();
+        let mut record = null();
+        let mut records = Vec::new();
+        let mut has_metadata = false;
+        loop {
+            match DbnPushDecoder_decode(decoder, &mut record, &mut error) {
+                DecodeStatus::ReadMore => return (records, has_metadata),
+                DecodeStatus::Metadata => has_metadata = true,
+                DecodeStatus::Record => records.push(
+                    RecordRef::unchecked_from_header(record)
+                        .get::<TradeMsg>()
+                        .unwrap()
+                        .clone(),
+                ),
+                DecodeStatus::Error => panic!(
+                    "{:?}: {}",
+                    error.code,
+                    CStr::from_ptr(error.message.as_ptr()).to_str().unwrap()
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_decode_byte_by_byte() {
+        let bytes = encode(&[TS, TS + 1]);
+        unsafe {
+            let decoder = DbnPushDecoder_create(&DbnDecoderOptions_default(), null_mut());
+            assert!(!decoder.is_null());
+            let mut records = Vec::new();
+            for byte in bytes.iter() {
+                assert_eq!(DbnPushDecoder_write(decoder, byte, 1, null_mut()), 0);
+                records.extend(decode_all(decoder).0);
+            }
+            assert!(!DbnPushDecoder_metadata(decoder).is_null());
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[1].hd.ts_event, TS + 1);
+            DbnPushDecoder_free(decoder);
+        }
+    }
+
+    #[test]
+    fn test_push_decode_fragment() {
+        let bytes = encode(&[TS]);
+        let metadata_len = 8 + u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        let options = DecoderOptions {
+            has_metadata: false,
+            ..DbnDecoderOptions_default()
+        };
+        unsafe {
+            let decoder = DbnPushDecoder_create(&options, null_mut());
+            assert_eq!(
+                DbnPushDecoder_write(
+                    decoder,
+                    bytes[metadata_len..].as_ptr(),
+                    bytes.len() - metadata_len,
+                    null_mut()
+                ),
+                0
+            );
+            let (records, has_metadata) = decode_all(decoder);
+            assert!(!has_metadata);
+            assert!(DbnPushDecoder_metadata(decoder).is_null());
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].ts_recv, TS + 1);
+            DbnPushDecoder_free(decoder);
+        }
+    }
+
+    unsafe extern "C" fn read_slice(
+        context: *mut c_void,
+        buffer: *mut u8,
+        length: libc::size_t,
+    ) -> isize {
+        let remaining = &mut *(context as *mut &[u8]);
+        let read = remaining.len().min(length);
+        std::ptr::copy_nonoverlapping(remaining.as_ptr(), buffer, read);
+        *remaining = &remaining[read..];
+        read as isize
+    }
+
+    #[test]
+    fn test_push_decode_from_callback() {
+        let bytes = encode(&[TS, TS + 1, TS + 2]);
+        let mut remaining = bytes.as_slice();
+        unsafe {
+            let decoder = DbnPushDecoder_create(&DbnDecoderOptions_default(), null_mut());
+            let context = &mut remaining as *mut &[u8] as *mut c_void;
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            while DbnPushDecoder_write_from_callback(decoder, read_slice, context, &mut error) > 0 {
+            }
+            assert_eq!(error.code, ErrorCode::Ok);
+            let (records, has_metadata) = decode_all(decoder);
+            assert!(has_metadata);
+            assert_eq!(records.len(), 3);
+            DbnPushDecoder_free(decoder);
+        }
+    }
+
+    unsafe extern "C" fn read_too_much(
+        _context: *mut c_void,
+        _buffer: *mut u8,
+        length: libc::size_t,
+    ) -> isize {
+        length as isize + 1
+    }
+
+    #[test]
+    fn test_push_decode_from_callback_read_too_much() {
+        unsafe {
+            let decoder = DbnPushDecoder_create(&DbnDecoderOptions_default(), null_mut());
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            assert_eq!(
+                DbnPushDecoder_write_from_callback(decoder, read_too_much, null_mut(), &mut error),
+                ErrorCode::BadArgument as isize
+            );
+            assert_eq!(error.code, ErrorCode::BadArgument);
+            let mut record = null();
+            assert_eq!(
+                DbnPushDecoder_decode(decoder, &mut record, &mut error),
+                DecodeStatus::ReadMore
+            );
+            DbnPushDecoder_free(decoder);
+        }
+    }
+
+    #[test]
+    fn test_push_decode_error() {
+        unsafe {
+            let decoder = DbnPushDecoder_create(&DbnDecoderOptions_default(), null_mut());
+            let bytes = b"not DBN at all";
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            assert_eq!(
+                DbnPushDecoder_write(decoder, bytes.as_ptr(), bytes.len(), &mut error),
+                0
+            );
+            assert_eq!(error.code, ErrorCode::Ok);
+            let mut record = null();
+            assert_eq!(
+                DbnPushDecoder_decode(decoder, &mut record, &mut error),
+                DecodeStatus::Error
+            );
+            assert_eq!(error.code, ErrorCode::Decode);
+            assert!(!CStr::from_ptr(error.message.as_ptr()).is_empty());
+            DbnPushDecoder_free(decoder);
+        }
+    }
+
+    #[test]
+    fn test_push_decode_write_null() {
+        unsafe {
+            let decoder = DbnPushDecoder_create(&DbnDecoderOptions_default(), null_mut());
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            assert_eq!(
+                DbnPushDecoder_write(decoder, null(), 1, &mut error),
+                ErrorCode::NullPointer as libc::c_int
+            );
+            assert_eq!(error.code, ErrorCode::NullPointer);
+            assert!(!CStr::from_ptr(error.message.as_ptr()).is_empty());
+            DbnPushDecoder_free(decoder);
+        }
+    }
+
+    #[test]
+    fn test_create_with_invalid_version() {
+        let options = DecoderOptions {
+            has_metadata: false,
+            input_version: dbn::DBN_VERSION + 1,
+            ..DbnDecoderOptions_default()
+        };
+        unsafe {
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            assert!(DbnPushDecoder_create(&options, &mut error).is_null());
+            assert_ne!(error.code, ErrorCode::Ok);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CStr, CString, NulError},
+    ptr::{null, null_mut},
+    sync::Arc,
+};
+
+use dbn::{Metadata, Record, RecordHeader, RecordRef};
+
+use crate::{
+    error::{clear_error, set_dbn_error, set_error, ErrorCode, ErrorInfo},
+    metadata::date_from_yyyymmdd,
+};
+
+/// A timeseries symbol map for resolving the instrument IDs of historical records to
+/// symbols on a given date. Each symbol is only stored once, regardless of how many
+/// days it's mapped for.
+pub struct TsSymbolMap(HashMap<(time::Date, u32), Arc<CStr>>);
+
+/// A point-in-time symbol map for resolving instrument IDs to symbols, such as for
+/// live data.
+#[derive(Default)]
+pub struct PitSymbolMap(HashMap<u32, CString>);
+
+/// Creates a timeseries symbol map from the mappings in `metadata`. Returns null in
+/// case of error, such as if neither `stype_in` nor `stype_out` of the metadata is
+/// `InstrumentId`, with the details written to `error`.
+///
+/// # Safety
+/// Verifies `metadata` is not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnTsSymbolMap_create(
+    metadata: *const Metadata,
+    error: *mut ErrorInfo,
+) -> *mut TsSymbolMap {
+    let Some(metadata) = metadata.as_ref() else {
+        set_error(error, ErrorCode::NullPointer, "metadata is null");
+        return null_mut();
+    };
+    let symbol_map = match dbn::TsSymbolMap::from_metadata(metadata) {
+        Ok(symbol_map) => symbol_map,
+        Err(err) => {
+            set_dbn_error(error, &err);
+            return null_mut();
+        }
+    };
+    let mut interned = HashMap::<&str, Arc<CStr>>::new();
+    let symbols = symbol_map
+        .inner()
+        .iter()
+        .map(|(key, symbol)| {
+            let symbol = match interned.get(symbol.as_str()) {
+                Some(symbol) => symbol.clone(),
+                None => {
+                    let c_symbol = Arc::<CStr>::from(CString::new(symbol.as_str())?);
+                    interned.insert(symbol.as_str(), c_symbol.clone());
+                    c_symbol
+                }
+            };
+            Ok((*key, symbol))
+        })
+        .collect::<Result<HashMap<_, _>, NulError>>();
+    match symbols {
+        Ok(symbols) => {
+            clear_error(error);
+            Box::into_raw(Box::new(TsSymbolMap(symbols)))
+        }
+        Err(err) => {
+            set_nul_error(error, &err);
+            null_mut()
+        }
+    }
+}
+
+/// Returns the symbol for `instrument_id` on `date`, an integer of the form YYYYMMDD.
+/// Returns null if there's no mapping. The returned string is valid until `map` is
+/// freed.
+///
+/// # Safety
+/// Verifies `map` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnTsSymbolMap_get(
+    map: *const TsSymbolMap,
+    date: u32,
+    instrument_id: u32,
+) -> *const c_char {
+    let Some(map) = map.as_ref() else {
+        return null();
+    };
+    date_from_yyyymmdd(date)
+        .and_then(|date| map.0.get(&(date, instrument_id)))
+        .map_or(null(), |symbol| symbol.as_ptr())
+}
+
+/// Returns the symbol for the instrument ID of `record` on the date of its index
+/// timestamp. Returns null if there's no mapping. The returned string is valid until
+/// `map` is freed.
+///
+/// # Safety
+/// Verifies `map` and `record` are not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnTsSymbolMap_get_for_rec(
+    map: *const TsSymbolMap,
+    record: *const RecordHeader,
+) -> *const c_char {
+    let (Some(map), false) = (map.as_ref(), record.is_null()) else {
+        return null();
+    };
+    let record = RecordRef::unchecked_from_header(record);
+    record
+        .index_date()
+        .and_then(|date| map.0.get(&(date, record.header().instrument_id)))
+        .map_or(null(), |symbol| symbol.as_ptr())
+}
+
+/// Frees memory associated with the timeseries symbol map.
+///
+/// # Safety
+/// Verifies `map` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnTsSymbolMap_free(map: *mut TsSymbolMap) {
+    if let Some(map) = map.as_mut() {
+        drop(Box::from_raw(map));
+    }
+}
+
+/// Creates an empty point-in-time symbol map, to be updated with
+/// `DbnPitSymbolMap_on_record`.
+#[no_mangle]
+pub extern "C" fn DbnPitSymbolMap_create() -> *mut PitSymbolMap {
+    Box::into_raw(Box::default())
+}
+
+/// Creates a point-in-time symbol map from the mappings in `metadata` for `date`, an
+/// integer of the form YYYYMMDD. Returns null in case of error, such as if `date` is
+/// outside the query range of the metadata, with the details written to `error`.
+///
+/// # Safety
+/// Verifies `metadata` is not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPitSymbolMap_from_metadata(
+    metadata: *const Metadata,
+    date: u32,
+    error: *mut ErrorInfo,
+) -> *mut PitSymbolMap {
+    let Some(metadata) = metadata.as_ref() else {
+        set_error(error, ErrorCode::NullPointer, "metadata is null");
+        return null_mut();
+    };
+    let Some(date) = date_from_yyyymmdd(date) else {
+        set_error(
+            error,
+            ErrorCode::BadArgument,
+            format!("invalid date {date}, expected YYYYMMDD"),
+        );
+        return null_mut();
+    };
+    let mut symbol_map = match dbn::PitSymbolMap::from_metadata(metadata, date) {
+        Ok(symbol_map) => symbol_map,
+        Err(err) => {
+            set_dbn_error(error, &err);
+            return null_mut();
+        }
+    };
+    let mut map = PitSymbolMap::default();
+    if let Err(err) = map.insert_all(&mut symbol_map) {
+        set_nul_error(error, &err);
+        return null_mut();
+    }
+    clear_error(error);
+    Box::into_raw(Box::new(map))
+}
+
+/// Updates the map from `record` if it's a symbol mapping record, otherwise is a no-op.
+/// Returns 0 on success, otherwise a negative `DbnErrorCode` with the details written
+/// to `error`.
+///
+/// # Safety
+/// `record` must point to a complete record of the length given in its header. Verifies
+/// `map` and `record` are not null. `error` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPitSymbolMap_on_record(
+    map: *mut PitSymbolMap,
+    record: *const RecordHeader,
+    error: *mut ErrorInfo,
+) -> libc::c_int {
+    let (Some(map), false) = (map.as_mut(), record.is_null()) else {
+        return set_error(error, ErrorCode::NullPointer, "map or record is null") as libc::c_int;
+    };
+    let mut update = dbn::PitSymbolMap::new();
+    if let Err(err) = update.on_record(RecordRef::unchecked_from_header(record)) {
+        return set_dbn_error(error, &err) as libc::c_int;
+    }
+    if let Err(err) = map.insert_all(&mut update) {
+        return set_nul_error(error, &err) as libc::c_int;
+    }
+    clear_error(error);
+    0
+}
+
+/// Returns the current symbol for `instrument_id`. Returns null if there's no mapping.
+/// The returned string is valid until the mapping for `instrument_id` is updated or
+/// `map` is freed.
+///
+/// # Safety
+/// Verifies `map` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPitSymbolMap_get(
+    map: *const PitSymbolMap,
+    instrument_id: u32,
+) -> *const c_char {
+    map.as_ref()
+        .and_then(|map| map.0.get(&instrument_id))
+        .map_or(null(), |symbol| symbol.as_ptr())
+}
+
+/// Frees memory associated with the point-in-time symbol map.
+///
+/// # Safety
+/// Verifies `map` is not null.
+#[no_mangle]
+pub unsafe extern "C" fn DbnPitSymbolMap_free(map: *mut PitSymbolMap) {
+    if let Some(map) = map.as_mut() {
+        drop(Box::from_raw(map));
+    }
+}
+
+/// Writes the details of a symbol that can't be converted to a C string to `error`.
+///
+/// # Safety
+/// `error` must be null or a valid pointer.
+unsafe fn set_nul_error(error: *mut ErrorInfo, nul_error: &NulError) -> ErrorCode {
+    set_error(
+        error,
+        ErrorCode::Conversion,
+        format!("symbol contains a null byte: {nul_error}"),
+    )
+}
+
+impl PitSymbolMap {
+    fn insert_all(&mut self, symbol_map: &mut dbn::PitSymbolMap) -> Result<(), NulError> {
+        for (instrument_id, symbol) in symbol_map.inner_mut().drain() {
+            self.0.insert(instrument_id, CString::new(symbol)?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use dbn::{
+        rtype, MappingInterval, MboMsg, SType, Schema, SymbolMapping, SymbolMappingMsg,
+        UNDEF_TIMESTAMP,
+    };
+
+    use super::*;
+
+    // 2023-07-03 00:00 UTC
+    const TS: u64 = 1_688_342_400_000_000_000;
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+    fn metadata() -> Metadata {
+        Metadata::builder()
+            .dataset("XNAS.ITCH")
+            .schema(Some(Schema::Trades))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(TS)
+            .end(std::num::NonZeroU64::new(TS + 2 * DAY))
+            .mappings(vec![SymbolMapping {
+                raw_symbol: "AAPL".to_owned(),
+                intervals: vec![
+                    MappingInterval {
+                        start_date: date_from_yyyymmdd(20230703).unwrap(),
+                        end_date: date_from_yyyymmdd(20230704).unwrap(),
+                        symbol: "10".to_owned(),
+                    },
+                    MappingInterval {
+                        start_date: date_from_yyyymmdd(20230704).unwrap(),
+                        end_date: date_from_yyyymmdd(20230705).unwrap(),
+                        symbol: "11".to_owned(),
+                    },
+                ],
+            }])
+            .build()
+    }
+
+    unsafe fn to_str<'a>(symbol: *const c_char) -> Option<&'a str> {
+        (!symbol.is_null()).then(|| CStr::from_ptr(symbol).to_str().unwrap())
+    }
+
+    #[test]
+    fn test_ts_symbol_map() {
+        let metadata = metadata();
+        let rec = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 11, TS + DAY),
+            ts_recv: TS + DAY + 1,
+            ..Default::default()
+        };
+        unsafe {
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            let map = DbnTsSymbolMap_create(&metadata, &mut error);
+            assert!(!map.is_null());
+            assert_eq!(error.code, ErrorCode::Ok);
+            assert_eq!(to_str(DbnTsSymbolMap_get(map, 20230703, 10)), Some("AAPL"));
+            assert_eq!(to_str(DbnTsSymbolMap_get(map, 20230704, 11)), Some("AAPL"));
+            assert_eq!(to_str(DbnTsSymbolMap_get(map, 20230704, 10)), None);
+            assert_eq!(to_str(DbnTsSymbolMap_get(map, 20231399, 10)), None);
+            assert_eq!(
+                to_str(DbnTsSymbolMap_get_for_rec(map, rec.header())),
+                Some("AAPL")
+            );
+            // The symbol is shared across dates
+            assert_eq!(
+                DbnTsSymbolMap_get(map, 20230703, 10),
+                DbnTsSymbolMap_get(map, 20230704, 11)
+            );
+            DbnTsSymbolMap_free(map);
+        }
+    }
+
+    #[test]
+    fn test_pit_symbol_map() {
+        let metadata = metadata();
+        let mapping = SymbolMappingMsg::new(
+            12,
+            TS + DAY,
+            SType::RawSymbol,
+            "MSFT",
+            SType::RawSymbol,
+            "MSFT",
+            TS,
+            UNDEF_TIMESTAMP,
+        )
+        .unwrap();
+        unsafe {
+            let mut error = std::mem::zeroed::<ErrorInfo>();
+            let map = DbnPitSymbolMap_from_metadata(&metadata, 20230704, &mut error);
+            assert!(!map.is_null());
+            assert_eq!(to_str(DbnPitSymbolMap_get(map, 11)), Some("AAPL"));
+            assert_eq!(to_str(DbnPitSymbolMap_get(map, 12)), None);
+            assert_eq!(
+                DbnPitSymbolMap_on_record(map, mapping.header(), &mut error),
+                0
+            );
+            assert_eq!(error.code, ErrorCode::Ok);
+            assert_eq!(to_str(DbnPitSymbolMap_get(map, 12)), Some("MSFT"));
+            assert_eq!(
+                DbnPitSymbolMap_on_record(map, null(), &mut error),
+                ErrorCode::NullPointer as libc::c_int
+            );
+            assert_eq!(error.code, ErrorCode::NullPointer);
+            DbnPitSymbolMap_free(map);
+        }
+    }
+
+    #[test]
+    fn test_symbol_map_errors() {
+        let metadata = metadata();
+        let mut error = unsafe { std::mem::zeroed::<ErrorInfo>() };
+        unsafe {
+            // Outside the query range
+            assert!(DbnPitSymbolMap_from_metadata(&metadata, 20230801, &mut error).is_null());
+            assert_ne!(error.code, ErrorCode::Ok);
+            assert!(!CStr::from_ptr(error.message.as_ptr()).is_empty());
+            assert!(DbnPitSymbolMap_from_metadata(&metadata, 20231399, &mut error).is_null());
+            assert_eq!(error.code, ErrorCode::BadArgument);
+            assert!(DbnPitSymbolMap_from_metadata(null(), 20230703, &mut error).is_null());
+            assert_eq!(error.code, ErrorCode::NullPointer);
+            assert!(DbnTsSymbolMap_create(null(), &mut error).is_null());
+            assert_eq!(error.code, ErrorCode::NullPointer);
+            let mut no_instrument_ids = metadata.clone();
+            no_instrument_ids.stype_out = SType::RawSymbol;
+            assert!(DbnTsSymbolMap_create(&no_instrument_ids, &mut error).is_null());
+            assert_eq!(error.code, ErrorCode::BadArgument);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// The C program uses file descriptors
+#![cfg(unix)]
+
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// Encodes records to the file given as the first argument, then decodes them back and
+/// checks they match, the way a C or C++ program would.
+const PROGRAM: &str = r#"
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+static const char *MESSAGES[] = {"Heartbeat", "Subscription acknowledged"};
+static const size_t MESSAGE_COUNT = sizeof(MESSAGES) / sizeof(MESSAGES[0]);
+
+static int encode(const char *path) {
+    int fd = open(path, O_WRONLY | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0) {
+        perror("open for writing");
+        return 1;
+    }
+    DbnMetadata *metadata =
+        DbnMetadata_create(DbnDBN_VERSION, "XNAS.ITCH", DbnSchema_Mbo, 0, DbnUNDEF_TIMESTAMP, false);
+    DbnErrorInfo error;
+    DbnEncoder *encoder = DbnEncoder_create(fd, metadata, DbnCompression_Zstd, &error);
+    DbnMetadata_free(metadata);
+    if (encoder == NULL) {
+        fprintf(stderr, "DbnEncoder_create: %d: %s\n", (int)error.code, error.message);
+        return 1;
+    }
+    int res = 0;
+    for (size_t i = 0; i < MESSAGE_COUNT && res == 0; ++i) {
+        DbnSystemMsg msg;
+        memset(&msg, 0, sizeof(msg));
+        msg.hd.length = sizeof(msg) / DbnRecordHeader_LENGTH_MULTIPLIER;
+        msg.hd.rtype = DbnRType_System;
+        msg.hd.instrument_id = (uint32_t)i;
+        msg.hd.ts_event = 1000 + i;
+        strncpy(msg.msg, MESSAGES[i], sizeof(msg.msg) - 1);
+        res = DbnEncoder_encode(encoder, &msg.hd, &error);
+    }
+    if (res == 0) {
+        res = DbnEncoder_flush(encoder, &error);
+    }
+    if (res != 0) {
+        fprintf(stderr, "DbnEncoder: %d: %s\n", (int)error.code, error.message);
+    }
+    DbnEncoder_free(encoder);
+    return res == 0 ? 0 : 1;
+}
+
+static int decode(const char *path) {
+    int fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        perror("open for reading");
+        return 1;
+    }
+    DbnDecoder *decoder = DbnDecoder_create(fd, DbnCompression_Zstd);
+    if (decoder == NULL) {
+        fprintf(stderr, "DbnDecoder_create failed\n");
+        return 1;
+    }
+    int res = 0;
+    char dataset[32];
+    if (DbnMetadata_dataset(DbnDecoder_metadata(decoder), dataset, sizeof(dataset)) < 0
+        || strcmp(dataset, "XNAS.ITCH") != 0) {
+        fprintf(stderr, "unexpected dataset\n");
+        res = 1;
+    }
+    size_t count = 0;
+    const DbnRecordHeader *record;
+    while (res == 0 && (record = DbnDecoder_decode(decoder)) != NULL) {
+        const DbnSystemMsg *msg = (const DbnSystemMsg *)record;
+        if (count >= MESSAGE_COUNT || record->rtype != DbnRType_System
+            || record->instrument_id != count || record->ts_event != 1000 + count
+            || strcmp(msg->msg, MESSAGES[count]) != 0) {
+            fprintf(stderr, "unexpected record %zu\n", count);
+            res = 1;
+        }
+        ++count;
+    }
+    if (res == 0 && count != MESSAGE_COUNT) {
+        fprintf(stderr, "decoded %zu records, expected %zu\n", count, MESSAGE_COUNT);
+        res = 1;
+    }
+    DbnDecoder_free(decoder);
+    return res;
+}
+
+int main(int argc, char *argv[]) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s FILE\n", argv[0]);
+        return 2;
+    }
+    int res = encode(argv[1]);
+    if (res == 0) {
+        res = decode(argv[1]);
+    }
+    return res;
+}
+"#;
+
+/// Returns the directory containing the `libdbn_c` archive built for this test.
+fn lib_dir() -> PathBuf {
+    let mut dir = env::current_exe().unwrap();
+    // Test executables are in a `deps` directory next to the archive
+    dir.pop();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+    dir
+}
+
+fn build_and_run(lang: &str, compiler_var: &str, default_compiler: &str) {
+    let header = env!("DBN_C_HEADER_PATH");
+    let prefix = format!("dbn-c-round-trip-{lang}-{}", std::process::id());
+    let tmp_dir = env::temp_dir();
+    let src_path = tmp_dir.join(format!("{prefix}.{lang}"));
+    let exe_path = tmp_dir.join(&prefix);
+    let dbn_path = tmp_dir.join(format!("{prefix}.dbn.zst"));
+    fs::write(&src_path, format!("#include \"{header}\"\n{PROGRAM}")).unwrap();
+    let compiler = env::var(compiler_var).unwrap_or_else(|_| default_compiler.to_owned());
+    let mut compile = Command::new(&compiler);
+    compile
+        .args(["-x", lang, "-Wall", "-Werror"])
+        .arg(&src_path)
+        .args(["-x", "none", "-o"])
+        .arg(&exe_path)
+        .arg("-L")
+        .arg(lib_dir())
+        .arg("-ldbn_c");
+    if cfg!(target_os = "linux") {
+        compile.args(["-lpthread", "-ldl", "-lm"]);
+    }
+    let compiled = compile.output();
+    fs::remove_file(&src_path).unwrap();
+    let compiled = compiled.unwrap_or_else(|e| panic!("failed to run {compiler}: {e}"));
+    assert!(
+        compiled.status.success(),
+        "{}",
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+
+    let ran = Command::new(&exe_path).arg(&dbn_path).output();
+    fs::remove_file(&exe_path).unwrap();
+    let _ = fs::remove_file(&dbn_path);
+    let ran = ran.unwrap();
+    assert!(
+        ran.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ran.stderr)
+    );
+    assert!(ran.stdout.is_empty());
+}
+
+#[test]
+fn test_round_trip_c() {
+    build_and_run("c", "CC", "cc");
+}
+
+#[test]
+fn test_round_trip_cpp() {
+    build_and_run("c++", "CXX", "c++");
+}
@@ -3,12 +3,17 @@ from __future__ import annotations
 
 import datetime as dt
 from collections.abc import Iterable
+from collections.abc import Iterator
 from collections.abc import Sequence
 from enum import Enum
+from os import PathLike
 from typing import BinaryIO
 from typing import ClassVar
 from typing import TextIO
 
+import numpy as np
+import pyarrow as pa
+
 from databento_dbn import DBNRecord
 from databento_dbn.metadata import MappingIntervalDict
 from databento_dbn.metadata import SymbolMapping
@@ -728,12 +733,15 @@ class Encoding(Enum):
         Comma-separated values.
     JSON
         JavaScript object notation.
+    PARQUET
+        Apache Parquet columnar format.
 
     """
 
     DBN: str
     CSV: str
     JSON: str
+    PARQUET: str
 
     def __init__(self, value: str) -> None: ...
     def __index__(self) -> int: ...
@@ -8926,6 +8934,68 @@ class DBNDecoder:
 
         """
 
+class DBNReader(Iterator[np.ndarray]):
+    """
+    A class for reading DBN data in batches of NumPy structured arrays. Each batch
+    contains records of a single type, with a dtype matching the record's layout.
+    Records are copied directly into each array's buffer without creating a Python
+    object per record, and the arrays remain valid after later batches are read.
+    Requires NumPy.
+
+    Parameters
+    ----------
+    source : PathLike[str] | str | BinaryIO
+        The path of a DBN file or a file-like object to read DBN data from. Zstd-compressed
+        data is detected automatically.
+    batch_size : int, default 65536
+        The maximum number of records in each batch.
+    pretty_px : bool, default False
+        Whether to convert fixed-precision prices to floats. Undefined prices are
+        converted to NaN.
+    upgrade_policy : VersionUpgradePolicy, default UPGRADE
+        How to decode data from prior DBN versions. Defaults to upgrade decoding.
+
+    Raises
+    ------
+    DBNError
+        When the metadata can't be decoded.
+
+    """
+
+    def __init__(
+        self,
+        source: PathLike[str] | str | BinaryIO,
+        batch_size: int = 65536,
+        pretty_px: bool = False,
+        upgrade_policy: VersionUpgradePolicy | None = None,
+    ): ...
+    @property
+    def metadata(self) -> Metadata:
+        """
+        The metadata decoded from the beginning of the DBN data.
+
+        Returns
+        -------
+        Metadata
+
+        """
+
+    def __iter__(self) -> DBNReader: ...
+    def __next__(self) -> np.ndarray:
+        """
+        Decode the next batch of records.
+
+        Returns
+        -------
+        np.ndarray
+
+        Raises
+        ------
+        DBNError
+            When the decoding fails.
+
+        """
+
 class Transcoder:
     """
     A class for transcoding DBN i.e. converting it from one compression and encoding to
@@ -8936,15 +9006,15 @@ class Transcoder:
     file : BinaryIO | TextIO
         The file-like object to write the transcoded output to.
     encoding : Encoding
-        The encoding for the output.
+        The encoding for the output. Parquet output is finished by `flush`.
     compression : Compression
-        The compression for the output.
+        The compression for the output. For Parquet, applied to the column chunks.
     pretty_px : bool, default True
-        Whether to serialize fixed-precision prices as decimal strings. Only applicable
-        to CSV and JSON.
+        Whether to serialize fixed-precision prices as decimal strings, or decimal
+        columns for Parquet. Not applicable to DBN.
     pretty_ts : bool, default True | None
-        Whether to serialize nanosecond UNIX timestamps as ISO8601 datetime strings.
-        Only applicable to CSV and JSON.
+        Whether to serialize nanosecond UNIX timestamps as ISO8601 datetime strings,
+        or timestamp columns for Parquet. Not applicable to DBN.
     map_symbols : bool, default None
         If symbology mappings from the metadata should be used to create
         a 'symbol' field, mapping the instrument ID to its requested symbol for
@@ -8959,8 +9029,8 @@ class Transcoder:
         Specify the initial symbol mappings to use with map_symbols. If not specified,
         only the mappings in the metadata header will be used.
     schema : Schema | None, default None
-        The data record schema to encode. This is required for transcoding Live CSV data,
-        as the tabular format is incompatible with mixed schemas.
+        The data record schema to encode. This is required for transcoding Live CSV and
+        Parquet data, as the tabular formats are incompatible with mixed schemas.
     input_version : int, default None
         Specify the DBN version of the input. Only used when transcoding data without
         metadata.
@@ -9011,6 +9081,9 @@ class Transcoder:
         """
         Flushes remaining bytes from buffer through to the output file.
 
+        For Parquet, this also writes the file footer and no more bytes can be
+        written afterwards.
+
         Raises
         ------
         DBNError
@@ -9045,3 +9118,45 @@ def update_encoded_metadata(
         When the file update fails.
 
     """
+
+def read_arrow(
+    source: PathLike[str] | str | BinaryIO,
+    batch_size: int = 8192,
+    pretty_px: bool = True,
+    pretty_ts: bool = True,
+    map_symbols: bool | None = None,
+    upgrade_policy: VersionUpgradePolicy | None = None,
+) -> pa.RecordBatchReader:
+    """
+    Read DBN data as a stream of Arrow record batches. All records must be of the
+    schema in the metadata. Requires pyarrow.
+
+    Parameters
+    ----------
+    source : PathLike[str] | str | BinaryIO
+        The path of a DBN file or a file-like object to read DBN data from. Zstd-compressed
+        data is detected automatically.
+    batch_size : int, default 8192
+        The maximum number of rows in each record batch.
+    pretty_px : bool, default True
+        Whether to encode prices as decimals with a scale of 9 instead of the raw
+        fixed-precision integers.
+    pretty_ts : bool, default True
+        Whether to encode timestamps as nanosecond UTC timestamps instead of the raw
+        UNIX nanoseconds.
+    map_symbols : bool, default None
+        Whether to add a dictionary-encoded 'symbol' column using the symbology
+        mappings in the metadata. Defaults to True if the metadata contains mappings.
+    upgrade_policy : VersionUpgradePolicy, default UPGRADE
+        How to decode data from prior DBN versions. Defaults to upgrade decoding.
+
+    Returns
+    -------
+    pa.RecordBatchReader
+
+    Raises
+    ------
+    DBNError
+        When the metadata can't be decoded or has no schema.
+
+    """
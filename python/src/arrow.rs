@@ -0,0 +1,103 @@
+use arrow_array::ffi_stream::FFI_ArrowArrayStream;
+use dbn::{
+    decode::DbnMetadata,
+    encode::arrow::{RecordBatchEncoderBuilder, RecordBatchIter, DEFAULT_BATCH_SIZE},
+    VersionUpgradePolicy,
+};
+use pyo3::{intern, prelude::*};
+
+use crate::reader::open_decoder;
+
+/// Reads DBN data from a path or file-like object as a `pyarrow.RecordBatchReader`.
+/// The batches are passed to `pyarrow` through the Arrow C stream interface without
+/// copying.
+#[pyfunction]
+#[pyo3(signature = (
+    source,
+    batch_size = DEFAULT_BATCH_SIZE,
+    pretty_px = true,
+    pretty_ts = true,
+    map_symbols = None,
+    upgrade_policy = VersionUpgradePolicy::default(),
+))]
+pub fn read_arrow<'py>(
+    py: Python<'py>,
+    source: &Bound<'py, PyAny>,
+    batch_size: usize,
+    pretty_px: bool,
+    pretty_ts: bool,
+    map_symbols: Option<bool>,
+    upgrade_policy: VersionUpgradePolicy,
+) -> PyResult<Bound<'py, PyAny>> {
+    let decoder = open_decoder(source, upgrade_policy)?;
+    let metadata = decoder.metadata();
+    let symbol_map = if map_symbols.unwrap_or(!metadata.mappings.is_empty()) {
+        Some(metadata.symbol_map()?)
+    } else {
+        None
+    };
+    let encoder = RecordBatchEncoderBuilder::from_metadata(metadata)?
+        .use_pretty_px(pretty_px)
+        .use_pretty_ts(pretty_ts)
+        .symbol_map(symbol_map)
+        .batch_size(batch_size)
+        .build()?;
+    let mut stream = Box::new(FFI_ArrowArrayStream::new(Box::new(RecordBatchIter::new(
+        decoder, encoder,
+    ))));
+    // `pyarrow` moves the stream out of `stream`, leaving it released
+    py.import(intern!(py, "pyarrow"))?
+        .getattr(intern!(py, "RecordBatchReader"))?
+        .call_method1(
+            intern!(py, "_import_from_c"),
+            (&mut *stream as *mut FFI_ArrowArrayStream as usize,),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{ffi::c_str, types::PyDict};
+    use rstest::*;
+
+    use super::*;
+    use crate::tests::{python, TEST_DATA_PATH};
+
+    #[rstest]
+    fn test_read_arrow(_python: ()) {
+        Python::attach(|py| {
+            // PyArrow is only an optional dependency of the package, but it's required
+            // for testing
+            py.import("pyarrow")
+                .expect("PyArrow must be installed to test read_arrow");
+            let globals = PyDict::new(py);
+            globals
+                .set_item("path", format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                .unwrap();
+            py.run(
+                c_str!(
+                    r#"import decimal
+import pyarrow as pa
+from _lib import read_arrow
+
+table = read_arrow(path).read_all()
+assert table.num_rows == 2
+assert table.schema.field("price").type == pa.decimal128(19, 9)
+assert table.schema.field("ts_recv").type == pa.timestamp("ns", tz="UTC")
+assert pa.types.is_dictionary(table.schema.field("symbol").type)
+assert table.column("price")[0].as_py() == decimal.Decimal("3722.75")
+assert table.column("side")[0].as_py() == "A"
+assert table.column("symbol")[0].as_py() == "ESH1"
+
+with open(path, "rb") as f:
+    table = read_arrow(f, pretty_px=False, map_symbols=False).read_all()
+assert table.column("price")[0].as_py() == 3_722_750_000_000
+assert "symbol" not in table.schema.names
+"#
+                ),
+                Some(&globals),
+                None,
+            )
+        })
+        .unwrap();
+    }
+}
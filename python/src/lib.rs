@@ -20,9 +20,11 @@ use dbn::{
     UNDEF_ORDER_SIZE, UNDEF_PRICE, UNDEF_STAT_QUANTITY, UNDEF_TIMESTAMP,
 };
 
+mod arrow;
 mod dbn_decoder;
 mod encode;
 mod enums;
+mod reader;
 mod transcoder;
 
 /// A Python module wrapping dbn functions
@@ -36,10 +38,12 @@ fn databento_dbn(_py: Python<'_>, m: &Bound<PyModule>) -> PyResult<()> {
     }
     // all functions exposed to Python need to be added here
     m.add_wrapped(wrap_pyfunction!(encode::update_encoded_metadata))?;
+    m.add_wrapped(wrap_pyfunction!(arrow::read_arrow))?;
     m.add("DBNError", m.py().get_type::<DBNError>())?;
     checked_add_class::<EnumIterator>(m)?;
     checked_add_class::<Metadata>(m)?;
     checked_add_class::<dbn_decoder::DbnDecoder>(m)?;
+    checked_add_class::<reader::DbnReader>(m)?;
     checked_add_class::<transcoder::Transcoder>(m)?;
     // Records
     checked_add_class::<PyMboMsg>(m)?;
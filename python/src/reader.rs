@@ -0,0 +1,321 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use dbn::{
+    decode::{DbnMetadata, DecodeRecordRef, DynDecoder},
+    python::to_py_err,
+    rtype_dispatch, Metadata, Record, RecordRef, VersionUpgradePolicy, FIXED_PRICE_SCALE,
+    UNDEF_PRICE,
+};
+use pyo3::{
+    basic::CompareOp,
+    exceptions::PyValueError,
+    intern,
+    prelude::*,
+    types::{PySlice, PyType},
+    IntoPyObjectExt,
+};
+
+use crate::encode::PyFileLike;
+
+pub(crate) type Reader = BufReader<Box<dyn io::Read + Send>>;
+
+/// Reads DBN data in batches of NumPy structured arrays. Records are copied from the
+/// decoder's buffer directly into each array's buffer without creating a Python object
+/// per record. With `pretty_px`, each batch is then converted to a dtype with float
+/// prices. The arrays own their data, so they remain valid after later batches are
+/// read.
+#[pyclass(module = "databento_dbn", name = "DBNReader")]
+pub struct DbnReader {
+    decoder: Mutex<DynDecoder<'static, Reader>>,
+    batch_size: usize,
+    pretty_px: bool,
+    /// The layout of the most recent batch, reused while the record type is unchanged.
+    layout: Option<BatchLayout>,
+    /// A record that didn't match the type of the previous batch and will begin the
+    /// next one.
+    pending: Option<Vec<u8>>,
+}
+
+/// The NumPy dtypes for a batch of records of a single type.
+struct BatchLayout {
+    rtype: u8,
+    length: usize,
+    fields: Vec<String>,
+    price_fields: Vec<String>,
+    dtype: Py<PyAny>,
+    /// The dtype with fixed-precision price fields as floats. Only set with `pretty_px`.
+    pretty_dtype: Option<Py<PyAny>>,
+}
+
+#[pymethods]
+impl DbnReader {
+    #[new]
+    #[pyo3(signature = (
+        source,
+        batch_size = 65_536,
+        pretty_px = false,
+        upgrade_policy = VersionUpgradePolicy::default(),
+    ))]
+    fn new(
+        source: &Bound<'_, PyAny>,
+        batch_size: usize,
+        pretty_px: bool,
+        upgrade_policy: VersionUpgradePolicy,
+    ) -> PyResult<Self> {
+        if batch_size == 0 {
+            return Err(PyValueError::new_err("batch_size must be greater than 0"));
+        }
+        Ok(Self {
+            decoder: Mutex::new(open_decoder(source, upgrade_policy)?),
+            batch_size,
+            pretty_px,
+            layout: None,
+            pending: None,
+        })
+    }
+
+    #[getter]
+    fn metadata(&self) -> Metadata {
+        self.decoder.lock().unwrap().metadata().clone()
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let decoder = self.decoder.get_mut().unwrap();
+        let ts_out = decoder.metadata().ts_out;
+        let first = match self.pending.take() {
+            Some(first) => first,
+            None => match decoder.decode_record_ref()? {
+                Some(rec) => rec.as_ref().to_vec(),
+                None => return Ok(None),
+            },
+        };
+        // Safety: `first` was copied from a complete record
+        let first_rec = unsafe { RecordRef::new(&first) };
+        let (rtype, length) = (first_rec.header().rtype, first.len());
+        if !self
+            .layout
+            .as_ref()
+            .is_some_and(|l| l.rtype == rtype && l.length == length)
+        {
+            self.layout = Some(BatchLayout::new(py, first_rec, ts_out, self.pretty_px)?);
+        }
+        let layout = self.layout.as_ref().unwrap();
+
+        let numpy = py.import(intern!(py, "numpy"))?;
+        let array = numpy.call_method1(
+            intern!(py, "empty"),
+            (self.batch_size, layout.dtype.bind(py)),
+        )?;
+        let data = array
+            .getattr(intern!(py, "ctypes"))?
+            .getattr(intern!(py, "data"))?
+            .extract::<usize>()? as *mut u8;
+        // Safety: the array was just allocated with room for `batch_size` records of
+        // `length` bytes and the dtype has no padding
+        unsafe {
+            std::ptr::copy_nonoverlapping(first.as_ptr(), data, length);
+        }
+        let mut count = 1;
+        while count < self.batch_size {
+            match decoder.decode_record_ref()? {
+                None => break,
+                Some(rec) if rec.header().rtype == rtype && rec.record_size() == length => {
+                    unsafe {
+                        std::ptr::copy_nonoverlapping(
+                            rec.as_ref().as_ptr(),
+                            data.add(count * length),
+                            length,
+                        );
+                    }
+                    count += 1;
+                }
+                Some(rec) => {
+                    self.pending = Some(rec.as_ref().to_vec());
+                    break;
+                }
+            }
+        }
+        let array = array.get_item(PySlice::new(py, 0, count as isize, 1))?;
+        if self.pretty_px {
+            layout.to_pretty_px(py, &array).map(Some)
+        } else {
+            Ok(Some(array))
+        }
+    }
+}
+
+/// Creates a decoder for `source`, which is either a path or a file-like object. The
+/// compression is inferred.
+pub(crate) fn open_decoder(
+    source: &Bound<'_, PyAny>,
+    upgrade_policy: VersionUpgradePolicy,
+) -> PyResult<DynDecoder<'static, Reader>> {
+    let reader: Box<dyn io::Read + Send> = if let Ok(path) = source.extract::<PathBuf>() {
+        Box::new(File::open(path)?)
+    } else {
+        Box::new(source.extract::<PyFileLike>()?)
+    };
+    DynDecoder::inferred_with_buffer(BufReader::new(reader), upgrade_policy).map_err(to_py_err)
+}
+
+impl BatchLayout {
+    fn new(py: Python<'_>, rec: RecordRef, ts_out: bool, pretty_px: bool) -> PyResult<Self> {
+        fn record_class<'py, R>(rec: &R, py: Python<'py>) -> PyResult<Bound<'py, PyType>>
+        where
+            R: Clone + IntoPyObject<'py>,
+        {
+            Ok(rec.clone().into_bound_py_any(py)?.get_type())
+        }
+
+        let class = rtype_dispatch!(rec, record_class(py))??;
+        let mut dtypes: Vec<(String, String)> = class.getattr(intern!(py, "_dtypes"))?.extract()?;
+        if ts_out {
+            dtypes.push(("ts_out".to_owned(), "u8".to_owned()));
+        }
+        let price_fields: Vec<String> = class.getattr(intern!(py, "_price_fields"))?.extract()?;
+        let numpy = py.import(intern!(py, "numpy"))?;
+        let dtype = numpy.call_method1(intern!(py, "dtype"), (dtypes.clone(),))?;
+        let itemsize: usize = dtype.getattr(intern!(py, "itemsize"))?.extract()?;
+        if itemsize != rec.record_size() {
+            return Err(to_py_err(format!(
+                "dtype of {} with size {itemsize} doesn't match record size {}",
+                class.name()?,
+                rec.record_size()
+            )));
+        }
+        let pretty_dtype = if pretty_px {
+            let pretty_dtypes = dtypes
+                .iter()
+                .map(|(field, dtype)| {
+                    if price_fields.contains(field) {
+                        (field.clone(), "f8".to_owned())
+                    } else {
+                        (field.clone(), dtype.clone())
+                    }
+                })
+                .collect::<Vec<_>>();
+            Some(
+                numpy
+                    .call_method1(intern!(py, "dtype"), (pretty_dtypes,))?
+                    .unbind(),
+            )
+        } else {
+            None
+        };
+        Ok(Self {
+            rtype: rec.header().rtype,
+            length: rec.record_size(),
+            fields: dtypes.into_iter().map(|(field, _)| field).collect(),
+            price_fields,
+            dtype: dtype.unbind(),
+            pretty_dtype,
+        })
+    }
+
+    /// Copies `array` to a new array with fixed-precision prices converted to floats.
+    /// `UNDEF_PRICE` is converted to NaN.
+    fn to_pretty_px<'py>(
+        &self,
+        py: Python<'py>,
+        array: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let numpy = py.import(intern!(py, "numpy"))?;
+        let pretty = numpy.call_method1(
+            intern!(py, "empty"),
+            (array.len()?, self.pretty_dtype.as_ref().unwrap().bind(py)),
+        )?;
+        for field in self.fields.iter() {
+            let column = array.get_item(field)?;
+            if self.price_fields.contains(field) {
+                let is_undef = column.rich_compare(UNDEF_PRICE, CompareOp::Eq)?;
+                let px = column.div(FIXED_PRICE_SCALE as f64)?;
+                pretty.set_item(
+                    field,
+                    numpy.call_method1(intern!(py, "where"), (is_undef, f64::NAN, px))?,
+                )?;
+            } else {
+                pretty.set_item(field, column)?;
+            }
+        }
+        Ok(pretty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::{ffi::c_str, types::PyDict};
+    use rstest::*;
+
+    use super::*;
+    use crate::tests::{python, TEST_DATA_PATH};
+
+    #[rstest]
+    fn test_reader_batches(_python: ()) {
+        Python::attach(|py| {
+            // NumPy is only an optional dependency of the package, but it's required
+            // for testing
+            py.import("numpy")
+                .expect("NumPy must be installed to test DBNReader");
+            let globals = PyDict::new(py);
+            globals
+                .set_item("path", format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                .unwrap();
+            py.run(
+                c_str!(
+                    r#"from _lib import DBNReader, MBOMsg, Schema
+
+reader = DBNReader(path, batch_size=1)
+assert reader.metadata.schema == Schema.MBO
+batches = list(reader)
+assert len(batches) == 2
+assert all(len(batch) == 1 for batch in batches)
+assert batches[0].dtype.itemsize == MBOMsg.size_hint
+assert batches[0]["price"][0] == 3_722_750_000_000
+assert batches[0]["action"][0] == b"C"
+
+with open(path, "rb") as f:
+    batches = list(DBNReader(f, pretty_px=True))
+assert len(batches) == 1
+assert len(batches[0]) == 2
+assert batches[0]["price"][0] == 3722.75
+assert batches[0]["price"][1] == 3723.0
+assert batches[0]["ts_recv"][0] == 1609160400000704060
+"#
+                ),
+                Some(&globals),
+                None,
+            )
+        })
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_reader_missing_file(_python: ()) {
+        Python::attach(|py| {
+            py.run(
+                c_str!(
+                    r#"from _lib import DBNReader
+
+try:
+    DBNReader("missing.dbn")
+    assert False
+except FileNotFoundError:
+    pass
+"#
+                ),
+                Some(&PyDict::new(py)),
+                None,
+            )
+        })
+        .unwrap();
+    }
+}
@@ -10,7 +10,7 @@ use dbn::{
     decode::dbn::fsm::{DbnFsm, ProcessResult},
     encode::{
         CsvEncoder, DbnMetadataEncoder, DbnRecordEncoder, DynWriter, EncodeRecordRef,
-        EncodeRecordTextExt, JsonEncoder,
+        EncodeRecordTextExt, JsonEncoder, ParquetEncoder,
     },
     python::{py_to_time_date, to_py_err},
     Compression, Encoding, Metadata, PitSymbolMap, RType, Record, RecordRef, Schema, SymbolIndex,
@@ -117,6 +117,19 @@ impl Transcoder {
                 input_version,
                 upgrade_policy,
             )?),
+            Encoding::Parquet => Box::new(ParquetInner::new(
+                file,
+                compression,
+                pretty_px,
+                pretty_ts,
+                map_symbols,
+                has_metadata,
+                ts_out,
+                symbol_map,
+                schema,
+                input_version,
+                upgrade_policy,
+            )?),
         })))
     }
 
@@ -301,17 +314,7 @@ impl<const OUTPUT_ENC: u8> Inner<OUTPUT_ENC> {
             DbnMetadataEncoder::new(&mut self.output).encode(&metadata)?;
         // CSV or JSON
         } else if self.map_symbols {
-            if metadata.schema.is_some() {
-                // historical
-                // only read from metadata mappings if symbol_map is unpopulated,
-                // i.e. no `symbol_map` was passed in
-                if self.symbol_map.is_empty() {
-                    self.symbol_map = metadata.symbol_map().map(SymbolMap::Historical)?;
-                }
-            } else {
-                // live
-                self.symbol_map = SymbolMap::Live(Default::default());
-            }
+            self.symbol_map.update_from_metadata(&metadata)?;
         }
         // decoding metadata and the header are both done once at the beginning
         Self::encode_header_if_csv(
@@ -355,6 +358,153 @@ impl<const OUTPUT_ENC: u8> Inner<OUTPUT_ENC> {
     }
 }
 
+/// Parquet files have a single schema and end with a footer, so unlike the text
+/// encodings, one encoder is used for the lifetime of the transcoder and the file is
+/// finished when the transcoder is flushed.
+struct ParquetInner {
+    fsm: DbnFsm,
+    // Taken when the encoder is created
+    file: Option<BufWriter<PyFileLike>>,
+    encoder: Option<ParquetEncoder<BufWriter<PyFileLike>>>,
+    compression: Compression,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    map_symbols: bool,
+    symbol_map: SymbolMap,
+    schema: Option<Schema>,
+}
+
+impl Transcode for ParquetInner {
+    fn write(&mut self, bytes: &[u8]) -> PyResult<()> {
+        self.fsm.write_all(bytes);
+        self.encode()
+    }
+
+    fn flush(&mut self) -> PyResult<()> {
+        self.encode()?;
+        if let Some(encoder) = self.encoder.as_mut() {
+            encoder.finish()?;
+        }
+        Ok(())
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.fsm.data()
+    }
+}
+
+impl ParquetInner {
+    fn new(
+        file: PyFileLike,
+        compression: Compression,
+        pretty_px: bool,
+        pretty_ts: bool,
+        map_symbols: Option<bool>,
+        has_metadata: bool,
+        ts_out: bool,
+        symbol_map: Option<TsSymbolMap>,
+        schema: Option<Schema>,
+        input_version: Option<u8>,
+        upgrade_policy: VersionUpgradePolicy,
+    ) -> PyResult<Self> {
+        let fsm = DbnFsm::builder()
+            .skip_metadata(!has_metadata)
+            .input_dbn_version(input_version)
+            .map_err(to_py_err)?
+            .upgrade_policy(upgrade_policy)
+            .ts_out(ts_out)
+            .build()
+            .map_err(to_py_err)?;
+        let mut inner = Self {
+            fsm,
+            file: Some(BufWriter::new(file)),
+            encoder: None,
+            compression,
+            use_pretty_px: pretty_px,
+            use_pretty_ts: pretty_ts,
+            map_symbols: map_symbols.unwrap_or(true),
+            symbol_map: symbol_map.map(SymbolMap::Historical).unwrap_or_default(),
+            schema,
+        };
+        if !has_metadata {
+            // if there's metadata, the encoder will be created when the metadata is
+            // processed
+            inner.init_encoder()?;
+        }
+        Ok(inner)
+    }
+
+    fn encode(&mut self) -> PyResult<()> {
+        loop {
+            match self.fsm.process() {
+                ProcessResult::ReadMore(_) => return Ok(()),
+                ProcessResult::Err(e) => return Err(PyErr::from(e)),
+                ProcessResult::Metadata(metadata) => {
+                    if self.schema.is_none() {
+                        self.schema = metadata.schema;
+                    }
+                    if self.map_symbols {
+                        self.symbol_map.update_from_metadata(&metadata)?;
+                    }
+                    self.init_encoder()?;
+                }
+                ProcessResult::Record(_) => self.encode_record().map_err(to_py_err)?,
+            }
+        }
+    }
+
+    fn init_encoder(&mut self) -> PyResult<()> {
+        let Some(input_version) = self.fsm.input_dbn_version() else {
+            return Err(PyValueError::new_err(
+                "must specify input_version when has_metadata=False",
+            ));
+        };
+        let Some(schema) = self.schema else {
+            return Err(PyValueError::new_err(
+                "A schema must be specified when transcoding mixed schema DBN to Parquet",
+            ));
+        };
+        let file = self.file.take().expect("Parquet encoder already created");
+        self.encoder = Some(
+            ParquetEncoder::builder(file)
+                .version(self.fsm.upgrade_policy().output_version(input_version))
+                .use_pretty_px(self.use_pretty_px)
+                .use_pretty_ts(self.use_pretty_ts)
+                .ts_out(self.fsm.ts_out())
+                .schema(Some(schema))
+                .with_symbol(self.map_symbols)
+                .compression(self.compression)
+                .build()?,
+        );
+        Ok(())
+    }
+
+    fn encode_record(&mut self) -> dbn::Result<()> {
+        let rec = self.fsm.last_record().unwrap();
+        if self.map_symbols {
+            self.symbol_map.update_live(rec);
+        }
+        // Filter by rtype based on metadata schema or schema parameter, which is set
+        // once the encoder has been created
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(());
+        };
+        if rec
+            .rtype()
+            .map(|rtype| rtype == RType::from(self.schema.unwrap()))
+            .unwrap_or(false)
+        {
+            if self.map_symbols {
+                let symbol = self.symbol_map.get_for_rec(&rec).map(|s| s.as_str());
+                unsafe { encoder.encode_ref_ts_out_with_sym(rec, self.fsm.ts_out(), symbol) }
+            } else {
+                unsafe { encoder.encode_record_ref_ts_out(rec, self.fsm.ts_out()) }
+            }?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 enum SymbolMap {
     Historical(TsSymbolMap),
@@ -378,6 +528,21 @@ impl SymbolMap {
         }
     }
 
+    fn update_from_metadata(&mut self, metadata: &Metadata) -> PyResult<()> {
+        if metadata.schema.is_some() {
+            // historical
+            // only read from metadata mappings if symbol_map is unpopulated,
+            // i.e. no `symbol_map` was passed in
+            if self.is_empty() {
+                *self = metadata.symbol_map().map(SymbolMap::Historical)?;
+            }
+        } else {
+            // live
+            *self = SymbolMap::Live(Default::default());
+        }
+        Ok(())
+    }
+
     fn update_live(&mut self, rec: RecordRef) {
         let SymbolMap::Live(ref mut symbol_map) = self else {
             return;
@@ -397,11 +562,13 @@ impl Default for SymbolMap {
 mod tests {
     use std::{io::Read, num::NonZeroU64};
 
+    use bytes::Bytes;
     use dbn::{
         encode::{DbnEncoder, EncodeRecord},
         rtype, Dataset, ErrorMsg, MappingInterval, MetadataBuilder, OhlcvMsg, RecordHeader, SType,
         Schema, SymbolMapping, SymbolMappingMsg, WithTsOut, DBN_VERSION, UNDEF_TIMESTAMP,
     };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use rstest::*;
     use time::macros::{date, datetime};
 
@@ -548,6 +715,47 @@ mod tests {
         assert_eq!(output.chars().filter(|c| *c == '\n').count(), 2);
     }
 
+    #[rstest]
+    fn test_parquet(_python: ()) {
+        let file = MockPyFile::new();
+        let output_buf = file.inner();
+        let mut transcoder = Python::attach(|py| {
+            Transcoder::new(
+                Py::new(py, file).unwrap().extract(py).unwrap(),
+                Encoding::Parquet,
+                Compression::Zstd,
+                true,
+                true,
+                None,
+                true,
+                false,
+                None,
+                None,
+                None,
+                VersionUpgradePolicy::default(),
+            )
+            .unwrap()
+        });
+        let mut input = Vec::new();
+        std::fs::File::open(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"))
+            .unwrap()
+            .read_to_end(&mut input)
+            .unwrap();
+        transcoder.write(&input).unwrap();
+        transcoder.flush().unwrap();
+        let output = output_buf.lock().unwrap().get_ref().clone();
+        let batches = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(output))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+        assert!(batches[0].column_by_name("symbol").is_some());
+        // The file is finished on flush
+        assert!(transcoder.write(&input[input.len() - 56..]).is_err());
+    }
+
     #[rstest]
     #[case::csv(Encoding::Csv, false)]
     #[case::csv_map_symbols(Encoding::Csv, true)]
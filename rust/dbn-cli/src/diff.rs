@@ -0,0 +1,112 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::Context;
+use clap::ArgAction;
+use dbn::{decode::DynDecoder, diff::Differ, VersionUpgradePolicy};
+
+use crate::output;
+
+/// Arguments for the `diff` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    #[clap(
+        help = "The DBN file to compare against, Zstd-compressed or not",
+        value_name = "LEFT"
+    )]
+    pub left: PathBuf,
+    #[clap(
+        help = "The DBN file to compare, Zstd-compressed or not",
+        value_name = "RIGHT"
+    )]
+    pub right: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Saves the differences to FILE. If no path is specified, the differences will be written to standard output",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "The maximum number of record differences to list. Differences past the maximum are still counted",
+        default_value_t = Differ::DEFAULT_MAX_DIFFERENCES,
+        value_name = "NUM"
+    )]
+    pub max_differences: usize,
+    #[clap(
+        short = 'u',
+        long = "upgrade",
+        default_value = "false",
+        action = ArgAction::SetTrue,
+        help = "Upgrade data when decoding previous DBN versions before comparing. By default data is compared as-is."
+    )]
+    pub should_upgrade: bool,
+    #[clap(
+        short = 'J',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Output the differences as a JSON report"
+    )]
+    pub json: bool,
+    #[clap(
+        short = 'p',
+        long = "pretty",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        requires = "json",
+        help = "Indent the JSON report"
+    )]
+    pub should_pretty_print: bool,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing output file"
+    )]
+    pub force: bool,
+}
+
+impl DiffArgs {
+    pub fn upgrade_policy(&self) -> VersionUpgradePolicy {
+        if self.should_upgrade {
+            VersionUpgradePolicy::UpgradeToV3
+        } else {
+            VersionUpgradePolicy::AsIs
+        }
+    }
+}
+
+/// The exit status when the files differ, following the convention of diff(1).
+pub const DIFFERENT_EXIT_CODE: i32 = 1;
+/// The exit status when the files couldn't be compared, following the convention of
+/// diff(1).
+pub const ERROR_EXIT_CODE: i32 = 2;
+
+/// Compares the two input files and writes their differences to the output.
+/// Returns whether the files are identical.
+pub fn run(args: &DiffArgs) -> anyhow::Result<bool> {
+    let open = |path: &PathBuf| {
+        DynDecoder::from_file(path, args.upgrade_policy())
+            .with_context(|| format!("opening '{}' to compare", path.display()))
+    };
+    let report = Differ::new()
+        .max_differences(args.max_differences)
+        .diff(open(&args.left)?, open(&args.right)?)
+        .with_context(|| {
+            format!(
+                "comparing '{}' and '{}'",
+                args.left.display(),
+                args.right.display()
+            )
+        })?;
+    let mut writer = output(args.output.as_deref(), args.force)?;
+    if args.json {
+        writeln!(writer, "{}", report.to_json(args.should_pretty_print))?;
+    } else if !report.is_empty() {
+        writeln!(writer, "{report}")?;
+    }
+    writer.flush()?;
+    Ok(report.is_empty())
+}
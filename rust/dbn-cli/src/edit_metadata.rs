@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use dbn::{edit::edit_metadata_file, MappingInterval, Metadata, SType, SymbolMapping};
+use time::{format_description::well_known::Iso8601, Date};
+
+/// Arguments for the `edit-metadata` subcommand.
+#[derive(Debug, clap::Args)]
+#[clap(group(clap::ArgGroup::new("edits").required(true).multiple(true)))]
+pub struct EditMetadataArgs {
+    #[clap(
+        help = "The DBN file to edit, Zstd-compressed or not",
+        value_name = "FILE"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        long,
+        group = "edits",
+        help = "Set the dataset code",
+        value_name = "DATASET"
+    )]
+    pub dataset: Option<String>,
+    #[clap(
+        long,
+        group = "edits",
+        help = "Set the input symbology type, such as 'raw_symbol' or 'parent'",
+        value_name = "STYPE"
+    )]
+    pub stype_in: Option<SType>,
+    #[clap(
+        long,
+        group = "edits",
+        help = "Replace the requested symbols with a comma-separated list",
+        value_name = "SYMBOLS",
+        value_delimiter = ',',
+        num_args = 1..
+    )]
+    pub symbols: Option<Vec<String>>,
+    #[clap(
+        long,
+        group = "edits",
+        help = "Add the symbol mappings in FILE, a CSV file with the columns raw_symbol, start_date, end_date, and symbol. Mappings for a raw symbol already in the metadata replace the existing ones",
+        value_name = "FILE"
+    )]
+    pub mappings: Option<PathBuf>,
+}
+
+/// Rewrites the metadata of the input file with the requested edits.
+pub fn run(args: &EditMetadataArgs) -> anyhow::Result<()> {
+    let mappings = args.mappings.as_deref().map(read_mappings).transpose()?;
+    edit_metadata_file(&args.input, |metadata| {
+        if let Some(dataset) = &args.dataset {
+            metadata.dataset = dataset.clone();
+        }
+        if let Some(stype_in) = args.stype_in {
+            metadata.stype_in = Some(stype_in);
+        }
+        if let Some(symbols) = &args.symbols {
+            metadata.symbols = symbols.clone();
+        }
+        if let Some(mappings) = mappings {
+            merge_mappings(metadata, mappings);
+        }
+    })
+    .with_context(|| format!("editing metadata of '{}'", args.input.display()))?;
+    Ok(())
+}
+
+/// Reads symbol mappings from a CSV file, grouping the intervals of each raw symbol.
+fn read_mappings(path: &Path) -> anyhow::Result<Vec<SymbolMapping>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("reading mappings from '{}'", path.display()))?;
+    let mut mappings: Vec<SymbolMapping> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line.starts_with("raw_symbol")) {
+            continue;
+        }
+        let context = || format!("invalid mapping on line {} of '{}'", i + 1, path.display());
+        let [raw_symbol, start_date, end_date, symbol] = line
+            .split(',')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|fields: Vec<_>| anyhow!("expected 4 columns, got {}", fields.len()))
+            .with_context(context)?;
+        let parse_date = |s: &str| {
+            Date::parse(s, &Iso8601::DEFAULT)
+                .map_err(|_| anyhow!("expected an ISO 8601 date, got '{s}'"))
+                .with_context(context)
+        };
+        let interval = MappingInterval {
+            start_date: parse_date(start_date)?,
+            end_date: parse_date(end_date)?,
+            symbol: symbol.to_owned(),
+        };
+        if let Some(mapping) = mappings.iter_mut().find(|m| m.raw_symbol == raw_symbol) {
+            mapping.intervals.push(interval);
+        } else {
+            mappings.push(SymbolMapping {
+                raw_symbol: raw_symbol.to_owned(),
+                intervals: vec![interval],
+            });
+        }
+    }
+    Ok(mappings)
+}
+
+/// Adds `mappings` to the metadata, replacing any existing mappings for the same raw
+/// symbol.
+fn merge_mappings(metadata: &mut Metadata, mappings: Vec<SymbolMapping>) {
+    for mapping in mappings {
+        if let Some(existing) = metadata
+            .mappings
+            .iter_mut()
+            .find(|m| m.raw_symbol == mapping.raw_symbol)
+        {
+            *existing = mapping;
+        } else {
+            metadata.mappings.push(mapping);
+        }
+    }
+}
@@ -1,17 +1,23 @@
 use std::{io, path::Path};
 
+use anyhow::Context;
+
 use dbn::{
     decode::{DbnMetadata, DecodeRecordRef},
     encode::{
         json, DbnEncodable, DbnRecordEncoder, DynEncoder, DynWriter, EncodeDbn, EncodeRecord,
-        EncodeRecordRef, EncodeRecordTextExt, NoSchemaBehavior, SchemaSplitter, SplitEncoder,
-        Splitter, SymbolSplitter, TimeSplitter,
+        EncodeRecordRef, EncodeRecordTextExt, InstrumentSplitter, IntervalSplitter,
+        NoSchemaBehavior, PublisherSplitter, SchemaSplitter, SplitEncoder, Splitter,
+        SymbolSplitter, TimeSplitter,
     },
     rtype_dispatch, Compression, Encoding, Metadata, MetadataBuilder, SType, Schema, SymbolIndex,
     TsSymbolMap,
 };
+use time::{OffsetDateTime, Time, UtcOffset};
 
-use crate::{infer_encoding, output_from_args, Args, InferredEncoding, SplitBy};
+use crate::{
+    infer_encoding, output_from_args, transform::ColumnSelector, Args, InferredEncoding, SplitBy,
+};
 
 pub fn silence_broken_pipe(err: anyhow::Error) -> anyhow::Result<()> {
     // Handle broken pipe as a non-error.
@@ -51,13 +57,14 @@ where
     } else if is_fragment {
         encode_fragment(decoder, writer, compression)?;
     } else {
+        let (writer, compression) = select_columns(args, writer, encoding, compression, delimiter)?;
         let mut encoder = DynEncoder::builder(writer, encoding, compression, decoder.metadata())
             .delimiter(delimiter)
-            .write_header(args.write_header)
+            .write_header(args.should_write_header())
             .all_pretty(args.should_pretty_print)
-            .with_symbol(args.map_symbols)
+            .with_symbol(args.should_map_symbols())
             .build()?;
-        if args.map_symbols {
+        if args.should_map_symbols() {
             let symbol_map = decoder.metadata().symbol_map()?;
             let ts_out = decoder.metadata().ts_out;
             while let Some(rec) = decoder.decode_record_ref()? {
@@ -83,6 +90,7 @@ pub fn split_encode_from_dbn<D>(
 where
     D: DecodeRecordRef + DbnMetadata,
 {
+    check_interval_args(args, split_by)?;
     let InferredEncoding {
         encoding,
         compression,
@@ -100,27 +108,25 @@ where
                 compression,
             )?))
         };
-        split_by_encode_fragment(decoder, split_by, output_pattern, build_encoder)
+        split_by_encode_fragment(decoder, split_by, args, output_pattern, build_encoder)
     } else {
         let build_encoder = |path: &str, metadata: Option<Metadata>| -> dbn::Result<_> {
-            DynEncoder::builder(
-                open_output(path)?,
-                encoding,
-                compression,
-                &metadata.unwrap(),
-            )
-            .delimiter(delimiter)
-            .write_header(args.write_header)
-            .all_pretty(args.should_pretty_print)
-            .with_symbol(args.map_symbols)
-            .build()
+            let (writer, compression) =
+                select_columns(args, open_output(path)?, encoding, compression, delimiter)?;
+            DynEncoder::builder(writer, encoding, compression, &metadata.unwrap())
+                .delimiter(delimiter)
+                .write_header(args.should_write_header())
+                .all_pretty(args.should_pretty_print)
+                .with_symbol(args.should_map_symbols())
+                .build()
         };
         split_by_encode(
             decoder,
             split_by,
+            args,
             output_pattern,
             build_encoder,
-            args.map_symbols,
+            args.should_map_symbols(),
         )
     }
 }
@@ -128,6 +134,7 @@ where
 fn split_by_encode<D, E, F>(
     decoder: D,
     split_by: SplitBy,
+    args: &Args,
     output_pattern: &str,
     build_encoder: F,
     map_symbols: bool,
@@ -174,12 +181,37 @@ where
             );
             split_encode_impl(decoder, map_symbols, splitter, Some(symbol_map))
         }
+        SplitBy::Hour | SplitBy::Duration => {
+            let splitter = interval_splitter(args, split_by, |start: OffsetDateTime, metadata| {
+                build_encoder(&interval_path(output_pattern, start), metadata)
+            })?;
+            split_encode_impl(decoder, map_symbols, splitter, Some(symbol_map))
+        }
+        SplitBy::Publisher => {
+            let splitter = PublisherSplitter::new(|publisher_id: u16, metadata| {
+                build_encoder(
+                    &output_pattern.replace("{publisher_id}", &publisher_id.to_string()),
+                    metadata,
+                )
+            });
+            split_encode_impl(decoder, map_symbols, splitter, Some(symbol_map))
+        }
+        SplitBy::Instrument => {
+            let splitter = InstrumentSplitter::new(|instrument_id: u32, metadata| {
+                build_encoder(
+                    &output_pattern.replace("{instrument_id}", &instrument_id.to_string()),
+                    metadata,
+                )
+            });
+            split_encode_impl(decoder, map_symbols, splitter, Some(symbol_map))
+        }
     }
 }
 
 fn split_by_encode_fragment<D, E, F>(
     decoder: D,
     split_by: SplitBy,
+    args: &Args,
     output_pattern: &str,
     build_encoder: F,
 ) -> anyhow::Result<()>
@@ -224,6 +256,30 @@ where
             );
             split_encode_fragment_impl(decoder, splitter)
         }
+        SplitBy::Hour | SplitBy::Duration => {
+            let splitter = interval_splitter(args, split_by, |start: OffsetDateTime, metadata| {
+                build_encoder(&interval_path(output_pattern, start), metadata)
+            })?;
+            split_encode_fragment_impl(decoder, splitter)
+        }
+        SplitBy::Publisher => {
+            let splitter = PublisherSplitter::new(|publisher_id: u16, metadata| {
+                build_encoder(
+                    &output_pattern.replace("{publisher_id}", &publisher_id.to_string()),
+                    metadata,
+                )
+            });
+            split_encode_fragment_impl(decoder, splitter)
+        }
+        SplitBy::Instrument => {
+            let splitter = InstrumentSplitter::new(|instrument_id: u32, metadata| {
+                build_encoder(
+                    &output_pattern.replace("{instrument_id}", &instrument_id.to_string()),
+                    metadata,
+                )
+            });
+            split_encode_fragment_impl(decoder, splitter)
+        }
     }
 }
 
@@ -290,6 +346,7 @@ where
     }
     assert!(!args.should_output_metadata);
 
+    let (writer, compression) = select_columns(args, writer, encoding, compression, delimiter)?;
     let mut encoder = DynEncoder::builder(
         writer,
         encoding,
@@ -302,7 +359,7 @@ where
     .write_header(false)
     .all_pretty(args.should_pretty_print)
     .build()?;
-    let mut has_written_header = (encoding != Encoding::Csv) || !args.write_header;
+    let mut has_written_header = (encoding != Encoding::Csv) || !args.should_write_header();
     fn write_header<T: DbnEncodable>(
         _record: &T,
         encoder: &mut DynEncoder<Box<dyn io::Write>>,
@@ -319,6 +376,76 @@ where
     Ok(())
 }
 
+/// Returns an error if `--split-origin` or `--split-utc-offset` were passed for a split
+/// method other than `hour` or `duration`.
+fn check_interval_args(args: &Args, split_by: SplitBy) -> anyhow::Result<()> {
+    if !matches!(split_by, SplitBy::Hour | SplitBy::Duration)
+        && (args.split_origin.is_some() || args.split_utc_offset.is_some())
+    {
+        return Err(anyhow::anyhow!(
+            "--split-origin and --split-utc-offset are only valid with '--split-by hour' or '--split-by duration'"
+        ));
+    }
+    Ok(())
+}
+
+/// Creates an [`IntervalSplitter`] for `split_by` aligned to the `--split-origin` in the
+/// `--split-utc-offset`.
+fn interval_splitter<E, F>(
+    args: &Args,
+    split_by: SplitBy,
+    build_encoder: F,
+) -> anyhow::Result<IntervalSplitter<E, F>>
+where
+    F: Fn(OffsetDateTime, Option<Metadata>) -> dbn::Result<E>,
+{
+    let interval = split_by
+        .interval(args.split_duration)
+        .context("Must specify --split-duration to split by duration")?;
+    Ok(IntervalSplitter::new(build_encoder, interval)
+        .utc_offset(args.split_utc_offset.unwrap_or(UtcOffset::UTC))
+        .origin(args.split_origin.unwrap_or(Time::MIDNIGHT)))
+}
+
+/// Fills in the `{date}` and `{time}` placeholders of `output_pattern` with the start
+/// of an interval split.
+fn interval_path(output_pattern: &str, start: OffsetDateTime) -> String {
+    let mut time = format!(
+        "{:02}{:02}{:02}",
+        start.hour(),
+        start.minute(),
+        start.second()
+    );
+    if start.nanosecond() != 0 {
+        time = format!("{time}.{:09}", start.nanosecond());
+    }
+    output_pattern
+        .replace("{date}", &start.date().to_string())
+        .replace("{time}", &time)
+}
+
+/// Wraps `writer` in a [`ColumnSelector`] if columns were selected with `--columns`.
+/// Returns the writer and the compression the encoder should apply, as the output
+/// must be compressed after the columns are selected.
+fn select_columns(
+    args: &Args,
+    writer: Box<dyn io::Write>,
+    encoding: Encoding,
+    compression: Compression,
+    delimiter: u8,
+) -> dbn::Result<(Box<dyn io::Write>, Compression)> {
+    if args.columns.is_empty() {
+        return Ok((writer, compression));
+    }
+    let selector = ColumnSelector::new(
+        DynWriter::new(writer, compression)?,
+        (encoding == Encoding::Csv).then_some(delimiter),
+        args.columns.clone(),
+        args.write_header,
+    );
+    Ok((Box::new(selector), Compression::None))
+}
+
 fn dummy_metadata() -> Metadata {
     MetadataBuilder::new()
         .dataset(String::new())
@@ -343,8 +470,8 @@ fn encode_fragment<D: DecodeRecordRef>(
 
 /// Split encode from a fragment input (no metadata).
 ///
-/// Only supports time-based and schema-based splitting. Symbol splitting requires
-/// a symbol map which is not available in fragment inputs.
+/// Supports all splitting except by symbol, which requires a symbol map which is not
+/// available in fragment inputs.
 pub fn split_encode_from_frag<D>(
     args: &Args,
     split_by: SplitBy,
@@ -354,6 +481,7 @@ pub fn split_encode_from_frag<D>(
 where
     D: DecodeRecordRef,
 {
+    check_interval_args(args, split_by)?;
     if matches!(split_by, SplitBy::Symbol) {
         return Err(anyhow::anyhow!(
             "Cannot split by symbol when input is a fragment: no symbol map available"
@@ -396,13 +524,38 @@ where
                 );
                 split_encode_fragment_impl(decoder, splitter)
             }
+            SplitBy::Hour | SplitBy::Duration => {
+                let splitter =
+                    interval_splitter(args, split_by, |start: OffsetDateTime, _metadata| {
+                        build_encoder(&interval_path(output_pattern, start))
+                    })?;
+                split_encode_fragment_impl(decoder, splitter)
+            }
+            SplitBy::Publisher => {
+                let splitter = PublisherSplitter::new(|publisher_id: u16, _metadata| {
+                    build_encoder(
+                        &output_pattern.replace("{publisher_id}", &publisher_id.to_string()),
+                    )
+                });
+                split_encode_fragment_impl(decoder, splitter)
+            }
+            SplitBy::Instrument => {
+                let splitter = InstrumentSplitter::new(|instrument_id: u32, _metadata| {
+                    build_encoder(
+                        &output_pattern.replace("{instrument_id}", &instrument_id.to_string()),
+                    )
+                });
+                split_encode_fragment_impl(decoder, splitter)
+            }
         }
     } else {
         let metadata = dummy_metadata();
         let build_encoder = |path: &str| -> dbn::Result<_> {
-            DynEncoder::builder(open_output(path)?, encoding, compression, &metadata)
+            let (writer, compression) =
+                select_columns(args, open_output(path)?, encoding, compression, delimiter)?;
+            DynEncoder::builder(writer, encoding, compression, &metadata)
                 .delimiter(delimiter)
-                .write_header(args.write_header)
+                .write_header(args.should_write_header())
                 .all_pretty(args.should_pretty_print)
                 .build()
         };
@@ -426,6 +579,29 @@ where
                 );
                 split_encode_fragment_impl(decoder, splitter)
             }
+            SplitBy::Hour | SplitBy::Duration => {
+                let splitter =
+                    interval_splitter(args, split_by, |start: OffsetDateTime, _metadata| {
+                        build_encoder(&interval_path(output_pattern, start))
+                    })?;
+                split_encode_fragment_impl(decoder, splitter)
+            }
+            SplitBy::Publisher => {
+                let splitter = PublisherSplitter::new(|publisher_id: u16, _metadata| {
+                    build_encoder(
+                        &output_pattern.replace("{publisher_id}", &publisher_id.to_string()),
+                    )
+                });
+                split_encode_fragment_impl(decoder, splitter)
+            }
+            SplitBy::Instrument => {
+                let splitter = InstrumentSplitter::new(|instrument_id: u32, _metadata| {
+                    build_encoder(
+                        &output_pattern.replace("{instrument_id}", &instrument_id.to_string()),
+                    )
+                });
+                split_encode_fragment_impl(decoder, splitter)
+            }
         }
     }
 }
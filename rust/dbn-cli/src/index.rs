@@ -0,0 +1,58 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::Context;
+use dbn::index::Index;
+
+use crate::output;
+
+/// Arguments for the `index` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct IndexArgs {
+    #[clap(
+        help = "The DBN file to index, Zstd-compressed or not",
+        value_name = "FILE"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Saves the index to FILE. By default it's saved next to the input with '.idx' appended, where it will be used automatically when filtering with --start",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "The maximum number of records between index entries",
+        default_value_t = Index::DEFAULT_INTERVAL,
+        value_parser = clap::value_parser!(u32).range(1..),
+        value_name = "NUM_RECORDS"
+    )]
+    pub interval: u32,
+    #[clap(
+        short,
+        long,
+        action = clap::ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of an existing index file"
+    )]
+    pub force: bool,
+}
+
+impl IndexArgs {
+    /// Returns the path the index will be written to.
+    pub fn output_path(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| Index::sidecar_path(&self.input))
+    }
+}
+
+/// Builds an index of the input file and writes it to the output path.
+pub fn run(args: &IndexArgs) -> anyhow::Result<()> {
+    let index = Index::build_from_file(&args.input, args.interval)
+        .with_context(|| format!("indexing '{}'", args.input.display()))?;
+    let mut writer = output(Some(&args.output_path()), args.force)?;
+    index.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::{io::Write, num::NonZeroU64, path::PathBuf};
+
+use anyhow::Context;
+use clap::ArgAction;
+use dbn::summary::Summarizer;
+
+use crate::{output, resample::parse_interval};
+
+/// Arguments for the `info` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct InfoArgs {
+    #[clap(
+        help = "The DBN file to summarize, Zstd-compressed or not",
+        value_name = "FILE"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Saves the summary to FILE. If no path is specified, the summary will be written to standard output",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Report gaps between consecutive records longer than INTERVAL, such as '30s' or '5m'. Accepts nanoseconds or a number with a unit of ns, us, ms, s, m, h, or d [default: 1m]",
+        value_name = "INTERVAL",
+        value_parser = parse_interval
+    )]
+    pub gap_threshold: Option<NonZeroU64>,
+    #[clap(
+        long,
+        help = "The maximum number of gaps to list. Gaps past the maximum are still counted",
+        default_value_t = Summarizer::DEFAULT_MAX_GAPS,
+        value_name = "NUM"
+    )]
+    pub max_gaps: usize,
+    #[clap(
+        short = 'J',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Output the summary as JSON"
+    )]
+    pub json: bool,
+    #[clap(
+        short = 'p',
+        long = "pretty",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        requires = "json",
+        help = "Indent the JSON summary"
+    )]
+    pub should_pretty_print: bool,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing output file"
+    )]
+    pub force: bool,
+}
+
+/// Summarizes the records in the input file and writes the summary to the output.
+pub fn run(args: &InfoArgs) -> anyhow::Result<()> {
+    let mut summarizer = Summarizer::new().max_gaps(args.max_gaps);
+    if let Some(gap_threshold) = args.gap_threshold {
+        summarizer = summarizer.gap_threshold(gap_threshold.get());
+    }
+    let summary = summarizer
+        .summarize_file(&args.input)
+        .with_context(|| format!("summarizing '{}'", args.input.display()))?;
+    let mut writer = output(args.output.as_deref(), args.force)?;
+    if args.json {
+        writeln!(writer, "{}", summary.to_json(args.should_pretty_print))?;
+    } else {
+        writeln!(writer, "{summary}")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
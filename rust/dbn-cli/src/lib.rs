@@ -6,7 +6,11 @@ use std::{
 };
 
 use anyhow::{anyhow, Context};
-use clap::{ArgAction, Parser, ValueEnum};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use time::{
+    format_description::well_known::Iso8601, Date, OffsetDateTime, PrimitiveDateTime, Time,
+    UtcOffset,
+};
 
 use dbn::{
     encode::SplitDuration,
@@ -14,8 +18,16 @@ use dbn::{
     Schema, VersionUpgradePolicy,
 };
 
+pub mod diff;
+pub mod edit_metadata;
 pub mod encode;
 pub mod filter;
+pub mod index;
+pub mod info;
+pub mod resample;
+pub mod sort;
+pub mod transform;
+pub mod validate;
 
 /// How the output of the `dbn` command will be encoded.
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -27,6 +39,7 @@ pub enum OutputEncoding {
     Tsv,
     Json,
     DbnFragment,
+    Parquet,
 }
 
 /// How to split a DBN file
@@ -37,6 +50,10 @@ pub enum SplitBy {
     Day,
     Week,
     Month,
+    Hour,
+    Duration,
+    Publisher,
+    Instrument,
 }
 
 impl SplitBy {
@@ -45,7 +62,27 @@ impl SplitBy {
             SplitBy::Day => Some(SplitDuration::Day),
             SplitBy::Week => Some(SplitDuration::Week),
             SplitBy::Month => Some(SplitDuration::Month),
-            SplitBy::Symbol | SplitBy::Schema => None,
+            SplitBy::Symbol
+            | SplitBy::Schema
+            | SplitBy::Hour
+            | SplitBy::Duration
+            | SplitBy::Publisher
+            | SplitBy::Instrument => None,
+        }
+    }
+
+    /// Returns the length of each split in nanoseconds for intraday splits.
+    pub fn interval(self, split_duration: Option<NonZeroU64>) -> Option<NonZeroU64> {
+        match self {
+            SplitBy::Hour => NonZeroU64::new(60 * 60 * 1_000_000_000),
+            SplitBy::Duration => split_duration,
+            SplitBy::Symbol
+            | SplitBy::Schema
+            | SplitBy::Day
+            | SplitBy::Week
+            | SplitBy::Month
+            | SplitBy::Publisher
+            | SplitBy::Instrument => None,
         }
     }
 }
@@ -58,12 +95,39 @@ pub struct InferredEncoding {
     pub is_fragment: bool,
 }
 
+/// Operations other than converting DBN files.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compare two DBN files record by record, exiting with an error if they differ
+    Diff(diff::DiffArgs),
+    /// Rewrite the metadata of a DBN file, such as its dataset, symbols, or mappings
+    EditMetadata(edit_metadata::EditMetadataArgs),
+    /// Build an index of a DBN file for seeking to a timestamp with --start
+    Index(index::IndexArgs),
+    /// Summarize the records of a DBN file: counts, time ranges, gaps, and size
+    Info(info::InfoArgs),
+    /// Aggregate trades into OHLCV bars
+    Resample(resample::ResampleArgs),
+    /// Sort the records of a DBN file by index timestamp, optionally removing duplicates
+    Sort(sort::SortArgs),
+    /// Check the records of a DBN file and report any problems as JSON
+    Validate(validate::ValidateArgs),
+}
+
 #[derive(Debug, Parser)]
-#[clap(name = "dbn", version, about)]
+#[clap(
+    name = "dbn",
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 #[cfg_attr(test, derive(Default))]
 pub struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
     #[clap(
-        help = "One or more DBN or legacy DBZ files to decode. Passing multiple files will result in a merge. Pass '-' to read from standard input",
+        help = "One or more DBN or legacy DBZ files to decode. A single CSV, TSV, or JSON file is also accepted and detected by its extension. Passing multiple files will result in a merge. Pass '-' to read from standard input",
         value_name = "FILE...",
         value_delimiter = ' ',
         num_args = 1..,
@@ -94,6 +158,44 @@ pub struct Args {
         value_name = "SPLIT_BY"
     )]
     pub split_by: Option<SplitBy>,
+    #[clap(
+        long,
+        help = "The length of each split with '--split-by duration', such as '15m'. Accepts nanoseconds or a number with a unit of ns, us, ms, s, m, h, or d",
+        required_if_eq("split_by", "duration"),
+        requires = "split_by",
+        value_name = "DURATION",
+        value_parser = resample::parse_interval
+    )]
+    pub split_duration: Option<NonZeroU64>,
+    #[clap(
+        long,
+        help = "The time of day when the first split of each day begins with '--split-by hour' or '--split-by duration', such as the session open '09:30'. Accepts HH:MM or HH:MM:SS in the --split-utc-offset. Defaults to midnight",
+        requires = "split_by",
+        value_name = "TIME",
+        value_parser = parse_time_of_day
+    )]
+    pub split_origin: Option<Time>,
+    #[clap(
+        long,
+        help = "The fixed UTC offset the splits are aligned to with '--split-by hour' or '--split-by duration', such as '-05:00'. It doesn't follow daylight saving time. Also used for the {date} and {time} placeholders. Defaults to UTC",
+        requires = "split_by",
+        allow_hyphen_values = true,
+        value_name = "OFFSET",
+        value_parser = parse_utc_offset
+    )]
+    pub split_utc_offset: Option<UtcOffset>,
+    #[clap(
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        conflicts_with_all = [
+            "split_by", "json", "csv", "tsv", "fragment", "parquet", "should_upgrade",
+            "should_output_metadata", "input_fragment", "limit", "schema_filter", "start",
+            "end", "symbols", "instrument_ids", "shift_ts", "instrument_id_map", "columns",
+        ],
+        help = "Concatenate the input files in the order they're given instead of merging them by timestamp. The records are copied without being decoded, so the inputs must be DBN files with the same version, schema, dataset, stype_out, and ts_out. Only valid with DBN output"
+    )]
+    pub concat: bool,
     #[clap(
         short = 'J',
         long,
@@ -139,7 +241,16 @@ pub struct Args {
         help = "Output the result as a DBN fragment (no metadata)"
     )]
     pub fragment: bool,
-    #[clap(short, long, action = ArgAction::SetTrue, default_value = "false", help = "Zstd compress the output")]
+    #[clap(
+        short = 'P',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        group = "output_encoding",
+        help = "Output the result as Apache Parquet"
+    )]
+    pub parquet: bool,
+    #[clap(short, long, action = ArgAction::SetTrue, default_value = "false", help = "Zstd compress the output. For Parquet, compresses the column data within the file")]
     pub zstd: bool,
     #[clap(
         short = 'u',
@@ -162,7 +273,7 @@ pub struct Args {
         long = "metadata",
         action = ArgAction::SetTrue,
         default_value = "false",
-        conflicts_with_all = ["csv", "dbn", "fragment"],
+        conflicts_with_all = ["csv", "dbn", "fragment", "parquet"],
         help = "Output the metadata section instead of the body of the DBN file. Only valid for JSON output encoding"
     )]
     pub should_output_metadata: bool,
@@ -172,7 +283,7 @@ pub struct Args {
          action = ArgAction::SetTrue,
          default_value = "false",
          conflicts_with_all = ["dbn", "fragment"],
-         help ="Make the CSV or JSON output easier to read by converting timestamps to ISO 8601 and prices to decimals"
+         help ="Make the CSV or JSON output easier to read by converting timestamps to ISO 8601 and prices to decimals. For Parquet, converts timestamps and prices to the Parquet timestamp and decimal types"
     )]
     pub should_pretty_print: bool,
     #[clap(
@@ -224,11 +335,69 @@ pub struct Args {
         value_name = "SCHEMA"
     )]
     pub schema_filter: Option<Schema>,
+    #[clap(
+        long = "start",
+        help = "Only encode records with an index timestamp at or after START. Accepts UNIX nanoseconds or an ISO 8601 datetime, which is assumed to be UTC if it has no offset. If the input has an index from `dbn index`, it's used to skip to START",
+        value_name = "START",
+        value_parser = parse_timestamp
+    )]
+    pub start: Option<u64>,
+    #[clap(
+        long = "end",
+        help = "Only encode records with an index timestamp before END. Accepts the same formats as --start",
+        value_name = "END",
+        value_parser = parse_timestamp
+    )]
+    pub end: Option<u64>,
+    #[clap(
+        long = "sorted",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        requires = "end",
+        help = "Assume the records are sorted by index timestamp, so decoding stops at the first record at or after END instead of reading the rest of the input"
+    )]
+    pub is_sorted: bool,
+    #[clap(
+        long = "symbols",
+        help = "Only encode records for these symbols, resolved through the symbology mappings in the metadata and any symbol mapping records",
+        value_name = "SYMBOL,...",
+        value_delimiter = ','
+    )]
+    pub symbols: Vec<String>,
+    #[clap(
+        long = "instrument-ids",
+        help = "Only encode records for these instrument IDs",
+        value_name = "ID,...",
+        value_delimiter = ','
+    )]
+    pub instrument_ids: Vec<u32>,
+    #[clap(
+        long = "shift-ts",
+        help = "Shift every timestamp in the records, along with the start and end of the metadata, by OFFSET, such as '30s' or '-1h'. Accepts nanoseconds or a number with a unit of ns, us, ms, s, m, h, or d, with an optional leading '-'",
+        value_name = "OFFSET",
+        allow_hyphen_values = true,
+        value_parser = transform::parse_offset
+    )]
+    pub shift_ts: Option<i64>,
+    #[clap(
+        long = "remap-instrument-ids",
+        help = "Replace instrument IDs according to FILE, a CSV file with the columns from and to. Instrument IDs not in FILE are left unchanged",
+        value_name = "FILE"
+    )]
+    pub instrument_id_map: Option<PathBuf>,
+    #[clap(
+        long = "columns",
+        conflicts_with_all = ["dbn", "fragment", "parquet", "should_output_metadata"],
+        help = "Only output these columns, in their original order. Fields of the record header can also be selected by name in JSON. Selecting 'symbol' implies --map-symbols. Only valid with CSV, TSV, or JSON output",
+        value_name = "COLUMN,...",
+        value_delimiter = ','
+    )]
+    pub columns: Vec<String>,
     #[clap(
         long = "omit-header",
         action = ArgAction::SetFalse,
         default_value = "true",
-        conflicts_with_all = ["json", "dbn", "fragment"],
+        conflicts_with_all = ["json", "dbn", "fragment", "parquet"],
         help = "Skip encoding the header. Only valid when encoding CSV or TSV."
     )]
     pub write_header: bool,
@@ -247,6 +416,8 @@ impl Args {
             OutputEncoding::Dbn
         } else if self.fragment {
             OutputEncoding::DbnFragment
+        } else if self.parquet {
+            OutputEncoding::Parquet
         } else {
             OutputEncoding::Infer
         }
@@ -263,15 +434,90 @@ impl Args {
     pub fn input_version(&self) -> u8 {
         self.input_dbn_version_override.unwrap_or(dbn::DBN_VERSION)
     }
+
+    /// Whether to add a 'symbol' field, either with `--map-symbols` or by selecting it
+    /// with `--columns`.
+    pub fn should_map_symbols(&self) -> bool {
+        self.map_symbols || self.columns.iter().any(|column| column == "symbol")
+    }
+
+    /// Whether the encoder should write a CSV header. Selecting columns requires
+    /// the header to find them, so it's then removed after selection if omitted.
+    pub fn should_write_header(&self) -> bool {
+        self.write_header || !self.columns.is_empty()
+    }
+}
+
+/// Parses a time of day as `HH:MM` or `HH:MM:SS`.
+pub fn parse_time_of_day(s: &str) -> Result<Time, String> {
+    let err = || format!("expected a time of day as HH:MM or HH:MM:SS, got '{s}'");
+    let parts = s
+        .split(':')
+        .map(|part| part.parse::<u8>().map_err(|_| err()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (hour, minute, second) = match parts.as_slice() {
+        [hour, minute] => (*hour, *minute, 0),
+        [hour, minute, second] => (*hour, *minute, *second),
+        _ => return Err(err()),
+    };
+    Time::from_hms(hour, minute, second).map_err(|_| err())
+}
+
+/// Parses a UTC offset as `+HH:MM`, `-HH:MM`, `+HH`, `-HH`, or `Z`.
+pub fn parse_utc_offset(s: &str) -> Result<UtcOffset, String> {
+    let err = || format!("expected a UTC offset such as '-05:00' or 'Z', got '{s}'");
+    if s == "Z" {
+        return Ok(UtcOffset::UTC);
+    }
+    let (sign, rest) = if let Some(rest) = s.strip_prefix('+') {
+        (1, rest)
+    } else if let Some(rest) = s.strip_prefix('-') {
+        (-1, rest)
+    } else {
+        return Err(err());
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours = hours.parse::<i8>().map_err(|_| err())?;
+    let minutes = minutes.parse::<i8>().map_err(|_| err())?;
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| err())
+}
+
+/// Parses a timestamp as either UNIX nanoseconds or an ISO 8601 date or datetime.
+/// Datetimes without an offset and dates are assumed to be UTC.
+pub fn parse_timestamp(s: &str) -> Result<u64, String> {
+    if let Ok(ts) = s.parse::<u64>() {
+        return Ok(ts);
+    }
+    let datetime = OffsetDateTime::parse(s, &Iso8601::DEFAULT)
+        .or_else(|_| PrimitiveDateTime::parse(s, &Iso8601::DEFAULT).map(|dt| dt.assume_utc()))
+        .or_else(|_| Date::parse(s, &Iso8601::DEFAULT).map(|d| d.midnight().assume_utc()))
+        .map_err(|_| format!("expected UNIX nanoseconds or an ISO 8601 datetime, got '{s}'"))?;
+    u64::try_from(datetime.unix_timestamp_nanos())
+        .map_err(|_| format!("'{s}' is out of range for UNIX nanoseconds"))
 }
 
 pub fn infer_encoding(args: &Args) -> anyhow::Result<InferredEncoding> {
-    let compression = if args.zstd {
+    let output = args
+        .output
+        .as_ref()
+        .map(|p| p.to_string_lossy().into_owned())
+        .or_else(|| args.output_pattern.clone());
+    infer_output_encoding(args.output_encoding(), output.as_deref(), args.zstd)
+}
+
+/// Infers the encoding and compression of the output from the explicit
+/// `output_encoding` or otherwise the extension of the `output` path.
+pub fn infer_output_encoding(
+    output_encoding: OutputEncoding,
+    output: Option<&str>,
+    zstd: bool,
+) -> anyhow::Result<InferredEncoding> {
+    let compression = if zstd {
         Compression::Zstd
     } else {
         Compression::None
     };
-    match args.output_encoding() {
+    match output_encoding {
         OutputEncoding::DbnFragment => Ok(InferredEncoding {
             encoding: Encoding::Dbn,
             compression,
@@ -302,12 +548,13 @@ pub fn infer_encoding(args: &Args) -> anyhow::Result<InferredEncoding> {
             delimiter: 0,
             is_fragment: false,
         }),
+        OutputEncoding::Parquet => Ok(InferredEncoding {
+            encoding: Encoding::Parquet,
+            compression,
+            delimiter: 0,
+            is_fragment: false,
+        }),
         OutputEncoding::Infer => {
-            let output = args
-                .output
-                .as_ref()
-                .map(|p| p.to_string_lossy().into_owned())
-                .or_else(|| args.output_pattern.clone());
             if let Some(output) = output {
                 if output.ends_with(".dbn.frag.zst") {
                     Ok(InferredEncoding {
@@ -379,6 +626,13 @@ pub fn infer_encoding(args: &Args) -> anyhow::Result<InferredEncoding> {
                         delimiter: 0,
                         is_fragment: false,
                     })
+                } else if output.ends_with(".parquet") {
+                    Ok(InferredEncoding {
+                        encoding: Encoding::Parquet,
+                        compression,
+                        delimiter: 0,
+                        is_fragment: false,
+                    })
                 } else {
                     Err(anyhow!(
                         "Unable to infer output encoding from output path '{output}'",
@@ -393,6 +647,32 @@ pub fn infer_encoding(args: &Args) -> anyhow::Result<InferredEncoding> {
     }
 }
 
+/// Infers the encoding of a CSV, TSV, or JSON input file from its extension. Returns
+/// `None` for all other inputs, which are decoded as DBN.
+pub fn infer_text_input_encoding(input: &Path) -> Option<InferredEncoding> {
+    let input = input.to_string_lossy();
+    let (input, compression) = if let Some(input) = input.strip_suffix(".zst") {
+        (input, Compression::Zstd)
+    } else {
+        (input.as_ref(), Compression::None)
+    };
+    let (encoding, delimiter) = if input.ends_with(".csv") {
+        (Encoding::Csv, b',')
+    } else if input.ends_with(".tsv") || input.ends_with(".xls") {
+        (Encoding::Csv, b'\t')
+    } else if input.ends_with(".json") {
+        (Encoding::Json, 0)
+    } else {
+        return None;
+    };
+    Some(InferredEncoding {
+        encoding,
+        compression,
+        delimiter,
+        is_fragment: false,
+    })
+}
+
 /// Returns a writeable object where the `dbn` output will be directed.
 pub fn output_from_args(args: &Args) -> anyhow::Result<Box<dyn io::Write>> {
     output(args.output.as_deref(), args.force)
@@ -506,6 +786,28 @@ mod tests {
         );
     }
 
+    #[rstest]
+    fn test_infer_encoding_parquet(#[values(false, true)] zstd: bool) {
+        let args = Args {
+            parquet: true,
+            zstd,
+            ..Default::default()
+        };
+        assert_eq!(
+            infer_encoding(&args).unwrap(),
+            InferredEncoding {
+                encoding: Encoding::Parquet,
+                compression: if zstd {
+                    Compression::Zstd
+                } else {
+                    Compression::None
+                },
+                delimiter: 0,
+                is_fragment: false,
+            }
+        );
+    }
+
     #[rstest]
     #[case("out.json", Encoding::Json, Compression::None, 0)]
     #[case("out.csv", Encoding::Csv, Compression::None, b',')]
@@ -517,6 +819,7 @@ mod tests {
     #[case("out.tsv.zst", Encoding::Csv, Compression::Zstd, b'\t')]
     #[case("out.xls.zst", Encoding::Csv, Compression::Zstd, b'\t')]
     #[case("out.dbn.zst", Encoding::Dbn, Compression::Zstd, 0)]
+    #[case("out.parquet", Encoding::Parquet, Compression::None, 0)]
     fn test_infer_encoding_and_compression_inference(
         #[case] output: &str,
         #[case] exp_enc: Encoding,
@@ -538,6 +841,85 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case("in.json", Some((Encoding::Json, Compression::None, 0)))]
+    #[case("in.csv", Some((Encoding::Csv, Compression::None, b',')))]
+    #[case("in.tsv", Some((Encoding::Csv, Compression::None, b'\t')))]
+    #[case("in.csv.zst", Some((Encoding::Csv, Compression::Zstd, b',')))]
+    #[case("in.json.zst", Some((Encoding::Json, Compression::Zstd, 0)))]
+    #[case("in.dbn", None)]
+    #[case("in.dbn.zst", None)]
+    #[case("in.parquet", None)]
+    fn test_infer_text_input_encoding(
+        #[case] input: &str,
+        #[case] exp: Option<(Encoding, Compression, u8)>,
+    ) {
+        assert_eq!(
+            infer_text_input_encoding(Path::new(input)),
+            exp.map(|(encoding, compression, delimiter)| InferredEncoding {
+                encoding,
+                compression,
+                delimiter,
+                is_fragment: false,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("1704205795000000000", 1704205795000000000)]
+    #[case("2024-01-02T14:29:55Z", 1704205795000000000)]
+    #[case("2024-01-02T14:29:55.5Z", 1704205795500000000)]
+    #[case("2024-01-02T09:29:55-05:00", 1704205795000000000)]
+    #[case("2024-01-02T14:29:55", 1704205795000000000)]
+    #[case("2024-01-02", 1704153600000000000)]
+    fn test_parse_timestamp(#[case] s: &str, #[case] exp: u64) {
+        assert_eq!(parse_timestamp(s), Ok(exp));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("yesterday")]
+    #[case("1969-12-31T23:59:59Z")]
+    fn test_parse_timestamp_invalid(#[case] s: &str) {
+        assert!(parse_timestamp(s).is_err());
+    }
+
+    #[rstest]
+    #[case("09:30", Time::from_hms(9, 30, 0).unwrap())]
+    #[case("00:00", Time::MIDNIGHT)]
+    #[case("23:59:59", Time::from_hms(23, 59, 59).unwrap())]
+    fn test_parse_time_of_day(#[case] s: &str, #[case] exp: Time) {
+        assert_eq!(parse_time_of_day(s), Ok(exp));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("9")]
+    #[case("24:00")]
+    #[case("09:30:00:00")]
+    fn test_parse_time_of_day_invalid(#[case] s: &str) {
+        assert!(parse_time_of_day(s).is_err());
+    }
+
+    #[rstest]
+    #[case("Z", UtcOffset::UTC)]
+    #[case("+00:00", UtcOffset::UTC)]
+    #[case("-05:00", UtcOffset::from_hms(-5, 0, 0).unwrap())]
+    #[case("+05:30", UtcOffset::from_hms(5, 30, 0).unwrap())]
+    #[case("+09", UtcOffset::from_hms(9, 0, 0).unwrap())]
+    fn test_parse_utc_offset(#[case] s: &str, #[case] exp: UtcOffset) {
+        assert_eq!(parse_utc_offset(s), Ok(exp));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("05:00")]
+    #[case("-26:00")]
+    #[case("America/New_York")]
+    fn test_parse_utc_offset_invalid(#[case] s: &str) {
+        assert!(parse_utc_offset(s).is_err());
+    }
+
     #[test]
     fn test_infer_encoding_and_compression_bad() {
         let args = Args {
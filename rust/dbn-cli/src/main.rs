@@ -2,20 +2,31 @@ use std::{
     fs::File,
     io::{self, BufRead, BufReader},
     path::Path,
+    process,
 };
 
 use anyhow::{anyhow, Context};
 use clap::Parser;
-use dbn::decode::{
-    DbnMetadata, DbnRecordDecoder, DecodeRecordRef, DynDecoder, MergeDecoder, MergeRecordDecoder,
+use dbn::{
+    concat::concat_files,
+    decode::{
+        CsvDecoder, DbnDecoder, DbnMetadata, DbnRecordDecoder, DecodeRecordRef, DynDecoder,
+        InstrumentFilter, JsonDecoder, MergeDecoder, MergeRecordDecoder, TimeRangeFilter,
+    },
+    encode::DynWriter,
+    enums::{Compression, Encoding},
+    index::Index,
 };
 use dbn_cli::{
+    diff, edit_metadata,
     encode::{
         encode_from_dbn, encode_from_frag, silence_broken_pipe, split_encode_from_dbn,
         split_encode_from_frag,
     },
     filter::{LimitFilter, SchemaFilter},
-    Args,
+    index, infer_encoding, infer_text_input_encoding, info, output_from_args, resample, sort,
+    transform::{Transformer, Transforms},
+    validate, Args, Command, InferredEncoding,
 };
 
 const STDIN_SENTINEL: &str = "-";
@@ -24,18 +35,30 @@ fn open_input_file(path: &Path) -> anyhow::Result<File> {
     File::open(path).with_context(|| format!("opening file to decode at path '{}'", path.display()))
 }
 
-fn wrap_frag(args: &Args, decoder: impl DecodeRecordRef) -> impl DecodeRecordRef {
-    LimitFilter::new_no_metadata(
-        SchemaFilter::new_no_metadata(decoder, args.schema_filter),
-        args.limit,
-    )
+fn wrap_frag(args: &Args, decoder: impl DecodeRecordRef) -> anyhow::Result<impl DecodeRecordRef> {
+    Ok(Transformer::new_no_metadata(
+        LimitFilter::new_no_metadata(
+            SchemaFilter::new_no_metadata(
+                InstrumentFilter::new_no_metadata(
+                    TimeRangeFilter::new_no_metadata(decoder, args.start, args.end)
+                        .sorted(args.is_sorted),
+                    args.instrument_ids.iter().copied(),
+                    args.symbols.iter().cloned(),
+                ),
+                args.schema_filter,
+            ),
+            args.limit,
+        ),
+        Transforms::from_args(args)?,
+        FRAG_TS_OUT,
+    ))
 }
 
 /// assume no ts_out for fragments
 const FRAG_TS_OUT: bool = false;
 
 fn decode_frag(args: &Args, reader: impl io::Read) -> anyhow::Result<impl DecodeRecordRef> {
-    Ok(wrap_frag(
+    wrap_frag(
         args,
         DbnRecordDecoder::with_version(
             reader,
@@ -43,17 +66,37 @@ fn decode_frag(args: &Args, reader: impl io::Read) -> anyhow::Result<impl Decode
             args.upgrade_policy(),
             FRAG_TS_OUT,
         )?,
-    ))
+    )
 }
 
 fn wrap(
     args: &Args,
     decoder: impl DecodeRecordRef + DbnMetadata,
-) -> impl DecodeRecordRef + DbnMetadata {
-    LimitFilter::new(SchemaFilter::new(decoder, args.schema_filter), args.limit)
+) -> anyhow::Result<impl DecodeRecordRef + DbnMetadata> {
+    Ok(Transformer::new(
+        LimitFilter::new(
+            SchemaFilter::new(
+                InstrumentFilter::new(
+                    TimeRangeFilter::new(decoder, args.start, args.end).sorted(args.is_sorted),
+                    args.instrument_ids.iter().copied(),
+                    args.symbols.iter().cloned(),
+                )?,
+                args.schema_filter,
+            ),
+            args.limit,
+        ),
+        Transforms::from_args(args)?,
+    ))
 }
 
 fn with_inputs(args: Args) -> anyhow::Result<()> {
+    if args
+        .input
+        .iter()
+        .any(|input| infer_text_input_encoding(input).is_some())
+    {
+        return Err(anyhow!("Can't merge CSV, TSV, or JSON files"));
+    }
     if args.is_input_fragment {
         let decoders = args
             .input
@@ -67,7 +110,7 @@ fn with_inputs(args: Args) -> anyhow::Result<()> {
                 )?)
             })
             .collect::<anyhow::Result<Vec<DbnRecordDecoder<BufReader<File>>>>>()?;
-        encode_from_frag(&args, MergeRecordDecoder::new(decoders)?)
+        encode_from_frag(&args, wrap_frag(&args, MergeRecordDecoder::new(decoders)?)?)
     } else if args.is_input_zstd_fragment {
         let decoders = args
             .input
@@ -81,17 +124,119 @@ fn with_inputs(args: Args) -> anyhow::Result<()> {
                 )?)
             })
             .collect::<anyhow::Result<Vec<DbnRecordDecoder<zstd::stream::Decoder<BufReader<File>>>>>>()?;
-        encode_from_frag(&args, MergeRecordDecoder::new(decoders)?)
+        encode_from_frag(&args, wrap_frag(&args, MergeRecordDecoder::new(decoders)?)?)
     } else {
         let decoders = args
             .input
             .iter()
             .map(|input| DynDecoder::from_file(input, args.upgrade_policy()))
             .collect::<dbn::Result<Vec<DynDecoder<BufReader<File>>>>>()?;
-        encode_from_dbn(&args, wrap(&args, MergeDecoder::new(decoders)?))
+        encode_from_dbn(&args, wrap(&args, MergeDecoder::new(decoders)?)?)
+    }
+}
+
+fn concat_inputs(args: &Args) -> anyhow::Result<()> {
+    let InferredEncoding {
+        encoding,
+        compression,
+        is_fragment,
+        ..
+    } = infer_encoding(args)?;
+    if encoding != Encoding::Dbn || is_fragment {
+        return Err(anyhow!("--concat is only valid with DBN output"));
+    }
+    if let Some(input) = args
+        .input
+        .iter()
+        .find(|input| input.as_os_str() == STDIN_SENTINEL)
+    {
+        return Err(anyhow!(
+            "Can't concatenate standard input '{}'",
+            input.display()
+        ));
+    }
+    let writer = DynWriter::new(output_from_args(args)?, compression)?;
+    concat_files(&args.input, writer)?;
+    Ok(())
+}
+
+fn with_text_input(
+    args: Args,
+    reader: impl BufRead,
+    input_encoding: InferredEncoding,
+) -> anyhow::Result<()> {
+    if input_encoding.compression == Compression::Zstd {
+        decode_text(
+            &args,
+            BufReader::new(zstd::stream::Decoder::with_buffer(reader)?),
+            input_encoding,
+        )
+    } else {
+        decode_text(&args, reader, input_encoding)
+    }
+}
+
+fn decode_text(
+    args: &Args,
+    reader: impl BufRead,
+    input_encoding: InferredEncoding,
+) -> anyhow::Result<()> {
+    if input_encoding.encoding == Encoding::Json {
+        encode_from_decoder(
+            args,
+            JsonDecoder::builder(reader)
+                .schema(args.schema_filter)
+                .version(args.input_version())
+                .build()?,
+        )
+    } else {
+        encode_from_decoder(
+            args,
+            CsvDecoder::builder(reader)
+                .delimiter(input_encoding.delimiter)
+                .schema(args.schema_filter)
+                .version(args.input_version())
+                .build()?,
+        )
     }
 }
 
+fn encode_from_decoder(
+    args: &Args,
+    decoder: impl DecodeRecordRef + DbnMetadata,
+) -> anyhow::Result<()> {
+    if let Some(split_by) = args.split_by {
+        let Some(output_pattern) = &args.output_pattern else {
+            return Err(anyhow!(
+                "Must specify an output pattern when splitting files"
+            ));
+        };
+        split_encode_from_dbn(args, split_by, output_pattern, wrap(args, decoder)?)
+    } else {
+        encode_from_dbn(args, wrap(args, decoder)?)
+    }
+}
+
+fn has_index(args: &Args) -> bool {
+    !args.is_input_fragment
+        && !args.is_input_zstd_fragment
+        && infer_text_input_encoding(&args.input[0]).is_none()
+        && Index::sidecar_path(&args.input[0]).exists()
+}
+
+/// Uses the index sidecar of `input` to skip to `start`.
+fn with_index(args: Args, input: &Path, start: u64) -> anyhow::Result<()> {
+    let index = Index::from_file(Index::sidecar_path(input))?;
+    let decoder = DbnDecoder::seek_with_index(
+        open_input_file(input)?,
+        &index,
+        start,
+        args.upgrade_policy(),
+    )
+    .with_context(|| format!("seeking in '{}' with its index", input.display()))?;
+    encode_from_decoder(&args, decoder)
+}
+
 fn with_input(args: Args, reader: impl BufRead) -> anyhow::Result<()> {
     if let Some(split_by) = args.split_by {
         let Some(output_pattern) = &args.output_pattern else {
@@ -110,7 +255,7 @@ fn with_input(args: Args, reader: impl BufRead) -> anyhow::Result<()> {
             )
         } else {
             let decoder = DynDecoder::inferred_with_buffer(reader, args.upgrade_policy())?;
-            split_encode_from_dbn(&args, split_by, output_pattern, wrap(&args, decoder))
+            split_encode_from_dbn(&args, split_by, output_pattern, wrap(&args, decoder)?)
         }
     } else if args.is_input_fragment {
         encode_from_frag(&args, decode_frag(&args, reader)?)
@@ -125,23 +270,62 @@ fn with_input(args: Args, reader: impl BufRead) -> anyhow::Result<()> {
             wrap(
                 &args,
                 DynDecoder::inferred_with_buffer(reader, args.upgrade_policy())?,
-            ),
+            )?,
         )
     }
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    if args.input.len() > 1 {
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Diff(diff_args) => match diff::run(diff_args) {
+                Ok(true) => Ok(()),
+                Ok(false) => process::exit(diff::DIFFERENT_EXIT_CODE),
+                Err(e) => {
+                    eprintln!("Error: {e:?}");
+                    process::exit(diff::ERROR_EXIT_CODE)
+                }
+            },
+            Command::EditMetadata(edit_args) => edit_metadata::run(edit_args),
+            Command::Index(index_args) => index::run(index_args),
+            Command::Info(info_args) => info::run(info_args),
+            Command::Resample(resample_args) => resample::run(resample_args),
+            Command::Sort(sort_args) => sort::run(sort_args),
+            Command::Validate(validate_args) => validate::run(validate_args),
+        };
+    }
+    if !args.columns.is_empty() {
+        let InferredEncoding {
+            encoding,
+            is_fragment,
+            ..
+        } = infer_encoding(&args)?;
+        if !matches!(encoding, Encoding::Csv | Encoding::Json) || is_fragment {
+            return Err(anyhow!(
+                "--columns is only valid with CSV, TSV, or JSON output"
+            ));
+        }
+    }
+    if args.concat {
+        concat_inputs(&args)
+    } else if args.input.len() > 1 {
         if args.split_by.is_some() {
             return Err(anyhow!("Can't split by files while merging files"));
         }
         with_inputs(args)
     } else if args.input[0].as_os_str() == STDIN_SENTINEL {
         with_input(args, io::stdin().lock())
+    } else if let Some(start) = args.start.filter(|_| has_index(&args)) {
+        let input = args.input[0].clone();
+        with_index(args, &input, start)
     } else {
         let reader = BufReader::new(open_input_file(&args.input[0])?);
-        with_input(args, reader)
+        if let Some(input_encoding) = infer_text_input_encoding(&args.input[0]) {
+            with_text_input(args, reader, input_encoding)
+        } else {
+            with_input(args, reader)
+        }
     }
     .or_else(silence_broken_pipe)
 }
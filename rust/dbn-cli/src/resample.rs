@@ -0,0 +1,233 @@
+use std::{num::NonZeroU64, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use clap::ArgAction;
+use dbn::{
+    decode::{DbnMetadata, DecodeRecordRef, DynDecoder},
+    encode::{DynEncoder, EncodeRecord, EncodeRecordRef},
+    resample::{BarDecoder, BarSpec, BboDecoder},
+    Schema, VersionUpgradePolicy,
+};
+
+use crate::{infer_output_encoding, output, OutputEncoding};
+
+/// Arguments for the `resample` subcommand.
+#[derive(Debug, clap::Args)]
+#[clap(group(clap::ArgGroup::new("bars").required(true)))]
+pub struct ResampleArgs {
+    #[clap(
+        help = "The DBN file containing trades to resample, Zstd-compressed or not",
+        value_name = "FILE"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        long,
+        group = "bars",
+        help = "Resample to this schema: ohlcv-1s, ohlcv-1m, ohlcv-1h, or ohlcv-1d from trades, bbo-1s or bbo-1m from MBP-1, or cbbo-1s or cbbo-1m from CMBP-1",
+        value_name = "SCHEMA"
+    )]
+    pub to: Option<Schema>,
+    #[clap(
+        long,
+        group = "bars",
+        help = "Build bars of a custom interval, such as '5m' or '250ms'. Accepts nanoseconds or a number with a unit of ns, us, ms, s, m, h, or d",
+        value_name = "INTERVAL",
+        value_parser = parse_interval
+    )]
+    pub interval: Option<NonZeroU64>,
+    #[clap(
+        long,
+        group = "bars",
+        help = "Build bars of NUM_TRADES trades",
+        value_name = "NUM_TRADES"
+    )]
+    pub ticks: Option<NonZeroU64>,
+    #[clap(
+        long,
+        group = "bars",
+        help = "Build bars that end once their volume reaches QUANTITY",
+        value_name = "QUANTITY"
+    )]
+    pub volume: Option<NonZeroU64>,
+    #[clap(
+        short,
+        long,
+        help = "Saves the bars to FILE. If no path is specified, the output will be written to standard output",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        short = 'J',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        group = "output_encoding",
+        help = "Output the bars as JSON lines"
+    )]
+    pub json: bool,
+    #[clap(
+        short = 'C',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        group = "output_encoding",
+        help = "Output the bars as CSV"
+    )]
+    pub csv: bool,
+    #[clap(
+        short = 'T',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        group = "output_encoding",
+        help = "Output the bars as tab-separated values (TSV)"
+    )]
+    pub tsv: bool,
+    #[clap(
+        short = 'D',
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        group = "output_encoding",
+        help = "Output the bars as DBN"
+    )]
+    pub dbn: bool,
+    #[clap(short, long, action = ArgAction::SetTrue, default_value = "false", help = "Zstd compress the output")]
+    pub zstd: bool,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing output file"
+    )]
+    pub force: bool,
+    #[clap(
+        short = 'p',
+        long = "pretty",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        conflicts_with = "dbn",
+        help = "Make the CSV or JSON output easier to read by converting timestamps to ISO 8601 and prices to decimals"
+    )]
+    pub should_pretty_print: bool,
+}
+
+impl ResampleArgs {
+    /// Returns the specification of the bars to build.
+    pub fn bar_spec(&self) -> anyhow::Result<BarSpec> {
+        if let Some(schema) = self.to {
+            Ok(BarSpec::from_schema(schema)?)
+        } else if let Some(interval) = self.interval {
+            Ok(BarSpec::Time(interval))
+        } else if let Some(ticks) = self.ticks {
+            Ok(BarSpec::Ticks(ticks))
+        } else if let Some(volume) = self.volume {
+            Ok(BarSpec::Volume(volume))
+        } else {
+            Err(anyhow!("Must specify the type of bars to build"))
+        }
+    }
+
+    /// Consolidates the several output flag booleans into a single enum.
+    pub fn output_encoding(&self) -> OutputEncoding {
+        if self.json {
+            OutputEncoding::Json
+        } else if self.csv {
+            OutputEncoding::Csv
+        } else if self.tsv {
+            OutputEncoding::Tsv
+        } else if self.dbn {
+            OutputEncoding::Dbn
+        } else {
+            OutputEncoding::Infer
+        }
+    }
+}
+
+/// Parses an interval as either nanoseconds or a number followed by a unit.
+pub fn parse_interval(s: &str) -> Result<NonZeroU64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num = num
+        .parse::<u64>()
+        .map_err(|_| format!("expected a number followed by an optional unit, got '{s}'"))?;
+    let multiplier = match unit {
+        "" | "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        "m" => 60 * 1_000_000_000,
+        "h" => 60 * 60 * 1_000_000_000,
+        "d" => 24 * 60 * 60 * 1_000_000_000,
+        _ => return Err(format!("unknown interval unit '{unit}'")),
+    };
+    num.checked_mul(multiplier)
+        .and_then(NonZeroU64::new)
+        .ok_or_else(|| format!("interval '{s}' is out of range"))
+}
+
+/// Resamples the input file and encodes the result to the output.
+pub fn run(args: &ResampleArgs) -> anyhow::Result<()> {
+    let decoder = DynDecoder::from_file(&args.input, VersionUpgradePolicy::UpgradeToV3)
+        .with_context(|| format!("opening '{}' to resample", args.input.display()))?;
+    match args.to {
+        Some(schema @ (Schema::Bbo1S | Schema::Bbo1M | Schema::Cbbo1S | Schema::Cbbo1M)) => {
+            encode(args, BboDecoder::new(decoder, schema)?)
+        }
+        _ => encode(args, BarDecoder::new(decoder, args.bar_spec()?)),
+    }
+}
+
+fn encode(
+    args: &ResampleArgs,
+    mut decoder: impl DecodeRecordRef + DbnMetadata,
+) -> anyhow::Result<()> {
+    let output_path = args
+        .output
+        .as_ref()
+        .map(|p| p.to_string_lossy().into_owned());
+    let encoding =
+        infer_output_encoding(args.output_encoding(), output_path.as_deref(), args.zstd)?;
+    if encoding.is_fragment {
+        return Err(anyhow!("Can't resample to a DBN fragment"));
+    }
+    let writer = output(args.output.as_deref(), args.force)?;
+    let mut encoder = DynEncoder::builder(
+        writer,
+        encoding.encoding,
+        encoding.compression,
+        decoder.metadata(),
+    )
+    .delimiter(encoding.delimiter)
+    .all_pretty(args.should_pretty_print)
+    .build()?;
+    // Bars of custom intervals have no schema, so they're encoded one at a time
+    // rather than with `encode_decoded`, which requires a schema for CSV
+    while let Some(rec) = decoder.decode_record_ref()? {
+        encoder.encode_record_ref(rec)?;
+    }
+    encoder.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case("1", 1)]
+    #[case("250ms", 250_000_000)]
+    #[case("5m", 300_000_000_000)]
+    #[case("1d", 86_400_000_000_000)]
+    fn test_parse_interval(#[case] s: &str, #[case] exp: u64) {
+        assert_eq!(parse_interval(s).unwrap().get(), exp);
+    }
+
+    #[rstest]
+    fn test_parse_interval_invalid(#[values("", "0", "0s", "m", "5y", "-1s")] s: &str) {
+        assert!(parse_interval(s).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::ArgAction;
+use dbn::{decode::DynDecoder, encode::DynWriter, sort::Sorter, Compression, VersionUpgradePolicy};
+
+use crate::output;
+
+/// Arguments for the `sort` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct SortArgs {
+    #[clap(
+        help = "The DBN file to sort, Zstd-compressed or not",
+        value_name = "FILE"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Saves the sorted DBN to FILE. If no path is specified, the output will be written to standard output",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Remove records that are exact duplicates of an earlier record"
+    )]
+    pub dedup: bool,
+    #[clap(
+        long,
+        help = "The maximum number of MiB of records to hold in memory before spilling sorted runs to temporary files",
+        default_value_t = Sorter::DEFAULT_MAX_MEMORY >> 20,
+        value_name = "MIB"
+    )]
+    pub max_memory: usize,
+    #[clap(
+        long,
+        help = "The directory for temporary files. Defaults to the system's temporary directory",
+        value_name = "DIR"
+    )]
+    pub temp_dir: Option<PathBuf>,
+    #[clap(short, long, action = ArgAction::SetTrue, default_value = "false", help = "Zstd compress the output")]
+    pub zstd: bool,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing output file"
+    )]
+    pub force: bool,
+}
+
+impl SortArgs {
+    /// Returns the compression of the output, either explicitly requested or
+    /// inferred from the extension of the output path.
+    pub fn compression(&self) -> Compression {
+        if self.zstd
+            || self
+                .output
+                .as_ref()
+                .is_some_and(|p| p.to_string_lossy().ends_with(".zst"))
+        {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Sorts the records in the input file by index timestamp and writes them to the
+/// output as DBN.
+pub fn run(args: &SortArgs) -> anyhow::Result<()> {
+    // Sort the records as they're stored, without upgrading them
+    let decoder = DynDecoder::from_file(&args.input, VersionUpgradePolicy::AsIs)
+        .with_context(|| format!("opening '{}' to sort", args.input.display()))?;
+    let mut sorter = Sorter::new()
+        .dedup(args.dedup)
+        .max_memory(args.max_memory.saturating_mul(1 << 20));
+    if let Some(temp_dir) = &args.temp_dir {
+        sorter = sorter.temp_dir(temp_dir);
+    }
+    let writer = DynWriter::new(
+        output(args.output.as_deref(), args.force)?,
+        args.compression(),
+    )?;
+    let summary = sorter
+        .sort(decoder, writer)
+        .with_context(|| format!("sorting '{}'", args.input.display()))?;
+    if args.dedup {
+        eprintln!("Removed {} duplicate record(s)", summary.duplicate_count);
+    }
+    Ok(())
+}
@@ -0,0 +1,611 @@
+use std::{collections::HashMap, fs, io, num::NonZeroU64, path::Path};
+
+use anyhow::{anyhow, Context};
+use dbn::{
+    decode::{DbnMetadata, DecodeRecordRef},
+    v1, v2, BboMsg, CbboMsg, Cmbp1Msg, ErrorMsg, ImbalanceMsg, InstrumentDefMsg, MboMsg, Mbp10Msg,
+    Mbp1Msg, Metadata, OhlcvMsg, Record, RecordBuf, RecordMut, RecordRef, SType, StatMsg,
+    StatusMsg, SymbolMappingMsg, SystemMsg, TradeMsg, UNDEF_TIMESTAMP,
+};
+
+use crate::{resample::parse_interval, Args};
+
+/// Parses a signed offset as an interval with an optional leading '-'.
+pub fn parse_offset(s: &str) -> Result<i64, String> {
+    let (sign, interval) = match s.strip_prefix('-') {
+        Some(interval) => (-1, interval),
+        None => (1, s),
+    };
+    let interval = parse_interval(interval)?;
+    i64::try_from(interval.get())
+        .map(|offset| sign * offset)
+        .map_err(|_| format!("offset '{s}' is out of range"))
+}
+
+/// The changes to make to each record.
+#[derive(Debug, Default, Clone)]
+pub struct Transforms {
+    /// The offset in nanoseconds to add to every timestamp.
+    pub shift_ts: Option<i64>,
+    /// Replacement instrument IDs by the original ID.
+    pub instrument_ids: HashMap<u32, u32>,
+}
+
+impl Transforms {
+    pub fn from_args(args: &Args) -> anyhow::Result<Self> {
+        Ok(Self {
+            shift_ts: args.shift_ts,
+            instrument_ids: args
+                .instrument_id_map
+                .as_deref()
+                .map(read_instrument_id_map)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shift_ts.is_none() && self.instrument_ids.is_empty()
+    }
+
+    /// Applies the transforms to the time range and symbology mappings of `metadata`.
+    fn apply_to_metadata(&self, metadata: &mut Metadata) {
+        if let Some(offset) = self.shift_ts {
+            metadata.start = shift(metadata.start, offset);
+            if let Some(end) = metadata.end.as_mut() {
+                *end = NonZeroU64::new(shift(end.get(), offset)).unwrap_or(*end);
+            }
+        }
+        if !self.instrument_ids.is_empty() && metadata.stype_out == SType::InstrumentId {
+            for interval in metadata
+                .mappings
+                .iter_mut()
+                .flat_map(|mapping| mapping.intervals.iter_mut())
+            {
+                if let Some(id) = interval
+                    .symbol
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(|id| self.instrument_ids.get(&id))
+                {
+                    interval.symbol = id.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Reads a CSV file of original and replacement instrument IDs.
+fn read_instrument_id_map(path: &Path) -> anyhow::Result<HashMap<u32, u32>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("reading instrument ID map from '{}'", path.display()))?;
+    let mut instrument_ids = HashMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line.starts_with("from")) {
+            continue;
+        }
+        let context = || format!("invalid mapping on line {} of '{}'", i + 1, path.display());
+        let (from, to) = line
+            .split_once(',')
+            .ok_or_else(|| anyhow!("expected 2 columns"))
+            .with_context(context)?;
+        let parse_id = |s: &str| {
+            s.trim()
+                .parse::<u32>()
+                .map_err(|_| anyhow!("expected an instrument ID, got '{s}'"))
+                .with_context(context)
+        };
+        instrument_ids.insert(parse_id(from)?, parse_id(to)?);
+    }
+    Ok(instrument_ids)
+}
+
+/// A decoder adapter that rewrites each record with the given [`Transforms`].
+#[derive(Debug)]
+pub struct Transformer<D> {
+    decoder: D,
+    transforms: Transforms,
+    ts_out: bool,
+    buf: Option<RecordBuf>,
+}
+
+impl<D> Transformer<D>
+where
+    D: DbnMetadata,
+{
+    pub fn new(mut decoder: D, transforms: Transforms) -> Self {
+        transforms.apply_to_metadata(decoder.metadata_mut());
+        let ts_out = decoder.metadata().ts_out;
+        Self::new_no_metadata(decoder, transforms, ts_out)
+    }
+}
+
+impl<D> Transformer<D> {
+    pub fn new_no_metadata(decoder: D, transforms: Transforms, ts_out: bool) -> Self {
+        Self {
+            decoder,
+            transforms,
+            ts_out,
+            buf: None,
+        }
+    }
+
+    fn transform(&self, rec: &mut RecordBuf) {
+        if let Some(&id) = self
+            .transforms
+            .instrument_ids
+            .get(&rec.header().instrument_id)
+        {
+            rec.header_mut().instrument_id = id;
+        }
+        if let Some(offset) = self.transforms.shift_ts {
+            shift_record(rec, offset, self.ts_out);
+        }
+    }
+}
+
+impl<D: DbnMetadata> DbnMetadata for Transformer<D> {
+    fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for Transformer<D> {
+    fn decode_record_ref(&mut self) -> dbn::Result<Option<RecordRef<'_>>> {
+        let Some(record) = self.decoder.decode_record_ref()? else {
+            return Ok(None);
+        };
+        if self.transforms.is_empty() {
+            // Safe: casting reference to pointer so the pointer will always be valid.
+            // Getting around borrow checker limitation.
+            return Ok(Some(unsafe {
+                RecordRef::unchecked_from_header(record.header())
+            }));
+        }
+        let mut buf = RecordBuf::try_from(record)?;
+        self.transform(&mut buf);
+        Ok(Some(self.buf.insert(buf).as_rec_ref()))
+    }
+}
+
+/// Adds `offset` to `ts`, leaving null timestamps unchanged.
+fn shift(ts: u64, offset: i64) -> u64 {
+    if ts == UNDEF_TIMESTAMP {
+        ts
+    } else {
+        ts.saturating_add_signed(offset)
+    }
+}
+
+/// Shifts every timestamp of `rec` by `offset`.
+fn shift_record(rec: &mut RecordBuf, offset: i64, ts_out: bool) {
+    let ts_event = rec.header().ts_event;
+    rec.header_mut().ts_event = shift(ts_event, offset);
+    macro_rules! handler {
+        ($r:ty) => {{
+            if ts_out {
+                let with_ts_out = rec.get_mut::<WithTsOut<$r>>().unwrap();
+                with_ts_out.rec.shift_ts(offset);
+                with_ts_out.ts_out = shift(with_ts_out.ts_out, offset);
+            } else {
+                rec.get_mut::<$r>().unwrap().shift_ts(offset);
+            }
+        }};
+    }
+    // Records with an unknown rtype only have their `ts_event` shifted
+    let _ = dbn::rtype_dispatch_base!(rec, handler);
+}
+
+/// Shifting the timestamps of a record other than `ts_event`.
+trait ShiftTs {
+    fn shift_ts(&mut self, offset: i64);
+}
+
+macro_rules! impl_shift_ts {
+    ($($rec:ty: [$($field:ident),*]),* $(,)?) => {
+        $(
+            impl ShiftTs for $rec {
+                fn shift_ts(&mut self, _offset: i64) {
+                    $(self.$field = shift(self.$field, _offset);)*
+                }
+            }
+        )*
+    };
+}
+
+impl_shift_ts! {
+    MboMsg: [ts_recv],
+    TradeMsg: [ts_recv],
+    Mbp1Msg: [ts_recv],
+    Mbp10Msg: [ts_recv],
+    BboMsg: [ts_recv],
+    Cmbp1Msg: [ts_recv],
+    CbboMsg: [ts_recv],
+    OhlcvMsg: [],
+    StatusMsg: [ts_recv],
+    ImbalanceMsg: [ts_recv, auction_time],
+    v1::InstrumentDefMsg: [ts_recv, expiration, activation],
+    v2::InstrumentDefMsg: [ts_recv, expiration, activation],
+    InstrumentDefMsg: [ts_recv, expiration, activation],
+    v1::StatMsg: [ts_recv, ts_ref],
+    StatMsg: [ts_recv, ts_ref],
+    v1::SymbolMappingMsg: [start_ts, end_ts],
+    SymbolMappingMsg: [start_ts, end_ts],
+    v1::ErrorMsg: [],
+    ErrorMsg: [],
+    v1::SystemMsg: [],
+    SystemMsg: [],
+}
+
+/// A writer adapter for CSV, TSV, or JSON output that only keeps the selected
+/// columns, in their original order.
+///
+/// For CSV and TSV, the names are matched against the header row, which must be
+/// written. For JSON, they're matched against the keys of each object, including the
+/// keys of the nested record header `hd`.
+pub struct ColumnSelector<W> {
+    writer: W,
+    columns: Vec<String>,
+    format: ColumnFormat,
+    /// The bytes of the current row or object.
+    pending: Vec<u8>,
+    in_string: bool,
+    is_escaped: bool,
+    depth: usize,
+}
+
+enum ColumnFormat {
+    Delimited {
+        delimiter: u8,
+        write_header: bool,
+        /// The indices of the selected columns, set once the header row is read.
+        indices: Option<Vec<usize>>,
+    },
+    Json,
+}
+
+impl<W: io::Write> ColumnSelector<W> {
+    /// Creates a new column selector for CSV or TSV output with `delimiter` or JSON
+    /// output if `delimiter` is `None`. `write_header` controls whether the header row
+    /// of CSV and TSV output is passed through.
+    pub fn new(writer: W, delimiter: Option<u8>, columns: Vec<String>, write_header: bool) -> Self {
+        let format = if let Some(delimiter) = delimiter {
+            ColumnFormat::Delimited {
+                delimiter,
+                write_header,
+                indices: None,
+            }
+        } else {
+            ColumnFormat::Json
+        };
+        Self {
+            writer,
+            columns,
+            format,
+            pending: Vec::new(),
+            in_string: false,
+            is_escaped: false,
+            depth: 0,
+        }
+    }
+
+    fn write_csv_row(&mut self) -> io::Result<()> {
+        let ColumnFormat::Delimited {
+            delimiter,
+            write_header,
+            indices,
+        } = &mut self.format
+        else {
+            unreachable!()
+        };
+        let row = String::from_utf8_lossy(&self.pending);
+        let terminator_len = row.len() - row.trim_end_matches(['\r', '\n']).len();
+        let (row, terminator) = row.split_at(row.len() - terminator_len);
+        let fields = split_csv_row(row, *delimiter);
+        let Some(indices) = indices else {
+            let selected = self
+                .columns
+                .iter()
+                .map(|column| {
+                    fields
+                        .iter()
+                        .position(|field| field == column)
+                        .ok_or_else(|| {
+                            io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!(
+                                    "unknown column '{column}', expected one of {}",
+                                    fields.join(", ")
+                                ),
+                            )
+                        })
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let mut sorted = selected.clone();
+            sorted.sort_unstable();
+            *indices = Some(sorted);
+            if *write_header {
+                let header = join_fields(&fields, indices.as_deref().unwrap(), *delimiter);
+                self.writer.write_all(header.as_bytes())?;
+                self.writer.write_all(terminator.as_bytes())?;
+            }
+            return Ok(());
+        };
+        let row = join_fields(&fields, indices, *delimiter);
+        self.writer.write_all(row.as_bytes())?;
+        self.writer.write_all(terminator.as_bytes())
+    }
+
+    fn write_json_object(&mut self) -> io::Result<()> {
+        let object = String::from_utf8_lossy(&self.pending);
+        let selected = select_json_members(&object, &|key: &str| {
+            if self.columns.iter().any(|column| column == key) {
+                Selection::Keep
+            } else if key == "hd" {
+                Selection::Nested
+            } else {
+                Selection::Drop
+            }
+        })
+        .unwrap_or_else(|| "{}".to_owned());
+        self.writer.write_all(selected.as_bytes())
+    }
+}
+
+impl<W: io::Write> io::Write for ColumnSelector<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            let is_json = matches!(self.format, ColumnFormat::Json);
+            if is_json && self.depth == 0 && byte != b'{' {
+                // Separators between objects
+                self.writer.write_all(&[byte])?;
+                continue;
+            }
+            self.pending.push(byte);
+            if self.in_string {
+                if self.is_escaped {
+                    self.is_escaped = false;
+                } else if is_json && byte == b'\\' {
+                    self.is_escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' if is_json => self.depth += 1,
+                b'}' | b']' if is_json => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        self.write_json_object()?;
+                        self.pending.clear();
+                    }
+                }
+                b'\n' if !is_json => {
+                    self.write_csv_row()?;
+                    self.pending.clear();
+                }
+                _ => {}
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Splits a CSV row into its raw fields, keeping any quoting.
+fn split_csv_row(row: &str, delimiter: u8) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    for c in row.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && c == delimiter as char {
+            fields.push(std::mem::take(&mut field));
+            continue;
+        }
+        field.push(c);
+    }
+    fields.push(field);
+    fields
+}
+
+fn join_fields(fields: &[String], indices: &[usize], delimiter: u8) -> String {
+    indices
+        .iter()
+        .filter_map(|&i| fields.get(i).map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(&(delimiter as char).to_string())
+}
+
+enum Selection {
+    Keep,
+    /// Keep the selected members of a nested object.
+    Nested,
+    Drop,
+}
+
+/// Removes the unselected members of the JSON `object`, preserving its formatting.
+/// Returns `None` if no members are selected.
+fn select_json_members(object: &str, select: &dyn Fn(&str) -> Selection) -> Option<String> {
+    let inner = object.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let members = inner.trim_end();
+    let closing = &inner[members.len()..];
+    let mut selected = Vec::new();
+    for member in split_json_members(members) {
+        let Some((key, value)) = member.split_once(':') else {
+            continue;
+        };
+        match select(key.trim().trim_matches('"')) {
+            Selection::Keep => selected.push(member.to_owned()),
+            Selection::Nested => {
+                let value_start = value.len() - value.trim_start().len();
+                let nested = select_json_members(value, &|key: &str| {
+                    if matches!(select(key), Selection::Keep) {
+                        Selection::Keep
+                    } else {
+                        Selection::Drop
+                    }
+                });
+                if let Some(nested) = nested {
+                    selected.push(format!("{key}:{}{nested}", &value[..value_start]));
+                }
+            }
+            Selection::Drop => {}
+        }
+    }
+    if selected.is_empty() {
+        None
+    } else {
+        Some(format!("{{{}{closing}}}", selected.join(",")))
+    }
+}
+
+/// Splits the members of a JSON object at the commas that aren't nested or within a
+/// string.
+fn split_json_members(members: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut is_escaped = false;
+    for (i, c) in members.char_indices() {
+        if in_string {
+            if is_escaped {
+                is_escaped = false;
+            } else if c == '\\' {
+                is_escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(&members[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !members[start..].trim().is_empty() {
+        split.push(&members[start..]);
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use rstest::*;
+
+    use super::*;
+    use dbn::{rtype, RecordHeader, WithTsOut};
+
+    #[rstest]
+    #[case("1s", 1_000_000_000)]
+    #[case("-1h", -3_600_000_000_000)]
+    #[case("250", 250)]
+    fn test_parse_offset(#[case] s: &str, #[case] exp: i64) {
+        assert_eq!(parse_offset(s).unwrap(), exp);
+    }
+
+    #[rstest]
+    fn test_parse_offset_invalid(#[values("", "-", "--1s", "0", "1y")] s: &str) {
+        assert!(parse_offset(s).is_err());
+    }
+
+    #[rstest]
+    fn test_shift_record(#[values(false, true)] ts_out: bool) {
+        let mbo = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 5, 100),
+            ts_recv: 200,
+            ..Default::default()
+        };
+        let mut rec = if ts_out {
+            RecordBuf::from(WithTsOut::new(mbo, 300))
+        } else {
+            RecordBuf::from(mbo)
+        };
+        shift_record(&mut rec, -50, ts_out);
+        let mbo = rec.get::<MboMsg>().unwrap();
+        assert_eq!(mbo.hd.ts_event, 50);
+        assert_eq!(mbo.ts_recv, 150);
+        if ts_out {
+            assert_eq!(rec.get::<WithTsOut<MboMsg>>().unwrap().ts_out, 250);
+        }
+    }
+
+    #[test]
+    fn test_shift_keeps_null_timestamps() {
+        let mut rec = RecordBuf::from(InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(rtype::INSTRUMENT_DEF, 1, 5, 100),
+            ts_recv: 200,
+            ..Default::default()
+        });
+        shift_record(&mut rec, 1, false);
+        let def = rec.get::<InstrumentDefMsg>().unwrap();
+        assert_eq!(def.ts_recv, 201);
+        assert_eq!(def.expiration, UNDEF_TIMESTAMP);
+    }
+
+    fn select(delimiter: Option<u8>, columns: &[&str], write_header: bool, input: &str) -> String {
+        let mut selector = ColumnSelector::new(
+            Vec::new(),
+            delimiter,
+            columns.iter().map(|c| c.to_string()).collect(),
+            write_header,
+        );
+        // Write one byte at a time to check rows and objects split across writes
+        for byte in input.as_bytes() {
+            selector.write_all(&[*byte]).unwrap();
+        }
+        String::from_utf8(selector.writer).unwrap()
+    }
+
+    #[rstest]
+    #[case::header(true, "price,symbol\n1.5,\"A,B\"\n")]
+    #[case::omit_header(false, "1.5,\"A,B\"\n")]
+    fn test_select_csv(#[case] write_header: bool, #[case] exp: &str) {
+        let input = "ts_event,price,size,symbol\n1,1.5,10,\"A,B\"\n";
+        assert_eq!(
+            select(Some(b','), &["symbol", "price"], write_header, input),
+            exp
+        );
+    }
+
+    #[test]
+    fn test_select_csv_unknown_column() {
+        let mut selector =
+            ColumnSelector::new(Vec::new(), Some(b','), vec!["sym".to_owned()], true);
+        assert!(selector.write_all(b"ts_event,symbol\n").is_err());
+    }
+
+    #[rstest]
+    #[case::compact(
+        "{\"ts_recv\":\"2\",\"hd\":{\"ts_event\":\"1\",\"rtype\":160},\"text\":\"a,}\"}\n",
+        "{\"hd\":{\"ts_event\":\"1\"},\"text\":\"a,}\"}\n"
+    )]
+    #[case::pretty(
+        "{\n    \"ts_recv\": \"2\",\n    \"hd\": {\n        \"ts_event\": \"1\",\n        \"rtype\": 160\n    },\n    \"text\": \"a,}\"\n}\n",
+        "{\n    \"hd\": {\n        \"ts_event\": \"1\"\n    },\n    \"text\": \"a,}\"\n}\n"
+    )]
+    #[case::none_selected("{\"ts_recv\":\"2\"}\n", "{}\n")]
+    fn test_select_json(#[case] input: &str, #[case] exp: &str) {
+        assert_eq!(select(None, &["ts_event", "text"], true, input), exp);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use clap::ArgAction;
+use dbn::{
+    decode::{DbnMetadata, DynDecoder},
+    validate::Validator,
+    VersionUpgradePolicy,
+};
+
+use crate::output;
+
+/// Arguments for the `validate` subcommand.
+#[derive(Debug, clap::Args)]
+pub struct ValidateArgs {
+    #[clap(
+        help = "The DBN file to validate, Zstd-compressed or not",
+        value_name = "FILE"
+    )]
+    pub input: PathBuf,
+    #[clap(
+        short,
+        long,
+        help = "Saves the JSON report to FILE. If no path is specified, the report will be written to standard output",
+        value_name = "FILE"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "The maximum number of diagnostics to include in the report. Problems past the maximum are still counted",
+        default_value_t = Validator::DEFAULT_MAX_DIAGNOSTICS,
+        value_name = "NUM"
+    )]
+    pub max_diagnostics: usize,
+    #[clap(
+        short = 'p',
+        long = "pretty",
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Indent the JSON report"
+    )]
+    pub should_pretty_print: bool,
+    #[clap(
+        short,
+        long,
+        action = ArgAction::SetTrue,
+        default_value = "false",
+        help = "Allow overwriting of existing output file"
+    )]
+    pub force: bool,
+}
+
+/// Validates the records in the input file and writes a JSON report to the output.
+/// Returns an error after writing the report if any problems were found.
+pub fn run(args: &ValidateArgs) -> anyhow::Result<()> {
+    // Check the records as they're stored
+    let mut decoder = DynDecoder::from_file(&args.input, VersionUpgradePolicy::AsIs)
+        .with_context(|| format!("opening '{}' to validate", args.input.display()))?;
+    let mut validator = Validator::new(decoder.metadata())?.max_diagnostics(args.max_diagnostics);
+    validator.validate_decoder(&mut decoder);
+    let report = validator.finish();
+    let mut writer = output(args.output.as_deref(), args.force)?;
+    writeln!(writer, "{}", report.to_json(args.should_pretty_print))?;
+    writer.flush()?;
+    if report.is_valid() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Found {} problem(s) in '{}'",
+            report.diagnostic_count,
+            args.input.display()
+        ))
+    }
+}
@@ -1,11 +1,14 @@
 use std::{
     fs,
     io::{Read, Write},
-    process,
+    mem, process,
 };
 
 use assert_cmd::{cargo::cargo_bin_cmd, Command};
-use dbn::Schema;
+use dbn::{
+    encode::{DbnEncoder, EncodeRecord},
+    rtype, MboMsg, MetadataBuilder, RecordHeader, SType, Schema, TradeMsg,
+};
 use predicates::{
     boolean::PredicateBooleanExt,
     ord::eq,
@@ -115,6 +118,74 @@ fn write_csv(output_dir: TempDir) {
     assert!(contents.ends_with('\n'));
 }
 
+#[rstest]
+fn write_parquet(output_dir: TempDir, #[values(false, true)] zstd: bool) {
+    let output_path = format!("{}/a.parquet", output_dir.path().to_str().unwrap());
+    let mut cmd = cmd();
+    cmd.args([
+        &format!("{TEST_DATA_PATH}/test_data.mbp-1.v3.dbn.zst"),
+        "--output",
+        &output_path,
+        "--map-symbols",
+    ]);
+    if zstd {
+        cmd.arg("--zstd");
+    }
+    cmd.assert().success().stdout(is_empty());
+    let contents = fs::read(output_path).unwrap();
+    // Parquet magic bytes
+    assert!(contents.starts_with(b"PAR1"));
+    assert!(contents.ends_with(b"PAR1"));
+}
+
+#[test]
+fn parquet_to_stdout() {
+    let output = cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.trades.v3.dbn.zst"),
+            "--parquet",
+            "--pretty",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert!(output.starts_with(b"PAR1"));
+    assert!(output.ends_with(b"PAR1"));
+}
+
+#[rstest]
+fn text_round_trip(
+    output_dir: TempDir,
+    #[values("csv", "tsv", "json", "csv.zst")] extension: &str,
+    #[values(false, true)] pretty: bool,
+) {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+    let text_path = format!("{}/a.{extension}", output_dir.path().to_str().unwrap());
+    let dbn_path = format!("{}/a.dbn", output_dir.path().to_str().unwrap());
+    let mut encode_cmd = cmd();
+    encode_cmd.args([&input_path, "--output", &text_path]);
+    if pretty {
+        encode_cmd.arg("--pretty");
+    }
+    encode_cmd.assert().success().stdout(is_empty());
+    cmd()
+        .args([&text_path, "--schema", "mbo", "--output", &dbn_path])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+
+    let expected = cmd().args([&input_path, "--json"]).output().unwrap();
+    let round_tripped = cmd().args([&dbn_path, "--json"]).output().unwrap();
+    assert!(round_tripped.status.success());
+    assert_eq!(
+        String::from_utf8(round_tripped.stdout).unwrap(),
+        String::from_utf8(expected.stdout).unwrap()
+    );
+}
+
 #[rstest]
 fn encoding_overrides_extension(output_dir: TempDir) {
     // output file extension is csv, but the encoding argument is json
@@ -342,35 +413,645 @@ fn read_from_stdin(#[values("csv", "json")] output_enc: &str) {
 }
 
 #[rstest]
-fn convert_dbz_to_dbn(output_dir: TempDir) {
-    let output_path = format!("{}/a.dbn", output_dir.path().to_str().unwrap());
+fn convert_dbz_to_dbn(output_dir: TempDir) {
+    let output_path = format!("{}/a.dbn", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.definition.dbz"),
+            "--dbn",
+            "-o",
+            &output_path,
+        ])
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+#[test]
+fn limit_and_schema_filter_update_metadata() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.ohlcv-1m.v3.dbn.zst"),
+            "--json",
+            "--metadata",
+            "--limit",
+            "1",
+            "--schema",
+            "ohlcv-1d",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(r#""limit":"1""#).and(contains(r#""schema":"ohlcv-1d""#)));
+}
+
+fn csv_index_ts(output: &[u8]) -> Vec<u64> {
+    // the first column of MBO CSV is ts_recv, the index timestamp
+    std::str::from_utf8(output)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap().parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn time_range_filter() {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+    let all = csv_index_ts(&cmd().args([&input_path, "--csv"]).output().unwrap().stdout);
+    assert!(all.len() > 1);
+    let (start, end) = (all[1], all[all.len() - 1] + 1);
+    let output = cmd()
+        .args([
+            &input_path,
+            "--csv",
+            "--start",
+            &start.to_string(),
+            "--end",
+            &end.to_string(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let filtered = csv_index_ts(&output.stdout);
+    assert!(!filtered.is_empty());
+    assert_eq!(
+        filtered,
+        all.into_iter()
+            .filter(|ts| (start..end).contains(ts))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[rstest]
+#[case::unsorted(false, 1)]
+#[case::sorted(true, 0)]
+fn time_range_filter_unsorted_input(
+    output_dir: TempDir,
+    #[case] is_sorted: bool,
+    #[case] exp_count: usize,
+) {
+    let input_path = format!("{}/unsorted.dbn", output_dir.path().to_str().unwrap());
+    let metadata = MetadataBuilder::new()
+        .dataset("XNAS.ITCH")
+        .schema(Some(Schema::Trades))
+        .start(0)
+        .stype_in(Some(SType::InstrumentId))
+        .stype_out(SType::InstrumentId)
+        .build();
+    let mut encoder = DbnEncoder::new(fs::File::create(&input_path).unwrap(), &metadata).unwrap();
+    for ts in [1, 3, 2] {
+        encoder
+            .encode_record(&TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, ts),
+                ts_recv: ts,
+                ..Default::default()
+            })
+            .unwrap();
+    }
+    drop(encoder);
+    let mut args = vec![input_path.as_str(), "--json", "--start", "2", "--end", "3"];
+    if is_sorted {
+        args.push("--sorted");
+    }
+    let output = cmd().args(args).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), exp_count);
+}
+
+#[test]
+fn time_range_filter_updates_metadata() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--json",
+            "--metadata",
+            "--start",
+            "2020-12-28T13:30:00Z",
+            "--end",
+            "1609180000000000000",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#""start":"1609162200000000000""#)
+                .and(contains(r#""end":"1609180000000000000""#)),
+        );
+}
+
+#[rstest]
+#[case::uncompressed("--input-fragment", "dbn.frag")]
+#[case::zstd("--input-zstd-fragment", "dbn.frag.zst")]
+fn time_range_filter_merged_fragments(#[case] input_flag: &str, #[case] extension: &str) {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.definition.v3.{extension}");
+    cmd()
+        .args([&input_path, &input_path, input_flag, "--json", "--end", "1"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+}
+
+#[rstest]
+#[case::symbol("--symbols", "ESH1")]
+#[case::instrument_id("--instrument-ids", "5482")]
+#[case::multiple("--symbols", "ESM1,ESH1")]
+fn instrument_filter(#[case] flag: &str, #[case] value: &str) {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+    let all = cmd().args([&input_path, "--csv"]).output().unwrap();
+    assert!(all.status.success());
+    cmd()
+        .args([&input_path, "--csv", flag, value])
+        .assert()
+        .success()
+        .stdout(eq(String::from_utf8(all.stdout).unwrap()));
+}
+
+#[rstest]
+#[case::instrument_id("--instrument-ids", "31778", 2)]
+// Fragments have no metadata and these have no symbol mapping records to resolve
+// symbols with
+#[case::symbol("--symbols", "GEM3 P9812", 0)]
+fn instrument_filter_merged_fragments(
+    #[case] flag: &str,
+    #[case] value: &str,
+    #[case] exp_count: usize,
+) {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.definition.v3.dbn.frag");
+    let output = cmd()
+        .args([
+            &input_path,
+            &input_path,
+            "--input-fragment",
+            "--json",
+            flag,
+            value,
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), exp_count);
+    assert!(stdout
+        .lines()
+        .all(|line| line.contains(r#""instrument_id":31778"#)));
+}
+
+#[test]
+fn instrument_filter_updates_metadata() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--json",
+            "--metadata",
+            "--symbols",
+            "ESM1",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(r#""symbols":[]"#).and(contains(r#""mappings":[]"#)));
+}
+
+#[test]
+fn instrument_filter_no_match() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--json",
+            "--instrument-ids",
+            "1,2",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+}
+
+#[rstest]
+fn index_then_seek(
+    #[values("test_data.mbo.v3.dbn", "test_data.mbo.v3.dbn.zst")] file_name: &str,
+    output_dir: TempDir,
+) {
+    let input_path = output_dir.path().join(file_name);
+    fs::copy(format!("{TEST_DATA_PATH}/{file_name}"), &input_path).unwrap();
+    let input_path = input_path.to_str().unwrap();
+    let all = csv_index_ts(&cmd().args([input_path, "--csv"]).output().unwrap().stdout);
+    cmd()
+        .args(["index", input_path, "--interval", "1"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    assert!(output_dir.path().join(format!("{file_name}.idx")).exists());
+    for start in all.iter().copied() {
+        let output = cmd()
+            .args([input_path, "--csv", "--start", &start.to_string()])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            csv_index_ts(&output.stdout),
+            all.iter()
+                .copied()
+                .filter(|ts| *ts >= start)
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+#[rstest]
+fn index_output_exists(output_dir: TempDir) {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+    let output_path = format!("{}/a.idx", output_dir.path().to_str().unwrap());
+    fs::write(&output_path, b"").unwrap();
+    cmd()
+        .args(["index", &input_path, "--output", &output_path])
+        .assert()
+        .failure()
+        .stderr(contains("Output file exists"));
+    cmd()
+        .args(["index", &input_path, "--output", &output_path, "--force"])
+        .assert()
+        .success();
+    assert!(fs::metadata(&output_path).unwrap().len() > 0);
+}
+
+#[test]
+fn index_conflicts_with_conversion_args() {
+    cmd()
+        .args([
+            "--csv",
+            "index",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+        ])
+        .assert()
+        .failure();
+}
+
+#[rstest]
+#[case::json("--json", r#""rtype":32"#)]
+#[case::csv(
+    "--csv",
+    "ts_event,rtype,publisher_id,instrument_id,open,high,low,close,volume"
+)]
+fn resample_to_ohlcv(#[case] encoding_flag: &str, #[case] exp: &str) {
+    cmd()
+        .args([
+            "resample",
+            &format!("{TEST_DATA_PATH}/test_data.trades.v3.dbn.zst"),
+            "--to",
+            "ohlcv-1s",
+            encoding_flag,
+        ])
+        .assert()
+        .success()
+        .stdout(contains(exp))
+        .stderr(is_empty());
+}
+
+#[rstest]
+#[case::bbo("test_data.mbp-1.v3.dbn.zst", "bbo-1s", r#""rtype":195"#)]
+#[case::cbbo("test_data.cmbp-1.v3.dbn.zst", "cbbo-1m", r#""rtype":193"#)]
+fn resample_to_bbo(#[case] file_name: &str, #[case] schema: &str, #[case] exp: &str) {
+    cmd()
+        .args([
+            "resample",
+            &format!("{TEST_DATA_PATH}/{file_name}"),
+            "--to",
+            schema,
+            "--json",
+        ])
+        .assert()
+        .success()
+        .stdout(contains(exp))
+        .stderr(is_empty());
+}
+
+#[test]
+fn resample_one_tick_bars() {
+    let input_path = format!("{TEST_DATA_PATH}/test_data.trades.v3.dbn.zst");
+    let trades = cmd().args([&input_path, "--json"]).output().unwrap();
+    assert!(trades.status.success());
+    let bars = cmd()
+        .args(["resample", &input_path, "--ticks", "1", "--json"])
+        .output()
+        .unwrap();
+    assert!(bars.status.success());
+    assert_eq!(
+        String::from_utf8(bars.stdout).unwrap().lines().count(),
+        String::from_utf8(trades.stdout).unwrap().lines().count()
+    );
+}
+
+#[rstest]
+#[case::no_bars(&[])]
+#[case::multiple_bars(&["--to", "ohlcv-1m", "--ticks", "10"])]
+#[case::unsupported_schema(&["--to", "mbo"])]
+#[case::invalid_interval(&["--interval", "5y"])]
+fn resample_invalid_args(#[case] bar_args: &[&str]) {
+    cmd()
+        .args([
+            "resample",
+            &format!("{TEST_DATA_PATH}/test_data.trades.v3.dbn.zst"),
+            "--json",
+        ])
+        .args(bar_args)
+        .assert()
+        .failure();
+}
+
+#[rstest]
+fn validate_test_data(#[values("mbo.v3.dbn.zst", "definition.v2.dbn.zst")] file: &str) {
+    let output = cmd()
+        .args(["validate", &format!("{TEST_DATA_PATH}/test_data.{file}")])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""record_count":"#));
+    assert_eq!(
+        output.status.success(),
+        stdout.contains(r#""valid":true"#),
+        "{stdout}"
+    );
+}
+
+#[rstest]
+fn validate_pretty_to_file(output_dir: TempDir) {
+    let output_path = format!("{}/report.json", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            "validate",
+            &format!("{TEST_DATA_PATH}/test_data.trades.v3.dbn.zst"),
+            "--pretty",
+            "--output",
+            &output_path,
+        ])
+        .assert()
+        .stdout(is_empty());
+    let contents = fs::read_to_string(output_path).unwrap();
+    assert!(contents.contains("\n  \"record_count\": "));
+}
+
+#[rstest]
+fn validate_corrupt_file(output_dir: TempDir) {
+    let input_path = output_dir.path().join("corrupt.dbn");
+    let mut contents = fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn")).unwrap();
+    // Give the second record an impossible length
+    let metadata_len = 8 + u32::from_le_bytes(contents[4..8].try_into().unwrap()) as usize;
+    let record_len = contents[metadata_len] as usize * 4;
+    contents[metadata_len + record_len] = 1;
+    fs::write(&input_path, contents).unwrap();
+    cmd()
+        .args(["validate", input_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(contains(r#""kind":"decode_error""#).and(contains(r#""index":1"#)))
+        .stderr(contains("problem(s)"));
+}
+
+#[test]
+fn diff_identical() {
+    cmd()
+        .args([
+            "diff",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+}
+
+#[rstest]
+fn diff_removed_record(output_dir: TempDir) {
+    let right_path = output_dir.path().join("truncated.dbn");
+    let mut contents = fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn")).unwrap();
+    // Drop the last record
+    let record_len = mem::size_of::<MboMsg>();
+    contents.truncate(contents.len() - record_len);
+    fs::write(&right_path, contents).unwrap();
+    cmd()
+        .args([
+            "diff",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            right_path.to_str().unwrap(),
+            "--json",
+        ])
+        .assert()
+        .code(1)
+        .stdout(
+            contains(r#""identical":false"#)
+                .and(contains(r#""removed_count":1"#))
+                .and(contains(r#""kind":"removed""#)),
+        )
+        .stderr(is_empty());
+    cmd()
+        .args([
+            "diff",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            right_path.to_str().unwrap(),
+        ])
+        .assert()
+        .code(1)
+        .stdout(contains("0 record(s) added, 1 removed, 0 changed"))
+        .stderr(is_empty());
+}
+
+#[rstest]
+fn diff_missing_file_fails(output_dir: TempDir) {
+    cmd()
+        .args([
+            "diff",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            output_dir.path().join("missing.dbn").to_str().unwrap(),
+        ])
+        .assert()
+        .code(2)
+        .stdout(is_empty())
+        .stderr(contains("opening"));
+}
+
+#[rstest]
+fn edit_metadata_in_place(output_dir: TempDir) {
+    let orig_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn");
+    let path = output_dir.path().join("edited.dbn");
+    fs::copy(&orig_path, &path).unwrap();
+    let path = path.to_str().unwrap();
+    cmd()
+        .args([
+            "edit-metadata",
+            path,
+            "--dataset",
+            "XNAS.ITCH",
+            "--stype-in",
+            "parent",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    assert_eq!(
+        fs::metadata(path).unwrap().len(),
+        fs::metadata(&orig_path).unwrap().len()
+    );
+    cmd()
+        .args([path, "--json", "--metadata"])
+        .assert()
+        .success()
+        .stdout(contains("XNAS.ITCH").and(contains("parent")));
+    let orig_records = cmd().args([&orig_path, "--json"]).output().unwrap().stdout;
+    cmd()
+        .args([path, "--json"])
+        .assert()
+        .success()
+        .stdout(eq(orig_records));
+}
+
+#[rstest]
+fn edit_metadata_mappings_zstd(output_dir: TempDir) {
+    let orig_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+    let path = output_dir.path().join("edited.dbn.zst");
+    fs::copy(&orig_path, &path).unwrap();
+    let path = path.to_str().unwrap();
+    let mappings_path = output_dir.path().join("mappings.csv");
+    fs::write(
+        &mappings_path,
+        "raw_symbol,start_date,end_date,symbol\nESH1,2020-12-28,2020-12-29,5482\n",
+    )
+    .unwrap();
+    cmd()
+        .args([
+            "edit-metadata",
+            path,
+            "--mappings",
+            mappings_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty());
+    cmd()
+        .args([path, "--json", "--metadata"])
+        .assert()
+        .success()
+        .stdout(contains(r#""raw_symbol":"ESH1""#).and(contains("5482")));
+    let orig_records = cmd().args([&orig_path, "--json"]).output().unwrap().stdout;
+    cmd()
+        .args([path, "--json"])
+        .assert()
+        .success()
+        .stdout(eq(orig_records));
+}
+
+#[test]
+fn edit_metadata_requires_edit() {
+    cmd()
+        .args([
+            "edit-metadata",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("required"));
+}
+
+#[test]
+fn info_human_readable() {
+    cmd()
+        .args([
+            "info",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+        ])
+        .assert()
+        .success()
+        .stdout(
+            contains("DBN version: 3")
+                .and(contains("Records: 2"))
+                .and(contains("  mbo: 2"))
+                .and(contains("  5482: 2"))
+                .and(contains("bytes compressed, 472 bytes uncompressed")),
+        )
+        .stderr(is_empty());
+}
+
+#[test]
+fn info_json() {
+    cmd()
+        .args([
+            "info",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            "-J",
+            "--gap-threshold",
+            "1us",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#""instrument_counts":{"5482":2}"#)
+                .and(contains(r#""gap_count":1"#))
+                .and(contains(r#""compressed_size":null"#)),
+        )
+        .stderr(is_empty());
+}
+
+#[test]
+fn info_pretty_requires_json() {
+    cmd()
+        .args([
+            "info",
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            "--pretty",
+        ])
+        .assert()
+        .failure();
+}
+
+#[rstest]
+#[case::keep_duplicates(false, 4)]
+#[case::dedup(true, 2)]
+fn sort_out_of_order_file(#[case] dedup: bool, #[case] exp_count: usize, output_dir: TempDir) {
+    let input_path = output_dir.path().join("unsorted.dbn");
+    let output_path = output_dir.path().join("sorted.dbn.zst");
+    let mut contents = fs::read(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn")).unwrap();
+    // Append the records again so they're out of order and duplicated
+    let metadata_len = 8 + u32::from_le_bytes(contents[4..8].try_into().unwrap()) as usize;
+    contents.extend_from_within(metadata_len..);
+    fs::write(&input_path, contents).unwrap();
+    let mut args = vec![
+        "sort",
+        input_path.to_str().unwrap(),
+        "--output",
+        output_path.to_str().unwrap(),
+        "--max-memory",
+        "0",
+    ];
+    if dedup {
+        args.push("--dedup");
+    }
+    cmd().args(&args).assert().success().stdout(is_empty());
     cmd()
-        .args([
-            &format!("{TEST_DATA_PATH}/test_data.definition.dbz"),
-            "--dbn",
-            "-o",
-            &output_path,
-        ])
+        .args(["validate", output_path.to_str().unwrap()])
         .assert()
-        .success()
-        .stderr(is_empty());
+        .success();
+    let index_ts = csv_index_ts(
+        &cmd()
+            .args([output_path.to_str().unwrap(), "--csv"])
+            .output()
+            .unwrap()
+            .stdout,
+    );
+    assert_eq!(index_ts.len(), exp_count);
+    assert!(index_ts.is_sorted());
 }
 
 #[test]
-fn limit_and_schema_filter_update_metadata() {
+fn invalid_start() {
     cmd()
         .args([
-            &format!("{TEST_DATA_PATH}/test_data.ohlcv-1m.v3.dbn.zst"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
             "--json",
-            "--metadata",
-            "--limit",
-            "1",
-            "--schema",
-            "ohlcv-1d",
+            "--start",
+            "yesterday",
         ])
         .assert()
-        .success()
-        .stdout(contains(r#""limit":"1""#).and(contains(r#""schema":"ohlcv-1d""#)));
+        .failure()
+        .stderr(contains("--start").and(contains("ISO 8601")));
 }
 
 #[rstest]
@@ -649,6 +1330,109 @@ fn map_symbols_fails_for_invalid_output(#[case] output_flag: &str) {
         .stderr(contains(format!("'{output_flag}'")).and(contains("'--map-symbols'")));
 }
 
+#[rstest]
+fn shift_ts(output_dir: TempDir) {
+    let orig_path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+    let shifted_path = output_dir.path().join("shifted.dbn");
+    let shifted_path = shifted_path.to_str().unwrap();
+    cmd()
+        .args([&orig_path, "--shift-ts", "1d", "--output", shifted_path])
+        .assert()
+        .success()
+        .stderr(is_empty());
+    let orig_records = cmd().args([&orig_path, "--json"]).output().unwrap().stdout;
+    cmd()
+        .args([shifted_path, "--json"])
+        .assert()
+        .success()
+        .stdout(contains(r#""ts_recv":"160924"#).count(2));
+    cmd()
+        .args([shifted_path, "--shift-ts", "-1d", "--json"])
+        .assert()
+        .success()
+        .stdout(eq(orig_records));
+}
+
+#[rstest]
+fn remap_instrument_ids(output_dir: TempDir) {
+    let map_path = output_dir.path().join("instrument_ids.csv");
+    fs::write(&map_path, "from,to\n5482,1\n").unwrap();
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--json",
+            "--map-symbols",
+            "--remap-instrument-ids",
+            map_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#""instrument_id":1}"#)
+                .count(2)
+                .and(contains(r#""instrument_id":5482"#).not())
+                .and(contains(r#""symbol":"ESH1""#).count(2)),
+        )
+        .stderr(is_empty());
+}
+
+#[rstest]
+#[case::csv("--csv", "ts_event,price,symbol\n")]
+#[case::tsv("--tsv", "ts_event\tprice\tsymbol\n")]
+fn select_columns_text(#[case] output_flag: &str, #[case] exp_header: &str) {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            output_flag,
+            "--columns",
+            "ts_event,symbol,price",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            starts_with(exp_header)
+                .and(contains("\n").count(3))
+                .and(contains("ESH1").count(2)),
+        )
+        .stderr(is_empty());
+}
+
+#[test]
+fn select_columns_json() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--json",
+            "--columns",
+            "price,size",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            contains(r#"{"price":""#)
+                .count(2)
+                .and(contains(r#""size":"#).count(2))
+                .and(contains("ts_recv").not()),
+        )
+        .stderr(is_empty());
+}
+
+#[rstest]
+#[case::unknown("--csv", "unknown column 'bid_px_00'")]
+#[case::dbn("--dbn", "cannot be used with '--columns")]
+fn select_columns_fails(#[case] output_flag: &str, #[case] exp_err: &str) {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            output_flag,
+            "--columns",
+            "bid_px_00",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains(exp_err));
+}
+
 #[test]
 fn passing_current_dbn_version_is_accepted() {
     cmd()
@@ -792,6 +1576,59 @@ fn test_merge_mbo_and_mbp10_metadata() {
         .stderr(is_empty());
 }
 
+#[rstest]
+fn concat_files(output_dir: TempDir) {
+    let output_path = output_dir.path().join("concat.dbn.zst");
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            "--concat",
+            "--output",
+            output_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    cmd()
+        .args([output_path.to_str().unwrap(), "--json"])
+        .assert()
+        .success()
+        .stdout(contains("\"rtype\":160,").count(4));
+}
+
+#[test]
+fn concat_incompatible_schemas() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbp-10.v3.dbn.zst"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            "--concat",
+            "--dbn",
+        ])
+        .assert()
+        .failure()
+        .stdout(is_empty())
+        .stderr(contains("schema of input 1 doesn't match"));
+}
+
+#[test]
+fn concat_conflicts_with_filters() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn"),
+            "--concat",
+            "--dbn",
+            "--limit",
+            "1",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+}
+
 #[rstest]
 fn split_by_day(output_dir: TempDir) {
     let output_pattern = format!("{}/{{date}}.json", output_dir.path().to_str().unwrap());
@@ -878,6 +1715,175 @@ fn split_by_symbol(output_dir: TempDir) {
     assert!(contents.contains("\"rtype\":160"));
 }
 
+#[rstest]
+fn split_by_hour(output_dir: TempDir) {
+    let output_pattern = format!(
+        "{}/{{date}}T{{time}}.json",
+        output_dir.path().to_str().unwrap()
+    );
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "hour",
+            "--output-pattern",
+            &output_pattern,
+            "--json",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    let output_path = format!(
+        "{}/2020-12-28T130000.json",
+        output_dir.path().to_str().unwrap()
+    );
+    let contents = fs::read_to_string(&output_path).unwrap();
+    assert_eq!(contents.lines().count(), 2);
+}
+
+#[rstest]
+fn split_by_duration(output_dir: TempDir) {
+    let output_pattern = format!("{}/{{time}}.json", output_dir.path().to_str().unwrap());
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "duration",
+            "--split-duration",
+            "1us",
+            "--output-pattern",
+            &output_pattern,
+            "--json",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    for time in ["130000.000704000", "130000.000711000"] {
+        let output_path = format!("{}/{time}.json", output_dir.path().to_str().unwrap());
+        let contents = fs::read_to_string(&output_path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+    }
+}
+
+#[rstest]
+#[case::origin(&["--split-origin", "12:30"], "2020-12-28T123000.json")]
+#[case::utc_offset(&["--split-utc-offset", "-05:00"], "2020-12-28T080000.json")]
+#[case::both(
+    &["--split-origin", "07:30", "--split-utc-offset", "-05:00"],
+    "2020-12-28T073000.json"
+)]
+fn split_by_hour_aligned(
+    output_dir: TempDir,
+    #[case] alignment_args: &[&str],
+    #[case] exp_file_name: &str,
+) {
+    let output_pattern = format!(
+        "{}/{{date}}T{{time}}.json",
+        output_dir.path().to_str().unwrap()
+    );
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "hour",
+            "--output-pattern",
+            &output_pattern,
+            "--json",
+        ])
+        .args(alignment_args)
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    let output_path = output_dir.path().join(exp_file_name);
+    let contents = fs::read_to_string(&output_path).unwrap();
+    assert_eq!(contents.lines().count(), 2);
+}
+
+#[test]
+fn split_origin_requires_interval_split() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "day",
+            "--output-pattern",
+            "{date}.json",
+            "--split-origin",
+            "09:30",
+            "--json",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("only valid with '--split-by hour'"));
+}
+
+#[test]
+fn split_by_duration_requires_split_duration() {
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "duration",
+            "--output-pattern",
+            "{time}.json",
+            "--json",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("split-duration"));
+}
+
+#[rstest]
+fn split_by_publisher(output_dir: TempDir) {
+    let output_pattern = format!(
+        "{}/{{publisher_id}}.dbn",
+        output_dir.path().to_str().unwrap()
+    );
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "publisher",
+            "--output-pattern",
+            &output_pattern,
+            "--dbn",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    let output_path = format!("{}/1.dbn", output_dir.path().to_str().unwrap());
+    let contents = std::fs::read(&output_path).unwrap();
+    assert_eq!(&contents[..3], b"DBN");
+}
+
+#[rstest]
+fn split_by_instrument(output_dir: TempDir) {
+    let output_pattern = format!(
+        "{}/{{instrument_id}}.json",
+        output_dir.path().to_str().unwrap()
+    );
+    cmd()
+        .args([
+            &format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"),
+            "--split-by",
+            "instrument",
+            "--output-pattern",
+            &output_pattern,
+            "--json",
+        ])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+    let output_path = format!("{}/5482.json", output_dir.path().to_str().unwrap());
+    let contents = fs::read_to_string(&output_path).unwrap();
+    assert_eq!(contents.lines().count(), 2);
+}
+
 #[test]
 fn output_pattern_requires_split_by() {
     cmd()
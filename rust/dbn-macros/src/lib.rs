@@ -59,6 +59,41 @@ pub fn derive_json_serialize(input: TokenStream) -> TokenStream {
     serialize::derive_json_macro_impl(input)
 }
 
+/// Derive macro for Arrow serialization.
+///
+/// Supports the following `dbn` attributes:
+/// - `c_char`: serializes the field as a single-character string
+/// - `encode_order`: overrides the position of the field in the Arrow schema
+/// - `fixed_price`: serializes the field as fixed-price, with the output type
+///   depending on `PRETTY_PX`
+/// - `skip`: does not serialize the field
+/// - `unix_nanos`: serializes the field as a UNIX timestamp, with the output type
+///   depending on `PRETTY_TS`
+///
+/// Note: fields beginning with `_` will automatically be skipped, e.g. `_reserved`
+/// isn't serialized.
+#[proc_macro_derive(ArrowSerialize, attributes(dbn))]
+pub fn derive_arrow_serialize(input: TokenStream) -> TokenStream {
+    serialize::derive_arrow_macro_impl(input)
+}
+
+/// Derive macro for deserializing records from CSV and JSON.
+///
+/// Supports the following `dbn` attributes:
+/// - `c_char`: deserializes the field from a single, possibly escaped, character
+/// - `fixed_price`: deserializes the field from either a fixed-precision integer or a
+///   decimal
+/// - `skip`: does not deserialize the field
+/// - `unix_nanos`: deserializes the field from either a UNIX nanosecond timestamp or
+///   an ISO 8601 datetime
+///
+/// Note: fields beginning with `_` will automatically be skipped, e.g. `_reserved`
+/// isn't deserialized.
+#[proc_macro_derive(TextDeserialize, attributes(dbn))]
+pub fn derive_text_deserialize(input: TokenStream) -> TokenStream {
+    serialize::derive_text_deserialize_macro_impl(input)
+}
+
 /// Derive macro for field descriptions exposed to Python.
 ///
 /// Supports the following `dbn` attributes:
@@ -93,6 +93,95 @@ pub fn derive_json_macro_impl(input: proc_macro::TokenStream) -> proc_macro::Tok
         .into()
 }
 
+pub fn derive_arrow_macro_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input as DeriveInput);
+
+    if let Data::Struct(data_struct) = data {
+        if let syn::Fields::Named(fields) = data_struct.fields {
+            let crate_name = crate_name();
+            let fields = match get_sorted_fields(fields) {
+                Ok(fields) => fields,
+                Err(ts) => {
+                    return ts.into_compile_error().into();
+                }
+            };
+            let serialize_schema_fields = fields
+                .iter()
+                .map(write_arrow_schema_token_stream)
+                .collect::<syn::Result<Vec<_>>>()
+                .unwrap_or_else(|e| vec![syn::Error::to_compile_error(&e)]);
+            let serialize_fields = fields
+                .iter()
+                .map(write_arrow_field_token_stream)
+                .collect::<syn::Result<Vec<_>>>()
+                .unwrap_or_else(|e| vec![syn::Error::to_compile_error(&e)]);
+            return quote! {
+                impl #crate_name::encode::arrow::serialize::ArrowSerialize for #ident {
+                    fn serialize_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+                        fields: &mut ::std::vec::Vec<::arrow_schema::Field>,
+                    ) {
+                        use #crate_name::encode::arrow::serialize::WriteField;
+
+                        #(#serialize_schema_fields)*
+                    }
+
+                    fn serialize_to<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+                        &self,
+                        columns: &mut #crate_name::encode::arrow::serialize::Columns,
+                    ) {
+                        use #crate_name::encode::arrow::serialize::WriteField;
+
+                        #(#serialize_fields)*
+                    }
+                }
+            }
+            .into();
+        }
+    }
+    syn::Error::new(ident.span(), "Can only derive ArrowSerialize for structs")
+        .into_compile_error()
+        .into()
+}
+
+pub fn derive_text_deserialize_macro_impl(
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input as DeriveInput);
+
+    if let Data::Struct(data_struct) = data {
+        if let syn::Fields::Named(fields) = data_struct.fields {
+            let fields = match get_sorted_fields(fields) {
+                Ok(fields) => fields,
+                Err(ts) => {
+                    return ts.into_compile_error().into();
+                }
+            };
+            let deserialize_fields = fields
+                .iter()
+                .map(read_text_field_token_stream)
+                .collect::<syn::Result<Vec<_>>>()
+                .unwrap_or_else(|e| vec![syn::Error::to_compile_error(&e)]);
+            return quote! {
+                impl crate::decode::text::TextDeserialize for #ident {
+                    fn deserialize_from<F: crate::decode::text::TextFields + ?Sized>(
+                        &mut self,
+                        fields: &F,
+                    ) -> crate::Result<()> {
+                        use crate::decode::text::ReadField;
+
+                        #(#deserialize_fields)*
+                        Ok(())
+                    }
+                }
+            }
+            .into();
+        }
+    }
+    syn::Error::new(ident.span(), "Can only derive TextDeserialize for structs")
+        .into_compile_error()
+        .into()
+}
+
 fn write_csv_header_token_stream(field: &Field) -> TokenStream {
     let ident = field.ident.as_ref().unwrap();
     let field_type = &field.ty;
@@ -169,6 +258,103 @@ fn write_json_field_token_stream(field: &Field) -> syn::Result<TokenStream> {
     }
 }
 
+fn write_arrow_schema_token_stream(field: &Field) -> syn::Result<TokenStream> {
+    let ident = field.ident.as_ref().unwrap();
+    let field_type = &field.ty;
+    // ignore dummy fields
+    if is_hidden(field) {
+        return Ok(quote! {});
+    }
+    if let Some(dbn_attr_id) = find_dbn_serialize_attr(field)? {
+        if dbn_attr_id == UNIX_NANOS_ATTR {
+            Ok(quote! {
+                crate::encode::arrow::serialize::write_ts_schema::<PRETTY_TS>(fields, stringify!(#ident));
+            })
+        } else if dbn_attr_id == FIXED_PRICE_ATTR {
+            Ok(quote! {
+                crate::encode::arrow::serialize::write_px_schema::<PRETTY_PX>(fields, stringify!(#ident));
+            })
+        } else if dbn_attr_id == C_CHAR_ATTR {
+            Ok(quote! {
+                crate::encode::arrow::serialize::write_c_char_schema(fields, stringify!(#ident));
+            })
+        } else {
+            Err(syn::Error::new(
+                dbn_attr_id.span(),
+                format!("Invalid attr `{dbn_attr_id}` passed to `#[dbn]`"),
+            ))
+        }
+    } else {
+        Ok(quote! {
+            <#field_type>::write_schema::<PRETTY_PX, PRETTY_TS>(fields, stringify!(#ident));
+        })
+    }
+}
+
+fn write_arrow_field_token_stream(field: &Field) -> syn::Result<TokenStream> {
+    let ident = field.ident.as_ref().unwrap();
+    // ignore dummy fields
+    if is_hidden(field) {
+        return Ok(quote! {});
+    }
+    if let Some(dbn_attr_id) = find_dbn_serialize_attr(field)? {
+        if dbn_attr_id == UNIX_NANOS_ATTR {
+            Ok(quote! {
+                crate::encode::arrow::serialize::write_ts_field::<PRETTY_TS>(columns, self.#ident);
+            })
+        } else if dbn_attr_id == FIXED_PRICE_ATTR {
+            Ok(quote! {
+                crate::encode::arrow::serialize::write_px_field::<PRETTY_PX>(columns, self.#ident);
+            })
+        } else if dbn_attr_id == C_CHAR_ATTR {
+            Ok(quote! {
+                crate::encode::arrow::serialize::write_c_char_field(columns, self.#ident);
+            })
+        } else {
+            Err(syn::Error::new(
+                dbn_attr_id.span(),
+                format!("Invalid attr `{dbn_attr_id}` passed to `#[dbn]`"),
+            ))
+        }
+    } else {
+        Ok(quote! {
+            self.#ident.write_field::<PRETTY_PX, PRETTY_TS>(columns);
+        })
+    }
+}
+
+fn read_text_field_token_stream(field: &Field) -> syn::Result<TokenStream> {
+    let ident = field.ident.as_ref().unwrap();
+    // ignore dummy fields
+    if is_hidden(field) {
+        return Ok(quote! {});
+    }
+    if let Some(dbn_attr_id) = find_dbn_serialize_attr(field)? {
+        if dbn_attr_id == UNIX_NANOS_ATTR {
+            Ok(quote! {
+                crate::decode::text::read_ts_field(fields, stringify!(#ident), &mut self.#ident)?;
+            })
+        } else if dbn_attr_id == FIXED_PRICE_ATTR {
+            Ok(quote! {
+                crate::decode::text::read_px_field(fields, stringify!(#ident), &mut self.#ident)?;
+            })
+        } else if dbn_attr_id == C_CHAR_ATTR {
+            Ok(quote! {
+                crate::decode::text::read_c_char_field(fields, stringify!(#ident), &mut self.#ident)?;
+            })
+        } else {
+            Err(syn::Error::new(
+                dbn_attr_id.span(),
+                format!("Invalid attr `{dbn_attr_id}` passed to `#[dbn]`"),
+            ))
+        }
+    } else {
+        Ok(quote! {
+            self.#ident.read_field(fields, stringify!(#ident))?;
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use syn::FieldsNamed;
@@ -185,8 +371,13 @@ mod tests {
         assert_eq!(fields.named.len(), 1);
         let csv_generated = write_csv_field_token_stream(fields.named.first().unwrap()).unwrap();
         let json_generated = write_json_field_token_stream(fields.named.first().unwrap()).unwrap();
+        let arrow_generated =
+            write_arrow_field_token_stream(fields.named.first().unwrap()).unwrap();
+        let text_generated = read_text_field_token_stream(fields.named.first().unwrap()).unwrap();
         assert!(csv_generated.is_empty());
         assert!(json_generated.is_empty());
+        assert!(arrow_generated.is_empty());
+        assert!(text_generated.is_empty());
     }
 
     #[test]
@@ -198,7 +389,12 @@ mod tests {
         assert_eq!(fields.named.len(), 1);
         let csv_generated = write_csv_field_token_stream(fields.named.first().unwrap()).unwrap();
         let json_generated = write_json_field_token_stream(fields.named.first().unwrap()).unwrap();
+        let arrow_generated =
+            write_arrow_field_token_stream(fields.named.first().unwrap()).unwrap();
+        let text_generated = read_text_field_token_stream(fields.named.first().unwrap()).unwrap();
         assert!(csv_generated.is_empty());
         assert!(json_generated.is_empty());
+        assert!(arrow_generated.is_empty());
+        assert!(text_generated.is_empty());
     }
 }
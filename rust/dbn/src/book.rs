@@ -0,0 +1,880 @@
+//! Order book reconstruction from market-by-order (MBO) data.
+//!
+//! A [`Book`] maintains both the individual resting orders and the aggregated price
+//! levels for a single instrument from a single publisher. A [`Market`] holds a
+//! [`Book`] for every `(instrument_id, publisher_id)` pair seen in a stream and can be
+//! fed directly from any [`DecodeRecordRef`].
+//!
+//! Books are only guaranteed to be consistent after applying a record with the
+//! [`LAST`](crate::flags::LAST) flag set. See [`Book::is_consistent()`].
+//!
+//! # Example
+//! ```no_run
+//! use dbn::{book::Market, decode::DbnDecoder};
+//!
+//! let mut decoder = DbnDecoder::from_zstd_file("20241007.mbo.dbn.zst")?;
+//! let mut market = Market::new();
+//! market.replay(&mut decoder)?;
+//! for ((instrument_id, publisher_id), book) in market.iter() {
+//!     let (bid, ask) = book.bbo();
+//!     println!("{instrument_id} {publisher_id}: {bid:?} x {ask:?}");
+//! }
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    decode::DecodeRecordRef, rtype, Action, BboMsg, BidAskPair, Error, MboMsg, Mbp10Msg, Mbp1Msg,
+    RecordHeader, RecordRef, Result, Schema, Side, UNDEF_PRICE,
+};
+
+/// An aggregated price level in an order book.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PriceLevel {
+    /// The price of the level where every 1 unit corresponds to 1e-9, i.e.
+    /// 1/1,000,000,000 or 0.000000001.
+    pub price: i64,
+    /// The total resting quantity at the level.
+    pub size: u32,
+    /// The number of resting orders at the level.
+    pub count: u32,
+}
+
+/// The position of a resting order within the queue at its price level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueuePosition {
+    /// The side of the book the order rests on.
+    pub side: Side,
+    /// The price of the order.
+    pub price: i64,
+    /// The number of orders ahead of the order at the same price level.
+    pub orders_ahead: usize,
+    /// The total quantity ahead of the order at the same price level.
+    pub size_ahead: u64,
+}
+
+/// A price-level and order-level book for a single instrument from a single
+/// publisher, reconstructed from [`MboMsg`] records.
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    orders_by_id: HashMap<u64, (Side, i64)>,
+    bids: BTreeMap<i64, Vec<MboMsg>>,
+    offers: BTreeMap<i64, Vec<MboMsg>>,
+    last_update: Option<MboMsg>,
+    last_trade: Option<MboMsg>,
+}
+
+impl PriceLevel {
+    fn from_orders(price: i64, orders: &[MboMsg]) -> Self {
+        Self {
+            price,
+            size: orders.iter().map(|o| o.size).sum(),
+            count: orders.len() as u32,
+        }
+    }
+}
+
+#[allow(clippy::clone_on_copy)]
+impl Book {
+    /// Creates a new, empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a single MBO record to the book.
+    ///
+    /// Trades, fills, and records with no action don't change the state of the book,
+    /// however the most recent trade is retained for [`Self::to_bbo()`].
+    ///
+    /// Data that starts mid-session without a snapshot can reference orders that
+    /// were never added. Cancels for unknown orders are ignored and modifies for
+    /// unknown orders are treated as adds, so the book converges once it's cleared
+    /// or the missing orders are replaced.
+    ///
+    /// # Errors
+    /// This function returns an error if `mbo` has an invalid action or side, if it
+    /// would cancel more than the resting size of an order, or if it would add an
+    /// order with an order ID already in the book.
+    pub fn apply(&mut self, mbo: &MboMsg) -> Result<()> {
+        match mbo.action()? {
+            Action::Clear => self.clear(),
+            Action::Add => self.add(mbo)?,
+            Action::Cancel => self.cancel(mbo)?,
+            Action::Modify => self.modify(mbo)?,
+            Action::Trade => self.last_trade = Some(mbo.clone()),
+            Action::Fill | Action::None => {}
+        }
+        self.last_update = Some(mbo.clone());
+        Ok(())
+    }
+
+    /// Removes all orders and price levels from the book.
+    pub fn clear(&mut self) {
+        self.orders_by_id.clear();
+        self.bids.clear();
+        self.offers.clear();
+    }
+
+    /// Returns `true` if there are no resting orders on either side of the book.
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.offers.is_empty()
+    }
+
+    /// Returns `true` if the last applied record completed an event, i.e. it had the
+    /// [`LAST`](crate::flags::LAST) flag set. Books are only guaranteed to be
+    /// consistent at event boundaries, including the end of a snapshot.
+    pub fn is_consistent(&self) -> bool {
+        self.last_update
+            .as_ref()
+            .is_none_or(|mbo| mbo.flags.is_last())
+    }
+
+    /// Returns `true` if the last applied record was part of a snapshot, i.e. it had
+    /// the [`SNAPSHOT`](crate::flags::SNAPSHOT) flag set.
+    pub fn is_snapshot(&self) -> bool {
+        self.last_update
+            .as_ref()
+            .is_some_and(|mbo| mbo.flags.is_snapshot())
+    }
+
+    /// Returns the last record applied to the book, if any.
+    pub fn last_update(&self) -> Option<&MboMsg> {
+        self.last_update.as_ref()
+    }
+
+    /// Returns the best bid and best offer levels.
+    pub fn bbo(&self) -> (Option<PriceLevel>, Option<PriceLevel>) {
+        (self.bid_level(0), self.ask_level(0))
+    }
+
+    /// Returns the bid level at `idx`, where `0` is the best bid.
+    pub fn bid_level(&self, idx: usize) -> Option<PriceLevel> {
+        self.bids().nth(idx)
+    }
+
+    /// Returns the ask level at `idx`, where `0` is the best offer.
+    pub fn ask_level(&self, idx: usize) -> Option<PriceLevel> {
+        self.asks().nth(idx)
+    }
+
+    /// Returns an iterator over the bid levels from best (highest) to worst price.
+    pub fn bids(&self) -> impl Iterator<Item = PriceLevel> + '_ {
+        self.bids
+            .iter()
+            .rev()
+            .map(|(price, orders)| PriceLevel::from_orders(*price, orders))
+    }
+
+    /// Returns an iterator over the ask levels from best (lowest) to worst price.
+    pub fn asks(&self) -> impl Iterator<Item = PriceLevel> + '_ {
+        self.offers
+            .iter()
+            .map(|(price, orders)| PriceLevel::from_orders(*price, orders))
+    }
+
+    /// Returns the top `N` levels of the book as [`BidAskPair`]s. Missing levels
+    /// have [`UNDEF_PRICE`] prices and zero sizes and counts.
+    pub fn depth<const N: usize>(&self) -> [BidAskPair; N] {
+        let mut levels: [BidAskPair; N] = std::array::from_fn(|_| BidAskPair::default());
+        for (level, bid) in levels.iter_mut().zip(self.bids()) {
+            level.bid_px = bid.price;
+            level.bid_sz = bid.size;
+            level.bid_ct = bid.count;
+        }
+        for (level, ask) in levels.iter_mut().zip(self.asks()) {
+            level.ask_px = ask.price;
+            level.ask_sz = ask.size;
+            level.ask_ct = ask.count;
+        }
+        levels
+    }
+
+    /// Returns the resting orders at `price` on `side` in priority order.
+    pub fn orders_at(&self, side: Side, price: i64) -> &[MboMsg] {
+        self.side_levels(side)
+            .and_then(|levels| levels.get(&price))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the resting order with `order_id`, if it's in the book.
+    pub fn order(&self, order_id: u64) -> Option<&MboMsg> {
+        let (side, price) = self.orders_by_id.get(&order_id)?;
+        self.orders_at(*side, *price)
+            .iter()
+            .find(|o| o.order_id == order_id)
+    }
+
+    /// Returns the queue position of the resting order with `order_id`, if it's in the
+    /// book.
+    pub fn queue_position(&self, order_id: u64) -> Option<QueuePosition> {
+        let (side, price) = *self.orders_by_id.get(&order_id)?;
+        let orders = self.orders_at(side, price);
+        let orders_ahead = orders.iter().position(|o| o.order_id == order_id)?;
+        Some(QueuePosition {
+            side,
+            price,
+            orders_ahead,
+            size_ahead: orders[..orders_ahead]
+                .iter()
+                .map(|o| u64::from(o.size))
+                .sum(),
+        })
+    }
+
+    /// Creates an [`Mbp1Msg`] from the current state of the book, with the event
+    /// fields taken from `mbo`, the last record applied to the book.
+    pub fn to_mbp1(&self, mbo: &MboMsg) -> Mbp1Msg {
+        Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(
+                rtype::MBP_1,
+                mbo.hd.publisher_id,
+                mbo.hd.instrument_id,
+                mbo.hd.ts_event,
+            ),
+            price: mbo.price,
+            size: mbo.size,
+            action: mbo.action,
+            side: mbo.side,
+            flags: mbo.flags,
+            depth: self.depth_of(mbo),
+            ts_recv: mbo.ts_recv,
+            ts_in_delta: mbo.ts_in_delta,
+            sequence: mbo.sequence,
+            levels: self.depth(),
+        }
+    }
+
+    /// Creates an [`Mbp10Msg`] from the current state of the book, with the event
+    /// fields taken from `mbo`, the last record applied to the book.
+    pub fn to_mbp10(&self, mbo: &MboMsg) -> Mbp10Msg {
+        Mbp10Msg {
+            hd: RecordHeader::new::<Mbp10Msg>(
+                rtype::MBP_10,
+                mbo.hd.publisher_id,
+                mbo.hd.instrument_id,
+                mbo.hd.ts_event,
+            ),
+            price: mbo.price,
+            size: mbo.size,
+            action: mbo.action,
+            side: mbo.side,
+            flags: mbo.flags,
+            depth: self.depth_of(mbo),
+            ts_recv: mbo.ts_recv,
+            ts_in_delta: mbo.ts_in_delta,
+            sequence: mbo.sequence,
+            levels: self.depth(),
+        }
+    }
+
+    /// Creates a [`BboMsg`] from the current state of the book for the interval
+    /// ending at `ts_recv`. `rtype` must be either [`rtype::BBO_1S`] or
+    /// [`rtype::BBO_1M`]. Returns `None` if no records have been applied to the book.
+    ///
+    /// # Errors
+    /// This function returns an error if `rtype` isn't a BBO rtype.
+    pub fn to_bbo(&self, rtype: u8, ts_recv: u64) -> Result<Option<BboMsg>> {
+        if !matches!(rtype, rtype::BBO_1S | rtype::BBO_1M) {
+            return Err(Error::BadArgument {
+                param_name: "rtype".to_owned(),
+                desc: format!("{rtype:#04X} is not a BBO rtype"),
+            });
+        }
+        let Some(last_update) = self.last_update.as_ref() else {
+            return Ok(None);
+        };
+        let mut bbo = BboMsg {
+            hd: RecordHeader::new::<BboMsg>(
+                rtype,
+                last_update.hd.publisher_id,
+                last_update.hd.instrument_id,
+                last_update.hd.ts_event,
+            ),
+            flags: last_update.flags,
+            ts_recv,
+            sequence: last_update.sequence,
+            levels: self.depth(),
+            ..BboMsg::default_for_schema(Schema::Bbo1S)
+        };
+        if let Some(trade) = self.last_trade.as_ref() {
+            bbo.price = trade.price;
+            bbo.size = trade.size;
+            bbo.side = trade.side;
+        }
+        Ok(Some(bbo))
+    }
+
+    fn side_levels(&self, side: Side) -> Option<&BTreeMap<i64, Vec<MboMsg>>> {
+        match side {
+            Side::Bid => Some(&self.bids),
+            Side::Ask => Some(&self.offers),
+            Side::None => None,
+        }
+    }
+
+    fn side_levels_mut(&mut self, side: Side) -> Result<&mut BTreeMap<i64, Vec<MboMsg>>> {
+        match side {
+            Side::Bid => Ok(&mut self.bids),
+            Side::Ask => Ok(&mut self.offers),
+            Side::None => Err(Error::BadArgument {
+                param_name: "mbo".to_owned(),
+                desc: "book update with side None".to_owned(),
+            }),
+        }
+    }
+
+    fn depth_of(&self, mbo: &MboMsg) -> u8 {
+        let Ok(side) = mbo.side() else {
+            return 0;
+        };
+        let position = match side {
+            Side::Bid => self.bids.keys().rev().position(|px| *px == mbo.price),
+            Side::Ask => self.offers.keys().position(|px| *px == mbo.price),
+            Side::None => None,
+        };
+        position
+            .map(|pos| pos.min(u8::MAX as usize) as u8)
+            .unwrap_or(0)
+    }
+
+    fn add(&mut self, mbo: &MboMsg) -> Result<()> {
+        let side = mbo.side()?;
+        // Top-of-book publishers send aggregated levels instead of individual orders
+        if mbo.flags.is_tob() {
+            let levels = self.side_levels_mut(side)?;
+            levels.clear();
+            if mbo.price != UNDEF_PRICE {
+                levels.insert(mbo.price, vec![mbo.clone()]);
+            }
+            return Ok(());
+        }
+        if self.orders_by_id.contains_key(&mbo.order_id) {
+            return Err(Error::BadArgument {
+                param_name: "mbo".to_owned(),
+                desc: format!("order ID {} is already in the book", mbo.order_id),
+            });
+        }
+        self.side_levels_mut(side)?
+            .entry(mbo.price)
+            .or_default()
+            .push(mbo.clone());
+        self.orders_by_id.insert(mbo.order_id, (side, mbo.price));
+        Ok(())
+    }
+
+    fn cancel(&mut self, mbo: &MboMsg) -> Result<()> {
+        let Some(&(side, price)) = self.orders_by_id.get(&mbo.order_id) else {
+            // Cancels for unknown orders are ignored
+            return Ok(());
+        };
+        let order = self
+            .order_mut(side, price, mbo.order_id)
+            .ok_or_else(|| missing_order(mbo.order_id))?;
+        let Some(remaining) = order.size.checked_sub(mbo.size) else {
+            return Err(Error::BadArgument {
+                param_name: "mbo".to_owned(),
+                desc: format!(
+                    "cancel size {} exceeds resting size {} of order ID {}",
+                    mbo.size, order.size, mbo.order_id
+                ),
+            });
+        };
+        order.size = remaining;
+        if remaining == 0 {
+            self.remove_order(side, price, mbo.order_id)?;
+            self.orders_by_id.remove(&mbo.order_id);
+        }
+        Ok(())
+    }
+
+    fn modify(&mut self, mbo: &MboMsg) -> Result<()> {
+        let Some(&(prev_side, prev_price)) = self.orders_by_id.get(&mbo.order_id) else {
+            // Modifies for unknown orders are treated as adds
+            return self.add(mbo);
+        };
+        let side = mbo.side()?;
+        let prev_size = self
+            .order_mut(prev_side, prev_price, mbo.order_id)
+            .ok_or_else(|| missing_order(mbo.order_id))?
+            .size;
+        if prev_side != side || prev_price != mbo.price || prev_size < mbo.size {
+            // Order loses priority
+            self.remove_order(prev_side, prev_price, mbo.order_id)?;
+            self.side_levels_mut(side)?
+                .entry(mbo.price)
+                .or_default()
+                .push(mbo.clone());
+            self.orders_by_id.insert(mbo.order_id, (side, mbo.price));
+        } else {
+            let order = self
+                .order_mut(side, mbo.price, mbo.order_id)
+                .ok_or_else(|| missing_order(mbo.order_id))?;
+            order.size = mbo.size;
+            order.flags = mbo.flags;
+            order.ts_recv = mbo.ts_recv;
+        }
+        Ok(())
+    }
+
+    fn order_mut(&mut self, side: Side, price: i64, order_id: u64) -> Option<&mut MboMsg> {
+        self.side_levels_mut(side)
+            .ok()?
+            .get_mut(&price)?
+            .iter_mut()
+            .find(|o| o.order_id == order_id)
+    }
+
+    fn remove_order(&mut self, side: Side, price: i64, order_id: u64) -> Result<()> {
+        let levels = self.side_levels_mut(side)?;
+        let level = levels
+            .get_mut(&price)
+            .ok_or_else(|| missing_order(order_id))?;
+        let idx = level
+            .iter()
+            .position(|o| o.order_id == order_id)
+            .ok_or_else(|| missing_order(order_id))?;
+        level.remove(idx);
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+        Ok(())
+    }
+}
+
+fn missing_order(order_id: u64) -> Error {
+    Error::BadArgument {
+        param_name: "mbo".to_owned(),
+        desc: format!("order ID {order_id} is not in the book"),
+    }
+}
+
+/// Order books for every instrument and publisher in a stream of MBO data.
+///
+/// Books are kept separate per `(instrument_id, publisher_id)` because the same
+/// instrument can be traded on multiple venues within a dataset.
+#[derive(Debug, Clone, Default)]
+pub struct Market {
+    books: HashMap<(u32, u16), Book>,
+}
+
+impl Market {
+    /// Creates a new market with no books.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the book for `instrument_id` from `publisher_id`, if any records have
+    /// been applied for it.
+    pub fn book(&self, instrument_id: u32, publisher_id: u16) -> Option<&Book> {
+        self.books.get(&(instrument_id, publisher_id))
+    }
+
+    /// Returns an iterator over all books and their `(instrument_id, publisher_id)`
+    /// keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&(u32, u16), &Book)> {
+        self.books.iter()
+    }
+
+    /// Returns the best bid and offer for `instrument_id` aggregated across all
+    /// publishers. Sizes and counts are summed across publishers quoting the same
+    /// best price.
+    pub fn aggregated_bbo(&self, instrument_id: u32) -> (Option<PriceLevel>, Option<PriceLevel>) {
+        let mut best_bid: Option<PriceLevel> = None;
+        let mut best_ask: Option<PriceLevel> = None;
+        let books = self
+            .books
+            .iter()
+            .filter(|((iid, _), _)| *iid == instrument_id)
+            .map(|(_, book)| book);
+        for book in books {
+            let (bid, ask) = book.bbo();
+            if let Some(bid) = bid {
+                best_bid = Some(match best_bid {
+                    Some(best) if best.price > bid.price => best,
+                    Some(best) if best.price == bid.price => merge_levels(best, bid),
+                    _ => bid,
+                });
+            }
+            if let Some(ask) = ask {
+                best_ask = Some(match best_ask {
+                    Some(best) if best.price < ask.price => best,
+                    Some(best) if best.price == ask.price => merge_levels(best, ask),
+                    _ => ask,
+                });
+            }
+        }
+        (best_bid, best_ask)
+    }
+
+    /// Applies `mbo` to the book for its instrument and publisher, returning the
+    /// updated book.
+    ///
+    /// # Errors
+    /// This function returns an error if the record can't be applied to the book.
+    /// See [`Book::apply()`].
+    pub fn apply(&mut self, mbo: &MboMsg) -> Result<&Book> {
+        let book = self
+            .books
+            .entry((mbo.hd.instrument_id, mbo.hd.publisher_id))
+            .or_default();
+        book.apply(mbo)?;
+        Ok(book)
+    }
+
+    /// Applies `record` to the corresponding book if it's an [`MboMsg`], otherwise
+    /// it's ignored and `None` is returned.
+    ///
+    /// # Errors
+    /// This function returns an error if the record can't be applied to the book.
+    /// See [`Book::apply()`].
+    pub fn apply_record(&mut self, record: RecordRef) -> Result<Option<&Book>> {
+        match record.get::<MboMsg>() {
+            Some(mbo) => self.apply(mbo).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Applies all remaining records from `decoder`, ignoring non-MBO records.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to decode a record or a record
+    /// can't be applied to its book.
+    pub fn replay<D: DecodeRecordRef>(&mut self, decoder: &mut D) -> Result<()> {
+        while let Some(record) = decoder.decode_record_ref()? {
+            self.apply_record(record)?;
+        }
+        Ok(())
+    }
+}
+
+fn merge_levels(a: PriceLevel, b: PriceLevel) -> PriceLevel {
+    PriceLevel {
+        price: a.price,
+        size: a.size + b.size,
+        count: a.count + b.count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use std::ffi::c_char;
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder, DecodeRecord},
+        flags, FlagSet,
+    };
+
+    const PUBLISHER: u16 = 1;
+    const INSTRUMENT: u32 = 5482;
+
+    fn mbo(action: Action, side: Side, order_id: u64, price: i64, size: u32) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, PUBLISHER, INSTRUMENT, 1),
+            order_id,
+            price,
+            size,
+            flags: FlagSet::empty().set_last(),
+            action: u8::from(action) as c_char,
+            side: u8::from(side) as c_char,
+            ..Default::default()
+        }
+    }
+
+    fn book_with(records: &[MboMsg]) -> Book {
+        let mut book = Book::new();
+        for rec in records {
+            book.apply(rec).unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_add_and_bbo() {
+        let book = book_with(&[
+            mbo(Action::Add, Side::Bid, 1, 100, 10),
+            mbo(Action::Add, Side::Bid, 2, 100, 5),
+            mbo(Action::Add, Side::Bid, 3, 99, 7),
+            mbo(Action::Add, Side::Ask, 4, 101, 3),
+            mbo(Action::Add, Side::Ask, 5, 102, 4),
+        ]);
+        assert_eq!(
+            book.bbo(),
+            (
+                Some(PriceLevel {
+                    price: 100,
+                    size: 15,
+                    count: 2
+                }),
+                Some(PriceLevel {
+                    price: 101,
+                    size: 3,
+                    count: 1
+                })
+            )
+        );
+        assert_eq!(book.bid_level(1).unwrap().price, 99);
+        assert_eq!(book.ask_level(1).unwrap().price, 102);
+        assert!(book.bid_level(2).is_none());
+    }
+
+    #[test]
+    fn test_depth() {
+        let book = book_with(&[
+            mbo(Action::Add, Side::Bid, 1, 100, 10),
+            mbo(Action::Add, Side::Ask, 2, 101, 3),
+            mbo(Action::Add, Side::Ask, 3, 103, 4),
+        ]);
+        let levels = book.depth::<3>();
+        assert_eq!(levels[0].bid_px, 100);
+        assert_eq!(levels[0].ask_px, 101);
+        assert_eq!(levels[1].bid_px, UNDEF_PRICE);
+        assert_eq!(levels[1].bid_sz, 0);
+        assert_eq!(levels[1].ask_px, 103);
+        assert_eq!(levels[1].ask_ct, 1);
+        assert_eq!(levels[2], BidAskPair::default());
+    }
+
+    #[rstest]
+    #[case::partial(4, Some(6))]
+    #[case::full(10, None)]
+    fn test_cancel(#[case] cancel_size: u32, #[case] exp_size: Option<u32>) {
+        let mut book = book_with(&[mbo(Action::Add, Side::Bid, 1, 100, 10)]);
+        book.apply(&mbo(Action::Cancel, Side::Bid, 1, 100, cancel_size))
+            .unwrap();
+        assert_eq!(book.order(1).map(|o| o.size), exp_size);
+        assert_eq!(book.bid_level(0).map(|l| l.size), exp_size);
+    }
+
+    #[test]
+    fn test_cancel_unknown_order() {
+        let mut book = book_with(&[mbo(Action::Add, Side::Bid, 1, 100, 10)]);
+        let cancel = mbo(Action::Cancel, Side::Bid, 2, 100, 1);
+        book.apply(&cancel).unwrap();
+        assert_eq!(book.bid_level(0).unwrap().size, 10);
+        assert_eq!(book.last_update(), Some(&cancel));
+    }
+
+    #[test]
+    fn test_cancel_exceeds_resting_size() {
+        let mut book = book_with(&[mbo(Action::Add, Side::Bid, 1, 100, 10)]);
+        assert!(matches!(
+            book.apply(&mbo(Action::Cancel, Side::Bid, 1, 100, 11)),
+            Err(Error::BadArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn test_add_duplicate_order() {
+        let mut book = book_with(&[mbo(Action::Add, Side::Bid, 1, 100, 10)]);
+        book.apply(&mbo(Action::Add, Side::Bid, 1, 100, 5))
+            .unwrap_err();
+        assert_eq!(book.order(1).unwrap().size, 10);
+        assert_eq!(book.bid_level(0).unwrap().count, 1);
+    }
+
+    #[rstest]
+    #[case::size_decrease(100, 5, 1, 10)]
+    #[case::size_increase(100, 15, 2, 15)]
+    #[case::price_change(101, 5, 0, 0)]
+    fn test_modify_priority(
+        #[case] new_price: i64,
+        #[case] new_size: u32,
+        #[case] exp_orders_ahead: usize,
+        #[case] exp_size_ahead: u64,
+    ) {
+        let mut book = book_with(&[
+            mbo(Action::Add, Side::Bid, 1, 100, 10),
+            mbo(Action::Add, Side::Bid, 2, 100, 10),
+            mbo(Action::Add, Side::Bid, 3, 100, 5),
+        ]);
+        assert_eq!(book.queue_position(2).unwrap().orders_ahead, 1);
+        book.apply(&mbo(Action::Modify, Side::Bid, 2, new_price, new_size))
+            .unwrap();
+        let position = book.queue_position(2).unwrap();
+        assert_eq!(position.price, new_price);
+        assert_eq!(position.orders_ahead, exp_orders_ahead);
+        assert_eq!(position.size_ahead, exp_size_ahead);
+        assert_eq!(book.order(2).unwrap().size, new_size);
+    }
+
+    #[test]
+    fn test_modify_unknown_order_is_add() {
+        let book = book_with(&[mbo(Action::Modify, Side::Ask, 1, 100, 10)]);
+        assert_eq!(book.order(1).unwrap().size, 10);
+    }
+
+    #[test]
+    fn test_trade_and_fill_dont_change_book() {
+        let book = book_with(&[
+            mbo(Action::Add, Side::Ask, 1, 100, 10),
+            mbo(Action::Trade, Side::Bid, 0, 100, 2),
+            mbo(Action::Fill, Side::Ask, 1, 100, 2),
+        ]);
+        assert_eq!(book.ask_level(0).unwrap().size, 10);
+        let bbo = book.to_bbo(rtype::BBO_1S, 2).unwrap().unwrap();
+        assert_eq!(bbo.price, 100);
+        assert_eq!(bbo.size, 2);
+        assert_eq!(bbo.side, b'B' as c_char);
+        assert_eq!(bbo.levels[0].ask_px, 100);
+        assert_eq!(bbo.ts_recv, 2);
+    }
+
+    #[test]
+    fn test_to_bbo_bad_rtype() {
+        let book = Book::new();
+        assert!(book.to_bbo(rtype::BBO_1M, 0).unwrap().is_none());
+        book.to_bbo(rtype::MBP_1, 0).unwrap_err();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut clear = mbo(Action::Clear, Side::None, 0, UNDEF_PRICE, 0);
+        clear.flags = FlagSet::empty().set_snapshot();
+        let mut first = mbo(Action::Add, Side::Bid, 2, 100, 1);
+        first.flags = FlagSet::empty().set_snapshot();
+        let mut last = mbo(Action::Add, Side::Ask, 3, 101, 1);
+        last.flags = FlagSet::new(flags::SNAPSHOT | flags::LAST);
+
+        let mut book = book_with(&[mbo(Action::Add, Side::Bid, 1, 99, 10)]);
+        assert!(book.is_consistent());
+        book.apply(&clear).unwrap();
+        assert!(book.is_empty());
+        assert!(book.is_snapshot());
+        book.apply(&first).unwrap();
+        assert!(!book.is_consistent());
+        book.apply(&last).unwrap();
+        assert!(book.is_consistent());
+        assert!(book.order(1).is_none());
+        assert_eq!(book.bbo().0.unwrap().price, 100);
+        assert_eq!(book.bbo().1.unwrap().price, 101);
+    }
+
+    #[test]
+    fn test_tob() {
+        let mut rec = mbo(Action::Add, Side::Bid, 0, 100, 10);
+        rec.flags = FlagSet::empty().set_tob().set_last();
+        let mut book = book_with(&[rec.clone()]);
+        rec.price = 101;
+        book.apply(&rec).unwrap();
+        assert_eq!(book.bids().count(), 1);
+        assert_eq!(book.bid_level(0).unwrap().price, 101);
+        rec.price = UNDEF_PRICE;
+        book.apply(&rec).unwrap();
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test_to_mbp1() {
+        let add = mbo(Action::Add, Side::Bid, 2, 99, 5);
+        let book = book_with(&[
+            mbo(Action::Add, Side::Bid, 1, 100, 10),
+            mbo(Action::Add, Side::Ask, 3, 101, 3),
+            add.clone(),
+        ]);
+        let mbp1 = book.to_mbp1(&add);
+        assert_eq!(mbp1.hd.rtype, rtype::MBP_1);
+        assert_eq!(mbp1.hd.instrument_id, INSTRUMENT);
+        assert_eq!(mbp1.depth, 1);
+        assert_eq!(mbp1.price, 99);
+        assert_eq!(mbp1.levels[0].bid_px, 100);
+        assert_eq!(mbp1.levels[0].ask_px, 101);
+        let mbp10 = book.to_mbp10(&add);
+        assert_eq!(mbp10.hd.rtype, rtype::MBP_10);
+        assert_eq!(mbp10.levels[1].bid_px, 99);
+        assert_eq!(mbp10.levels[1].ask_px, UNDEF_PRICE);
+    }
+
+    #[test]
+    fn test_market_separates_publishers() {
+        let mut market = Market::new();
+        let mut rec = mbo(Action::Add, Side::Bid, 1, 100, 10);
+        market.apply(&rec).unwrap();
+        rec.hd.publisher_id = PUBLISHER + 1;
+        rec.size = 5;
+        market.apply(&rec).unwrap();
+        rec.hd.instrument_id = INSTRUMENT + 1;
+        market.apply(&rec).unwrap();
+
+        assert_eq!(market.iter().count(), 3);
+        assert_eq!(
+            market
+                .book(INSTRUMENT, PUBLISHER)
+                .unwrap()
+                .order(1)
+                .unwrap()
+                .size,
+            10
+        );
+        assert_eq!(
+            market.aggregated_bbo(INSTRUMENT).0,
+            Some(PriceLevel {
+                price: 100,
+                size: 15,
+                count: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_replay_matches_mbp1() {
+        let mut mbo_decoder =
+            DbnDecoder::from_file(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn")).unwrap();
+        let mbp1_decoder =
+            DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.mbp-1.v3.dbn.zst"))
+                .unwrap();
+        let expected = mbp1_decoder.decode_records::<Mbp1Msg>().unwrap();
+        assert_eq!(expected.len(), 2);
+        // Neither file includes a snapshot, so seed the book with the state before the
+        // first MBP-1 update
+        let first = &expected[0];
+        let (bid_px, ask_px) = (first.levels[0].bid_px, first.levels[0].ask_px);
+        let mut snapshot = vec![mbo(Action::Clear, Side::None, 0, UNDEF_PRICE, 0)];
+        snapshot.extend((1..=14).map(|id| mbo(Action::Add, Side::Bid, id, bid_px, 1)));
+        snapshot.push(mbo(Action::Add, Side::Bid, 15, bid_px, 10));
+        snapshot.extend((16..=22).map(|id| mbo(Action::Add, Side::Ask, id, ask_px, 1)));
+        snapshot.push(mbo(Action::Add, Side::Ask, 23, ask_px, 3));
+        for rec in snapshot.iter_mut() {
+            rec.flags = FlagSet::empty().set_snapshot();
+        }
+        snapshot.last_mut().unwrap().flags.set_last();
+        let mut market = Market::new();
+        for rec in snapshot.iter() {
+            market.apply(rec).unwrap();
+        }
+        // The MBO test data consists of cancels for orders that aren't in the book
+        market.replay(&mut mbo_decoder).unwrap();
+        let book = market.book(INSTRUMENT, PUBLISHER).unwrap();
+        assert_eq!(book.bid_level(0).unwrap().size, 24);
+        assert_eq!(book.ask_level(0).unwrap().size, 10);
+
+        for (order_id, exp) in (100..).zip(expected.iter()) {
+            let add = MboMsg {
+                hd: RecordHeader::new::<MboMsg>(
+                    rtype::MBO,
+                    exp.hd.publisher_id,
+                    exp.hd.instrument_id,
+                    exp.hd.ts_event,
+                ),
+                order_id,
+                price: exp.price,
+                size: exp.size,
+                flags: exp.flags,
+                action: exp.action,
+                side: exp.side,
+                ts_recv: exp.ts_recv,
+                ts_in_delta: exp.ts_in_delta,
+                sequence: exp.sequence,
+                ..Default::default()
+            };
+            let book = market.apply(&add).unwrap();
+            assert_eq!(book.to_mbp1(&add), *exp);
+        }
+    }
+}
@@ -52,7 +52,7 @@ use std::os::raw::c_char;
 use dbn_macros::MockPyo3;
 
 use crate::{
-    macros::{dbn_record, CsvSerialize, JsonSerialize},
+    macros::{dbn_record, CsvSerialize, JsonSerialize, TextDeserialize},
     rtype, RecordHeader, SecurityUpdateAction, UserDefinedInstrument,
 };
 
@@ -60,7 +60,8 @@ use crate::{
 
 /// An error message from the Databento Live Subscription Gateway (LSG) in DBN version 1.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -79,7 +80,8 @@ pub struct ErrorMsgV1 {
 /// A definition of an instrument in DBN version 1. The record of the
 /// [`Definition`](crate::Schema::Definition) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -316,7 +318,8 @@ pub struct InstrumentDefMsgV1 {
 /// A statistics message. A catchall for various data disseminated by publishers. The
 /// [`stat_type`](Self::stat_type) indicates the statistic contained in the message.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -374,7 +377,8 @@ pub struct StatMsgV1 {
 
 /// A symbol mapping message from the live API in DBN version 1.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -408,7 +412,8 @@ pub struct SymbolMappingMsgV1 {
 /// A non-error message from the Databento Live Subscription Gateway (LSG) in DBN version 1.
 /// Also used for heartbeating.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -427,7 +432,8 @@ pub struct SystemMsgV1 {
 /// A definition of an instrument in DBN version 2. The record of the
 /// [`Definition`](crate::Schema::Definition) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
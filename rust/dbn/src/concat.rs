@@ -0,0 +1,254 @@
+//! Concatenation of DBN inputs that are already in order.
+//!
+//! Unlike the [`MergeDecoder`](crate::decode::MergeDecoder), which decodes every record
+//! to merge the inputs by timestamp, [`concat()`] merges only the metadata with
+//! [`Metadata::merge()`] and then copies the record bytes of each input in the order
+//! they're given without parsing them. This makes it much faster for inputs that don't
+//! overlap in time, such as a directory of daily files.
+//!
+//! Because the records are copied as-is, all inputs must have the same DBN version,
+//! `schema`, `dataset`, `stype_out`, and `ts_out`.
+//!
+//! # Example
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use dbn::concat::concat_files;
+//!
+//! let output = File::create("202401.trades.dbn").unwrap();
+//! concat_files(
+//!     &["20240102.trades.dbn.zst", "20240103.trades.dbn.zst"],
+//!     output,
+//! )?;
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{io, path::Path};
+
+use crate::{
+    decode::{self, DbnMetadataDecoder, DynReader},
+    encode::dbn::MetadataEncoder,
+    Error, Metadata, Result, VersionUpgradePolicy,
+};
+
+/// Concatenates the DBN `inputs` to `writer`, writing a single merged metadata header
+/// followed by the records of each input in order. Each input must be uncompressed
+/// DBN; see [`concat_files()`] for reading compressed files. Returns the merged
+/// metadata.
+///
+/// # Errors
+/// This function returns an error if `inputs` is empty, an input isn't DBN, the
+/// metadata of the inputs are incompatible, or it fails to read from an input or
+/// write to `writer`.
+pub fn concat<R, W>(mut inputs: Vec<R>, mut writer: W) -> Result<Metadata>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let mut metadatas = inputs
+        .iter_mut()
+        .map(read_metadata)
+        .collect::<Result<Vec<_>>>()?;
+    if metadatas.is_empty() {
+        return Err(Error::BadArgument {
+            param_name: "inputs".to_owned(),
+            desc: "none provided".to_owned(),
+        });
+    }
+    let first = metadatas.remove(0);
+    for (i, metadata) in metadatas.iter().enumerate() {
+        check_compatible(&first, metadata, i + 1)?;
+    }
+    let metadata = first.merge(metadatas)?;
+    MetadataEncoder::new(&mut writer).encode(&metadata)?;
+    for (i, input) in inputs.iter_mut().enumerate() {
+        io::copy(input, &mut writer)
+            .map_err(|e| Error::io(e, format!("copying records of input {i}")))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| Error::io(e, "flushing output"))?;
+    Ok(metadata)
+}
+
+/// Concatenates the DBN files at `paths`, Zstd-compressed or not, to `writer`. See
+/// [`concat()`].
+///
+/// # Errors
+/// This function returns an error if `paths` is empty, it fails to open one of the
+/// files, a file isn't DBN, the metadata of the files are incompatible, or it fails to
+/// read from a file or write to `writer`.
+pub fn concat_files<P, W>(paths: &[P], writer: W) -> Result<Metadata>
+where
+    P: AsRef<Path>,
+    W: io::Write,
+{
+    let inputs = paths
+        .iter()
+        .map(DynReader::from_file)
+        .collect::<Result<Vec<_>>>()?;
+    concat(inputs, writer)
+}
+
+/// Reads the metadata of a DBN input, leaving `reader` positioned at the first record.
+pub(crate) fn read_metadata<R: io::Read>(reader: &mut R) -> Result<Metadata> {
+    // The prefix, version, and length of the metadata
+    let mut buf = vec![0; 8];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|e| Error::io(e, "reading metadata prelude"))?;
+    if !decode::dbn::starts_with_prefix(&buf) {
+        return Err(Error::decode("input isn't DBN"));
+    }
+    let length = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
+    buf.resize(8 + length, 0);
+    reader
+        .read_exact(&mut buf[8..])
+        .map_err(|e| Error::io(e, "reading metadata"))?;
+    // The metadata must match the records, which are copied as-is
+    DbnMetadataDecoder::with_upgrade_policy(buf.as_slice(), VersionUpgradePolicy::AsIs).decode()
+}
+
+/// Checks that the records of the input at `index` can be concatenated to those of the
+/// first input without being rewritten.
+fn check_compatible(first: &Metadata, metadata: &Metadata, index: usize) -> Result<()> {
+    let mismatch = |field: &str, first: String, other: String| {
+        Err(Error::BadArgument {
+            param_name: "inputs".to_owned(),
+            desc: format!(
+                "{field} of input {index} doesn't match the first input: {other} vs {first}"
+            ),
+        })
+    };
+    if first.version != metadata.version {
+        return mismatch(
+            "DBN version",
+            first.version.to_string(),
+            metadata.version.to_string(),
+        );
+    }
+    if first.schema != metadata.schema {
+        return mismatch(
+            "schema",
+            format!("{:?}", first.schema),
+            format!("{:?}", metadata.schema),
+        );
+    }
+    if first.dataset != metadata.dataset {
+        return mismatch("dataset", first.dataset.clone(), metadata.dataset.clone());
+    }
+    if first.stype_out != metadata.stype_out {
+        return mismatch(
+            "stype_out",
+            first.stype_out.to_string(),
+            metadata.stype_out.to_string(),
+        );
+    }
+    if first.ts_out != metadata.ts_out {
+        return mismatch(
+            "ts_out",
+            first.ts_out.to_string(),
+            metadata.ts_out.to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder, DbnMetadata, DecodeRecord, DynDecoder},
+        encode::{DbnEncoder, EncodeRecord},
+        rtype, MboMsg, RecordHeader, SType, Schema,
+    };
+
+    // 2023-07-03 00:00 UTC
+    const TS: u64 = 1_688_342_400_000_000_000;
+
+    fn input(metadata: &Metadata, ts: &[u64]) -> Cursor<Vec<u8>> {
+        let mut encoder = DbnEncoder::new(Vec::new(), metadata).unwrap();
+        for ts in ts {
+            encoder
+                .encode_record(&MboMsg {
+                    hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 1, *ts),
+                    ts_recv: *ts,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        Cursor::new(encoder.get_ref().clone())
+    }
+
+    fn metadata(start: u64) -> Metadata {
+        Metadata::builder()
+            .dataset("XNAS.ITCH")
+            .schema(Some(Schema::Mbo))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(start)
+            .symbols(vec![start.to_string()])
+            .build()
+    }
+
+    #[test]
+    fn test_concat() {
+        let inputs = vec![
+            input(&metadata(TS), &[TS, TS + 1]),
+            input(&metadata(TS + 10), &[TS + 10]),
+            input(&metadata(TS + 20), &[]),
+        ];
+        let mut output = Vec::new();
+        let metadata = concat(inputs, &mut output).unwrap();
+        assert_eq!(metadata.start, TS);
+        assert_eq!(metadata.symbols.len(), 3);
+        let mut decoder = DbnDecoder::new(output.as_slice()).unwrap();
+        assert_eq!(decoder.metadata(), &metadata);
+        let mut ts = Vec::new();
+        while let Some(rec) = decoder.decode_record::<MboMsg>().unwrap() {
+            ts.push(rec.ts_recv);
+        }
+        assert_eq!(ts, [TS, TS + 1, TS + 10]);
+    }
+
+    #[test]
+    fn test_concat_files_matches_original() {
+        let path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+        let mut output = Vec::new();
+        concat_files(&[&path, &path], &mut output).unwrap();
+        let records = DbnDecoder::new(output.as_slice())
+            .unwrap()
+            .decode_records::<MboMsg>()
+            .unwrap();
+        let expected = DynDecoder::from_file(&path, VersionUpgradePolicy::AsIs)
+            .unwrap()
+            .decode_records::<MboMsg>()
+            .unwrap();
+        assert_eq!(records, [expected.clone(), expected].concat());
+    }
+
+    #[rstest]
+    #[case::schema(|m: &mut Metadata| m.schema = Some(Schema::Trades), "schema")]
+    #[case::dataset(|m: &mut Metadata| m.dataset = "GLBX.MDP3".to_owned(), "dataset")]
+    #[case::stype_out(|m: &mut Metadata| m.stype_out = SType::RawSymbol, "stype_out")]
+    #[case::ts_out(|m: &mut Metadata| m.ts_out = true, "ts_out")]
+    fn test_concat_incompatible(#[case] modify: fn(&mut Metadata), #[case] field: &str) {
+        let mut other = metadata(TS + 10);
+        modify(&mut other);
+        let inputs = vec![input(&metadata(TS), &[TS]), input(&other, &[])];
+        let err = concat(inputs, Vec::new()).unwrap_err();
+        assert!(
+            matches!(&err, Error::BadArgument { desc, .. } if desc.starts_with(field)),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_concat_no_inputs() {
+        assert!(concat(Vec::<&[u8]>::new(), Vec::new()).is_err());
+    }
+}
@@ -1,4 +1,5 @@
-//! Decoding DBN and Zstd-compressed DBN files and streams.
+//! Decoding DBN and Zstd-compressed DBN files and streams, as well as CSV and JSON
+//! text back into DBN records.
 //!
 //! The primary entry point is [`DbnDecoder`], which reads DBN data from any
 //! [`io::Read`](std::io::Read) source (files, network streams, in-memory buffers).
@@ -8,6 +9,9 @@
 //! Sync decoders implement the [`DecodeDbn`] trait. With the `async` feature flag,
 //! async variants are also available.
 //!
+//! [`CsvDecoder`] and [`JsonDecoder`] parse the output of the corresponding encoders
+//! with either raw or pretty prices and timestamps.
+//!
 //! # Examples
 //!
 //! Decode a DBN file, dispatching on record type:
@@ -38,6 +42,7 @@
 //! println!("{} MBO records", records.len());
 //! # Ok::<(), dbn::Error>(())
 //! ```
+pub mod csv;
 pub mod dbn;
 // Having any tests in a deprecated module emits many warnings that can't be silenced, see
 // https://github.com/rust-lang/rust/issues/47238
@@ -51,21 +56,28 @@ pub mod dbn;
 pub mod dbz;
 mod dyn_decoder;
 mod dyn_reader;
+mod filter;
+pub mod json;
 mod merge;
 mod stream;
+pub(crate) mod text;
 // used in databento_dbn
 #[doc(hidden)]
 pub mod zstd;
 
 // Re-exports
+pub use self::csv::{Decoder as CsvDecoder, DecoderBuilder as CsvDecoderBuilder};
 pub use self::dbn::{
     Decoder as DbnDecoder, MetadataDecoder as DbnMetadataDecoder, RecordDecoder as DbnRecordDecoder,
 };
+pub use self::json::{Decoder as JsonDecoder, DecoderBuilder as JsonDecoderBuilder};
 #[doc(inline)]
 pub use dyn_decoder::DynDecoder;
 #[doc(inline)]
 pub use dyn_reader::*;
 #[doc(inline)]
+pub use filter::{InstrumentFilter, TimeRangeFilter};
+#[doc(inline)]
 pub use merge::{Decoder as MergeDecoder, RecordDecoder as MergeRecordDecoder};
 #[doc(inline)]
 pub use stream::StreamIterDecoder;
@@ -308,8 +320,11 @@ pub use self::dbn::{
     AsyncDecoder as AsyncDbnDecoder, AsyncMetadataDecoder as AsyncDbnMetadataDecoder,
     AsyncRecordDecoder as AsyncDbnRecordDecoder,
 };
+#[cfg(feature = "async")]
+#[doc(inline)]
+pub use merge::{AsyncDecoder as AsyncMergeDecoder, AsyncRecordDecoder as AsyncMergeRecordDecoder};
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     pub const TEST_DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/data");
 }
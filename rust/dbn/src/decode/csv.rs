@@ -0,0 +1,5 @@
+//! Decoding DBN records from comma-separated values (CSV).
+
+mod sync;
+
+pub use sync::{Decoder, DecoderBuilder};
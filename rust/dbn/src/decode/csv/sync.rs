@@ -0,0 +1,453 @@
+use std::{collections::HashMap, fs::File, io, path::Path};
+
+use crate::{
+    decode::{
+        private::LastRecord, text::TextFields, DbnMetadata, DecodeRecord, DecodeRecordRef,
+        DecodeStream, StreamIterDecoder,
+    },
+    HasRType, MboMsg, Metadata, MetadataBuilder, RecordBuf, RecordRef, Result, SType, Schema,
+    DBN_VERSION,
+};
+
+/// Type for decoding DBN records from CSV or other text-delimited tabular formats
+/// including TSV (tab-separated values), such as the output of
+/// [`CsvEncoder`](crate::encode::CsvEncoder).
+///
+/// The input must begin with a header row. Columns are matched to record fields by
+/// name, so the column order doesn't matter and unknown columns like "symbol" are
+/// ignored. Fields without a column keep the default value for the record type.
+/// Prices and timestamps can be either raw integers or the pretty decimal and
+/// ISO 8601 forms.
+///
+/// The record type of each row is determined by its `rtype` column or, if there's no
+/// such column, by the schema set with [`DecoderBuilder::schema()`]. If there's a
+/// "ts_out" column, every record will be decoded with `ts_out`.
+pub struct Decoder<R>
+where
+    R: io::Read,
+{
+    reader: csv::Reader<R>,
+    metadata: Metadata,
+    columns: HashMap<String, usize>,
+    row: csv::StringRecord,
+    buf: RecordBuf,
+    has_decoded: bool,
+}
+
+/// Helper for constructing a CSV [`Decoder`].
+///
+/// No fields are required.
+pub struct DecoderBuilder<R>
+where
+    R: io::Read,
+{
+    reader: R,
+    schema: Option<Schema>,
+    version: u8,
+    delimiter: u8,
+}
+
+impl<R> DecoderBuilder<R>
+where
+    R: io::Read,
+{
+    /// Creates a new CSV decoder builder.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            schema: None,
+            version: DBN_VERSION,
+            delimiter: b',',
+        }
+    }
+
+    /// Sets the schema of the records, which is used when the input has no `rtype`
+    /// column. Also sets the schema in the decoder's [`Metadata`].
+    pub fn schema(mut self, schema: Option<Schema>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Sets the DBN version of the records, which determines the record type for
+    /// schemas whose fields have changed between versions such as definitions and
+    /// statistics.
+    ///
+    /// If not specified, defaults to [`DBN_VERSION`].
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the field delimiter. Defaults to `b','` for comma-separated values (CSV).
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Creates the new decoder with the previously specified settings and reads the
+    /// header row.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to read the header row or `version`
+    /// is invalid.
+    pub fn build(self) -> Result<Decoder<R>> {
+        if self.version == 0 || self.version > DBN_VERSION {
+            return Err(crate::Error::BadArgument {
+                param_name: "version".to_owned(),
+                desc: format!("must be between 1 and {DBN_VERSION}"),
+            });
+        }
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(true)
+            .from_reader(self.reader);
+        let columns: HashMap<_, _> = reader
+            .headers()
+            .map_err(csv_err)?
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_owned(), i))
+            .collect();
+        let metadata = MetadataBuilder::new()
+            .dataset(String::new())
+            .schema(self.schema)
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .version(self.version)
+            .ts_out(columns.contains_key("ts_out"))
+            .build();
+        Ok(Decoder {
+            reader,
+            metadata,
+            columns,
+            row: csv::StringRecord::new(),
+            buf: RecordBuf::from(MboMsg::default()),
+            has_decoded: false,
+        })
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: io::Read,
+{
+    /// Creates a builder for configuring a `Decoder` object.
+    pub fn builder(reader: R) -> DecoderBuilder<R> {
+        DecoderBuilder::new(reader)
+    }
+
+    /// Creates a new CSV [`Decoder`] from `reader` and reads the header row.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to read the header row.
+    pub fn new(reader: R) -> Result<Self> {
+        Self::builder(reader).build()
+    }
+
+    /// Returns a mutable reference to the inner reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+
+    /// Returns an immutable reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        self.reader.get_ref()
+    }
+
+    /// Consumes the decoder and returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl Decoder<io::BufReader<File>> {
+    /// Creates a CSV [`Decoder`] from the file at `path`.
+    ///
+    /// # Errors
+    /// This function returns an error if it is unable to read the file at `path` or
+    /// if it is unable to read the header row.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref()).map_err(|e| {
+            crate::Error::io(
+                e,
+                format!(
+                    "opening CSV file to decode at path '{}'",
+                    path.as_ref().display()
+                ),
+            )
+        })?;
+        Self::new(io::BufReader::new(file))
+    }
+}
+
+struct Row<'a> {
+    columns: &'a HashMap<String, usize>,
+    row: &'a csv::StringRecord,
+}
+
+impl TextFields for Row<'_> {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.columns.get(name).and_then(|&i| self.row.get(i))
+    }
+}
+
+fn csv_err(e: csv::Error) -> crate::Error {
+    crate::Error::decode(format!("failed to read CSV: {e}"))
+}
+
+impl<R> DecodeRecordRef for Decoder<R>
+where
+    R: io::Read,
+{
+    fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
+        if !self.reader.read_record(&mut self.row).map_err(csv_err)? {
+            return Ok(None);
+        }
+        let row = Row {
+            columns: &self.columns,
+            row: &self.row,
+        };
+        super::super::text::decode_record(
+            &mut self.buf,
+            &row,
+            self.metadata.schema,
+            self.metadata.version,
+            self.metadata.ts_out,
+        )
+        .map_err(|e| match (e, self.row.position()) {
+            (crate::Error::Decode(msg), Some(pos)) => {
+                crate::Error::decode(format!("{msg} on line {}", pos.line()))
+            }
+            (e, _) => e,
+        })?;
+        self.has_decoded = true;
+        Ok(Some(self.buf.as_rec_ref()))
+    }
+}
+
+impl<R> DecodeRecord for Decoder<R>
+where
+    R: io::Read,
+{
+    fn decode_record<T: HasRType>(&mut self) -> Result<Option<&T>> {
+        self.decode_record_ref()?
+            .map(|rec| rec.try_get::<T>())
+            .transpose()
+    }
+}
+
+impl<R> DbnMetadata for Decoder<R>
+where
+    R: io::Read,
+{
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+}
+
+impl<R> DecodeStream for Decoder<R>
+where
+    R: io::Read,
+{
+    fn decode_stream<T: HasRType>(self) -> StreamIterDecoder<Self, T> {
+        StreamIterDecoder::new(self)
+    }
+}
+
+impl<R> LastRecord for Decoder<R>
+where
+    R: io::Read,
+{
+    fn last_record(&self) -> Option<RecordRef<'_>> {
+        self.has_decoded.then(|| self.buf.as_rec_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use std::ffi::c_char;
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        encode::{CsvEncoder, EncodeDbn, EncodeRecord, EncodeRecordTextExt},
+        rtype, FlagSet, MboMsg, Mbp10Msg, RecordHeader, VersionUpgradePolicy, WithTsOut,
+    };
+
+    #[rstest]
+    fn test_round_trip_test_data(
+        #[values(
+            ("mbo", 3),
+            ("mbp-1", 3),
+            ("mbp-10", 3),
+            ("tbbo", 3),
+            ("trades", 3),
+            ("ohlcv-1s", 3),
+            ("imbalance", 3),
+            ("statistics", 3),
+            ("status", 3),
+            ("definition", 3),
+            ("bbo-1s", 3),
+            ("cbbo-1s", 3),
+            ("cmbp-1", 3),
+            ("definition", 1),
+            ("statistics", 1),
+            ("definition", 2)
+        )]
+        schema_version: (&str, u8),
+        #[values(b',', b'\t')] delimiter: u8,
+    ) {
+        let (schema, version) = schema_version;
+        let path = format!("{TEST_DATA_PATH}/test_data.{schema}.v{version}.dbn.zst");
+        let mut decoder = DbnDecoder::from_zstd_file(&path).unwrap();
+        decoder
+            .set_upgrade_policy(VersionUpgradePolicy::AsIs)
+            .unwrap();
+        let mut csv = Vec::new();
+        CsvEncoder::builder(&mut csv)
+            .delimiter(delimiter)
+            .version(version)
+            .schema(decoder.metadata().schema)
+            .build()
+            .unwrap()
+            .encode_decoded(decoder)
+            .unwrap();
+
+        let mut expected = DbnDecoder::from_zstd_file(&path).unwrap();
+        expected
+            .set_upgrade_policy(VersionUpgradePolicy::AsIs)
+            .unwrap();
+        let mut decoder = Decoder::builder(csv.as_slice())
+            .delimiter(delimiter)
+            .version(version)
+            .build()
+            .unwrap();
+        let mut count = 0;
+        while let Some(exp_rec) = expected.decode_record_ref().unwrap() {
+            let exp_rec = RecordBuf::<{ crate::MAX_RECORD_LEN }>::try_from(exp_rec).unwrap();
+            let rec = RecordBuf::<{ crate::MAX_RECORD_LEN }>::try_from(
+                decoder.decode_record_ref().unwrap().unwrap(),
+            )
+            .unwrap();
+            // Compare `Debug` output because reserved bytes, e.g. in v1 definitions,
+            // aren't represented in the text encoding
+            assert_eq!(
+                format!("{exp_rec:?}"),
+                format!("{rec:?}"),
+                "record {count} of {path}"
+            );
+            count += 1;
+        }
+        assert!(count > 0);
+        assert!(decoder.decode_record_ref().unwrap().is_none());
+    }
+
+    fn mbo() -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 323, 1658441851000000000),
+            order_id: 16,
+            price: -5_500_000_000,
+            size: 3,
+            flags: FlagSet::empty().set_last(),
+            channel_id: 14,
+            action: b'A' as c_char,
+            side: b'\\' as c_char,
+            ts_recv: 1658441891000000000,
+            ts_in_delta: 22_000,
+            sequence: 1_002_375,
+        }
+    }
+
+    #[rstest]
+    fn test_round_trip_pretty(
+        #[values(false, true)] use_pretty_px: bool,
+        #[values(false, true)] use_pretty_ts: bool,
+    ) {
+        let record = mbo();
+        let mut csv = Vec::new();
+        let mut encoder = CsvEncoder::builder(&mut csv)
+            .use_pretty_px(use_pretty_px)
+            .use_pretty_ts(use_pretty_ts)
+            .build()
+            .unwrap();
+        encoder
+            .encode_record_with_sym(&record, Some("ESZ4"))
+            .unwrap();
+        encoder.encode_record_with_sym(&record, None).unwrap();
+        drop(encoder);
+
+        let decoder = Decoder::new(csv.as_slice()).unwrap();
+        let records: Vec<MboMsg> = decoder.decode_records().unwrap();
+        assert_eq!(records, vec![record.clone(), record]);
+    }
+
+    #[test]
+    fn test_ts_out() {
+        let record = WithTsOut::new(mbo(), 1658441891000000001);
+        let mut csv = Vec::new();
+        CsvEncoder::new(&mut csv, true, true)
+            .encode_record(&record)
+            .unwrap();
+
+        let mut decoder = Decoder::new(csv.as_slice()).unwrap();
+        assert!(decoder.metadata().ts_out);
+        let rec = decoder
+            .decode_record::<WithTsOut<MboMsg>>()
+            .unwrap()
+            .unwrap();
+        assert_eq!(*rec, record);
+    }
+
+    #[test]
+    fn test_hand_written() {
+        let csv = "\
+ts_event,instrument_id,bid_px_00,ask_px_00,bid_sz_00,ask_sz_00,price
+2024-01-02T14:30:00Z,42,100.25,100.5,10,12,100.25
+";
+        let mut decoder = Decoder::builder(csv.as_bytes())
+            .schema(Some(Schema::Mbp10))
+            .build()
+            .unwrap();
+        assert_eq!(decoder.metadata().schema, Some(Schema::Mbp10));
+        let rec = decoder.decode_record::<Mbp10Msg>().unwrap().unwrap();
+        assert_eq!(rec.hd.rtype, rtype::MBP_10);
+        assert_eq!(rec.hd.instrument_id, 42);
+        assert_eq!(rec.hd.ts_event, 1704205800000000000);
+        assert_eq!(rec.price, 100_250_000_000);
+        assert_eq!(rec.levels[0].bid_px, 100_250_000_000);
+        assert_eq!(rec.levels[0].ask_px, 100_500_000_000);
+        assert_eq!(rec.levels[0].bid_sz, 10);
+        assert_eq!(rec.levels[1], Mbp10Msg::default().levels[1]);
+        assert_eq!(rec.ts_recv, crate::UNDEF_TIMESTAMP);
+    }
+
+    #[test]
+    fn test_missing_rtype_and_schema() {
+        let mut decoder = Decoder::new("instrument_id\n1\n".as_bytes()).unwrap();
+        assert!(matches!(
+            decoder.decode_record_ref(),
+            Err(crate::Error::Decode(msg)) if msg.contains("rtype")
+        ));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let mut decoder = Decoder::builder("instrument_id,price\n1,abc\n".as_bytes())
+            .schema(Some(Schema::Trades))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            decoder.decode_record_ref(),
+            Err(crate::Error::Decode(msg)) if msg.contains("`price`") && msg.contains("line 2")
+        ));
+    }
+}
@@ -9,6 +9,7 @@ use tokio::{
 use crate::{
     decode::{
         dbn::fsm::{DbnFsm, ProcessResult},
+        private::LastRecord,
         zstd::zstd_decoder,
         AsyncDecodeRecord, AsyncDecodeRecordRef, AsyncSkipBytes, DbnMetadata, VersionUpgradePolicy,
         ZSTD_FILE_BUFFER_CAPACITY,
@@ -259,6 +260,15 @@ where
     }
 }
 
+impl<R> LastRecord for Decoder<R>
+where
+    R: io::AsyncReadExt + Unpin,
+{
+    fn last_record(&self) -> Option<RecordRef<'_>> {
+        self.decoder.last_record()
+    }
+}
+
 /// An async decoder for files and streams of Databento Binary Encoding (DBN) records.
 pub struct RecordDecoder<R>
 where
@@ -458,6 +468,15 @@ where
     }
 }
 
+impl<R> LastRecord for RecordDecoder<R>
+where
+    R: io::AsyncReadExt + Unpin,
+{
+    fn last_record(&self) -> Option<RecordRef<'_>> {
+        self.fsm.last_record()
+    }
+}
+
 impl<R> RecordDecoder<R>
 where
     R: AsyncSkipBytes + io::AsyncReadExt + Unpin,
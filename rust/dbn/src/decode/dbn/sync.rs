@@ -8,10 +8,11 @@ use crate::{
     decode::{
         dbn::fsm::{DbnFsm, ProcessResult},
         private::LastRecord,
-        DbnMetadata, DecodeRecord, DecodeRecordRef, DecodeStream, SkipBytes, StreamIterDecoder,
-        VersionUpgradePolicy,
+        DbnMetadata, DecodeRecord, DecodeRecordRef, DecodeStream, DynReader, SkipBytes,
+        StreamIterDecoder, VersionUpgradePolicy,
     },
-    HasRType, Metadata, RecordRef, DBN_VERSION,
+    index::Index,
+    Compression, HasRType, Metadata, Record, RecordRef, DBN_VERSION,
 };
 
 /// Type for decoding files and streams in Databento Binary Encoding (DBN), both metadata and records.
@@ -156,6 +157,103 @@ impl Decoder<zstd::stream::Decoder<'_, BufReader<File>>> {
     }
 }
 
+impl<R> Decoder<DynReader<'_, BufReader<R>>>
+where
+    R: io::Read + io::Seek,
+{
+    /// Creates a DBN [`Decoder`] from `reader` that begins decoding at the first
+    /// record with an index timestamp greater than or equal to `ts`, using `index` to
+    /// skip most of the preceding records. The records in `reader` are assumed to be
+    /// sorted by their index timestamp. It will decode records from previous DBN
+    /// versions according to `upgrade_policy`.
+    ///
+    /// # Errors
+    /// This function will return an error if `index` doesn't match `reader`, it's
+    /// unable to seek within or read from `reader`, or it's unable to parse the
+    /// metadata or records in `reader`.
+    pub fn seek_with_index(
+        mut reader: R,
+        index: &Index,
+        ts: u64,
+        upgrade_policy: VersionUpgradePolicy,
+    ) -> crate::Result<Self> {
+        let seek_err = |e| crate::Error::io(e, "seeking in DBN input");
+        let file_len = reader.seek(io::SeekFrom::End(0)).map_err(seek_err)?;
+        if file_len != index.file_len() {
+            return Err(crate::Error::BadArgument {
+                param_name: "index".to_owned(),
+                desc: format!(
+                    "index is for an input of {} bytes, but the input is {file_len} bytes",
+                    index.file_len()
+                ),
+            });
+        }
+        reader.rewind().map_err(seek_err)?;
+        let mut metadata = MetadataDecoder::with_upgrade_policy(
+            DynReader::new(&mut reader, index.compression())?,
+            VersionUpgradePolicy::AsIs,
+        )
+        .decode()?;
+        let version = metadata.version;
+        let ts_out = metadata.ts_out;
+        metadata.upgrade(upgrade_policy);
+        // Scan from the preceding index entry to find the exact offset of the first
+        // record at or after `ts`
+        let mut offset = index.scan_start(ts);
+        let mut scanner = RecordDecoder::with_version(
+            reader_at(&mut reader, index, offset)?,
+            version,
+            VersionUpgradePolicy::AsIs,
+            ts_out,
+        )?;
+        while let Some(rec) = scanner.decode_ref()? {
+            if rec.raw_index_ts() >= ts {
+                break;
+            }
+            offset += rec.record_size() as u64;
+        }
+        drop(scanner);
+        Ok(Self {
+            decoder: RecordDecoder::with_version(
+                reader_at(reader, index, offset)?,
+                version,
+                upgrade_policy,
+                ts_out,
+            )?,
+            metadata,
+        })
+    }
+}
+
+/// Returns a reader positioned at `offset` in the uncompressed DBN stream.
+fn reader_at<'a, R>(
+    mut reader: R,
+    index: &Index,
+    offset: u64,
+) -> crate::Result<DynReader<'a, BufReader<R>>>
+where
+    R: io::Read + io::Seek,
+{
+    let seek_err = |e| crate::Error::io(e, format!("seeking to offset {offset}"));
+    match index.compression() {
+        Compression::None => {
+            reader.seek(io::SeekFrom::Start(offset)).map_err(seek_err)?;
+            DynReader::new(reader, Compression::None)
+        }
+        Compression::Zstd => {
+            let frame = index.frame_for(offset).ok_or_else(|| {
+                crate::Error::decode(format!("index has no frame containing offset {offset}"))
+            })?;
+            reader
+                .seek(io::SeekFrom::Start(frame.compressed_offset))
+                .map_err(seek_err)?;
+            let mut reader = DynReader::new(reader, Compression::Zstd)?;
+            reader.skip_bytes((offset - frame.offset) as usize)?;
+            Ok(reader)
+        }
+    }
+}
+
 impl<R> DecodeRecordRef for Decoder<R>
 where
     R: io::Read,
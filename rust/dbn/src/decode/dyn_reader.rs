@@ -1,15 +1,22 @@
 use std::{
     fs::File,
-    io::{self, BufReader, ErrorKind, Read},
+    io::{self, BufReader, ErrorKind, Read, Seek},
     path::Path,
 };
 
 use crate::Compression;
 
-use super::{zstd, SkipBytes};
+use super::{
+    zstd::{self, FrameReader, SeekTable},
+    SkipBytes,
+};
 
 /// Type for runtime polymorphism over whether decoding uncompressed or Zstd-compressed
 /// DBN records. Implements [`std::io::Write`].
+///
+/// Files in the Zstandard seekable format are decoded like any other Zstd-compressed
+/// input. When created with [`DynReader::inferred_seekable()`] or
+/// [`DynReader::from_file()`], the seek table is also used to skip over whole frames.
 pub struct DynReader<'a, R>(DynReaderImpl<'a, R>)
 where
     R: io::BufRead;
@@ -20,6 +27,7 @@ where
 {
     Uncompressed(R),
     Zstd(::zstd::stream::Decoder<'a, R>),
+    SeekableZstd(FrameReader<R>),
 }
 
 impl<R> DynReader<'_, BufReader<R>>
@@ -91,6 +99,7 @@ where
         match &mut self.0 {
             DynReaderImpl::Uncompressed(reader) => reader,
             DynReaderImpl::Zstd(reader) => reader.get_mut(),
+            DynReaderImpl::SeekableZstd(reader) => reader.get_mut(),
         }
     }
 
@@ -99,6 +108,37 @@ where
         match &self.0 {
             DynReaderImpl::Uncompressed(reader) => reader,
             DynReaderImpl::Zstd(reader) => reader.get_ref(),
+            DynReaderImpl::SeekableZstd(reader) => reader.get_ref(),
+        }
+    }
+}
+
+impl<R> DynReader<'_, R>
+where
+    R: io::BufRead + Seek,
+{
+    /// Creates a new [`DynReader`] from a buffered reader positioned at the start of
+    /// the input, inferring the compression. If the input is in the Zstandard seekable
+    /// format, its seek table will be used to skip ahead.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to read from `reader` or creating
+    /// the zstd decoder fails.
+    pub fn inferred_seekable(mut reader: R) -> crate::Result<Self> {
+        let first_bytes = reader
+            .fill_buf()
+            .map_err(|e| crate::Error::io(e, "creating buffer to infer encoding"))?;
+        if !zstd::starts_with_prefix(first_bytes) {
+            return Ok(Self(DynReaderImpl::Uncompressed(reader)));
+        }
+        // Inputs that can't be seeked, like pipes, or that have a malformed seek table
+        // can still be decompressed from start to finish
+        match SeekTable::read(&mut reader) {
+            Ok(Some(seek_table)) => Ok(Self(DynReaderImpl::SeekableZstd(FrameReader::new(
+                reader,
+                Some(seek_table),
+            )?))),
+            Ok(None) | Err(_) => Self::with_buffer(reader, Compression::Zstd),
         }
     }
 }
@@ -119,7 +159,7 @@ impl DynReader<'_, BufReader<File>> {
                 ),
             )
         })?;
-        DynReader::new_inferred(file)
+        DynReader::inferred_seekable(BufReader::new(file))
     }
 }
 
@@ -131,6 +171,7 @@ where
         match &mut self.0 {
             DynReaderImpl::Uncompressed(r) => r.read(buf),
             DynReaderImpl::Zstd(r) => r.read(buf),
+            DynReaderImpl::SeekableZstd(r) => r.read(buf),
         }
     }
 }
@@ -164,6 +205,7 @@ where
                 }
                 Ok(())
             }
+            DynReaderImpl::SeekableZstd(reader) => reader.skip_bytes(n_bytes),
         }
     }
 }
@@ -0,0 +1,552 @@
+//! Decoder adapters for filtering the records of another decoder.
+
+use std::{collections::HashSet, num::NonZeroU64};
+
+use crate::{
+    symbol_map::{PitSymbolMap, SymbolIndex, TsSymbolMap},
+    HasRType, Metadata, Record, RecordRef, UNDEF_TIMESTAMP,
+};
+
+use super::{DbnMetadata, DecodeRecord, DecodeRecordRef};
+
+/// Filters the records of another decoder to those whose index timestamp, as returned
+/// by [`Record::raw_index_ts()`], is within a half-open time range `[start, end)`.
+///
+/// Records with an undefined index timestamp are skipped.
+#[derive(Debug)]
+pub struct TimeRangeFilter<D> {
+    decoder: D,
+    start: u64,
+    end: u64,
+    is_sorted: bool,
+    is_done: bool,
+}
+
+impl<D> TimeRangeFilter<D>
+where
+    D: DbnMetadata,
+{
+    /// Creates a new filter that only returns records from `decoder` with an index
+    /// timestamp greater than or equal to `start` and less than `end`. Both are UNIX
+    /// nanosecond timestamps. `None` leaves that side of the range unbounded.
+    ///
+    /// The [`Metadata::start`] and [`Metadata::end`] of `decoder` are narrowed to the
+    /// range.
+    pub fn new(mut decoder: D, start: Option<u64>, end: Option<u64>) -> Self {
+        narrow_metadata(decoder.metadata_mut(), start, end);
+        Self::new_no_metadata(decoder, start, end)
+    }
+}
+
+impl<D> TimeRangeFilter<D> {
+    /// Creates a new filter that only returns records from `decoder` with an index
+    /// timestamp greater than or equal to `start` and less than `end`, without
+    /// modifying any metadata. Useful for decoders of DBN fragments.
+    pub fn new_no_metadata(decoder: D, start: Option<u64>, end: Option<u64>) -> Self {
+        Self {
+            decoder,
+            start: start.unwrap_or(0),
+            end: end.unwrap_or(UNDEF_TIMESTAMP),
+            is_sorted: false,
+            is_done: false,
+        }
+    }
+
+    /// Sets whether the records of the inner decoder are sorted by index timestamp.
+    /// When `true`, the filter stops decoding as soon as it encounters a record at or
+    /// after the end of the range, instead of reading the rest of the input.
+    /// Defaults to `false`.
+    pub fn sorted(mut self, is_sorted: bool) -> Self {
+        self.is_sorted = is_sorted;
+        self
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn get_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the inner decoder.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes the filter and returns the inner decoder.
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+fn narrow_metadata(metadata: &mut Metadata, start: Option<u64>, end: Option<u64>) {
+    if let Some(start) = start {
+        metadata.start = metadata.start.max(start);
+    }
+    if let Some(end) = end.and_then(NonZeroU64::new) {
+        metadata.end = Some(metadata.end.map_or(end, |e| e.min(end)));
+    }
+}
+
+impl<D: DbnMetadata> DbnMetadata for TimeRangeFilter<D> {
+    fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for TimeRangeFilter<D> {
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+        if self.is_done {
+            return Ok(None);
+        }
+        while let Some(record) = self.decoder.decode_record_ref()? {
+            let index_ts = record.raw_index_ts();
+            if index_ts == UNDEF_TIMESTAMP {
+                continue;
+            }
+            if index_ts >= self.end {
+                if self.is_sorted {
+                    self.is_done = true;
+                    return Ok(None);
+                }
+                continue;
+            }
+            if index_ts >= self.start {
+                // Safety: casting reference to pointer so the pointer will always be
+                // valid. Getting around borrow checker limitation.
+                return Ok(Some(unsafe {
+                    RecordRef::unchecked_from_header(record.header())
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecord for TimeRangeFilter<D> {
+    fn decode_record<T: HasRType>(&mut self) -> crate::Result<Option<&T>> {
+        self.decode_record_ref()?
+            .map(|rec| rec.try_get::<T>())
+            .transpose()
+    }
+}
+
+/// Filters the records of another decoder to those for a set of instruments, selected
+/// by instrument ID or by symbol.
+///
+/// Symbols are resolved to instrument IDs through the symbology mappings in the
+/// metadata with a [`TsSymbolMap`] and through any [`SymbolMappingMsg`] records in
+/// the stream with a [`PitSymbolMap`], as is the case with live data. Symbol mapping
+/// records are themselves kept only if they map to a selected instrument.
+///
+/// If no instrument IDs or symbols are specified, all records are kept.
+///
+/// [`SymbolMappingMsg`]: crate::SymbolMappingMsg
+#[derive(Debug)]
+pub struct InstrumentFilter<D> {
+    decoder: D,
+    selection: Selection,
+}
+
+#[derive(Debug, Default)]
+struct Selection {
+    instrument_ids: HashSet<u32>,
+    symbols: HashSet<String>,
+    ts_symbol_map: TsSymbolMap,
+    pit_symbol_map: PitSymbolMap,
+}
+
+impl<D> InstrumentFilter<D>
+where
+    D: DbnMetadata,
+{
+    /// Creates a new filter that only returns records from `decoder` for the given
+    /// `instrument_ids` or `symbols`.
+    ///
+    /// The [`Metadata::symbols`], [`Metadata::partial`], [`Metadata::not_found`], and
+    /// [`Metadata::mappings`] of `decoder` are pruned to only the selected instruments.
+    ///
+    /// # Errors
+    /// This function returns an error if `symbols` is non-empty and the symbology
+    /// mappings in the metadata can't be parsed into a [`TsSymbolMap`].
+    pub fn new(
+        mut decoder: D,
+        instrument_ids: impl IntoIterator<Item = u32>,
+        symbols: impl IntoIterator<Item = String>,
+    ) -> crate::Result<Self> {
+        let mut selection = Selection::new(instrument_ids, symbols);
+        let metadata = decoder.metadata_mut();
+        if !selection.symbols.is_empty() && !metadata.mappings.is_empty() {
+            selection.ts_symbol_map = metadata.symbol_map()?;
+        }
+        if !selection.is_empty() {
+            selection.prune_metadata(metadata);
+        }
+        Ok(Self { decoder, selection })
+    }
+}
+
+impl<D> InstrumentFilter<D> {
+    /// Creates a new filter that only returns records from `decoder` for the given
+    /// `instrument_ids` or `symbols`, without using or modifying any metadata. Symbols
+    /// are only resolved through symbol mapping records in the stream. Useful for
+    /// decoders of DBN fragments.
+    pub fn new_no_metadata(
+        decoder: D,
+        instrument_ids: impl IntoIterator<Item = u32>,
+        symbols: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            decoder,
+            selection: Selection::new(instrument_ids, symbols),
+        }
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn get_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the inner decoder.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes the filter and returns the inner decoder.
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+impl Selection {
+    fn new(
+        instrument_ids: impl IntoIterator<Item = u32>,
+        symbols: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            instrument_ids: instrument_ids.into_iter().collect(),
+            symbols: symbols.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.instrument_ids.is_empty() && self.symbols.is_empty()
+    }
+
+    /// Returns `true` if `symbol` is either a selected symbol or a selected instrument
+    /// ID.
+    fn is_selected_symbol(&self, symbol: &str) -> bool {
+        self.symbols.contains(symbol)
+            || symbol
+                .parse::<u32>()
+                .is_ok_and(|id| self.instrument_ids.contains(&id))
+    }
+
+    fn prune_metadata(&self, metadata: &mut Metadata) {
+        metadata.mappings.retain_mut(|mapping| {
+            if !self.is_selected_symbol(&mapping.raw_symbol) {
+                mapping
+                    .intervals
+                    .retain(|interval| self.is_selected_symbol(&interval.symbol));
+            }
+            !mapping.intervals.is_empty()
+        });
+        let mappings = &metadata.mappings;
+        let is_kept =
+            |s: &String| self.is_selected_symbol(s) || mappings.iter().any(|m| m.raw_symbol == *s);
+        metadata.symbols.retain(is_kept);
+        metadata.partial.retain(is_kept);
+        metadata.not_found.retain(is_kept);
+    }
+
+    fn is_selected(&mut self, record: RecordRef) -> crate::Result<bool> {
+        if self.is_empty() {
+            return Ok(true);
+        }
+        let instrument_id = record.header().instrument_id;
+        if self.instrument_ids.contains(&instrument_id) {
+            return Ok(true);
+        }
+        if self.symbols.is_empty() {
+            return Ok(false);
+        }
+        self.pit_symbol_map.on_record(record)?;
+        Ok(self
+            .pit_symbol_map
+            .get(instrument_id)
+            .or_else(|| self.ts_symbol_map.get_for_rec(&record))
+            .is_some_and(|symbol| self.symbols.contains(symbol)))
+    }
+}
+
+impl<D: DbnMetadata> DbnMetadata for InstrumentFilter<D> {
+    fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for InstrumentFilter<D> {
+    fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+        while let Some(record) = self.decoder.decode_record_ref()? {
+            if self.selection.is_selected(record)? {
+                // Safety: casting reference to pointer so the pointer will always be
+                // valid. Getting around borrow checker limitation.
+                return Ok(Some(unsafe {
+                    RecordRef::unchecked_from_header(record.header())
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecord for InstrumentFilter<D> {
+    fn decode_record<T: HasRType>(&mut self) -> crate::Result<Option<&T>> {
+        self.decode_record_ref()?
+            .map(|rec| rec.try_get::<T>())
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use time::macros::datetime;
+
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder, DbnRecordDecoder},
+        encode::{DbnEncoder, DbnRecordEncoder, EncodeRecord},
+        rtype,
+        symbol_map::tests::metadata_w_mappings,
+        test_utils::VecStream,
+        MetadataBuilder, RecordHeader, SType, Schema, SymbolMappingMsg, TradeMsg,
+    };
+
+    fn trade(ts_recv: u64) -> TradeMsg {
+        trade_for(1, ts_recv)
+    }
+
+    fn trade_for(instrument_id: u32, ts_recv: u64) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, ts_recv),
+            ts_recv,
+            ..Default::default()
+        }
+    }
+
+    fn collect_ids(mut decoder: impl DecodeRecordRef) -> Vec<(u8, u32)> {
+        let mut res = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            res.push((rec.header().rtype, rec.header().instrument_id));
+        }
+        res
+    }
+
+    fn collect_ts(mut decoder: impl DecodeRecordRef) -> Vec<u64> {
+        let mut res = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            res.push(rec.raw_index_ts());
+        }
+        res
+    }
+
+    #[rstest]
+    #[case::unbounded(None, None, vec![1, 5, 10, 15, 20])]
+    #[case::start(Some(10), None, vec![10, 15, 20])]
+    #[case::end(None, Some(15), vec![1, 5, 10])]
+    #[case::both(Some(5), Some(16), vec![5, 10, 15])]
+    #[case::empty(Some(11), Some(14), vec![])]
+    fn test_filter(
+        #[case] start: Option<u64>,
+        #[case] end: Option<u64>,
+        #[case] exp: Vec<u64>,
+        #[values(false, true)] is_sorted: bool,
+    ) {
+        let stream = VecStream::new(vec![
+            trade(1),
+            trade(5),
+            trade(UNDEF_TIMESTAMP),
+            trade(10),
+            trade(15),
+            trade(20),
+        ]);
+        let target = TimeRangeFilter::new_no_metadata(stream, start, end).sorted(is_sorted);
+        assert_eq!(collect_ts(target), exp);
+    }
+
+    #[test]
+    fn test_unsorted_keeps_reading() {
+        let stream = VecStream::new(vec![trade(1), trade(20), trade(5), trade(30), trade(6)]);
+        let target = TimeRangeFilter::new_no_metadata(stream, None, Some(10));
+        assert_eq!(collect_ts(target), vec![1, 5, 6]);
+        let stream = VecStream::new(vec![trade(1), trade(20), trade(5), trade(30), trade(6)]);
+        let target = TimeRangeFilter::new_no_metadata(stream, None, Some(10)).sorted(true);
+        assert_eq!(collect_ts(target), vec![1]);
+    }
+
+    #[rstest]
+    #[case::narrows(Some(15), Some(25), 15, Some(25))]
+    #[case::keeps_narrower(Some(5), Some(40), 10, Some(30))]
+    #[case::unbounded(None, None, 10, Some(30))]
+    fn test_narrow_metadata(
+        #[case] start: Option<u64>,
+        #[case] end: Option<u64>,
+        #[case] exp_start: u64,
+        #[case] exp_end: Option<u64>,
+    ) {
+        let mut metadata = MetadataBuilder::new()
+            .dataset("XNAS.ITCH".to_owned())
+            .schema(Some(Schema::Trades))
+            .start(10)
+            .end(NonZeroU64::new(30))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build();
+        narrow_metadata(&mut metadata, start, end);
+        assert_eq!(metadata.start, exp_start);
+        assert_eq!(metadata.end.map(NonZeroU64::get), exp_end);
+    }
+
+    #[test]
+    fn test_filter_test_data() {
+        let all = collect_ts(
+            DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                .unwrap(),
+        );
+        assert!(all.len() > 1);
+        let (start, end) = (all[1], all[all.len() - 1] + 1);
+        let decoder =
+            DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                .unwrap();
+        let target = TimeRangeFilter::new(decoder, Some(start), Some(end)).sorted(true);
+        assert!(target.metadata().start >= start);
+        assert!(target.metadata().end.unwrap().get() <= end);
+        let filtered = collect_ts(target);
+        assert!(!filtered.is_empty());
+        assert!(filtered.iter().all(|ts| (start..end).contains(ts)));
+        assert_eq!(
+            filtered.len(),
+            all.iter().filter(|ts| (start..end).contains(*ts)).count()
+        );
+    }
+
+    #[rstest]
+    #[case::none(vec![], vec![1, 2, 3, 1, 4])]
+    #[case::some(vec![1, 3], vec![1, 3, 1])]
+    #[case::missing(vec![5], vec![])]
+    fn test_instrument_filter_ids(#[case] instrument_ids: Vec<u32>, #[case] exp: Vec<u32>) {
+        let stream = VecStream::new(
+            [1, 2, 3, 1, 4]
+                .into_iter()
+                .map(|id| trade_for(id, 1))
+                .collect(),
+        );
+        let target = InstrumentFilter::new_no_metadata(stream, instrument_ids, []);
+        assert_eq!(
+            collect_ids(target),
+            exp.into_iter()
+                .map(|id| (rtype::MBP_0, id))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_instrument_filter_symbol_mappings() {
+        let mut buf = Vec::new();
+        let mut encoder = DbnRecordEncoder::new(&mut buf);
+        let mapping = |id, symbol| {
+            SymbolMappingMsg::new(
+                id,
+                0,
+                SType::RawSymbol,
+                symbol,
+                SType::RawSymbol,
+                symbol,
+                0,
+                UNDEF_TIMESTAMP,
+            )
+            .unwrap()
+        };
+        encoder.encode_record(&mapping(5, "AAPL")).unwrap();
+        encoder.encode_record(&trade_for(5, 1)).unwrap();
+        encoder.encode_record(&mapping(6, "TSLA")).unwrap();
+        encoder.encode_record(&trade_for(6, 2)).unwrap();
+        encoder.encode_record(&trade_for(5, 3)).unwrap();
+        // remapped
+        encoder.encode_record(&mapping(5, "NVDA")).unwrap();
+        encoder.encode_record(&trade_for(5, 4)).unwrap();
+        encoder.encode_record(&mapping(7, "AAPL")).unwrap();
+        encoder.encode_record(&trade_for(7, 5)).unwrap();
+
+        let target = InstrumentFilter::new_no_metadata(
+            DbnRecordDecoder::new(buf.as_slice()),
+            [],
+            ["AAPL".to_owned()],
+        );
+        assert_eq!(
+            collect_ids(target),
+            vec![
+                (rtype::SYMBOL_MAPPING, 5),
+                (rtype::MBP_0, 5),
+                (rtype::MBP_0, 5),
+                (rtype::SYMBOL_MAPPING, 7),
+                (rtype::MBP_0, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instrument_filter_metadata() {
+        let mut metadata = metadata_w_mappings();
+        metadata.symbols = vec!["AAPL".to_owned(), "TSLA".to_owned()];
+        metadata.not_found = vec!["MSFT".to_owned()];
+        let ts = |dt: time::OffsetDateTime| dt.unix_timestamp_nanos() as u64;
+        let mut buf = Vec::new();
+        let mut encoder = DbnEncoder::new(&mut buf, &metadata).unwrap();
+        for rec in [
+            trade_for(32, ts(datetime!(2023-07-01 14:30 UTC))),
+            trade_for(10221, ts(datetime!(2023-07-01 14:30 UTC))),
+            trade_for(10221, ts(datetime!(2023-07-05 14:30 UTC))),
+            trade_for(10209, ts(datetime!(2023-07-05 14:30 UTC))),
+        ] {
+            encoder.encode_record(&rec).unwrap();
+        }
+
+        let target = InstrumentFilter::new(
+            DbnDecoder::new(buf.as_slice()).unwrap(),
+            [],
+            ["TSLA".to_owned()],
+        )
+        .unwrap();
+        assert_eq!(target.metadata().symbols, vec!["TSLA".to_owned()]);
+        assert!(target.metadata().not_found.is_empty());
+        assert_eq!(target.metadata().mappings.len(), 1);
+        assert_eq!(target.metadata().mappings[0].raw_symbol, "TSLA");
+        assert_eq!(
+            collect_ids(target),
+            vec![(rtype::MBP_0, 10221), (rtype::MBP_0, 10209)]
+        );
+    }
+
+    #[test]
+    fn test_instrument_filter_prunes_intervals() {
+        let mut metadata = metadata_w_mappings();
+        metadata.symbols = vec!["AAPL".to_owned(), "TSLA".to_owned()];
+        let selection = Selection::new([10209], []);
+        selection.prune_metadata(&mut metadata);
+        assert_eq!(metadata.symbols, vec!["TSLA".to_owned()]);
+        assert_eq!(metadata.mappings.len(), 1);
+        assert_eq!(metadata.mappings[0].raw_symbol, "TSLA");
+        assert_eq!(metadata.mappings[0].intervals.len(), 1);
+        assert_eq!(metadata.mappings[0].intervals[0].symbol, "10209");
+    }
+}
@@ -0,0 +1,5 @@
+//! Decoding DBN records from JSON lines.
+
+mod sync;
+
+pub use sync::{Decoder, DecoderBuilder};
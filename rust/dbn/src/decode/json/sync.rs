@@ -0,0 +1,452 @@
+use std::{collections::HashMap, fs::File, io, path::Path};
+
+use serde_json::{de::IoRead, Map, StreamDeserializer, Value};
+
+use crate::{
+    decode::{
+        private::LastRecord, text::TextFields, DbnMetadata, DecodeRecord, DecodeRecordRef,
+        DecodeStream, StreamIterDecoder,
+    },
+    HasRType, MboMsg, Metadata, MetadataBuilder, RecordBuf, RecordRef, Result, SType, Schema,
+    DBN_VERSION,
+};
+
+/// Type for decoding DBN records from JSON lines, such as the output of
+/// [`JsonEncoder`](crate::encode::JsonEncoder). Both compact and pretty-printed
+/// objects are supported.
+///
+/// Nested objects like `hd` are flattened and the elements of arrays like `levels`
+/// are matched to record fields with a two-digit suffix, e.g. `bid_px_00`. Fields
+/// without a key keep the default value for the record type and unknown keys like
+/// "symbol" are ignored. Prices and timestamps can be either raw integers or the
+/// pretty decimal and ISO 8601 forms.
+///
+/// The record type of each object is determined by its `rtype` or, if there's no
+/// `rtype`, by the schema set with [`DecoderBuilder::schema()`]. If the first
+/// object has a "ts_out" key, every record will be decoded with `ts_out`.
+///
+/// The reader should be buffered, e.g. with [`io::BufReader`].
+pub struct Decoder<R>
+where
+    R: io::Read,
+{
+    stream: StreamDeserializer<'static, IoRead<R>, Value>,
+    peeked: Option<Value>,
+    metadata: Metadata,
+    fields: Fields,
+    buf: RecordBuf,
+    has_decoded: bool,
+}
+
+/// Helper for constructing a JSON [`Decoder`].
+///
+/// No fields are required.
+pub struct DecoderBuilder<R>
+where
+    R: io::Read,
+{
+    reader: R,
+    schema: Option<Schema>,
+    version: u8,
+}
+
+impl<R> DecoderBuilder<R>
+where
+    R: io::Read,
+{
+    /// Creates a new JSON decoder builder.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            schema: None,
+            version: DBN_VERSION,
+        }
+    }
+
+    /// Sets the schema of the records, which is used for objects without an `rtype`.
+    /// Also sets the schema in the decoder's [`Metadata`].
+    pub fn schema(mut self, schema: Option<Schema>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Sets the DBN version of the records, which determines the record type for
+    /// schemas whose fields have changed between versions such as definitions and
+    /// statistics.
+    ///
+    /// If not specified, defaults to [`DBN_VERSION`].
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Creates the new decoder with the previously specified settings and reads the
+    /// first object to determine whether the records have `ts_out`.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to read the first object or
+    /// `version` is invalid.
+    pub fn build(self) -> Result<Decoder<R>> {
+        if self.version == 0 || self.version > DBN_VERSION {
+            return Err(crate::Error::BadArgument {
+                param_name: "version".to_owned(),
+                desc: format!("must be between 1 and {DBN_VERSION}"),
+            });
+        }
+        let mut stream = serde_json::Deserializer::from_reader(self.reader).into_iter();
+        let peeked = stream.next().transpose().map_err(json_err)?;
+        let ts_out = matches!(&peeked, Some(Value::Object(obj)) if obj.contains_key("ts_out"));
+        let metadata = MetadataBuilder::new()
+            .dataset(String::new())
+            .schema(self.schema)
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .version(self.version)
+            .ts_out(ts_out)
+            .build();
+        Ok(Decoder {
+            stream,
+            peeked,
+            metadata,
+            fields: Fields::default(),
+            buf: RecordBuf::from(MboMsg::default()),
+            has_decoded: false,
+        })
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: io::Read,
+{
+    /// Creates a builder for configuring a `Decoder` object.
+    pub fn builder(reader: R) -> DecoderBuilder<R> {
+        DecoderBuilder::new(reader)
+    }
+
+    /// Creates a new JSON [`Decoder`] from `reader` and reads the first object.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to read the first object.
+    pub fn new(reader: R) -> Result<Self> {
+        Self::builder(reader).build()
+    }
+}
+
+impl Decoder<io::BufReader<File>> {
+    /// Creates a JSON [`Decoder`] from the file at `path`.
+    ///
+    /// # Errors
+    /// This function returns an error if it is unable to read the file at `path` or
+    /// if it is unable to read the first object.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref()).map_err(|e| {
+            crate::Error::io(
+                e,
+                format!(
+                    "opening JSON file to decode at path '{}'",
+                    path.as_ref().display()
+                ),
+            )
+        })?;
+        Self::new(io::BufReader::new(file))
+    }
+}
+
+/// A JSON object flattened into strings.
+#[derive(Debug, Default)]
+struct Fields(HashMap<String, String>);
+
+impl Fields {
+    fn set(&mut self, obj: Map<String, Value>) -> Result<()> {
+        self.0.clear();
+        for (key, value) in obj {
+            match value {
+                Value::Object(nested) => {
+                    for (key, value) in nested {
+                        self.insert(key, value)?;
+                    }
+                }
+                Value::Array(elems) => {
+                    for (i, elem) in elems.into_iter().enumerate() {
+                        let Value::Object(elem) = elem else {
+                            return Err(crate::Error::decode(format!(
+                                "expected objects in array `{key}`"
+                            )));
+                        };
+                        for (elem_key, value) in elem {
+                            self.insert(format!("{elem_key}_{i:02}"), value)?;
+                        }
+                    }
+                }
+                value => self.insert(key, value)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, key: String, value: Value) -> Result<()> {
+        let value = match value {
+            Value::Null => String::new(),
+            Value::String(s) => s,
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Array(_) | Value::Object(_) => {
+                return Err(crate::Error::decode(format!(
+                    "unexpected nested value for `{key}`"
+                )))
+            }
+        };
+        self.0.insert(key, value);
+        Ok(())
+    }
+}
+
+impl TextFields for Fields {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+fn json_err(e: serde_json::Error) -> crate::Error {
+    if e.is_io() {
+        crate::Error::io(io::Error::from(e), "reading JSON")
+    } else {
+        crate::Error::decode(format!("failed to read JSON: {e}"))
+    }
+}
+
+impl<R> DecodeRecordRef for Decoder<R>
+where
+    R: io::Read,
+{
+    fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
+        let value = match self.peeked.take() {
+            Some(value) => value,
+            None => match self.stream.next().transpose().map_err(json_err)? {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+        };
+        let Value::Object(obj) = value else {
+            return Err(crate::Error::decode(format!(
+                "expected a JSON object, found `{value}`"
+            )));
+        };
+        self.fields.set(obj)?;
+        super::super::text::decode_record(
+            &mut self.buf,
+            &self.fields,
+            self.metadata.schema,
+            self.metadata.version,
+            self.metadata.ts_out,
+        )?;
+        self.has_decoded = true;
+        Ok(Some(self.buf.as_rec_ref()))
+    }
+}
+
+impl<R> DecodeRecord for Decoder<R>
+where
+    R: io::Read,
+{
+    fn decode_record<T: HasRType>(&mut self) -> Result<Option<&T>> {
+        self.decode_record_ref()?
+            .map(|rec| rec.try_get::<T>())
+            .transpose()
+    }
+}
+
+impl<R> DbnMetadata for Decoder<R>
+where
+    R: io::Read,
+{
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+}
+
+impl<R> DecodeStream for Decoder<R>
+where
+    R: io::Read,
+{
+    fn decode_stream<T: HasRType>(self) -> StreamIterDecoder<Self, T> {
+        StreamIterDecoder::new(self)
+    }
+}
+
+impl<R> LastRecord for Decoder<R>
+where
+    R: io::Read,
+{
+    fn last_record(&self) -> Option<RecordRef<'_>> {
+        self.has_decoded.then(|| self.buf.as_rec_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use std::ffi::c_char;
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        encode::{EncodeDbn, EncodeRecord, EncodeRecordTextExt, JsonEncoder},
+        rtype, FlagSet, MboMsg, Mbp1Msg, RecordHeader, VersionUpgradePolicy, WithTsOut,
+    };
+
+    #[rstest]
+    fn test_round_trip_test_data(
+        #[values(
+            ("mbo", 3),
+            ("mbp-1", 3),
+            ("mbp-10", 3),
+            ("tbbo", 3),
+            ("trades", 3),
+            ("ohlcv-1s", 3),
+            ("imbalance", 3),
+            ("statistics", 3),
+            ("status", 3),
+            ("definition", 3),
+            ("bbo-1s", 3),
+            ("cbbo-1s", 3),
+            ("cmbp-1", 3),
+            ("definition", 1),
+            ("statistics", 1),
+            ("definition", 2)
+        )]
+        schema_version: (&str, u8),
+        #[values(false, true)] should_pretty_print: bool,
+    ) {
+        let (schema, version) = schema_version;
+        let path = format!("{TEST_DATA_PATH}/test_data.{schema}.v{version}.dbn.zst");
+        let mut decoder = DbnDecoder::from_zstd_file(&path).unwrap();
+        decoder
+            .set_upgrade_policy(VersionUpgradePolicy::AsIs)
+            .unwrap();
+        let mut json = Vec::new();
+        JsonEncoder::builder(&mut json)
+            .should_pretty_print(should_pretty_print)
+            .build()
+            .encode_decoded(decoder)
+            .unwrap();
+
+        let mut expected = DbnDecoder::from_zstd_file(&path).unwrap();
+        expected
+            .set_upgrade_policy(VersionUpgradePolicy::AsIs)
+            .unwrap();
+        let mut decoder = Decoder::builder(json.as_slice())
+            .version(version)
+            .build()
+            .unwrap();
+        let mut count = 0;
+        while let Some(exp_rec) = expected.decode_record_ref().unwrap() {
+            let exp_rec = RecordBuf::<{ crate::MAX_RECORD_LEN }>::try_from(exp_rec).unwrap();
+            let rec = RecordBuf::<{ crate::MAX_RECORD_LEN }>::try_from(
+                decoder.decode_record_ref().unwrap().unwrap(),
+            )
+            .unwrap();
+            // Compare `Debug` output because reserved bytes, e.g. in v1 definitions,
+            // aren't represented in the text encoding
+            assert_eq!(
+                format!("{exp_rec:?}"),
+                format!("{rec:?}"),
+                "record {count} of {path}"
+            );
+            count += 1;
+        }
+        assert!(count > 0);
+        assert!(decoder.decode_record_ref().unwrap().is_none());
+    }
+
+    fn mbo() -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 323, 1658441851000000000),
+            order_id: u64::MAX - 1,
+            price: -5_500_000_000,
+            size: 3,
+            flags: FlagSet::empty().set_last(),
+            channel_id: 14,
+            action: b'A' as c_char,
+            side: b'"' as c_char,
+            ts_recv: 1658441891000000000,
+            ts_in_delta: 22_000,
+            sequence: 1_002_375,
+        }
+    }
+
+    #[rstest]
+    fn test_round_trip_pretty(
+        #[values(false, true)] should_pretty_print: bool,
+        #[values(false, true)] use_pretty_px: bool,
+        #[values(false, true)] use_pretty_ts: bool,
+    ) {
+        let record = mbo();
+        let mut json = Vec::new();
+        let mut encoder = JsonEncoder::builder(&mut json)
+            .should_pretty_print(should_pretty_print)
+            .use_pretty_px(use_pretty_px)
+            .use_pretty_ts(use_pretty_ts)
+            .build();
+        encoder
+            .encode_record_with_sym(&record, Some("ESZ4"))
+            .unwrap();
+        encoder.encode_record(&record).unwrap();
+
+        let decoder = Decoder::new(json.as_slice()).unwrap();
+        let records: Vec<MboMsg> = decoder.decode_records().unwrap();
+        assert_eq!(records, vec![record.clone(), record]);
+    }
+
+    #[test]
+    fn test_ts_out() {
+        let record = WithTsOut::new(mbo(), 1658441891000000001);
+        let mut json = Vec::new();
+        JsonEncoder::new(&mut json, false, true, true)
+            .encode_record(&record)
+            .unwrap();
+
+        let mut decoder = Decoder::new(json.as_slice()).unwrap();
+        assert!(decoder.metadata().ts_out);
+        let rec = decoder
+            .decode_record::<WithTsOut<MboMsg>>()
+            .unwrap()
+            .unwrap();
+        assert_eq!(*rec, record);
+    }
+
+    #[test]
+    fn test_hand_written() {
+        let json = r#"{"hd":{"ts_event":"2024-01-02T14:30:00.5Z","instrument_id":42},"price":"100.25","levels":[{"bid_px":100,"ask_px":null}]}"#;
+        let mut decoder = Decoder::builder(json.as_bytes())
+            .schema(Some(Schema::Mbp1))
+            .build()
+            .unwrap();
+        let rec = decoder.decode_record::<Mbp1Msg>().unwrap().unwrap();
+        assert_eq!(rec.hd.rtype, rtype::MBP_1);
+        assert_eq!(rec.hd.instrument_id, 42);
+        assert_eq!(rec.hd.ts_event, 1704205800500000000);
+        assert_eq!(rec.price, 100_250_000_000);
+        assert_eq!(rec.levels[0].bid_px, 100);
+        assert_eq!(rec.levels[0].ask_px, crate::UNDEF_PRICE);
+        assert!(decoder.decode_record_ref().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_not_an_object() {
+        let mut decoder = Decoder::new("[1, 2]".as_bytes()).unwrap();
+        assert!(matches!(
+            decoder.decode_record_ref(),
+            Err(crate::Error::Decode(msg)) if msg.contains("expected a JSON object")
+        ));
+    }
+}
@@ -319,6 +319,451 @@ where
     }
 }
 
+#[cfg(feature = "async")]
+pub use r#async::{Decoder as AsyncDecoder, RecordDecoder as AsyncRecordDecoder};
+
+#[cfg(feature = "async")]
+mod r#async {
+    use std::{
+        cmp::Reverse,
+        collections::{binary_heap::PeekMut, BinaryHeap},
+    };
+
+    use super::{IndexTs, StreamHead};
+    use crate::{
+        decode::{private, AsyncDecodeRecord, AsyncDecodeRecordRef, DbnMetadata},
+        Error, HasRType, Metadata, Record, RecordRef,
+    };
+
+    /// Merges the DBN decoding streams from one or more async decoders. Both metadata
+    /// and the record streams are merged.
+    pub struct Decoder<D> {
+        metadata: Metadata,
+        decoder: RecordDecoder<D>,
+    }
+
+    impl<D> Decoder<D>
+    where
+        D: DbnMetadata + AsyncDecodeRecordRef,
+    {
+        /// Creates a new async merge decoder from the given `decoders`. Both the DBN
+        /// metadata and the records will be merged. The [`Metadata::start`] of each
+        /// decoder is used as a hint so a decoder isn't read from until it could hold
+        /// the next record.
+        ///
+        /// # Errors
+        /// This function returns an error if `decoders` is empty. Errors can also result
+        /// from failing to merge the DBN metadata.
+        pub fn new(decoders: Vec<D>) -> crate::Result<Self> {
+            let hints = decoders.iter().map(|d| d.metadata().start).collect();
+            let Some((first, rest)) = decoders.split_first() else {
+                return Err(Error::BadArgument {
+                    param_name: "decoders".to_owned(),
+                    desc: "none provided".to_owned(),
+                });
+            };
+            let metadata = first
+                .metadata()
+                .clone()
+                .merge(rest.iter().map(|d| d.metadata().clone()))?;
+            Ok(Self {
+                metadata,
+                decoder: RecordDecoder::with_hints(decoders, hints)?,
+            })
+        }
+    }
+
+    impl<D> DbnMetadata for Decoder<D> {
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+
+        fn metadata_mut(&mut self) -> &mut Metadata {
+            &mut self.metadata
+        }
+    }
+
+    impl<D> AsyncDecodeRecordRef for Decoder<D>
+    where
+        D: private::LastRecord + AsyncDecodeRecordRef,
+    {
+        async fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+            self.decoder.decode_record_ref().await
+        }
+    }
+
+    impl<D> AsyncDecodeRecord for Decoder<D>
+    where
+        D: private::LastRecord + AsyncDecodeRecordRef,
+    {
+        async fn decode_record<'a, T: HasRType + 'a>(&'a mut self) -> crate::Result<Option<&'a T>> {
+            self.decoder.decode_record().await
+        }
+    }
+
+    impl<D> private::LastRecord for Decoder<D>
+    where
+        D: private::LastRecord,
+    {
+        fn last_record(&self) -> Option<RecordRef<'_>> {
+            self.decoder.last_record()
+        }
+    }
+
+    /// Merges the record decoding streams from one or more async decoders, performing
+    /// a k-merge based on [`Record::index_ts()`](crate::Record::index_ts).
+    pub struct RecordDecoder<D> {
+        /// Should never change size because [`min_heap`] holds indices to this `Vec`.
+        decoders: Vec<D>,
+        /// heap for kmerge
+        min_heap: BinaryHeap<Reverse<StreamHead>>,
+        is_first: bool,
+    }
+
+    impl<D> RecordDecoder<D>
+    where
+        D: AsyncDecodeRecordRef,
+    {
+        /// Creates a new async record-stream merging decoder.
+        ///
+        /// # Errors
+        /// This function returns an error if `decoders` is empty. It will also return
+        /// an error if one of the inner decoders returns an error while decoding the
+        /// first record. A decoder returning `Ok(None)` does not result in a failure.
+        ///
+        /// # Cancel safety
+        /// This method is not cancellation safe. If this method is used in a
+        /// `tokio::select!` statement and another branch completes first, the first
+        /// records of some decoders may have been consumed.
+        pub async fn new(mut decoders: Vec<D>) -> crate::Result<Self> {
+            if decoders.is_empty() {
+                return Err(Error::BadArgument {
+                    param_name: "decoders".to_owned(),
+                    desc: "none provided".to_owned(),
+                });
+            };
+            let mut min_heap = BinaryHeap::with_capacity(decoders.len());
+            // Populate heap for first time or all streams fully processed
+            for (decoder_idx, decoder) in decoders.iter_mut().enumerate() {
+                if let Some(rec) = decoder.decode_record_ref().await? {
+                    min_heap.push(Reverse(StreamHead {
+                        index_ts: IndexTs::Real(rec.raw_index_ts()),
+                        decoder_idx,
+                    }));
+                };
+            }
+            Ok(Self {
+                decoders,
+                min_heap,
+                is_first: true,
+            })
+        }
+
+        /// Creates a new async record-stream merging decoder with a hint for the start
+        /// time for each decoder. A decoder won't be read from until its hint is the
+        /// earliest timestamp among all decoders, so no reads are made before necessary.
+        ///
+        /// The hint timestamp must be <= raw_index_ts() of the first record in the file.
+        /// [`Metadata::start`] is an example source of for hint.
+        ///
+        /// # Errors
+        /// This function returns an error if `decoders` is empty or `decoders` and
+        /// `start_ts_hints` are of different lengths.
+        pub fn with_hints(decoders: Vec<D>, start_ts_hints: Vec<u64>) -> crate::Result<Self> {
+            if decoders.is_empty() {
+                return Err(Error::BadArgument {
+                    param_name: "decoders".to_owned(),
+                    desc: "none provided".to_owned(),
+                });
+            };
+            if decoders.len() != start_ts_hints.len() {
+                return Err(Error::BadArgument {
+                    param_name: "hints".to_owned(),
+                    desc: "must have the same length as `decoders`".to_owned(),
+                });
+            }
+            let min_heap = start_ts_hints
+                .into_iter()
+                .enumerate()
+                .map(|(decoder_idx, hint)| {
+                    Reverse(StreamHead {
+                        index_ts: IndexTs::Hint(hint),
+                        decoder_idx,
+                    })
+                })
+                .collect();
+            Ok(Self {
+                decoders,
+                min_heap,
+                is_first: true,
+            })
+        }
+
+        // handles hints
+        async fn next_decoder_idx(&mut self) -> crate::Result<Option<usize>> {
+            loop {
+                let Some(Reverse(StreamHead {
+                    index_ts,
+                    decoder_idx,
+                })) = self.min_heap.peek().cloned()
+                else {
+                    return Ok(None);
+                };
+                match index_ts {
+                    IndexTs::Real(_) => return Ok(Some(decoder_idx)),
+                    IndexTs::Hint(_) => self.advance_head(decoder_idx).await?,
+                }
+            }
+        }
+
+        // Decodes the next record from the decoder at the top of the heap. The head is
+        // only replaced once the inner decoder returns so a cancelled read leaves the
+        // heap untouched.
+        async fn advance_head(&mut self, decoder_idx: usize) -> crate::Result<()> {
+            let next_index_ts = self.decoders[decoder_idx]
+                .decode_record_ref()
+                .await?
+                .map(|rec| rec.raw_index_ts());
+            let Some(mut head) = self.min_heap.peek_mut() else {
+                return Ok(());
+            };
+            debug_assert_eq!(head.0.decoder_idx, decoder_idx);
+            if let Some(index_ts) = next_index_ts {
+                head.0.index_ts = IndexTs::Real(index_ts);
+            } else {
+                PeekMut::pop(head);
+            }
+            Ok(())
+        }
+    }
+
+    impl<D> RecordDecoder<D> {
+        // does not handle hints. Should only be called after `decode_record_ref`
+        fn peek_decoder_idx(&self) -> Option<usize> {
+            self.min_heap
+                .peek()
+                .map(|Reverse(StreamHead { decoder_idx, .. })| *decoder_idx)
+        }
+    }
+
+    impl<D> AsyncDecodeRecordRef for RecordDecoder<D>
+    where
+        D: private::LastRecord + AsyncDecodeRecordRef,
+    {
+        /// Decodes the next record across all decoders by index timestamp.
+        ///
+        /// # Errors
+        /// This function returns an error if one of the inner decoders returns an
+        /// error.
+        ///
+        /// # Cancel safety
+        /// This method is cancel safe if the inner decoders' `decode_record_ref` is
+        /// cancel safe. The merge state is only updated after an inner decoder
+        /// returns, so dropping the future doesn't lose any of the inner decoders.
+        async fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+            if self.is_first {
+                self.is_first = false;
+            } else {
+                // Replace last record
+                let Some(decoder_idx) = self.peek_decoder_idx() else {
+                    return Ok(None);
+                };
+                self.advance_head(decoder_idx).await?;
+            }
+            let Some(decoder_idx) = self.next_decoder_idx().await? else {
+                return Ok(None);
+            };
+            Ok(self.decoders[decoder_idx].last_record())
+        }
+    }
+
+    impl<D> AsyncDecodeRecord for RecordDecoder<D>
+    where
+        D: private::LastRecord + AsyncDecodeRecordRef,
+    {
+        async fn decode_record<'a, T: HasRType + 'a>(&'a mut self) -> crate::Result<Option<&'a T>> {
+            if let Some(rec) = self.decode_record_ref().await? {
+                rec.try_get().map(Some)
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    impl<D> private::LastRecord for RecordDecoder<D>
+    where
+        D: private::LastRecord,
+    {
+        fn last_record(&self) -> Option<RecordRef<'_>> {
+            let Some(decoder_idx) = self.peek_decoder_idx() else {
+                return self.decoders[0].last_record();
+            };
+            self.decoders[decoder_idx].last_record()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rstest::*;
+
+        use super::*;
+        use crate::{
+            decode::{tests::TEST_DATA_PATH, AsyncDbnDecoder, AsyncDbnRecordDecoder},
+            encode::{DbnRecordEncoder, EncodeRecord},
+            rtype, MboMsg, Mbp1Msg, RecordHeader,
+        };
+
+        fn encode_mbp1(ts_recvs: &[u64]) -> Vec<u8> {
+            let mut buf = Vec::new();
+            let mut encoder = DbnRecordEncoder::new(&mut buf);
+            for ts_recv in ts_recvs.iter().copied() {
+                encoder
+                    .encode_record(&Mbp1Msg {
+                        hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 0, 0, 0),
+                        ts_recv,
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            buf
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn stream_merging(#[values(None, Some(vec![5, 1, 50, 0]))] hints: Option<Vec<u64>>) {
+            let bufs = [
+                encode_mbp1(&[10, 100, 1000]),
+                encode_mbp1(&[11, 12, 13, 14, 15, 101, 102, 103, 104, 105]),
+                encode_mbp1(&[50, 105, 500, 5000]),
+                encode_mbp1(&[]),
+            ];
+            let decoders = bufs
+                .iter()
+                .map(|buf| AsyncDbnRecordDecoder::new(buf.as_slice()))
+                .collect();
+            let mut target = if let Some(hints) = hints {
+                RecordDecoder::with_hints(decoders, hints)
+            } else {
+                RecordDecoder::new(decoders).await
+            }
+            .unwrap();
+            let mut timestamps = Vec::new();
+            while let Some(rec) = target.decode_record::<Mbp1Msg>().await.unwrap() {
+                timestamps.push(rec.raw_index_ts());
+            }
+            assert_eq!(
+                timestamps,
+                vec![
+                    10, 11, 12, 13, 14, 15, 50, 100, 101, 102, 103, 104, 105, 105, 500, 1000, 5000
+                ]
+            );
+            // extra advances should do nothing
+            assert!(target.decode_record_ref().await.unwrap().is_none());
+            assert!(target.decode_record_ref().await.unwrap().is_none());
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn hints_delay_reads() {
+            let early = encode_mbp1(&[10, 20, 30]);
+            // Impossible record length, so reading from it returns an error
+            let late = [3u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+            let decoders = vec![
+                AsyncDbnRecordDecoder::new(early.as_slice()),
+                AsyncDbnRecordDecoder::new(late.as_slice()),
+            ];
+            let mut target = RecordDecoder::with_hints(decoders, vec![0, 100]).unwrap();
+            for _ in 0..3 {
+                assert!(target.decode_record_ref().await.unwrap().is_some());
+            }
+            assert!(target.decode_record_ref().await.is_err());
+        }
+
+        /// Yields to the runtime before every read so the first poll of each
+        /// `decode_record_ref` is always pending.
+        struct YieldingDecoder<'a>(AsyncDbnRecordDecoder<&'a [u8]>);
+
+        impl AsyncDecodeRecordRef for YieldingDecoder<'_> {
+            async fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+                tokio::task::yield_now().await;
+                self.0.decode_record_ref().await
+            }
+        }
+
+        impl private::LastRecord for YieldingDecoder<'_> {
+            fn last_record(&self) -> Option<RecordRef<'_>> {
+                self.0.last_record()
+            }
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn cancelled_decode_keeps_decoders(
+            #[values(None, Some(vec![5, 1]))] hints: Option<Vec<u64>>,
+        ) {
+            let bufs = [encode_mbp1(&[10, 100, 1000]), encode_mbp1(&[11, 101])];
+            let decoders = bufs
+                .iter()
+                .map(|buf| YieldingDecoder(AsyncDbnRecordDecoder::new(buf.as_slice())))
+                .collect();
+            let mut target = if let Some(hints) = hints {
+                RecordDecoder::with_hints(decoders, hints)
+            } else {
+                RecordDecoder::new(decoders).await
+            }
+            .unwrap();
+            // Without hints the first record is already buffered, so decode it normally
+            let first = target.decode_record::<Mbp1Msg>().await.unwrap().unwrap();
+            let mut timestamps = vec![first.raw_index_ts()];
+            loop {
+                // Drop the first attempt after its first poll
+                tokio::select! {
+                    biased;
+                    _ = target.decode_record_ref() => panic!("inner decoder should yield"),
+                    _ = std::future::ready(()) => {}
+                }
+                let Some(rec) = target.decode_record::<Mbp1Msg>().await.unwrap() else {
+                    break;
+                };
+                timestamps.push(rec.raw_index_ts());
+            }
+            assert_eq!(timestamps, vec![10, 11, 100, 101, 1000]);
+        }
+
+        async fn count_records(path: &str) -> usize {
+            let mut decoder = AsyncDbnDecoder::from_zstd_file(path).await.unwrap();
+            let mut count = 0;
+            while decoder.decode_record_ref().await.unwrap().is_some() {
+                count += 1;
+            }
+            count
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn merge_files() {
+            let path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+            let decoders = vec![
+                AsyncDbnDecoder::from_zstd_file(&path).await.unwrap(),
+                AsyncDbnDecoder::from_zstd_file(&path).await.unwrap(),
+            ];
+            let expected_metadata = decoders[0].metadata().clone();
+            let mut target = Decoder::new(decoders).unwrap();
+            assert_eq!(target.metadata().schema, expected_metadata.schema);
+            assert_eq!(target.metadata().start, expected_metadata.start);
+            assert_eq!(target.metadata().end, expected_metadata.end);
+            let mut prev = 0;
+            let mut count = 0;
+            while let Some(rec) = target.decode_record::<MboMsg>().await.unwrap() {
+                assert!(rec.raw_index_ts() >= prev);
+                prev = rec.raw_index_ts();
+                count += 1;
+            }
+            assert_eq!(count, 2 * count_records(&path).await);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use fallible_streaming_iterator::FallibleStreamingIterator;
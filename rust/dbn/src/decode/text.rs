@@ -0,0 +1,419 @@
+//! Functionality shared between the CSV and JSON decoders for converting text fields
+//! back into DBN records.
+
+use std::{ffi::c_char, str::FromStr};
+
+use time::format_description::well_known::Rfc3339;
+
+use crate::{
+    enums::{SecurityUpdateAction, UserDefinedInstrument},
+    record::{str_to_c_chars, BidAskPair, ConsolidatedBidAskPair, HasRType, RecordHeader},
+    v1, v2, v3, Error, FlagSet, RType, RecordBuf, Result, Schema, WithTsOut, FIXED_PRICE_SCALE,
+    UNDEF_PRICE, UNDEF_TIMESTAMP,
+};
+
+/// A source of named text values, such as a CSV row or a flattened JSON object.
+pub trait TextFields {
+    /// Returns the value of the field `name` or `None` if there's no such field.
+    /// `null` and empty values are returned as an empty string.
+    fn get(&self, name: &str) -> Option<&str>;
+}
+
+/// The inverse of `CsvSerialize` and `JsonSerialize`: populates a record from the
+/// fields of the same names. Fields that aren't present are left unchanged.
+pub trait TextDeserialize {
+    /// Overwrites the fields of `self` with values from `fields`.
+    ///
+    /// # Errors
+    /// This function returns an error if a value can't be parsed.
+    fn deserialize_from<F: TextFields + ?Sized>(&mut self, fields: &F) -> Result<()>;
+}
+
+pub trait ReadField {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, name: &str) -> Result<()>;
+}
+
+impl ReadField for RecordHeader {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, _name: &str) -> Result<()> {
+        // Header fields are flattened
+        self.deserialize_from(fields)
+    }
+}
+
+impl<const N: usize> ReadField for [BidAskPair; N] {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, _name: &str) -> Result<()> {
+        for (i, level) in self.iter_mut().enumerate() {
+            read_px_field(fields, &format!("bid_px_{i:02}"), &mut level.bid_px)?;
+            read_px_field(fields, &format!("ask_px_{i:02}"), &mut level.ask_px)?;
+            level.bid_sz.read_field(fields, &format!("bid_sz_{i:02}"))?;
+            level.ask_sz.read_field(fields, &format!("ask_sz_{i:02}"))?;
+            level.bid_ct.read_field(fields, &format!("bid_ct_{i:02}"))?;
+            level.ask_ct.read_field(fields, &format!("ask_ct_{i:02}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> ReadField for [ConsolidatedBidAskPair; N] {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, _name: &str) -> Result<()> {
+        for (i, level) in self.iter_mut().enumerate() {
+            read_px_field(fields, &format!("bid_px_{i:02}"), &mut level.bid_px)?;
+            read_px_field(fields, &format!("ask_px_{i:02}"), &mut level.ask_px)?;
+            level.bid_sz.read_field(fields, &format!("bid_sz_{i:02}"))?;
+            level.ask_sz.read_field(fields, &format!("ask_sz_{i:02}"))?;
+            level.bid_pb.read_field(fields, &format!("bid_pb_{i:02}"))?;
+            level.ask_pb.read_field(fields, &format!("ask_pb_{i:02}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl ReadField for FlagSet {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, name: &str) -> Result<()> {
+        let mut raw = self.raw();
+        raw.read_field(fields, name)?;
+        *self = FlagSet::new(raw);
+        Ok(())
+    }
+}
+
+macro_rules! impl_read_field_for {
+        ($($ty:ident),+) => {
+            $(
+                impl ReadField for $ty {
+                    fn read_field<F: TextFields + ?Sized>(
+                        &mut self,
+                        fields: &F,
+                        name: &str,
+                    ) -> Result<()> {
+                        match fields.get(name) {
+                            None | Some("") => Ok(()),
+                            Some(value) => {
+                                *self = parse(name, value)?;
+                                Ok(())
+                            }
+                        }
+                    }
+                }
+            )*
+        };
+    }
+
+impl_read_field_for! {i64, u64, i32, u32, i16, u16, i8, u8, bool}
+
+impl<const N: usize> ReadField for [c_char; N] {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, name: &str) -> Result<()> {
+        if let Some(value) = fields.get(name) {
+            *self = str_to_c_chars(value).map_err(|e| invalid_value(name, value, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl ReadField for SecurityUpdateAction {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, name: &str) -> Result<()> {
+        read_c_char_enum_field(fields, name, self)
+    }
+}
+
+impl ReadField for UserDefinedInstrument {
+    fn read_field<F: TextFields + ?Sized>(&mut self, fields: &F, name: &str) -> Result<()> {
+        read_c_char_enum_field(fields, name, self)
+    }
+}
+
+fn read_c_char_enum_field<F, T>(fields: &F, name: &str, field: &mut T) -> Result<()>
+where
+    F: TextFields + ?Sized,
+    T: TryFrom<u8>,
+{
+    if let Some(value) = fields.get(name) {
+        let c = parse_c_char(value).ok_or_else(|| invalid_value(name, value, "not a character"))?;
+        *field =
+            T::try_from(c as u8).map_err(|_| invalid_value(name, value, "unknown enum variant"))?;
+    }
+    Ok(())
+}
+
+/// Reads a price field that was either serialized as a fixed-precision integer or
+/// as a decimal. Decimals must contain a decimal point. An empty value is
+/// interpreted as [`UNDEF_PRICE`].
+pub fn read_px_field<F: TextFields + ?Sized>(fields: &F, name: &str, px: &mut i64) -> Result<()> {
+    if let Some(value) = fields.get(name) {
+        *px = parse_px(value).ok_or_else(|| invalid_value(name, value, "not a valid price"))?;
+    }
+    Ok(())
+}
+
+/// Reads a timestamp field that was either serialized as UNIX nanoseconds or as an
+/// ISO 8601 datetime. An empty value is interpreted as [`UNDEF_TIMESTAMP`].
+pub fn read_ts_field<F: TextFields + ?Sized>(fields: &F, name: &str, ts: &mut u64) -> Result<()> {
+    if let Some(value) = fields.get(name) {
+        *ts = parse_ts(value).ok_or_else(|| invalid_value(name, value, "not a valid timestamp"))?;
+    }
+    Ok(())
+}
+
+/// Reads a character field that may have been escaped. An empty value is interpreted
+/// as a NUL byte.
+pub fn read_c_char_field<F: TextFields + ?Sized>(
+    fields: &F,
+    name: &str,
+    c: &mut c_char,
+) -> Result<()> {
+    if let Some(value) = fields.get(name) {
+        *c = parse_c_char(value).ok_or_else(|| invalid_value(name, value, "not a character"))?;
+    }
+    Ok(())
+}
+
+fn parse<T>(name: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    value.parse().map_err(|e| invalid_value(name, value, e))
+}
+
+fn invalid_value(name: &str, value: &str, reason: impl std::fmt::Display) -> Error {
+    Error::decode(format!(
+        "invalid value '{value}' for field `{name}`: {reason}"
+    ))
+}
+
+fn parse_px(value: &str) -> Option<i64> {
+    if value.is_empty() || value == "UNDEF_PRICE" {
+        return Some(UNDEF_PRICE);
+    }
+    let Some((integer, fraction)) = value.split_once('.') else {
+        return value.parse().ok();
+    };
+    let (is_negative, integer) = match integer.strip_prefix('-') {
+        Some(integer) => (true, integer),
+        None => (false, integer),
+    };
+    if fraction.len() > 9
+        || !integer.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let integer = if integer.is_empty() {
+        0
+    } else {
+        integer.parse::<i64>().ok()?
+    };
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<i64>().ok()? * 10_i64.pow(9 - fraction.len() as u32)
+    };
+    let px_abs = integer
+        .checked_mul(FIXED_PRICE_SCALE)?
+        .checked_add(fraction)?;
+    Some(if is_negative { -px_abs } else { px_abs })
+}
+
+fn parse_ts(value: &str) -> Option<u64> {
+    if value.is_empty() {
+        return Some(UNDEF_TIMESTAMP);
+    }
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return value.parse().ok();
+    }
+    let dt = time::OffsetDateTime::parse(value, &Rfc3339).ok()?;
+    u64::try_from(dt.unix_timestamp_nanos()).ok()
+}
+
+/// The inverse of [`std::ascii::escape_default`], which is used when encoding
+/// `c_char` fields.
+fn parse_c_char(value: &str) -> Option<c_char> {
+    let mut chars = value.chars();
+    let Some(first) = chars.next() else {
+        return Some(0);
+    };
+    let rest = chars.as_str();
+    if rest.is_empty() {
+        return u8::try_from(first).ok().map(|c| c as c_char);
+    }
+    if first != '\\' {
+        return None;
+    }
+    let c = match rest {
+        "t" => b'\t',
+        "r" => b'\r',
+        "n" => b'\n',
+        "'" => b'\'',
+        "\"" => b'"',
+        "\\" => b'\\',
+        _ => u8::from_str_radix(rest.strip_prefix('x')?, 16).ok()?,
+    };
+    Some(c as c_char)
+}
+
+/// Determines the record type from the `rtype` field, or from `schema` if there's
+/// no such field, then decodes `fields` into `buf`.
+pub(crate) fn decode_record<F: TextFields + ?Sized>(
+    buf: &mut RecordBuf,
+    fields: &F,
+    schema: Option<Schema>,
+    version: u8,
+    ts_out: bool,
+) -> Result<()> {
+    let rtype = match fields.get("rtype") {
+        Some(value) if !value.is_empty() => parse::<u8>("rtype", value)?,
+        _ => schema
+            .map(|schema| RType::from(schema) as u8)
+            .ok_or_else(|| {
+                Error::decode("record is missing an `rtype` and no schema was specified")
+            })?,
+    };
+    let rtype_enum =
+        RType::try_from(rtype).map_err(|_| Error::conversion::<RType>(format!("{rtype:#04X}")))?;
+
+    macro_rules! decode_versioned {
+        ($v:ident) => {{
+            match rtype_enum {
+                RType::Mbp0 => decode_into(buf, $v::TradeMsg::default(), rtype, fields, ts_out),
+                RType::Mbp1 => decode_into(buf, $v::Mbp1Msg::default(), rtype, fields, ts_out),
+                RType::Mbp10 => decode_into(buf, $v::Mbp10Msg::default(), rtype, fields, ts_out),
+                #[allow(deprecated)]
+                RType::OhlcvDeprecated
+                | RType::Ohlcv1S
+                | RType::Ohlcv1M
+                | RType::Ohlcv1H
+                | RType::Ohlcv1D
+                | RType::OhlcvEod => decode_into(
+                    buf,
+                    $v::OhlcvMsg::default_for_schema(Schema::Ohlcv1S),
+                    rtype,
+                    fields,
+                    ts_out,
+                ),
+                RType::Imbalance => {
+                    decode_into(buf, $v::ImbalanceMsg::default(), rtype, fields, ts_out)
+                }
+                RType::Status => decode_into(buf, $v::StatusMsg::default(), rtype, fields, ts_out),
+                RType::InstrumentDef => {
+                    decode_into(buf, $v::InstrumentDefMsg::default(), rtype, fields, ts_out)
+                }
+                RType::SymbolMapping => {
+                    decode_into(buf, $v::SymbolMappingMsg::default(), rtype, fields, ts_out)
+                }
+                RType::Error => decode_into(buf, $v::ErrorMsg::default(), rtype, fields, ts_out),
+                RType::System => decode_into(buf, $v::SystemMsg::default(), rtype, fields, ts_out),
+                RType::Statistics => {
+                    decode_into(buf, $v::StatMsg::default(), rtype, fields, ts_out)
+                }
+                RType::Mbo => decode_into(buf, $v::MboMsg::default(), rtype, fields, ts_out),
+                RType::Cmbp1 | RType::Tcbbo => decode_into(
+                    buf,
+                    $v::Cmbp1Msg::default_for_schema(Schema::Cmbp1),
+                    rtype,
+                    fields,
+                    ts_out,
+                ),
+                RType::Bbo1S | RType::Bbo1M => decode_into(
+                    buf,
+                    $v::BboMsg::default_for_schema(Schema::Bbo1S),
+                    rtype,
+                    fields,
+                    ts_out,
+                ),
+                RType::Cbbo1S | RType::Cbbo1M => decode_into(
+                    buf,
+                    $v::CbboMsg::default_for_schema(Schema::Cbbo1S),
+                    rtype,
+                    fields,
+                    ts_out,
+                ),
+            }
+        }};
+    }
+
+    match version {
+        1 => decode_versioned!(v1),
+        2 => decode_versioned!(v2),
+        _ => decode_versioned!(v3),
+    }
+}
+
+fn decode_into<R, F>(
+    buf: &mut RecordBuf,
+    mut rec: R,
+    rtype: u8,
+    fields: &F,
+    ts_out: bool,
+) -> Result<()>
+where
+    R: HasRType + TextDeserialize,
+    F: TextFields + ?Sized,
+{
+    rec.header_mut().rtype = rtype;
+    rec.deserialize_from(fields)?;
+    if ts_out {
+        let mut ts_out = UNDEF_TIMESTAMP;
+        read_ts_field(fields, "ts_out", &mut ts_out)?;
+        buf.set(WithTsOut::new(rec, ts_out));
+    } else {
+        buf.set(rec);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case::raw("5500", Some(5500))]
+    #[case::raw_neg("-5500", Some(-5500))]
+    #[case::undef("", Some(UNDEF_PRICE))]
+    #[case::pretty("0.000005500", Some(5500))]
+    #[case::pretty_neg("-1.5", Some(-1_500_000_000))]
+    #[case::pretty_short("12.", Some(12_000_000_000))]
+    #[case::pretty_frac_only(".25", Some(250_000_000))]
+    #[case::too_precise("0.0000000001", None)]
+    #[case::garbage("1.2.3", None)]
+    #[case::overflow("9300000000.0", None)]
+    fn test_parse_px(#[case] value: &str, #[case] exp: Option<i64>) {
+        assert_eq!(parse_px(value), exp);
+    }
+
+    #[rstest]
+    #[case::raw("1658441851000000000", Some(1658441851000000000))]
+    #[case::undef("", Some(UNDEF_TIMESTAMP))]
+    #[case::pretty("2022-07-21T22:17:31.000000000Z", Some(1658441851000000000))]
+    #[case::pretty_short("2022-07-21T22:17:31Z", Some(1658441851000000000))]
+    #[case::before_epoch("1969-12-31T23:59:59Z", None)]
+    #[case::garbage("yesterday", None)]
+    fn test_parse_ts(#[case] value: &str, #[case] exp: Option<u64>) {
+        assert_eq!(parse_ts(value), exp);
+    }
+
+    #[rstest]
+    #[case::nul("", Some(0))]
+    #[case::reg("C", Some(b'C'))]
+    #[case::tab("\\t", Some(b'\t'))]
+    #[case::newline("\\n", Some(b'\n'))]
+    #[case::backslash("\\\\", Some(b'\\'))]
+    #[case::hex("\\xff", Some(0xFF))]
+    #[case::multi("AB", None)]
+    fn test_parse_c_char(#[case] value: &str, #[case] exp: Option<u8>) {
+        assert_eq!(parse_c_char(value), exp.map(|c| c as c_char));
+    }
+
+    #[test]
+    fn test_c_char_round_trip() {
+        for c in 0..=u8::MAX {
+            let escaped = if c == 0 {
+                String::new()
+            } else {
+                std::ascii::escape_default(c).to_string()
+            };
+            assert_eq!(parse_c_char(&escaped), Some(c as c_char), "{escaped}");
+        }
+    }
+}
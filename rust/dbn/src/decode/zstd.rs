@@ -1,11 +1,25 @@
-use std::ops::Range;
+use std::{
+    io::{self, BufRead, Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
 
-use super::FromLittleEndianSlice;
+use ::zstd::stream::raw::{Decoder as RawDecoder, InBuffer, Operation, OutBuffer};
+
+use super::{FromLittleEndianSlice, SkipBytes};
+use crate::index::FrameEntry;
 
 /// Range of magic numbers for a Zstandard skippable frame.
 pub(crate) const ZSTD_SKIPPABLE_MAGIC_RANGE: Range<u32> = 0x184D2A50..0x184D2A60;
 /// Magic number for the beginning of a Zstandard frame.
 const ZSTD_MAGIC_NUMBER: u32 = 0xFD2FB528;
+/// Magic number of the skippable frame containing a seek table.
+const SEEK_TABLE_MAGIC_NUMBER: u32 = 0x184D2A5E;
+/// Magic number at the end of the seek table footer.
+const SEEKABLE_MAGIC_NUMBER: u32 = 0x8F92EAB1;
+/// Number of frames, descriptor, and magic number.
+const SEEK_TABLE_FOOTER_LEN: usize = 9;
+/// Flag in the seek table descriptor indicating each entry has a checksum.
+const SEEK_TABLE_CHECKSUM_FLAG: u8 = 0x80;
 
 pub fn starts_with_prefix(bytes: &[u8]) -> bool {
     if bytes.len() < 4 {
@@ -15,6 +29,286 @@ pub fn starts_with_prefix(bytes: &[u8]) -> bool {
     ZSTD_MAGIC_NUMBER == magic
 }
 
+/// An entry in a [`SeekTable`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeekTableEntry {
+    /// The size of the frame in bytes.
+    pub compressed_size: u32,
+    /// The size of the decompressed contents of the frame in bytes.
+    pub decompressed_size: u32,
+}
+
+/// The seek table of a file in the [Zstandard seekable format], which lists the sizes
+/// of its independently-compressed frames so decompression can begin at any frame.
+/// The seek table is stored in a skippable frame at the end of the file, so the file
+/// can still be decompressed by any Zstandard decoder.
+///
+/// [Zstandard seekable format]: https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SeekTable {
+    entries: Vec<SeekTableEntry>,
+    /// The start of each frame, in the same order as `entries`.
+    frames: Vec<FrameEntry>,
+}
+
+impl SeekTable {
+    /// Reads the seek table from the end of `reader`, returning `None` if it doesn't
+    /// end with one. The position of `reader` is restored afterward.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to seek within or read from
+    /// `reader` or the seek table is malformed.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> crate::Result<Option<Self>> {
+        let seek_err = |e| crate::Error::io(e, "seeking for Zstandard seek table");
+        let start_pos = reader.stream_position().map_err(seek_err)?;
+        let res = Self::read_from_end(reader);
+        reader.seek(SeekFrom::Start(start_pos)).map_err(seek_err)?;
+        res
+    }
+
+    fn read_from_end<R: Read + Seek>(reader: &mut R) -> crate::Result<Option<Self>> {
+        let io_err = |e| crate::Error::io(e, "reading Zstandard seek table");
+        let len = reader.seek(SeekFrom::End(0)).map_err(io_err)?;
+        // skippable frame header and footer
+        if len < (8 + SEEK_TABLE_FOOTER_LEN) as u64 {
+            return Ok(None);
+        }
+        reader
+            .seek(SeekFrom::End(-(SEEK_TABLE_FOOTER_LEN as i64)))
+            .map_err(io_err)?;
+        let mut footer = [0; SEEK_TABLE_FOOTER_LEN];
+        reader.read_exact(&mut footer).map_err(io_err)?;
+        if u32::from_le_slice(&footer[5..]) != SEEKABLE_MAGIC_NUMBER {
+            return Ok(None);
+        }
+        let frame_count = u32::from_le_slice(&footer[..4]) as u64;
+        let entry_len = if footer[4] & SEEK_TABLE_CHECKSUM_FLAG == 0 {
+            8
+        } else {
+            12
+        };
+        let table_len = frame_count * entry_len + SEEK_TABLE_FOOTER_LEN as u64;
+        if len < table_len + 8 {
+            return Err(crate::Error::decode(
+                "Zstandard seek table is longer than the input",
+            ));
+        }
+        reader
+            .seek(SeekFrom::End(-((table_len + 8) as i64)))
+            .map_err(io_err)?;
+        let mut header = [0; 8];
+        reader.read_exact(&mut header).map_err(io_err)?;
+        if u32::from_le_slice(&header[..4]) != SEEK_TABLE_MAGIC_NUMBER
+            || u32::from_le_slice(&header[4..]) as u64 != table_len
+        {
+            return Err(crate::Error::decode("invalid Zstandard seek table header"));
+        }
+        let mut buf = vec![0; (frame_count * entry_len) as usize];
+        reader.read_exact(&mut buf).map_err(io_err)?;
+        let mut table = Self::default();
+        for entry in buf.chunks_exact(entry_len as usize) {
+            table.push(SeekTableEntry {
+                compressed_size: u32::from_le_slice(&entry[..4]),
+                decompressed_size: u32::from_le_slice(&entry[4..8]),
+            });
+        }
+        Ok(Some(table))
+    }
+
+    /// Writes the seek table as a skippable frame to `writer`.
+    pub(crate) fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let table_len = self.entries.len() * 8 + SEEK_TABLE_FOOTER_LEN;
+        let mut buf = Vec::with_capacity(8 + table_len);
+        buf.extend_from_slice(&SEEK_TABLE_MAGIC_NUMBER.to_le_bytes());
+        buf.extend_from_slice(&(table_len as u32).to_le_bytes());
+        for entry in self.entries.iter() {
+            buf.extend_from_slice(&entry.compressed_size.to_le_bytes());
+            buf.extend_from_slice(&entry.decompressed_size.to_le_bytes());
+        }
+        buf.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        // no checksums
+        buf.push(0);
+        buf.extend_from_slice(&SEEKABLE_MAGIC_NUMBER.to_le_bytes());
+        writer.write_all(&buf)
+    }
+
+    /// Appends a frame to the table.
+    pub(crate) fn push(&mut self, entry: SeekTableEntry) {
+        let frame = self.frames.last().zip(self.entries.last()).map_or(
+            FrameEntry {
+                compressed_offset: 0,
+                offset: 0,
+            },
+            |(frame, prev)| FrameEntry {
+                compressed_offset: frame.compressed_offset + prev.compressed_size as u64,
+                offset: frame.offset + prev.decompressed_size as u64,
+            },
+        );
+        self.entries.push(entry);
+        self.frames.push(frame);
+    }
+
+    /// Returns the entries of the table in the order of the frames in the file.
+    pub fn entries(&self) -> &[SeekTableEntry] {
+        &self.entries
+    }
+
+    /// Returns the start of the frame containing the decompressed `offset`.
+    pub fn frame_for(&self, offset: u64) -> Option<FrameEntry> {
+        let pos = self.frames.partition_point(|frame| frame.offset <= offset);
+        let i = pos.checked_sub(1)?;
+        (offset < self.frames[i].offset + self.entries[i].decompressed_size as u64)
+            .then_some(self.frames[i])
+    }
+}
+
+/// A Zstandard decoder that tracks the boundaries of frames, which allows it to skip
+/// ahead by seeking to later frames when the input has a [`SeekTable`].
+pub(crate) struct FrameReader<R> {
+    reader: R,
+    decoder: RawDecoder<'static>,
+    seek_table: Option<SeekTable>,
+    at_frame_start: bool,
+    compressed_pos: u64,
+    pos: u64,
+    tracked_frames: Option<Vec<FrameEntry>>,
+}
+
+impl<R> FrameReader<R>
+where
+    R: BufRead,
+{
+    /// Creates a new decoder of the Zstandard-compressed `reader`. Skipping ahead will
+    /// use `seek_table` if present, whose offsets are relative to the current
+    /// position of `reader`.
+    pub fn new(reader: R, seek_table: Option<SeekTable>) -> crate::Result<Self> {
+        Ok(Self {
+            reader,
+            decoder: RawDecoder::new().map_err(|e| crate::Error::io(e, "creating zstd decoder"))?,
+            seek_table,
+            at_frame_start: true,
+            compressed_pos: 0,
+            pos: 0,
+            tracked_frames: None,
+        })
+    }
+
+    /// Records the start of every frame read, which can be retrieved with
+    /// [`Self::into_frames()`]. Frames that don't decompress to any bytes, like
+    /// skippable frames, are replaced by the following frame.
+    pub fn track_frames(mut self) -> Self {
+        self.tracked_frames = Some(Vec::new());
+        self
+    }
+
+    /// Returns the number of compressed bytes read.
+    pub fn compressed_pos(&self) -> u64 {
+        self.compressed_pos
+    }
+
+    /// Returns the frames read if tracking was enabled.
+    pub fn into_frames(self) -> Vec<FrameEntry> {
+        self.tracked_frames.unwrap_or_default()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> Read for FrameReader<R>
+where
+    R: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let input = self.reader.fill_buf()?;
+            let is_eof = input.is_empty();
+            if self.at_frame_start {
+                if is_eof {
+                    return Ok(0);
+                }
+                self.decoder.reinit()?;
+                self.at_frame_start = false;
+                if let Some(frames) = self.tracked_frames.as_mut() {
+                    let frame = FrameEntry {
+                        compressed_offset: self.compressed_pos,
+                        offset: self.pos,
+                    };
+                    match frames.last_mut() {
+                        Some(last) if last.offset == frame.offset => *last = frame,
+                        _ => frames.push(frame),
+                    }
+                }
+            }
+            let mut in_buf = InBuffer::around(input);
+            let mut out_buf = OutBuffer::around(&mut *buf);
+            let hint = self.decoder.run(&mut in_buf, &mut out_buf)?;
+            let consumed = in_buf.pos();
+            let produced = out_buf.pos();
+            self.reader.consume(consumed);
+            self.compressed_pos += consumed as u64;
+            self.pos += produced as u64;
+            if hint == 0 {
+                self.at_frame_start = true;
+            }
+            if produced > 0 {
+                return Ok(produced);
+            }
+            if is_eof && !self.at_frame_start {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "incomplete Zstandard frame",
+                ));
+            }
+        }
+    }
+}
+
+impl<R> SkipBytes for FrameReader<R>
+where
+    R: BufRead + Seek,
+{
+    fn skip_bytes(&mut self, n_bytes: usize) -> crate::Result<()> {
+        let handle_err = |err| crate::Error::io(err, format!("seeking ahead {n_bytes} bytes"));
+        let target = self.pos + n_bytes as u64;
+        if let Some(frame) = self
+            .seek_table
+            .as_ref()
+            .and_then(|table| table.frame_for(target))
+            .filter(|frame| frame.offset > self.pos)
+        {
+            let relative = frame.compressed_offset as i64 - self.compressed_pos as i64;
+            self.reader
+                .seek(SeekFrom::Current(relative))
+                .map_err(handle_err)?;
+            self.compressed_pos = frame.compressed_offset;
+            self.pos = frame.offset;
+            self.at_frame_start = true;
+        }
+        let remaining = target - self.pos;
+        let skipped =
+            io::copy(&mut self.by_ref().take(remaining), &mut io::sink()).map_err(handle_err)?;
+        if skipped < remaining {
+            return Err(crate::Error::io(
+                io::Error::from(io::ErrorKind::UnexpectedEof),
+                format!(
+                    "seeking ahead {n_bytes} bytes. Only able to seek {} bytes",
+                    n_bytes as u64 - (remaining - skipped)
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// Helper to create an async Zstandard decoder with multiple member support.
 #[cfg(feature = "async")]
 pub fn zstd_decoder<R>(reader: R) -> async_compression::tokio::bufread::ZstdDecoder<R>
@@ -0,0 +1,647 @@
+//! Record-by-record comparison of DBN files and streams.
+//!
+//! A [`Differ`] reads two decoders in parallel and aligns their records by index
+//! timestamp, instrument ID, and rtype. Records found only in the left input are
+//! reported as removed, records found only in the right input as added, and aligned
+//! records whose contents differ as changed, along with the fields that differ. The
+//! metadata of the inputs is compared the same way.
+//!
+//! Fields are compared through their JSON representation, so nested fields like the
+//! header or book levels are reported with paths like `hd.ts_event` and
+//! `levels.0.bid_px`.
+//!
+//! Both inputs are expected to be sorted by index timestamp, like DBN files from
+//! Databento are. Out-of-order records are still compared, but may be reported as
+//! removed and added instead of aligned.
+//!
+//! # Example
+//! ```no_run
+//! use dbn::{decode::DynDecoder, diff::diff, VersionUpgradePolicy};
+//!
+//! let left = DynDecoder::from_file("before.mbo.dbn.zst", VersionUpgradePolicy::AsIs)?;
+//! let right = DynDecoder::from_file("after.mbo.dbn.zst", VersionUpgradePolicy::AsIs)?;
+//! let report = diff(left, right)?;
+//! if !report.is_empty() {
+//!     println!("{report}");
+//! }
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    decode::{DbnMetadata, DecodeRecordRef},
+    encode::{json::serialize::to_json_in_buf, DbnEncodable},
+    enums::RType,
+    rtype_dispatch, Metadata, Record, RecordBuf, Result,
+};
+
+/// A field whose value differs between the left and right input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    /// The path to the field, with nested fields separated by `.`.
+    pub field: String,
+    /// The value of the field in the left input. `null` if the field is missing.
+    pub left: Value,
+    /// The value of the field in the right input. `null` if the field is missing.
+    pub right: Value,
+}
+
+/// How a record differs between the left and right input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordDiffKind {
+    /// The record is only in the right input.
+    Added {
+        /// The record as JSON.
+        record: Value,
+    },
+    /// The record is only in the left input.
+    Removed {
+        /// The record as JSON.
+        record: Value,
+    },
+    /// The record is in both inputs with different contents.
+    Changed {
+        /// The fields that differ.
+        fields: Vec<FieldDiff>,
+    },
+}
+
+impl RecordDiffKind {
+    /// Returns the name of the kind of difference in snake case, as used in the JSON
+    /// report.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Added { .. } => "added",
+            Self::Removed { .. } => "removed",
+            Self::Changed { .. } => "changed",
+        }
+    }
+}
+
+/// A difference in a record between the left and right input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordDiff {
+    /// The index timestamp of the record.
+    pub index_ts: u64,
+    /// The instrument ID of the record.
+    pub instrument_id: u32,
+    /// The rtype of the record.
+    pub rtype: u8,
+    /// How the record differs.
+    pub kind: RecordDiffKind,
+}
+
+/// The results of comparing two DBN inputs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    /// The fields of the metadata that differ.
+    pub metadata: Vec<FieldDiff>,
+    /// The number of records in the left input.
+    pub left_record_count: u64,
+    /// The number of records in the right input.
+    pub right_record_count: u64,
+    /// The differences found in the records, up to the maximum set with
+    /// [`Differ::max_differences()`].
+    pub differences: Vec<RecordDiff>,
+    /// The number of records only in the right input.
+    pub added_count: u64,
+    /// The number of records only in the left input.
+    pub removed_count: u64,
+    /// The number of records in both inputs with different contents.
+    pub changed_count: u64,
+}
+
+impl Report {
+    /// Returns `true` if neither the metadata nor the records differ.
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty() && self.record_difference_count() == 0
+    }
+
+    /// Returns the total number of records that differ, including those past the
+    /// maximum.
+    pub fn record_difference_count(&self) -> u64 {
+        self.added_count + self.removed_count + self.changed_count
+    }
+
+    /// Serializes the report to JSON, optionally with indentation.
+    pub fn to_json(&self, pretty: bool) -> String {
+        let differences = self
+            .differences
+            .iter()
+            .map(|diff| {
+                let mut res = json!({
+                    "kind": diff.kind.name(),
+                    "index_ts": diff.index_ts,
+                    "instrument_id": diff.instrument_id,
+                    "rtype": diff.rtype,
+                });
+                match &diff.kind {
+                    RecordDiffKind::Added { record } | RecordDiffKind::Removed { record } => {
+                        res["record"] = record.clone();
+                    }
+                    RecordDiffKind::Changed { fields } => {
+                        res["fields"] = fields_to_json(fields);
+                    }
+                }
+                res
+            })
+            .collect::<Vec<_>>();
+        let report = json!({
+            "identical": self.is_empty(),
+            "metadata": fields_to_json(&self.metadata),
+            "left_record_count": self.left_record_count,
+            "right_record_count": self.right_record_count,
+            "added_count": self.added_count,
+            "removed_count": self.removed_count,
+            "changed_count": self.changed_count,
+            "differences": differences,
+        });
+        let res = if pretty {
+            serde_json::to_string_pretty(&report)
+        } else {
+            serde_json::to_string(&report)
+        };
+        // Serializing a `Value` can't fail
+        res.unwrap()
+    }
+}
+
+fn fields_to_json(fields: &[FieldDiff]) -> Value {
+    Value::Array(
+        fields
+            .iter()
+            .map(|field| json!({ "field": field.field, "left": field.left, "right": field.right }))
+            .collect(),
+    )
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for field in self.metadata.iter() {
+            writeln!(
+                f,
+                "~ metadata.{}: {} -> {}",
+                field.field, field.left, field.right
+            )?;
+        }
+        for diff in self.differences.iter() {
+            match &diff.kind {
+                RecordDiffKind::Removed { record } => writeln!(f, "- {record}")?,
+                RecordDiffKind::Added { record } => writeln!(f, "+ {record}")?,
+                RecordDiffKind::Changed { fields } => {
+                    let rtype = RType::try_from(diff.rtype)
+                        .map(|rtype| rtype.as_str().to_owned())
+                        .unwrap_or_else(|_| format!("{:#04x}", diff.rtype));
+                    write!(
+                        f,
+                        "~ {rtype} instrument_id={} index_ts={}:",
+                        diff.instrument_id, diff.index_ts
+                    )?;
+                    for field in fields {
+                        write!(f, " {}: {} -> {};", field.field, field.left, field.right)?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        if self.record_difference_count() > self.differences.len() as u64 {
+            writeln!(f, "...")?;
+        }
+        write!(
+            f,
+            "{} record(s) added, {} removed, {} changed; {} metadata field(s) changed",
+            self.added_count,
+            self.removed_count,
+            self.changed_count,
+            self.metadata.len()
+        )
+    }
+}
+
+/// Compares the metadata and records of two DBN inputs. See the
+/// [module-level documentation](crate::diff) for how records are aligned.
+#[derive(Debug, Clone)]
+pub struct Differ {
+    max_differences: usize,
+}
+
+impl Differ {
+    /// The default maximum number of record differences retained in the report.
+    pub const DEFAULT_MAX_DIFFERENCES: usize = 1000;
+
+    /// Creates a new differ.
+    pub fn new() -> Self {
+        Self {
+            max_differences: Self::DEFAULT_MAX_DIFFERENCES,
+        }
+    }
+
+    /// Sets the maximum number of record differences retained in the report.
+    /// Differences past the maximum are still counted. Defaults to
+    /// [`DEFAULT_MAX_DIFFERENCES`](Self::DEFAULT_MAX_DIFFERENCES).
+    pub fn max_differences(mut self, max_differences: usize) -> Self {
+        self.max_differences = max_differences;
+        self
+    }
+
+    /// Compares the metadata and all remaining records of `left` and `right`.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to decode a record from either
+    /// input.
+    pub fn diff<L, R>(&self, mut left: L, mut right: R) -> Result<Report>
+    where
+        L: DecodeRecordRef + DbnMetadata,
+        R: DecodeRecordRef + DbnMetadata,
+    {
+        let mut report = Report {
+            metadata: diff_metadata(left.metadata(), right.metadata()),
+            ..Default::default()
+        };
+        let left_ts_out = left.metadata().ts_out;
+        let right_ts_out = right.metadata().ts_out;
+        let mut left = Side::new(&mut left, left_ts_out)?;
+        let mut right = Side::new(&mut right, right_ts_out)?;
+        loop {
+            let index_ts = match (left.head_index_ts(), right.head_index_ts()) {
+                (Some(l), Some(r)) => l.min(r),
+                (Some(l), None) => l,
+                (None, Some(r)) => r,
+                (None, None) => break,
+            };
+            let left_group = left.take_group(index_ts)?;
+            let right_group = right.take_group(index_ts)?;
+            self.diff_group(&mut report, index_ts, left_group, right_group)?;
+        }
+        report.left_record_count = left.record_count;
+        report.right_record_count = right.record_count;
+        Ok(report)
+    }
+
+    /// Aligns and compares the records of both inputs with the same index timestamp.
+    fn diff_group(
+        &self,
+        report: &mut Report,
+        index_ts: u64,
+        left: Vec<GroupRecord>,
+        mut right: Vec<GroupRecord>,
+    ) -> Result<()> {
+        let mut unmatched_left = Vec::new();
+        // Exact matches are paired first so an inserted record doesn't shift the
+        // alignment of the records after it
+        for rec in left {
+            if let Some(pos) = right
+                .iter()
+                .position(|other| rec.key == other.key && rec.bytes() == other.bytes())
+            {
+                right.remove(pos);
+            } else {
+                unmatched_left.push(rec);
+            }
+        }
+        for rec in unmatched_left {
+            let kind = if let Some(pos) = right.iter().position(|other| rec.key == other.key) {
+                let other = right.remove(pos);
+                RecordDiffKind::Changed {
+                    fields: diff_values("", &rec.to_json()?, &other.to_json()?),
+                }
+            } else {
+                RecordDiffKind::Removed {
+                    record: rec.to_json()?,
+                }
+            };
+            self.push(report, index_ts, &rec, kind);
+        }
+        for rec in right {
+            let kind = RecordDiffKind::Added {
+                record: rec.to_json()?,
+            };
+            self.push(report, index_ts, &rec, kind);
+        }
+        Ok(())
+    }
+
+    fn push(&self, report: &mut Report, index_ts: u64, rec: &GroupRecord, kind: RecordDiffKind) {
+        match kind {
+            RecordDiffKind::Added { .. } => report.added_count += 1,
+            RecordDiffKind::Removed { .. } => report.removed_count += 1,
+            RecordDiffKind::Changed { .. } => report.changed_count += 1,
+        }
+        if report.differences.len() < self.max_differences {
+            let (instrument_id, rtype) = rec.key;
+            report.differences.push(RecordDiff {
+                index_ts,
+                instrument_id,
+                rtype,
+                kind,
+            });
+        }
+    }
+}
+
+impl Default for Differ {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compares the metadata and records of `left` and `right` with the default
+/// [`Differ`] settings.
+///
+/// # Errors
+/// This function returns an error if it fails to decode a record from either input.
+pub fn diff<L, R>(left: L, right: R) -> Result<Report>
+where
+    L: DecodeRecordRef + DbnMetadata,
+    R: DecodeRecordRef + DbnMetadata,
+{
+    Differ::new().diff(left, right)
+}
+
+/// Returns the fields that differ between the metadata of the two inputs.
+pub fn diff_metadata(left: &Metadata, right: &Metadata) -> Vec<FieldDiff> {
+    diff_values("", &to_json_value(left), &to_json_value(right))
+}
+
+/// The instrument ID and rtype records are aligned by within an index timestamp.
+type AlignKey = (u32, u8);
+
+/// A record from one input with the index timestamp of the group being compared.
+struct GroupRecord {
+    key: AlignKey,
+    ts_out: bool,
+    rec: RecordBuf,
+}
+
+impl GroupRecord {
+    fn bytes(&self) -> &[u8] {
+        self.rec.as_ref()
+    }
+
+    fn to_json(&self) -> Result<Value> {
+        fn to_json<R: DbnEncodable>(rec: &R) -> Value {
+            to_json_value(rec)
+        }
+        let rec = self.rec.as_rec_ref();
+        rtype_dispatch!(rec, ts_out: self.ts_out, to_json())
+    }
+}
+
+/// One of the inputs being compared, with the next record buffered.
+struct Side<'a, D> {
+    decoder: &'a mut D,
+    ts_out: bool,
+    head: Option<RecordBuf>,
+    record_count: u64,
+}
+
+impl<'a, D> Side<'a, D>
+where
+    D: DecodeRecordRef,
+{
+    fn new(decoder: &'a mut D, ts_out: bool) -> Result<Self> {
+        let mut res = Self {
+            decoder,
+            ts_out,
+            head: None,
+            record_count: 0,
+        };
+        res.advance()?;
+        Ok(res)
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        self.head = self
+            .decoder
+            .decode_record_ref()?
+            .map(RecordBuf::try_from)
+            .transpose()?;
+        Ok(())
+    }
+
+    fn head_index_ts(&self) -> Option<u64> {
+        self.head
+            .as_ref()
+            .map(|rec| rec.as_rec_ref().raw_index_ts())
+    }
+
+    /// Takes the consecutive records with `index_ts`.
+    fn take_group(&mut self, index_ts: u64) -> Result<Vec<GroupRecord>> {
+        let mut group = Vec::new();
+        while self.head_index_ts() == Some(index_ts) {
+            let rec = self.head.take().unwrap();
+            let hd = rec.header();
+            group.push(GroupRecord {
+                key: (hd.instrument_id, hd.rtype),
+                ts_out: self.ts_out,
+                rec,
+            });
+            self.record_count += 1;
+            self.advance()?;
+        }
+        Ok(group)
+    }
+}
+
+fn to_json_value<T: crate::encode::json::serialize::JsonSerialize>(obj: &T) -> Value {
+    let mut buf = String::new();
+    to_json_in_buf(&mut buf, obj, false, false, false);
+    // The JSON serializer always produces valid JSON
+    serde_json::from_str(&buf).unwrap()
+}
+
+/// Compares `left` and `right` recursively, returning the differing leaf values.
+fn diff_values(path: &str, left: &Value, right: &Value) -> Vec<FieldDiff> {
+    fn join(path: &str, key: &str) -> String {
+        if path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{path}.{key}")
+        }
+    }
+
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => l
+            .keys()
+            .chain(r.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .flat_map(|key| {
+                diff_values(
+                    &join(path, key),
+                    l.get(key).unwrap_or(&Value::Null),
+                    r.get(key).unwrap_or(&Value::Null),
+                )
+            })
+            .collect(),
+        (Value::Array(l), Value::Array(r)) if l.len() == r.len() => l
+            .iter()
+            .zip(r.iter())
+            .enumerate()
+            .flat_map(|(i, (l, r))| diff_values(&join(path, &i.to_string()), l, r))
+            .collect(),
+        (l, r) if l == r => Vec::new(),
+        (l, r) => vec![FieldDiff {
+            field: path.to_owned(),
+            left: l.clone(),
+            right: r.clone(),
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        encode::{DbnEncoder, EncodeRecord},
+        rtype, Action, MboMsg, RecordHeader,
+    };
+
+    // 2023-07-03 00:00 UTC
+    const TS: u64 = 1_688_342_400_000_000_000;
+
+    fn mbo(instrument_id: u32, ts_recv: u64, order_id: u64, price: i64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, instrument_id, ts_recv - 10),
+            order_id,
+            price,
+            size: 1,
+            action: Action::Add as std::ffi::c_char,
+            ts_recv,
+            ..Default::default()
+        }
+    }
+
+    fn decoder(metadata: &Metadata, records: &[MboMsg]) -> DbnDecoder<std::io::Cursor<Vec<u8>>> {
+        let mut encoder = DbnEncoder::new(Vec::new(), metadata).unwrap();
+        for rec in records {
+            encoder.encode_record(rec).unwrap();
+        }
+        DbnDecoder::new(std::io::Cursor::new(encoder.get_ref().clone())).unwrap()
+    }
+
+    fn metadata() -> Metadata {
+        Metadata::builder()
+            .dataset("XNAS.ITCH")
+            .schema(Some(crate::Schema::Mbo))
+            .stype_in(Some(crate::SType::RawSymbol))
+            .stype_out(crate::SType::InstrumentId)
+            .start(TS)
+            .build()
+    }
+
+    #[test]
+    fn test_identical_files() {
+        let path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+        let report = diff(
+            DbnDecoder::from_zstd_file(&path).unwrap(),
+            DbnDecoder::from_zstd_file(&path).unwrap(),
+        )
+        .unwrap();
+        assert!(report.is_empty(), "{report}");
+        assert_eq!(report.left_record_count, 2);
+        assert_eq!(report.right_record_count, 2);
+    }
+
+    #[test]
+    fn test_added_removed_changed() {
+        let metadata = metadata();
+        let left = [
+            mbo(1, TS + 1, 10, 100),
+            mbo(2, TS + 1, 11, 100),
+            mbo(1, TS + 2, 12, 100),
+        ];
+        let right = [
+            mbo(1, TS + 1, 10, 100),
+            // changed price
+            mbo(2, TS + 1, 11, 105),
+            // instrument 1 at TS + 2 removed, instrument 3 added
+            mbo(3, TS + 2, 13, 100),
+        ];
+        let report = diff(decoder(&metadata, &left), decoder(&metadata, &right)).unwrap();
+        assert!(report.metadata.is_empty());
+        assert_eq!(report.changed_count, 1);
+        assert_eq!(report.removed_count, 1);
+        assert_eq!(report.added_count, 1);
+        assert_eq!(
+            report.differences[0],
+            RecordDiff {
+                index_ts: TS + 1,
+                instrument_id: 2,
+                rtype: rtype::MBO,
+                kind: RecordDiffKind::Changed {
+                    fields: vec![FieldDiff {
+                        field: "price".to_owned(),
+                        left: Value::from("100"),
+                        right: Value::from("105"),
+                    }]
+                }
+            }
+        );
+        assert_eq!(report.differences[1].kind.name(), "removed");
+        assert_eq!(report.differences[1].instrument_id, 1);
+        assert_eq!(report.differences[2].kind.name(), "added");
+        assert_eq!(report.differences[2].instrument_id, 3);
+    }
+
+    #[test]
+    fn test_insertion_doesnt_shift_alignment() {
+        let metadata = metadata();
+        let left = [mbo(1, TS, 10, 100), mbo(1, TS, 11, 100)];
+        let right = [mbo(1, TS, 9, 100), mbo(1, TS, 10, 100), mbo(1, TS, 11, 100)];
+        let report = diff(decoder(&metadata, &left), decoder(&metadata, &right)).unwrap();
+        assert_eq!(report.added_count, 1);
+        assert_eq!(report.changed_count, 0);
+        assert_eq!(report.removed_count, 0);
+    }
+
+    #[rstest]
+    #[case::all(Differ::DEFAULT_MAX_DIFFERENCES, 3)]
+    #[case::truncated(1, 1)]
+    fn test_max_differences(#[case] max_differences: usize, #[case] exp_len: usize) {
+        let metadata = metadata();
+        let left = [mbo(1, TS, 10, 100), mbo(1, TS + 1, 11, 100)];
+        let right = [mbo(1, TS + 2, 12, 100)];
+        let report = Differ::new()
+            .max_differences(max_differences)
+            .diff(decoder(&metadata, &left), decoder(&metadata, &right))
+            .unwrap();
+        assert_eq!(report.record_difference_count(), 3);
+        assert_eq!(report.differences.len(), exp_len);
+        assert_eq!(report.to_string().contains("...\n"), exp_len < 3);
+        assert!(report
+            .to_string()
+            .ends_with("1 record(s) added, 2 removed, 0 changed; 0 metadata field(s) changed"));
+    }
+
+    #[test]
+    fn test_metadata_diff() {
+        let left = metadata();
+        let mut right = metadata();
+        right.dataset = "GLBX.MDP3".to_owned();
+        right.symbols = vec!["ESM4".to_owned()];
+        let report = diff(decoder(&left, &[]), decoder(&right, &[])).unwrap();
+        assert!(!report.is_empty());
+        let fields = report
+            .metadata
+            .iter()
+            .map(|field| field.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["dataset", "symbols"]);
+        let json = report.to_json(false);
+        assert!(json.contains(r#""identical":false"#), "{json}");
+        assert!(
+            json.contains(r#"{"field":"dataset","left":"XNAS.ITCH","right":"GLBX.MDP3"}"#),
+            "{json}"
+        );
+    }
+}
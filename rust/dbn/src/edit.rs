@@ -0,0 +1,355 @@
+//! Editing the metadata of existing DBN files.
+//!
+//! [`MetadataEncoder::update_encoded()`] can only patch the fixed-length `start`, `end`,
+//! and `limit` fields. [`edit_metadata_file()`] rewrites the whole metadata section, so
+//! any field can be changed, including the variable-length `symbols` and `mappings`.
+//! When the re-encoded metadata fits within the length of the existing metadata, the
+//! file is overwritten in place and the records aren't touched. Otherwise, and always
+//! for Zstd-compressed files, the file is rewritten with a streaming copy of the
+//! records.
+//!
+//! The records are copied as-is, so the DBN `version` and `ts_out` can't be changed.
+//!
+//! # Example
+//! ```no_run
+//! use dbn::{edit::edit_metadata_file, SType};
+//!
+//! edit_metadata_file("20240102.trades.dbn.zst", |metadata| {
+//!     metadata.dataset = "XNAS.ITCH".to_owned();
+//!     metadata.stype_in = Some(SType::RawSymbol);
+//! })?;
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    concat::read_metadata,
+    decode,
+    encode::{dbn::MetadataEncoder, ZSTD_COMPRESSION_LEVEL},
+    Error, Metadata, Result,
+};
+
+/// How [`edit_metadata_file()`] updated a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditMethod {
+    /// The metadata was overwritten in place without touching the records.
+    InPlace,
+    /// The file was rewritten with the new metadata followed by a copy of the records.
+    Rewritten,
+}
+
+/// Reads uncompressed DBN from `reader`, applies `edit` to its metadata, and writes
+/// the edited metadata followed by the unmodified records to `writer`. Returns the
+/// edited metadata.
+///
+/// # Errors
+/// This function returns an error if the input isn't DBN, `edit` changes the DBN
+/// version or `ts_out`, or it fails to read from `reader` or write to `writer`.
+pub fn edit_metadata<R, W, F>(mut reader: R, mut writer: W, edit: F) -> Result<Metadata>
+where
+    R: io::Read,
+    W: io::Write,
+    F: FnOnce(&mut Metadata),
+{
+    let metadata = apply_edit(read_metadata(&mut reader)?, edit)?;
+    MetadataEncoder::new(&mut writer).encode(&metadata)?;
+    io::copy(&mut reader, &mut writer).map_err(|e| Error::io(e, "copying records"))?;
+    writer
+        .flush()
+        .map_err(|e| Error::io(e, "flushing output"))?;
+    Ok(metadata)
+}
+
+/// Applies `edit` to the metadata of the DBN file at `path`, Zstd-compressed or not.
+/// Uncompressed files are updated in place when the edited metadata fits in the space
+/// of the existing metadata, otherwise the file is replaced with a rewritten copy.
+///
+/// # Errors
+/// This function returns an error if it fails to open or replace the file, the file
+/// isn't DBN, or `edit` changes the DBN version or `ts_out`.
+pub fn edit_metadata_file<P, F>(path: P, edit: F) -> Result<EditMethod>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut Metadata),
+{
+    let path = path.as_ref();
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| {
+            Error::io(
+                e,
+                format!("opening file to edit at path '{}'", path.display()),
+            )
+        })?;
+    let mut prefix = [0; 4];
+    file.read_exact(&mut prefix)
+        .map_err(|e| Error::io(e, "reading file prefix"))?;
+    file.rewind()
+        .map_err(|e| Error::io(e, "seeking to start of file"))?;
+    let permissions = file
+        .metadata()
+        .map_err(|e| Error::io(e, "reading file permissions"))?
+        .permissions();
+    if decode::zstd::starts_with_prefix(&prefix) {
+        replace_file(path, permissions, move |writer| {
+            let reader = zstd::stream::Decoder::new(file)
+                .map_err(|e| Error::io(e, "creating zstd decoder"))?;
+            let mut encoder = zstd::Encoder::new(writer, ZSTD_COMPRESSION_LEVEL)
+                .map_err(|e| Error::io(e, "creating zstd encoder"))?;
+            encoder
+                .include_checksum(true)
+                .map_err(|e| Error::io(e, "setting zstd checksum"))?;
+            edit_metadata(reader, &mut encoder, edit)?;
+            encoder
+                .finish()
+                .map_err(|e| Error::io(e, "finishing zstd stream"))?
+                .flush()
+                .map_err(|e| Error::io(e, "flushing output"))
+        })?;
+        return Ok(EditMethod::Rewritten);
+    }
+    let metadata = apply_edit(read_metadata(&mut file)?, edit)?;
+    let existing_len =
+        file.stream_position()
+            .map_err(|e| Error::io(e, "getting length of existing metadata"))? as usize;
+    let mut encoded = Vec::new();
+    MetadataEncoder::new(&mut encoded).encode(&metadata)?;
+    if encoded.len() <= existing_len {
+        // The decoder ignores trailing bytes within the metadata length, so pad the
+        // edited metadata to the existing length to leave the records where they are
+        encoded.resize(existing_len, 0);
+        encoded[4..8].copy_from_slice(&((existing_len - 8) as u32).to_le_bytes());
+        file.rewind()
+            .map_err(|e| Error::io(e, "seeking to start of file"))?;
+        file.write_all(&encoded)
+            .map_err(|e| Error::io(e, "writing DBN metadata"))?;
+        file.flush().map_err(|e| Error::io(e, "flushing file"))?;
+        Ok(EditMethod::InPlace)
+    } else {
+        replace_file(path, permissions, move |mut writer| {
+            writer
+                .write_all(&encoded)
+                .map_err(|e| Error::io(e, "writing DBN metadata"))?;
+            io::copy(&mut BufReader::new(file), &mut writer)
+                .map_err(|e| Error::io(e, "copying records"))?;
+            writer.flush().map_err(|e| Error::io(e, "flushing output"))
+        })?;
+        Ok(EditMethod::Rewritten)
+    }
+}
+
+/// Applies `edit` to `metadata`, checking it didn't change any fields the records
+/// depend on.
+fn apply_edit<F>(metadata: Metadata, edit: F) -> Result<Metadata>
+where
+    F: FnOnce(&mut Metadata),
+{
+    let mut edited = metadata.clone();
+    edit(&mut edited);
+    let unchanged = |field: &str, original: String, edited: String| {
+        Err(Error::BadArgument {
+            param_name: "edit".to_owned(),
+            desc: format!(
+                "can't change {field} from {original} to {edited} without re-encoding the records"
+            ),
+        })
+    };
+    if edited.version != metadata.version {
+        return unchanged(
+            "DBN version",
+            metadata.version.to_string(),
+            edited.version.to_string(),
+        );
+    }
+    if edited.ts_out != metadata.ts_out {
+        return unchanged(
+            "ts_out",
+            metadata.ts_out.to_string(),
+            edited.ts_out.to_string(),
+        );
+    }
+    Ok(edited)
+}
+
+/// Writes a new copy of the file at `path` with `write` to a temporary file in the same
+/// directory with the same `permissions` and then renames it over the original.
+fn replace_file<F>(path: &Path, permissions: fs::Permissions, write: F) -> Result<()>
+where
+    F: FnOnce(BufWriter<File>) -> Result<()>,
+{
+    let mut temp_path = OsString::from(path.as_os_str());
+    temp_path.push(format!(".edit-{}", std::process::id()));
+    let temp_path = PathBuf::from(temp_path);
+    let temp_file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(|e| {
+            Error::io(
+                e,
+                format!("creating temporary file '{}'", temp_path.display()),
+            )
+        })?;
+    let res = temp_file
+        .set_permissions(permissions)
+        .map_err(|e| Error::io(e, "copying file permissions"))
+        .and_then(|_| write(BufWriter::new(temp_file)))
+        .and_then(|_| {
+            fs::rename(&temp_path, path).map_err(|e| {
+                Error::io(
+                    e,
+                    format!("replacing '{}' with edited copy", path.display()),
+                )
+            })
+        });
+    if res.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+    use time::macros::date;
+
+    use super::*;
+    use crate::{
+        decode::{DbnDecoder, DbnMetadata, DecodeRecord, DynDecoder},
+        encode::{DbnEncoder, DynWriter, EncodeRecord},
+        rtype, Compression, MappingInterval, MboMsg, RecordHeader, SType, Schema, SymbolMapping,
+        VersionUpgradePolicy,
+    };
+
+    // 2023-07-03 00:00 UTC
+    const TS: u64 = 1_688_342_400_000_000_000;
+
+    fn metadata() -> Metadata {
+        Metadata::builder()
+            .dataset("XNAS.ITCH")
+            .schema(Some(Schema::Mbo))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(TS)
+            .symbols(vec!["AAPL".to_owned(), "MSFT".to_owned()])
+            .build()
+    }
+
+    fn records() -> Vec<MboMsg> {
+        (0..3)
+            .map(|i| MboMsg {
+                hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 1, TS + i),
+                ts_recv: TS + i,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn test_file(name: &str, compression: Compression) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dbn-edit-{name}-{}", std::process::id()));
+        let mut encoder = DbnEncoder::new(
+            DynWriter::new(File::create(&path).unwrap(), compression).unwrap(),
+            &metadata(),
+        )
+        .unwrap();
+        for rec in records() {
+            encoder.encode_record(&rec).unwrap();
+        }
+        drop(encoder);
+        path
+    }
+
+    fn decode_file(path: &Path) -> (Metadata, Vec<MboMsg>) {
+        let decoder = DynDecoder::from_file(path, VersionUpgradePolicy::AsIs).unwrap();
+        let metadata = decoder.metadata().clone();
+        (metadata, decoder.decode_records().unwrap())
+    }
+
+    fn add_mappings(metadata: &mut Metadata) {
+        metadata.mappings = metadata
+            .symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| SymbolMapping {
+                raw_symbol: symbol.clone(),
+                intervals: vec![MappingInterval {
+                    start_date: date!(2023 - 07 - 03),
+                    end_date: date!(2023 - 07 - 04),
+                    symbol: (i + 1).to_string(),
+                }],
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_edit_metadata() {
+        let mut encoder = DbnEncoder::new(Vec::new(), &metadata()).unwrap();
+        for rec in records() {
+            encoder.encode_record(&rec).unwrap();
+        }
+        let input = encoder.get_ref();
+        let mut output = Vec::new();
+        let edited = edit_metadata(input.as_slice(), &mut output, add_mappings).unwrap();
+        assert_eq!(edited.mappings.len(), 2);
+        let decoder = DbnDecoder::new(output.as_slice()).unwrap();
+        assert_eq!(decoder.metadata(), &edited);
+        assert_eq!(decoder.decode_records::<MboMsg>().unwrap(), records());
+    }
+
+    #[test]
+    fn test_edit_in_place() {
+        let path = test_file("in-place", Compression::None);
+        let len = fs::metadata(&path).unwrap().len();
+        let method = edit_metadata_file(&path, |metadata| {
+            metadata.dataset = "GLBX.MDP3".to_owned();
+            metadata.symbols.pop();
+        })
+        .unwrap();
+        assert_eq!(method, EditMethod::InPlace);
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+        let (metadata, records) = decode_file(&path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(metadata.dataset, "GLBX.MDP3");
+        assert_eq!(metadata.symbols, ["AAPL"]);
+        assert_eq!(records, self::records());
+    }
+
+    #[rstest]
+    #[case::uncompressed(Compression::None)]
+    #[case::zstd(Compression::Zstd)]
+    fn test_edit_rewrites(#[case] compression: Compression) {
+        let path = test_file(&format!("rewrite-{compression:?}"), compression);
+        let method = edit_metadata_file(&path, add_mappings).unwrap();
+        assert_eq!(method, EditMethod::Rewritten);
+        let (metadata, records) = decode_file(&path);
+        fs::remove_file(path).unwrap();
+        let mut expected = self::metadata();
+        add_mappings(&mut expected);
+        assert_eq!(metadata, expected);
+        assert_eq!(records, self::records());
+    }
+
+    #[rstest]
+    #[case::version(|m: &mut Metadata| m.version = 2, "DBN version")]
+    #[case::ts_out(|m: &mut Metadata| m.ts_out = true, "ts_out")]
+    fn test_edit_record_fields(#[case] edit: fn(&mut Metadata), #[case] field: &str) {
+        let path = test_file(&format!("invalid-{field}"), Compression::None);
+        let contents = fs::read(&path).unwrap();
+        let err = edit_metadata_file(&path, edit).unwrap_err();
+        let edited_contents = fs::read(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(
+            matches!(&err, Error::BadArgument { desc, .. } if desc.contains(field)),
+            "{err:?}"
+        );
+        assert_eq!(edited_contents, contents);
+    }
+}
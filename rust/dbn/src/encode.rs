@@ -1,8 +1,10 @@
-//! Encoding DBN records to various formats: DBN binary, CSV, and JSON.
+//! Encoding DBN records to various formats: DBN binary, CSV, JSON, and Parquet.
 //!
 //! Each format has a dedicated encoder ([`DbnEncoder`], [`CsvEncoder`],
-//! [`JsonEncoder`]). When the format is chosen at runtime, use [`DynEncoder`]
-//! with [`DynEncoderBuilder`].
+//! [`JsonEncoder`]). With the `parquet` feature flag, [`ParquetEncoder`] is also
+//! available, and with the `arrow` feature flag, [`RecordBatchEncoder`] converts
+//! records into in-memory Arrow record batches. When the format is chosen at
+//! runtime, use [`DynEncoder`] with [`DynEncoderBuilder`].
 //!
 //! Sync encoders implement the [`EncodeDbn`] trait. With the `async` feature flag,
 //! async variants are also available.
@@ -36,12 +38,17 @@
 //! assert_eq!(*rec, MboMsg::default());
 //! # Ok::<(), dbn::Error>(())
 //! ```
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod csv;
 pub mod dbn;
 mod dyn_encoder;
 mod dyn_writer;
 mod io_utils;
 pub mod json;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+mod seekable_zstd;
 mod split;
 
 use std::{fmt, io, num::NonZeroU64};
@@ -49,6 +56,10 @@ use std::{fmt, io, num::NonZeroU64};
 use fallible_streaming_iterator::FallibleStreamingIterator;
 
 // Re-exports
+#[cfg(feature = "arrow")]
+pub use self::arrow::{RecordBatchEncoder, RecordBatchEncoderBuilder, RecordBatchIter};
+#[cfg(feature = "parquet")]
+pub use self::parquet::{Encoder as ParquetEncoder, EncoderBuilder as ParquetEncoderBuilder};
 pub use self::{
     csv::Encoder as CsvEncoder,
     dbn::{
@@ -57,27 +68,36 @@ pub use self::{
     },
     json::Encoder as JsonEncoder,
     split::{
-        NoSchemaBehavior, SchemaSplitter, SplitDuration, SplitEncoder, Splitter, SymbolSplitter,
-        TimeSplitter,
+        InstrumentSplitter, IntervalSplitter, NoSchemaBehavior, PublisherSplitter, SchemaSplitter,
+        SplitDuration, SplitEncoder, Splitter, SymbolSplitter, TimeSplitter,
     },
 };
 #[cfg(feature = "async")]
 pub use self::{
+    csv::{AsyncEncoder as AsyncCsvEncoder, AsyncEncoderBuilder as AsyncCsvEncoderBuilder},
     dbn::{
         AsyncEncoder as AsyncDbnEncoder, AsyncMetadataEncoder as AsyncDbnMetadataEncoder,
         AsyncRecordEncoder as AsyncDbnRecordEncoder,
     },
     json::AsyncEncoder as AsyncJsonEncoder,
+    split::{
+        AsyncSchemaSplitter, AsyncSplitEncoder, AsyncSplitter, AsyncSymbolSplitter,
+        AsyncTimeSplitter,
+    },
 };
 #[doc(inline)]
 pub use self::{
     dyn_encoder::{DynEncoder, DynEncoderBuilder},
     dyn_writer::DynWriter,
+    seekable_zstd::{SeekableFrameSize, SeekableZstdWriter},
 };
 
 #[cfg(feature = "async")]
 #[doc(inline)]
-pub use self::dyn_writer::{DynAsyncBufWriter, DynAsyncWriter};
+pub use self::{
+    dyn_encoder::{DynAsyncEncoder, DynAsyncEncoderBuilder},
+    dyn_writer::{DynAsyncBufWriter, DynAsyncWriter},
+};
 
 use crate::{
     decode::{DbnMetadata, DecodeRecordRef},
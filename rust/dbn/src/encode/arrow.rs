@@ -0,0 +1,552 @@
+//! Encoding DBN records into in-memory [Apache Arrow](https://arrow.apache.org/)
+//! record batches, along with functionality shared with the Arrow-based
+//! [Parquet](super::parquet) encoder.
+
+pub(crate) mod serialize;
+
+use std::{any::type_name, sync::Arc};
+
+pub use arrow_array::{RecordBatch, RecordBatchReader};
+pub use arrow_schema::SchemaRef;
+
+use arrow_schema::{ArrowError, Schema as ArrowSchema};
+
+use self::serialize::{write_symbol_dict_field, write_symbol_dict_schema, ArrowSerialize, Columns};
+use crate::{
+    decode::{DbnMetadata, DecodeRecordRef},
+    rtype_dispatch, schema_dispatch, v2, Error, Metadata, Record, RecordRef, Result, Schema,
+    SymbolIndex, TsSymbolMap, WithTsOut, DBN_VERSION,
+};
+
+/// The default maximum number of rows in each record batch.
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+/// Type for converting DBN records of a single [`Schema`] into Arrow [`RecordBatch`]es.
+///
+/// Each column has the same name as the corresponding CSV column. By default, prices
+/// are encoded as fixed-point `Decimal128` with a scale of 9 and timestamps as
+/// nanosecond UTC `Timestamp`s, with undefined values as nulls. Enum fields are
+/// encoded as single-character strings. When a [`TsSymbolMap`] is passed to the
+/// builder, a dictionary-encoded "symbol" column is appended.
+pub struct RecordBatchEncoder {
+    record_type: &'static str,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    symbol_map: Option<TsSymbolMap>,
+    batch_size: usize,
+    columns: Columns,
+}
+
+/// Helper for constructing a [`RecordBatchEncoder`].
+///
+/// Only `schema` is required.
+pub struct RecordBatchEncoderBuilder {
+    schema: Schema,
+    version: u8,
+    ts_out: bool,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    symbol_map: Option<TsSymbolMap>,
+    batch_size: usize,
+}
+
+/// A decoder adapter that converts the decoded records into Arrow [`RecordBatch`]es.
+/// Implements [`RecordBatchReader`], so it can be exported through the Arrow C stream
+/// interface.
+pub struct RecordBatchIter<D> {
+    decoder: D,
+    encoder: RecordBatchEncoder,
+    is_done: bool,
+}
+
+impl RecordBatchEncoderBuilder {
+    /// Creates a new builder for encoding records of `schema`.
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema,
+            version: DBN_VERSION,
+            ts_out: false,
+            use_pretty_px: true,
+            use_pretty_ts: true,
+            symbol_map: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// Creates a new builder with the schema, version, and `ts_out` of `metadata`.
+    ///
+    /// # Errors
+    /// This function returns an error if `metadata` doesn't have a schema.
+    pub fn from_metadata(metadata: &Metadata) -> Result<Self> {
+        let schema = metadata.schema.ok_or_else(|| Error::BadArgument {
+            param_name: "metadata".to_owned(),
+            desc: "can't convert records with mixed schemas to Arrow".to_owned(),
+        })?;
+        Ok(Self::new(schema)
+            .version(metadata.version)
+            .ts_out(metadata.ts_out))
+    }
+
+    /// Sets the DBN version of the records, which is used for determining which fields
+    /// to include in the Arrow schema. Currently only relevant to the definition schema
+    /// where fields have changed between versions.
+    ///
+    /// If not specified, defaults to [`DBN_VERSION`].
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets whether the records are followed by a `ts_out` send timestamp, which is
+    /// added as a "ts_out" column. Defaults to `false`.
+    pub fn ts_out(mut self, ts_out: bool) -> Self {
+        self.ts_out = ts_out;
+        self
+    }
+
+    /// Sets whether prices will be encoded as fixed-point decimals. If `false`, they're
+    /// encoded as the raw fixed-precision integers. Defaults to `true`.
+    pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
+        self.use_pretty_px = use_pretty_px;
+        self
+    }
+
+    /// Sets whether timestamps will be encoded as Arrow timestamps. If `false`, they're
+    /// encoded as the raw UNIX nanoseconds. Defaults to `true`.
+    pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
+        self.use_pretty_ts = use_pretty_ts;
+        self
+    }
+
+    /// Sets the symbol map used for populating a dictionary-encoded "symbol" column. If
+    /// `None`, no "symbol" column is added. Defaults to `None`.
+    pub fn symbol_map(mut self, symbol_map: Option<TsSymbolMap>) -> Self {
+        self.symbol_map = symbol_map;
+        self
+    }
+
+    /// Sets the maximum number of rows in each record batch. Defaults to
+    /// [`DEFAULT_BATCH_SIZE`].
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Creates the new encoder with the previously specified settings.
+    ///
+    /// # Errors
+    /// This function returns an error if `batch_size` is 0.
+    pub fn build(self) -> Result<RecordBatchEncoder> {
+        if self.batch_size == 0 {
+            return Err(Error::BadArgument {
+                param_name: "batch_size".to_owned(),
+                desc: "must be greater than 0".to_owned(),
+            });
+        }
+        let (use_pretty_px, use_pretty_ts) = (self.use_pretty_px, self.use_pretty_ts);
+        let with_symbol = self.symbol_map.is_some();
+        // Workaround for definitions fields changing between versions 1/2 and 3
+        let (record_type, schema) = if self.version < 3 && self.schema == Schema::Definition {
+            if self.ts_out {
+                batch_schema::<WithTsOut<v2::InstrumentDefMsg>>(
+                    use_pretty_px,
+                    use_pretty_ts,
+                    with_symbol,
+                )
+            } else {
+                batch_schema::<v2::InstrumentDefMsg>(use_pretty_px, use_pretty_ts, with_symbol)
+            }
+        } else {
+            schema_dispatch!(
+                self.schema,
+                ts_out: self.ts_out,
+                batch_schema(use_pretty_px, use_pretty_ts, with_symbol)
+            )
+        };
+        Ok(RecordBatchEncoder {
+            record_type,
+            use_pretty_px,
+            use_pretty_ts,
+            symbol_map: self.symbol_map,
+            batch_size: self.batch_size,
+            columns: Columns::new(Arc::new(schema), self.batch_size),
+        })
+    }
+}
+
+impl RecordBatchEncoder {
+    /// Creates a builder for configuring a `RecordBatchEncoder` object.
+    pub fn builder(schema: Schema) -> RecordBatchEncoderBuilder {
+        RecordBatchEncoderBuilder::new(schema)
+    }
+
+    /// Creates a new [`RecordBatchEncoder`] for records of `schema` with the default
+    /// settings.
+    pub fn new(schema: Schema) -> Self {
+        Self::builder(schema)
+            .build()
+            // Not setting `batch_size`
+            .unwrap()
+    }
+
+    /// Returns the Arrow schema of the record batches.
+    pub fn schema(&self) -> &SchemaRef {
+        self.columns.schema()
+    }
+
+    /// Returns the number of records buffered for the next batch.
+    pub fn buffered_len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Encodes a single DBN record of type `R`. Returns a record batch once
+    /// `batch_size` records have been buffered.
+    ///
+    /// # Errors
+    /// This function returns an error if `R` doesn't match the schema of the encoder.
+    pub fn encode_record<R>(&mut self, record: &R) -> Result<Option<RecordBatch>>
+    where
+        R: Record + ArrowSerialize,
+    {
+        if self.record_type != type_name::<R>() {
+            return Err(Error::encode(format!(
+                "can't encode {} in Arrow record batches of {}",
+                type_name::<R>(),
+                self.record_type
+            )));
+        }
+        match (self.use_pretty_px, self.use_pretty_ts) {
+            (true, true) => record.serialize_to::<true, true>(&mut self.columns),
+            (true, false) => record.serialize_to::<true, false>(&mut self.columns),
+            (false, true) => record.serialize_to::<false, true>(&mut self.columns),
+            (false, false) => record.serialize_to::<false, false>(&mut self.columns),
+        }
+        if let Some(symbol_map) = self.symbol_map.as_ref() {
+            write_symbol_dict_field(
+                &mut self.columns,
+                symbol_map.get_for_rec(record).map(String::as_str),
+            );
+        }
+        self.columns.end_row();
+        if self.columns.len() >= self.batch_size {
+            self.flush()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Encodes a single DBN record. Returns a record batch once `batch_size` records
+    /// have been buffered.
+    ///
+    /// # Errors
+    /// This function returns an error if the record type doesn't match the schema of
+    /// the encoder or the `rtype` is unknown.
+    pub fn encode_record_ref(&mut self, record: RecordRef) -> Result<Option<RecordBatch>> {
+        rtype_dispatch!(record, self.encode_record())?
+    }
+
+    /// Encodes a single DBN record, including its `ts_out` if `ts_out` is `true`.
+    /// Returns a record batch once `batch_size` records have been buffered.
+    ///
+    /// # Safety
+    /// `ts_out` must be `false` if `record` does not have an appended `ts_out`.
+    ///
+    /// # Errors
+    /// This function returns an error if the record type doesn't match the schema of
+    /// the encoder or the `rtype` is unknown.
+    pub unsafe fn encode_record_ref_ts_out(
+        &mut self,
+        record: RecordRef,
+        ts_out: bool,
+    ) -> Result<Option<RecordBatch>> {
+        rtype_dispatch!(record, ts_out: ts_out, self.encode_record())?
+    }
+
+    /// Converts any buffered records into a record batch, which may have fewer than
+    /// `batch_size` rows. Returns `None` if there are no buffered records.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to build the record batch.
+    pub fn flush(&mut self) -> Result<Option<RecordBatch>> {
+        if self.columns.is_empty() {
+            Ok(None)
+        } else {
+            self.columns.finish().map(Some)
+        }
+    }
+}
+
+impl<D> RecordBatchIter<D>
+where
+    D: DecodeRecordRef + DbnMetadata,
+{
+    /// Creates a new adapter converting the records decoded by `decoder` with
+    /// `encoder`. The `ts_out` of the records is taken from the decoder's metadata.
+    pub fn new(decoder: D, encoder: RecordBatchEncoder) -> Self {
+        Self {
+            decoder,
+            encoder,
+            is_done: false,
+        }
+    }
+
+    /// Creates a new adapter for `decoder` with the schema, version, and `ts_out` of its
+    /// metadata. If `map_symbols` is `true`, a "symbol" column is added using the
+    /// symbol mappings in the metadata.
+    ///
+    /// # Errors
+    /// This function returns an error if the metadata doesn't have a schema or its
+    /// symbol mappings are invalid.
+    pub fn from_decoder(decoder: D, map_symbols: bool) -> Result<Self> {
+        let metadata = decoder.metadata();
+        let symbol_map = if map_symbols {
+            Some(metadata.symbol_map()?)
+        } else {
+            None
+        };
+        let encoder = RecordBatchEncoderBuilder::from_metadata(metadata)?
+            .symbol_map(symbol_map)
+            .build()?;
+        Ok(Self::new(decoder, encoder))
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn get_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Consumes the adapter and returns the inner decoder.
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let ts_out = self.decoder.metadata().ts_out;
+        while let Some(record) = self.decoder.decode_record_ref()? {
+            // Safety: It's safe to cast to `WithTsOut` because we're passing in the
+            // `ts_out` from the metadata header.
+            let batch = unsafe { self.encoder.encode_record_ref_ts_out(record, ts_out) }?;
+            if batch.is_some() {
+                return Ok(batch);
+            }
+        }
+        self.encoder.flush()
+    }
+}
+
+impl<D> Iterator for RecordBatchIter<D>
+where
+    D: DecodeRecordRef + DbnMetadata,
+{
+    type Item = std::result::Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        let res = self.next_batch();
+        if !matches!(res, Ok(Some(_))) {
+            self.is_done = true;
+        }
+        res.map_err(|e| ArrowError::ExternalError(Box::new(e)))
+            .transpose()
+    }
+}
+
+impl<D> RecordBatchReader for RecordBatchIter<D>
+where
+    D: DecodeRecordRef + DbnMetadata,
+{
+    fn schema(&self) -> SchemaRef {
+        self.encoder.schema().clone()
+    }
+}
+
+fn batch_schema<R: ArrowSerialize>(
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    with_symbol: bool,
+) -> (&'static str, ArrowSchema) {
+    let mut fields = Vec::new();
+    match (use_pretty_px, use_pretty_ts) {
+        (true, true) => R::serialize_schema::<true, true>(&mut fields),
+        (true, false) => R::serialize_schema::<true, false>(&mut fields),
+        (false, true) => R::serialize_schema::<false, true>(&mut fields),
+        (false, false) => R::serialize_schema::<false, false>(&mut fields),
+    }
+    if with_symbol {
+        write_symbol_dict_schema(&mut fields);
+    }
+    (type_name::<R>(), ArrowSchema::new(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use arrow_array::{
+        cast::AsArray,
+        types::{Decimal128Type, Int32Type, TimestampNanosecondType, UInt64Type},
+        Array,
+    };
+    use arrow_schema::{DataType, TimeUnit};
+    use time::macros::date;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        rtype, RecordHeader, TradeMsg, UNDEF_PRICE,
+    };
+
+    fn trade(instrument_id: u32, price: i64) -> TradeMsg {
+        TradeMsg {
+            // 2024-06-03
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, 1717372800000000000),
+            price,
+            size: 10,
+            action: b'T' as c_char,
+            side: b'A' as c_char,
+            ts_recv: 1717372800000000010,
+            ..Default::default()
+        }
+    }
+
+    fn symbol_map() -> TsSymbolMap {
+        let mut symbol_map = TsSymbolMap::new();
+        symbol_map
+            .insert(
+                1,
+                date!(2024 - 06 - 03),
+                date!(2024 - 06 - 04),
+                Arc::new("ESM4".to_owned()),
+            )
+            .unwrap();
+        symbol_map
+    }
+
+    #[test]
+    fn test_schema() {
+        let encoder = RecordBatchEncoder::builder(Schema::Trades)
+            .ts_out(true)
+            .symbol_map(Some(symbol_map()))
+            .build()
+            .unwrap();
+        let schema = encoder.schema();
+        assert_eq!(
+            schema.field_with_name("price").unwrap().data_type(),
+            &DataType::Decimal128(19, 9)
+        );
+        assert_eq!(
+            schema.field_with_name("ts_recv").unwrap().data_type(),
+            &DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+        );
+        assert_eq!(
+            schema.field_with_name("side").unwrap().data_type(),
+            &DataType::Utf8
+        );
+        assert_eq!(
+            schema.field_with_name("symbol").unwrap().data_type(),
+            &DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+        );
+        assert_eq!(schema.fields()[schema.fields().len() - 2].name(), "ts_out");
+    }
+
+    #[test]
+    fn test_encode_batches() {
+        let mut encoder = RecordBatchEncoder::builder(Schema::Trades)
+            .symbol_map(Some(symbol_map()))
+            .batch_size(2)
+            .build()
+            .unwrap();
+        assert!(encoder
+            .encode_record(&trade(1, 5_500_000_000))
+            .unwrap()
+            .is_none());
+        let batch = encoder
+            .encode_record(&trade(2, UNDEF_PRICE))
+            .unwrap()
+            .unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(encoder.buffered_len(), 0);
+        let price = batch
+            .column_by_name("price")
+            .unwrap()
+            .as_primitive::<Decimal128Type>();
+        assert_eq!(price.value_as_string(0), "5.500000000");
+        assert!(price.is_null(1));
+        let ts_recv = batch
+            .column_by_name("ts_recv")
+            .unwrap()
+            .as_primitive::<TimestampNanosecondType>();
+        assert_eq!(ts_recv.value(0), 1717372800000000010);
+        let side = batch.column_by_name("side").unwrap().as_string::<i32>();
+        assert_eq!(side.value(0), "A");
+        let symbol = batch
+            .column_by_name("symbol")
+            .unwrap()
+            .as_dictionary::<Int32Type>();
+        assert_eq!(
+            symbol
+                .values()
+                .as_string::<i32>()
+                .value(symbol.key(0).unwrap()),
+            "ESM4"
+        );
+        assert!(symbol.is_null(1));
+
+        encoder.encode_record(&trade(1, 5_600_000_000)).unwrap();
+        assert_eq!(encoder.flush().unwrap().unwrap().num_rows(), 1);
+        assert!(encoder.flush().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_encode_raw() {
+        let mut encoder = RecordBatchEncoder::builder(Schema::Trades)
+            .use_pretty_px(false)
+            .use_pretty_ts(false)
+            .build()
+            .unwrap();
+        let rec = trade(1, 5_500_000_000);
+        encoder.encode_record_ref(RecordRef::from(&rec)).unwrap();
+        let batch = encoder.flush().unwrap().unwrap();
+        assert!(batch.column_by_name("symbol").is_none());
+        assert_eq!(
+            batch
+                .column_by_name("ts_event")
+                .unwrap()
+                .as_primitive::<UInt64Type>()
+                .value(0),
+            1717372800000000000
+        );
+    }
+
+    #[test]
+    fn test_mismatched_schema_fails() {
+        let mut encoder = RecordBatchEncoder::new(Schema::Mbo);
+        assert!(matches!(
+            encoder.encode_record(&trade(1, 5_500_000_000)),
+            Err(Error::Encode(_))
+        ));
+    }
+
+    #[test]
+    fn test_batch_iter() {
+        let decoder =
+            DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                .unwrap();
+        let batches = RecordBatchIter::from_decoder(decoder, true)
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+        let symbol = batch
+            .column_by_name("symbol")
+            .unwrap()
+            .as_dictionary::<Int32Type>();
+        assert_eq!(symbol.values().len(), 1);
+        assert_eq!(symbol.values().as_string::<i32>().value(0), "ESH1");
+        assert_eq!(symbol.null_count(), 0);
+    }
+}
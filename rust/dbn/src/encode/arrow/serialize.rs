@@ -0,0 +1,490 @@
+use std::ffi::c_char;
+
+use arrow_array::{
+    builder::{
+        make_builder, ArrayBuilder, BooleanBuilder, Decimal128Builder, Int16Builder, Int32Builder,
+        Int64Builder, Int8Builder, StringBuilder, StringDictionaryBuilder,
+        TimestampNanosecondBuilder, UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
+    },
+    types::Int32Type,
+    RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+
+use crate::{
+    enums::{SecurityUpdateAction, UserDefinedInstrument},
+    record::{
+        c_chars_to_str, BidAskPair, ConsolidatedBidAskPair, HasRType, RecordHeader, WithTsOut,
+    },
+    Error, FlagSet, UNDEF_PRICE, UNDEF_TIMESTAMP,
+};
+
+/// The precision of decimal price columns, which is enough to represent any `i64`.
+pub const PX_PRECISION: u8 = 19;
+/// The scale of decimal price columns, corresponding to
+/// [`FIXED_PRICE_SCALE`](crate::FIXED_PRICE_SCALE).
+pub const PX_SCALE: i8 = 9;
+/// The time zone of timestamp columns.
+pub const TS_TIME_ZONE: &str = "UTC";
+
+/// Maps the fields of a DBN record to typed Arrow columns. The layout of the
+/// columns matches the CSV encoding.
+pub trait ArrowSerialize {
+    /// Appends the Arrow fields for the type to `fields`.
+    fn serialize_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(fields: &mut Vec<Field>);
+
+    /// Appends the values of the type to `columns`, one for each field from
+    /// [`serialize_schema()`](Self::serialize_schema).
+    fn serialize_to<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns);
+}
+
+impl<T: HasRType + ArrowSerialize> ArrowSerialize for WithTsOut<T> {
+    fn serialize_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(fields: &mut Vec<Field>) {
+        T::serialize_schema::<PRETTY_PX, PRETTY_TS>(fields);
+        write_ts_schema::<PRETTY_TS>(fields, "ts_out");
+    }
+
+    fn serialize_to<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        self.rec.serialize_to::<PRETTY_PX, PRETTY_TS>(columns);
+        write_ts_field::<PRETTY_TS>(columns, self.ts_out);
+    }
+}
+
+/// Returns the Arrow schema for records of type `R`. If `with_symbol` is `true`, a
+/// nullable "symbol" column is appended.
+// Only used by the Parquet encoder
+#[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+pub fn schema_for<R: ArrowSerialize, const PRETTY_PX: bool, const PRETTY_TS: bool>(
+    with_symbol: bool,
+) -> Schema {
+    let mut fields = Vec::new();
+    R::serialize_schema::<PRETTY_PX, PRETTY_TS>(&mut fields);
+    if with_symbol {
+        fields.push(Field::new("symbol", DataType::Utf8, true));
+    }
+    Schema::new(fields)
+}
+
+/// Row-wise builder of Arrow columns for a single [`Schema`].
+pub struct Columns {
+    schema: SchemaRef,
+    builders: Vec<Box<dyn ArrayBuilder>>,
+    idx: usize,
+}
+
+impl Columns {
+    /// Creates a new set of empty columns for `schema`, each with space for
+    /// `capacity` rows.
+    pub fn new(schema: SchemaRef, capacity: usize) -> Self {
+        let builders = schema
+            .fields()
+            .iter()
+            .map(|field| make_builder(field.data_type(), capacity))
+            .collect();
+        Self {
+            schema,
+            builders,
+            idx: 0,
+        }
+    }
+
+    /// Returns the schema of the columns.
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Returns the number of complete rows.
+    pub fn len(&self) -> usize {
+        self.builders.first().map(|b| b.len()).unwrap_or_default()
+    }
+
+    /// Returns `true` if there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the builder for the next column in the current row.
+    ///
+    /// # Panics
+    /// This function panics if `B` doesn't match the type of the next column, which
+    /// indicates a mismatch between `ArrowSerialize::serialize_schema()` and
+    /// `ArrowSerialize::serialize_to()`.
+    pub fn next<B: ArrayBuilder>(&mut self) -> &mut B {
+        let idx = self.idx;
+        self.idx += 1;
+        self.builders[idx]
+            .as_any_mut()
+            .downcast_mut::<B>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "column builder for {} to match its field",
+                    self.schema.field(idx).name()
+                )
+            })
+    }
+
+    /// Marks the end of the current row.
+    pub fn end_row(&mut self) {
+        debug_assert_eq!(self.idx, self.builders.len());
+        self.idx = 0;
+    }
+
+    /// Converts the rows into a [`RecordBatch`], leaving the columns empty.
+    ///
+    /// # Errors
+    /// This function returns an error if the columns don't match the schema.
+    pub fn finish(&mut self) -> crate::Result<RecordBatch> {
+        let arrays = self.builders.iter_mut().map(|b| b.finish()).collect();
+        RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(|e| Error::encode(format!("failed to build Arrow record batch: {e}")))
+    }
+}
+
+pub trait WriteField {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        name: &str,
+    );
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns);
+}
+
+impl WriteField for RecordHeader {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        _name: &str,
+    ) {
+        Self::serialize_schema::<PRETTY_PX, PRETTY_TS>(fields)
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        self.serialize_to::<PRETTY_PX, PRETTY_TS>(columns)
+    }
+}
+
+impl<const N: usize> WriteField for [BidAskPair; N] {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        _name: &str,
+    ) {
+        for i in 0..N {
+            write_px_schema::<PRETTY_PX>(fields, &format!("bid_px_{i:02}"));
+            write_px_schema::<PRETTY_PX>(fields, &format!("ask_px_{i:02}"));
+            for f in ["bid_sz", "ask_sz", "bid_ct", "ask_ct"] {
+                u32::write_schema::<PRETTY_PX, PRETTY_TS>(fields, &format!("{f}_{i:02}"));
+            }
+        }
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        for level in self.iter() {
+            write_px_field::<PRETTY_PX>(columns, level.bid_px);
+            write_px_field::<PRETTY_PX>(columns, level.ask_px);
+            level.bid_sz.write_field::<false, false>(columns);
+            level.ask_sz.write_field::<false, false>(columns);
+            level.bid_ct.write_field::<false, false>(columns);
+            level.ask_ct.write_field::<false, false>(columns);
+        }
+    }
+}
+
+impl<const N: usize> WriteField for [ConsolidatedBidAskPair; N] {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        _name: &str,
+    ) {
+        for i in 0..N {
+            write_px_schema::<PRETTY_PX>(fields, &format!("bid_px_{i:02}"));
+            write_px_schema::<PRETTY_PX>(fields, &format!("ask_px_{i:02}"));
+            u32::write_schema::<PRETTY_PX, PRETTY_TS>(fields, &format!("bid_sz_{i:02}"));
+            u32::write_schema::<PRETTY_PX, PRETTY_TS>(fields, &format!("ask_sz_{i:02}"));
+            u16::write_schema::<PRETTY_PX, PRETTY_TS>(fields, &format!("bid_pb_{i:02}"));
+            u16::write_schema::<PRETTY_PX, PRETTY_TS>(fields, &format!("ask_pb_{i:02}"));
+        }
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        for level in self.iter() {
+            write_px_field::<PRETTY_PX>(columns, level.bid_px);
+            write_px_field::<PRETTY_PX>(columns, level.ask_px);
+            level.bid_sz.write_field::<false, false>(columns);
+            level.ask_sz.write_field::<false, false>(columns);
+            level.bid_pb.write_field::<false, false>(columns);
+            level.ask_pb.write_field::<false, false>(columns);
+        }
+    }
+}
+
+impl WriteField for FlagSet {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        name: &str,
+    ) {
+        u8::write_schema::<PRETTY_PX, PRETTY_TS>(fields, name)
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        self.raw().write_field::<false, false>(columns)
+    }
+}
+
+macro_rules! impl_write_field_for {
+        ($($ty:ident => ($data_type:ident, $builder:ident)),+) => {
+            $(
+                impl WriteField for $ty {
+                    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+                        fields: &mut Vec<Field>,
+                        name: &str,
+                    ) {
+                        fields.push(Field::new(name, DataType::$data_type, false));
+                    }
+
+                    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+                        &self,
+                        columns: &mut Columns,
+                    ) {
+                        columns.next::<$builder>().append_value(*self);
+                    }
+                }
+            )*
+        };
+    }
+
+impl_write_field_for! {
+    i64 => (Int64, Int64Builder),
+    u64 => (UInt64, UInt64Builder),
+    i32 => (Int32, Int32Builder),
+    u32 => (UInt32, UInt32Builder),
+    i16 => (Int16, Int16Builder),
+    u16 => (UInt16, UInt16Builder),
+    i8 => (Int8, Int8Builder),
+    u8 => (UInt8, UInt8Builder),
+    bool => (Boolean, BooleanBuilder)
+}
+
+impl<const N: usize> WriteField for [c_char; N] {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        name: &str,
+    ) {
+        fields.push(Field::new(name, DataType::Utf8, false));
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        columns
+            .next::<StringBuilder>()
+            .append_value(c_chars_to_str(self).unwrap_or_default());
+    }
+}
+
+impl WriteField for SecurityUpdateAction {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        name: &str,
+    ) {
+        write_c_char_schema(fields, name)
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        write_c_char_field(columns, *self as u8 as c_char)
+    }
+}
+
+impl WriteField for UserDefinedInstrument {
+    fn write_schema<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        fields: &mut Vec<Field>,
+        name: &str,
+    ) {
+        write_c_char_schema(fields, name)
+    }
+
+    fn write_field<const PRETTY_PX: bool, const PRETTY_TS: bool>(&self, columns: &mut Columns) {
+        write_c_char_field(columns, *self as u8 as c_char)
+    }
+}
+
+/// Prices are decimals with a precision of 9 when `PRETTY_PX` is `true`, otherwise
+/// the raw fixed-precision integer.
+pub fn write_px_schema<const PRETTY_PX: bool>(fields: &mut Vec<Field>, name: &str) {
+    if PRETTY_PX {
+        fields.push(Field::new(
+            name,
+            DataType::Decimal128(PX_PRECISION, PX_SCALE),
+            true,
+        ));
+    } else {
+        fields.push(Field::new(name, DataType::Int64, false));
+    }
+}
+
+pub fn write_px_field<const PRETTY_PX: bool>(columns: &mut Columns, px: i64) {
+    if PRETTY_PX {
+        let builder = columns.next::<Decimal128Builder>();
+        if px == UNDEF_PRICE {
+            builder.append_null();
+        } else {
+            builder.append_value(i128::from(px));
+        }
+    } else {
+        columns.next::<Int64Builder>().append_value(px);
+    }
+}
+
+/// Timestamps are nanosecond-resolution UTC timestamps when `PRETTY_TS` is `true`,
+/// otherwise the raw UNIX nanoseconds.
+pub fn write_ts_schema<const PRETTY_TS: bool>(fields: &mut Vec<Field>, name: &str) {
+    if PRETTY_TS {
+        fields.push(Field::new(
+            name,
+            DataType::Timestamp(TimeUnit::Nanosecond, Some(TS_TIME_ZONE.into())),
+            true,
+        ));
+    } else {
+        fields.push(Field::new(name, DataType::UInt64, false));
+    }
+}
+
+pub fn write_ts_field<const PRETTY_TS: bool>(columns: &mut Columns, ts: u64) {
+    if PRETTY_TS {
+        let builder = columns.next::<TimestampNanosecondBuilder>();
+        match ts {
+            0 | UNDEF_TIMESTAMP => builder.append_null(),
+            ts => builder.append_value(ts as i64),
+        }
+    } else {
+        columns.next::<UInt64Builder>().append_value(ts);
+    }
+}
+
+pub fn write_c_char_schema(fields: &mut Vec<Field>, name: &str) {
+    fields.push(Field::new(name, DataType::Utf8, true));
+}
+
+pub fn write_c_char_field(columns: &mut Columns, c: c_char) {
+    let builder = columns.next::<StringBuilder>();
+    // Handle NUL byte as null
+    if c == 0 {
+        builder.append_null();
+    } else {
+        builder.append_value(char::from(c as u8).encode_utf8(&mut [0; 4]));
+    }
+}
+
+#[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+pub fn write_symbol_field(columns: &mut Columns, symbol: Option<&str>) {
+    columns.next::<StringBuilder>().append_option(symbol);
+}
+
+/// Symbols are dictionary-encoded because there are typically far fewer distinct
+/// symbols than records.
+pub fn write_symbol_dict_schema(fields: &mut Vec<Field>) {
+    fields.push(Field::new(
+        "symbol",
+        DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+        true,
+    ));
+}
+
+pub fn write_symbol_dict_field(columns: &mut Columns, symbol: Option<&str>) {
+    columns
+        .next::<StringDictionaryBuilder<Int32Type>>()
+        .append_option(symbol);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        cast::AsArray,
+        types::{Decimal128Type, Int64Type, TimestampNanosecondType, UInt64Type},
+        Array,
+    };
+
+    use super::*;
+    use crate::{rtype, MboMsg};
+
+    fn mbo() -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 5482, 1658441851000000000),
+            order_id: 16,
+            price: 5_500_000_000,
+            size: 3,
+            action: b'A' as c_char,
+            side: b'B' as c_char,
+            ts_recv: 1658441891000000000,
+            ..Default::default()
+        }
+    }
+
+    fn serialize<const PRETTY_PX: bool, const PRETTY_TS: bool>(
+        records: &[MboMsg],
+        with_symbol: bool,
+    ) -> RecordBatch {
+        let schema = Arc::new(schema_for::<MboMsg, PRETTY_PX, PRETTY_TS>(with_symbol));
+        let mut columns = Columns::new(schema, records.len());
+        for rec in records {
+            rec.serialize_to::<PRETTY_PX, PRETTY_TS>(&mut columns);
+            if with_symbol {
+                write_symbol_field(&mut columns, Some("ESM4"));
+            }
+            columns.end_row();
+        }
+        columns.finish().unwrap()
+    }
+
+    #[test]
+    fn test_mbo_schema_matches_csv_header() {
+        let mut writer = csv::WriterBuilder::new().from_writer(Vec::new());
+        <MboMsg as crate::encode::csv::serialize::CsvSerialize>::serialize_header(&mut writer)
+            .unwrap();
+        writer.write_record(None::<&[u8]>).unwrap();
+        let header = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let schema = schema_for::<MboMsg, false, false>(false);
+        let names = schema
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(header.trim_end(), names.join(","));
+    }
+
+    #[test]
+    fn test_mbo_raw() {
+        let batch = serialize::<false, false>(&[mbo()], true);
+        assert_eq!(batch.num_rows(), 1);
+        let price = batch.column_by_name("price").unwrap();
+        assert_eq!(price.as_primitive::<Int64Type>().value(0), 5_500_000_000);
+        let ts_recv = batch.column_by_name("ts_recv").unwrap();
+        assert_eq!(
+            ts_recv.as_primitive::<UInt64Type>().value(0),
+            1658441891000000000
+        );
+        let side = batch.column_by_name("side").unwrap();
+        assert_eq!(side.as_string::<i32>().value(0), "B");
+        let symbol = batch.column_by_name("symbol").unwrap();
+        assert_eq!(symbol.as_string::<i32>().value(0), "ESM4");
+    }
+
+    #[test]
+    fn test_mbo_pretty() {
+        let mut undef = mbo();
+        undef.price = UNDEF_PRICE;
+        undef.ts_recv = UNDEF_TIMESTAMP;
+        let batch = serialize::<true, true>(&[mbo(), undef], false);
+        assert_eq!(batch.num_rows(), 2);
+        assert!(batch.column_by_name("symbol").is_none());
+        let price = batch
+            .column_by_name("price")
+            .unwrap()
+            .as_primitive::<Decimal128Type>();
+        assert_eq!(price.value_as_string(0), "5.500000000");
+        assert!(price.is_null(1));
+        let ts_recv = batch
+            .column_by_name("ts_recv")
+            .unwrap()
+            .as_primitive::<TimestampNanosecondType>();
+        assert_eq!(ts_recv.value(0), 1658441891000000000);
+        assert!(ts_recv.is_null(1));
+    }
+}
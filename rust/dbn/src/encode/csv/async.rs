@@ -0,0 +1,567 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::{
+    encode::{AsyncEncodeRecord, AsyncEncodeRecordRef, AsyncEncodeRecordTextExt, DbnEncodable},
+    rtype_dispatch, schema_dispatch, v2, Error, RecordRef, Result, Schema, WithTsOut, DBN_VERSION,
+};
+
+/// Type for asynchronously encoding files and streams of DBN records in CSV or other
+/// text-delimited tabular file formats including TSV (tab-separated values).
+///
+/// Records are serialized to an internal buffer with a persistent [`csv::Writer`]
+/// before being written to the underlying writer, so partial records are never
+/// written.
+///
+/// Note that encoding [`Metadata`](crate::Metadata) in CSV is not supported.
+pub struct Encoder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    writer: W,
+    csv_writer: csv::Writer<Buffer>,
+    /// Serialized rows drained from `csv_writer` to be written to `writer`.
+    buf: Vec<u8>,
+    /// Prevent writing header twice.
+    has_written_header: bool,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+}
+
+/// Helper for constructing an async CSV [`Encoder`].
+///
+/// No fields are required.
+pub struct EncoderBuilder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    writer: W,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    write_header: bool,
+    version: u8,
+    schema: Option<Schema>,
+    ts_out: bool,
+    with_symbol: bool,
+    delimiter: u8,
+}
+
+impl<W> EncoderBuilder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    /// Creates a new async CSV encoder builder.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            use_pretty_px: false,
+            use_pretty_ts: false,
+            write_header: true,
+            version: DBN_VERSION,
+            schema: None,
+            ts_out: false,
+            with_symbol: false,
+            delimiter: b',',
+        }
+    }
+
+    /// Sets whether the CSV encoder will serialize price fields as a decimal. Defaults
+    /// to `false`.
+    pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
+        self.use_pretty_px = use_pretty_px;
+        self
+    }
+
+    /// Sets whether the CSV encoder will serialize timestamp fields as ISO8601 datetime
+    /// strings. Defaults to `false`.
+    pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
+        self.use_pretty_ts = use_pretty_ts;
+        self
+    }
+
+    /// Sets whether the CSV encoder will write a header row automatically.
+    /// Defaults to `true`.
+    ///
+    /// If `false`, a header row can still be written with
+    /// [`Encoder::encode_header()`] or [`Encoder::encode_header_for_schema()`].
+    pub fn write_header(mut self, write_header: bool) -> Self {
+        self.write_header = write_header;
+        self
+    }
+
+    /// Sets the schema that will be encoded, used for determining the header row to write.
+    ///
+    /// If schema isn't set and `write_header` is left enabled, the header will be written
+    /// based on the type of the first record.
+    pub fn schema(mut self, schema: Option<Schema>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Sets whether to add a header field "ts_out". Defaults to `false`.
+    pub fn ts_out(mut self, ts_out: bool) -> Self {
+        self.ts_out = ts_out;
+        self
+    }
+
+    /// Sets whether to add a header field "symbol". Defaults to `false`.
+    pub fn with_symbol(mut self, with_symbol: bool) -> Self {
+        self.with_symbol = with_symbol;
+        self
+    }
+
+    /// Sets the field delimiter. Defaults to `b','` for comma-separated values (CSV).
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the DBN version which is used for determining which fields to include in
+    /// the header. Currently only relevant to the definition schema where fields have
+    /// changed between versions.
+    ///
+    /// If not specified, defaults to [`DBN_VERSION`].
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Creates the new encoder with the previously specified settings and if
+    /// `write_header` is `true`, encodes the header row.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to write the header row.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If this method is used in a
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// header row may have been partially written.
+    pub async fn build(self) -> Result<Encoder<W>> {
+        let mut encoder = Encoder {
+            writer: self.writer,
+            csv_writer: csv::WriterBuilder::new()
+                .has_headers(false)
+                .delimiter(self.delimiter)
+                .from_writer(Buffer::default()),
+            buf: Vec::new(),
+            has_written_header: true,
+            use_pretty_px: self.use_pretty_px,
+            use_pretty_ts: self.use_pretty_ts,
+        };
+        if self.write_header {
+            if let Some(schema) = self.schema {
+                encoder
+                    .encode_header_for_schema(self.version, schema, self.ts_out, self.with_symbol)
+                    .await?;
+            } else {
+                encoder.has_written_header = false;
+            }
+        }
+        Ok(encoder)
+    }
+}
+
+impl<W> Encoder<W>
+where
+    W: io::AsyncWriteExt + Unpin,
+{
+    /// Creates a builder for configuring an async `Encoder` object.
+    pub fn builder(writer: W) -> EncoderBuilder<W> {
+        EncoderBuilder::new(writer)
+    }
+
+    /// Creates a new async [`Encoder`] that will write to `writer`.
+    ///
+    /// If `use_pretty_px` is `true`, price fields will be serialized as a decimal. If
+    /// `pretty_ts` is `true`, timestamp fields will be serialized in a ISO8601 datetime
+    /// string. By default, a header will be written once a schema can be inferred.
+    pub fn new(writer: W, use_pretty_px: bool, use_pretty_ts: bool) -> Self {
+        Self {
+            writer,
+            csv_writer: csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Buffer::default()),
+            buf: Vec::new(),
+            has_written_header: false,
+            use_pretty_px,
+            use_pretty_ts,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Encodes the CSV header for the record type `R`, i.e. the names of each of the
+    /// fields to the output.
+    ///
+    /// If `with_symbol` is `true`, will add a header field for "symbol". This should
+    /// only be used with [`encode_record_with_sym()`](AsyncEncodeRecordTextExt::encode_record_with_sym)
+    /// and [`encode_ref_with_sym()`](AsyncEncodeRecordTextExt::encode_ref_with_sym),
+    /// otherwise there will be a mismatch between the number of fields in the header
+    /// and the body.
+    ///
+    /// # Errors
+    /// This function returns an error if there's an error writing to `writer`.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If this method is used in a
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// header row may have been partially written.
+    pub async fn encode_header<R: DbnEncodable>(&mut self, with_symbol: bool) -> Result<()> {
+        self.encode_header_to_buf::<R>(with_symbol)?;
+        self.write_buf(|e| Error::io(e, "writing CSV header")).await
+    }
+
+    /// Encodes the CSV header for `schema`, i.e. the names of each of the fields to
+    /// the output. Uses the definition fields from DBN versions 1 and 2 when `version`
+    /// is less than 3.
+    ///
+    /// If `ts_out` is `true`, it will add a header field "ts_out".
+    ///
+    /// If `with_symbol` is `true`, it will add a header field for "symbol". This should
+    /// only be used with [`encode_record_with_sym()`](AsyncEncodeRecordTextExt::encode_record_with_sym)
+    /// and [`encode_ref_with_sym()`](AsyncEncodeRecordTextExt::encode_ref_with_sym),
+    /// otherwise there will be a mismatch between the number of fields in the header
+    /// and the body.
+    ///
+    /// # Errors
+    /// This function returns an error if there's an error writing to `writer`.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe. If this method is used in a
+    /// `tokio::select!` statement and another branch completes first, then the
+    /// header row may have been partially written.
+    pub async fn encode_header_for_schema(
+        &mut self,
+        version: u8,
+        schema: Schema,
+        ts_out: bool,
+        with_symbol: bool,
+    ) -> Result<()> {
+        // Workaround for definitions fields changing between versions 1/2 and 3
+        if version < 3 && schema == Schema::Definition {
+            if ts_out {
+                self.encode_header_to_buf::<WithTsOut<v2::InstrumentDefMsg>>(with_symbol)?;
+            } else {
+                self.encode_header_to_buf::<v2::InstrumentDefMsg>(with_symbol)?;
+            }
+        } else {
+            schema_dispatch!(
+                schema,
+                ts_out: ts_out,
+                self.encode_header_to_buf(with_symbol)
+            )?;
+        }
+        self.write_buf(|e| Error::io(e, "writing CSV header")).await
+    }
+
+    /// Writes the header for `R` to `csv_writer`, but not the writer.
+    fn encode_header_to_buf<R: DbnEncodable>(&mut self, with_symbol: bool) -> Result<()> {
+        let csv_writer = &mut self.csv_writer;
+        R::serialize_header(csv_writer)?;
+        if with_symbol {
+            csv_writer.write_field("symbol")?;
+        }
+        // end of line
+        csv_writer.write_record(None::<&[u8]>)?;
+        self.has_written_header = true;
+        Ok(())
+    }
+
+    /// Writes `record` and an optional symbol to `csv_writer`, but not the writer.
+    fn encode_to_buf<R: DbnEncodable>(&mut self, record: &R, symbol: Option<&str>) -> Result<()> {
+        if !self.has_written_header {
+            self.encode_header_to_buf::<R>(symbol.is_some())?;
+        }
+        let csv_writer = &mut self.csv_writer;
+        let res = match (self.use_pretty_px, self.use_pretty_ts) {
+            (true, true) => record.serialize_to::<_, true, true>(csv_writer),
+            (true, false) => record.serialize_to::<_, true, false>(csv_writer),
+            (false, true) => record.serialize_to::<_, false, true>(csv_writer),
+            (false, false) => record.serialize_to::<_, false, false>(csv_writer),
+        }
+        .and_then(|_| match symbol {
+            Some(symbol) => csv_writer.write_field(symbol),
+            None => Ok(()),
+        })
+        // write new line
+        .and_then(|_| csv_writer.write_record(None::<&[u8]>));
+        res.map_err(|e| match e.into_kind() {
+            csv::ErrorKind::Io(err) => Error::io(err, format!("serializing {record:?}")),
+            e => Error::encode(format!("failed to serialize {record:?}: {e:?}")),
+        })
+    }
+
+    /// Moves the rows serialized by `csv_writer` to `buf`.
+    fn drain_csv_writer(&mut self) -> Result<()> {
+        self.csv_writer
+            .flush()
+            .map_err(|e| Error::io(e, "flushing CSV buffer"))?;
+        std::mem::swap(&mut self.buf, &mut *self.csv_writer.get_ref().lock());
+        Ok(())
+    }
+
+    /// Discards any rows that haven't been written to the writer.
+    fn clear_buf(&mut self) {
+        // `Buffer` never returns an error
+        let _ = self.drain_csv_writer();
+        self.buf.clear();
+    }
+
+    async fn write_buf<F>(&mut self, handle_err: F) -> Result<()>
+    where
+        F: FnOnce(io::Error) -> Error,
+    {
+        if let Err(e) = self.drain_csv_writer() {
+            self.clear_buf();
+            return Err(e);
+        }
+        let res = self
+            .writer
+            .write_all(self.buf.as_slice())
+            .await
+            .map_err(handle_err);
+        // Always clear `buf`
+        self.buf.clear();
+        res
+    }
+}
+
+impl<W> AsyncEncodeRecord for Encoder<W>
+where
+    W: AsyncWriteExt + Unpin,
+{
+    async fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        let res = self.encode_to_buf(record, None);
+        if res.is_err() {
+            self.clear_buf();
+            return res;
+        }
+        self.write_buf(|e| Error::io(e, "writing record")).await
+    }
+
+    async fn encode_records<R: DbnEncodable>(&mut self, records: &[R]) -> Result<()> {
+        for record in records {
+            if let Err(e) = self.encode_to_buf(record, None) {
+                self.clear_buf();
+                return Err(e);
+            }
+        }
+        self.write_buf(|e| Error::io(e, format!("writing {} records", records.len())))
+            .await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| Error::io(e, "flushing output"))
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.writer
+            .shutdown()
+            .await
+            .map_err(|e| Error::io(e, "shutting down"))
+    }
+}
+
+impl<W> AsyncEncodeRecordRef for Encoder<W>
+where
+    W: AsyncWriteExt + Unpin,
+{
+    async fn encode_record_ref(&mut self, record_ref: RecordRef<'_>) -> Result<()> {
+        rtype_dispatch!(record_ref, self.encode_record().await)?
+    }
+
+    async fn encode_record_refs(&mut self, record_refs: &[RecordRef<'_>]) -> Result<()> {
+        for record_ref in record_refs {
+            if let Err(e) = rtype_dispatch!(record_ref, self.encode_to_buf(None)).and_then(|r| r) {
+                self.clear_buf();
+                return Err(e);
+            }
+        }
+        self.write_buf(|e| Error::io(e, format!("writing {} records", record_refs.len())))
+            .await
+    }
+
+    async unsafe fn encode_record_ref_ts_out(
+        &mut self,
+        record_ref: RecordRef<'_>,
+        ts_out: bool,
+    ) -> Result<()> {
+        rtype_dispatch!(record_ref, ts_out: ts_out, self.encode_record().await)?
+    }
+}
+
+impl<W> AsyncEncodeRecordTextExt for Encoder<W>
+where
+    W: AsyncWriteExt + Unpin,
+{
+    async fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        // Always write the symbol field, even if empty, to match the header
+        let res = self.encode_to_buf(record, Some(symbol.unwrap_or_default()));
+        if res.is_err() {
+            self.clear_buf();
+            return res;
+        }
+        self.write_buf(|e| Error::io(e, "writing record")).await
+    }
+}
+
+/// The in-memory output of the [`csv::Writer`] of an [`Encoder`]. `csv::Writer` only
+/// provides shared access to the writer it wraps, so the rows are taken through a
+/// [`Mutex`], which is never contended.
+#[derive(Debug, Default)]
+struct Buffer(Mutex<Vec<u8>>);
+
+impl Buffer {
+    fn lock(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl std::io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder, DbnMetadata, DecodeRecordRef},
+        encode::{CsvEncoder, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt},
+        MboMsg,
+    };
+
+    fn records(schema: Schema) -> (crate::Metadata, Vec<crate::RecordBuf>) {
+        let mut decoder = DbnDecoder::from_zstd_file(format!(
+            "{TEST_DATA_PATH}/test_data.{}.v3.dbn.zst",
+            schema.as_str()
+        ))
+        .unwrap();
+        let metadata = decoder.metadata().clone();
+        let mut res = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            res.push(crate::RecordBuf::try_from(rec).unwrap());
+        }
+        (metadata, res)
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_matches_sync(
+        #[values(Schema::Mbo, Schema::Mbp10, Schema::Definition, Schema::Ohlcv1D)] schema: Schema,
+        #[values(false, true)] pretty: bool,
+        #[values(b',', b'\t')] delimiter: u8,
+    ) {
+        let (metadata, records) = records(schema);
+        let mut expected = Vec::new();
+        let mut sync_encoder = CsvEncoder::builder(&mut expected)
+            .use_pretty_px(pretty)
+            .use_pretty_ts(pretty)
+            .delimiter(delimiter)
+            .schema(metadata.schema)
+            .build()
+            .unwrap();
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::builder(&mut buf)
+            .use_pretty_px(pretty)
+            .use_pretty_ts(pretty)
+            .delimiter(delimiter)
+            .schema(metadata.schema)
+            .build()
+            .await
+            .unwrap();
+        for rec in records.iter() {
+            sync_encoder.encode_record_ref(rec.as_rec_ref()).unwrap();
+            encoder.encode_record_ref(rec.as_rec_ref()).await.unwrap();
+        }
+        drop(sync_encoder);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_header_inferred_from_first_record(#[values(false, true)] with_sym: bool) {
+        let (_, records) = records(Schema::Mbo);
+        let mut expected = Vec::new();
+        let mut sync_encoder = CsvEncoder::new(&mut expected, false, false);
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::new(&mut buf, false, false);
+        let rec = records[0].get::<MboMsg>().unwrap();
+        if with_sym {
+            sync_encoder
+                .encode_record_with_sym(rec, Some("ESM4"))
+                .unwrap();
+            encoder
+                .encode_record_with_sym(rec, Some("ESM4"))
+                .await
+                .unwrap();
+            sync_encoder.encode_record_with_sym(rec, None).unwrap();
+            encoder.encode_record_with_sym(rec, None).await.unwrap();
+        } else {
+            sync_encoder
+                .encode_records(&[rec.clone(), rec.clone()])
+                .unwrap();
+            encoder
+                .encode_records(&[rec.clone(), rec.clone()])
+                .await
+                .unwrap();
+        }
+        drop(sync_encoder);
+        let res = String::from_utf8(buf).unwrap();
+        assert_eq!(res.lines().count(), 3);
+        assert_eq!(res.lines().next().unwrap().ends_with(",symbol"), with_sym);
+        assert_eq!(res, String::from_utf8(expected).unwrap());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_no_header() {
+        let (metadata, records) = records(Schema::Mbo);
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::builder(&mut buf)
+            .schema(metadata.schema)
+            .write_header(false)
+            .build()
+            .await
+            .unwrap();
+        encoder
+            .encode_record_ref(records[0].as_rec_ref())
+            .await
+            .unwrap();
+        let res = String::from_utf8(buf).unwrap();
+        assert_eq!(res.lines().count(), 1);
+        assert!(!res.starts_with("ts_recv"));
+    }
+}
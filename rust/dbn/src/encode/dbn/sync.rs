@@ -7,7 +7,7 @@ use std::{
 use crate::{
     encode::{
         io_utils::write_all_vectored, zstd_encoder, DbnEncodable, EncodeDbn, EncodeRecord,
-        EncodeRecordRef,
+        EncodeRecordRef, SeekableFrameSize, SeekableZstdWriter,
     },
     Error, Metadata, RecordRef, Result, Schema, SymbolMapping, DBN_VERSION, NULL_LIMIT,
     NULL_RECORD_COUNT, NULL_SCHEMA, NULL_STYPE, UNDEF_TIMESTAMP,
@@ -62,6 +62,26 @@ where
     }
 }
 
+impl<W> Encoder<SeekableZstdWriter<W>>
+where
+    W: io::Write,
+{
+    /// Creates a new DBN [`Encoder`] that will write Zstd-compressed output to
+    /// `writer` in the Zstandard seekable format, with a new frame every
+    /// `frame_size`.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to encode `metadata` to
+    /// `writer`.
+    pub fn with_zstd_seekable(
+        writer: W,
+        metadata: &Metadata,
+        frame_size: SeekableFrameSize,
+    ) -> Result<Self> {
+        Encoder::new(SeekableZstdWriter::new(writer, frame_size)?, metadata)
+    }
+}
+
 impl<W> EncodeRecord for Encoder<W>
 where
     W: io::Write,
@@ -202,7 +222,7 @@ where
         Ok(())
     }
 
-    pub(super) fn calc_length(metadata: &Metadata) -> (u32, u32) {
+    pub(crate) fn calc_length(metadata: &Metadata) -> (u32, u32) {
         let mapping_interval_len = mem::size_of::<u32>() * 2 + metadata.symbol_cstr_len;
         // schema_definition_length, symbols_count, partial_count, not_found_count, mappings_count
         let var_len_counts_size = mem::size_of::<u32>() * 5;
@@ -25,6 +25,8 @@ where
     Dbn(DbnEncoder<DynWriter<'a, W>>),
     Csv(CsvEncoder<DynWriter<'a, W>>),
     Json(JsonEncoder<DynWriter<'a, W>>),
+    #[cfg(feature = "parquet")]
+    Parquet(super::ParquetEncoder<W>),
 }
 
 /// Helper for constructing a [`DynEncoder`].
@@ -96,22 +98,23 @@ where
         self
     }
 
-    /// Sets whether the encoder will serialize price fields as a decimal in CSV and
-    /// JSON encodings. Defaults to `false`.
+    /// Sets whether the encoder will serialize price fields as a decimal in CSV,
+    /// JSON, and Parquet encodings. Defaults to `false`.
     pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
         self.use_pretty_px = use_pretty_px;
         self
     }
 
     /// Sets whether the encoder will serialize timestamp fields as ISO8601 datetime
-    /// strings in CSV and JSON encodings. Defaults to `false`.
+    /// strings in CSV and JSON encodings and as timestamps in Parquet. Defaults to
+    /// `false`.
     pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
         self.use_pretty_ts = use_pretty_ts;
         self
     }
 
-    /// Sets whether to add a header field "symbol" if encoding CSV. Defaults to
-    /// `false`.
+    /// Sets whether to add a header field "symbol" if encoding CSV or a "symbol"
+    /// column if encoding Parquet. Defaults to `false`.
     pub fn with_symbol(mut self, with_symbol: bool) -> Self {
         self.with_symbol = with_symbol;
         self
@@ -128,8 +131,12 @@ where
     ///
     /// # Errors
     /// This function returns an error if it fails to write the CSV header row or the
-    /// DBN metadata.
+    /// DBN metadata, or if `encoding` is Parquet and the `parquet` feature isn't
+    /// enabled.
     pub fn build<'a>(self) -> crate::Result<DynEncoder<'a, W>> {
+        if self.encoding == Encoding::Parquet {
+            return self.build_parquet();
+        }
         let writer = DynWriter::new(self.writer, self.compression)?;
         Ok(DynEncoder(match self.encoding {
             Encoding::Dbn => DynEncoderImpl::Dbn(DbnEncoder::new(writer, self.metadata)?),
@@ -152,8 +159,33 @@ where
                     .use_pretty_ts(self.use_pretty_ts)
                     .build(),
             ),
+            Encoding::Parquet => unreachable!("handled above"),
         }))
     }
+
+    /// Parquet handles compression internally, so it doesn't use a [`DynWriter`].
+    #[cfg(feature = "parquet")]
+    fn build_parquet<'a>(self) -> crate::Result<DynEncoder<'a, W>> {
+        Ok(DynEncoder(DynEncoderImpl::Parquet(
+            super::ParquetEncoder::builder(self.writer)
+                .version(self.metadata.version)
+                .use_pretty_px(self.use_pretty_px)
+                .use_pretty_ts(self.use_pretty_ts)
+                .ts_out(self.metadata.ts_out)
+                .schema(self.metadata.schema)
+                .with_symbol(self.with_symbol)
+                .compression(self.compression)
+                .build()?,
+        )))
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn build_parquet<'a>(self) -> crate::Result<DynEncoder<'a, W>> {
+        Err(Error::BadArgument {
+            param_name: "encoding".to_owned(),
+            desc: "Parquet encoding requires the `parquet` feature".to_owned(),
+        })
+    }
 }
 
 impl<W> DynEncoder<'_, W>
@@ -300,6 +332,8 @@ where
             DynEncoderImpl::Dbn(enc) => enc.encode_record(record),
             DynEncoderImpl::Csv(enc) => enc.encode_record(record),
             DynEncoderImpl::Json(enc) => enc.encode_record(record),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.encode_record(record),
         }
     }
 
@@ -308,6 +342,8 @@ where
             DynEncoderImpl::Dbn(encoder) => encoder.encode_records(records),
             DynEncoderImpl::Csv(encoder) => encoder.encode_records(records),
             DynEncoderImpl::Json(encoder) => encoder.encode_records(records),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.encode_records(records),
         }
     }
 
@@ -316,6 +352,8 @@ where
             DynEncoderImpl::Dbn(enc) => enc.flush(),
             DynEncoderImpl::Csv(enc) => enc.flush(),
             DynEncoderImpl::Json(enc) => enc.flush(),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.flush(),
         }
     }
 }
@@ -329,6 +367,8 @@ where
             DynEncoderImpl::Dbn(enc) => enc.encode_record_ref(record),
             DynEncoderImpl::Csv(enc) => enc.encode_record_ref(record),
             DynEncoderImpl::Json(enc) => enc.encode_record_ref(record),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.encode_record_ref(record),
         }
     }
 
@@ -337,6 +377,8 @@ where
             DynEncoderImpl::Dbn(enc) => enc.encode_record_ref_ts_out(record, ts_out),
             DynEncoderImpl::Csv(enc) => enc.encode_record_ref_ts_out(record, ts_out),
             DynEncoderImpl::Json(enc) => enc.encode_record_ref_ts_out(record, ts_out),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(enc) => enc.encode_record_ref_ts_out(record, ts_out),
         }
     }
 }
@@ -353,6 +395,8 @@ where
             DynEncoderImpl::Dbn(encoder) => encoder.encode_stream(stream),
             DynEncoderImpl::Csv(encoder) => encoder.encode_stream(stream),
             DynEncoderImpl::Json(encoder) => encoder.encode_stream(stream),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.encode_stream(stream),
         }
     }
 
@@ -361,6 +405,8 @@ where
             DynEncoderImpl::Dbn(encoder) => encoder.encode_decoded(decoder),
             DynEncoderImpl::Csv(encoder) => encoder.encode_decoded(decoder),
             DynEncoderImpl::Json(encoder) => encoder.encode_decoded(decoder),
+            #[cfg(feature = "parquet")]
+            DynEncoderImpl::Parquet(encoder) => encoder.encode_decoded(decoder),
         }
     }
 }
@@ -379,6 +425,421 @@ where
             Self::Dbn(encoder) => encoder.encode_record(record),
             Self::Csv(encoder) => encoder.encode_record_with_sym(record, symbol),
             Self::Json(encoder) => encoder.encode_record_with_sym(record, symbol),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(encoder) => encoder.encode_record_with_sym(record, symbol),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use r#async::{DynEncoder as DynAsyncEncoder, DynEncoderBuilder as DynAsyncEncoderBuilder};
+
+#[cfg(feature = "async")]
+mod r#async {
+    use tokio::io;
+
+    use crate::{
+        encode::{
+            AsyncCsvEncoder, AsyncDbnEncoder, AsyncEncodeRecord, AsyncEncodeRecordRef,
+            AsyncEncodeRecordTextExt, AsyncJsonEncoder, DbnEncodable, DynAsyncWriter,
+        },
+        Compression, Encoding, Error, Metadata, RecordRef, Result, Schema,
+    };
+
+    /// An async encoder whose [`Encoding`] and [`Compression`] can be set at runtime.
+    /// Supports DBN, CSV, and JSON encodings.
+    ///
+    /// Call [`shutdown()`](AsyncEncodeRecord::shutdown) once done encoding to ensure
+    /// compressed output is complete.
+    pub struct DynEncoder<W>(DynEncoderImpl<W>)
+    where
+        W: io::AsyncWriteExt + Unpin;
+
+    #[allow(clippy::large_enum_variant)]
+    enum DynEncoderImpl<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        Dbn(AsyncDbnEncoder<DynAsyncWriter<W>>),
+        Csv(AsyncCsvEncoder<DynAsyncWriter<W>>),
+        Json(AsyncJsonEncoder<DynAsyncWriter<W>>),
+    }
+
+    /// Helper for constructing an async [`DynEncoder`].
+    pub struct DynEncoderBuilder<'m, W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        writer: W,
+        encoding: Encoding,
+        compression: Compression,
+        metadata: &'m Metadata,
+        write_header: bool,
+        should_pretty_print: bool,
+        use_pretty_px: bool,
+        use_pretty_ts: bool,
+        with_symbol: bool,
+        delimiter: u8,
+    }
+
+    impl<'m, W> DynEncoderBuilder<'m, W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        /// Creates a new builder. All required fields for the builder are passed to this
+        /// function.
+        pub fn new(
+            writer: W,
+            encoding: Encoding,
+            compression: Compression,
+            metadata: &'m Metadata,
+        ) -> Self {
+            Self {
+                writer,
+                encoding,
+                compression,
+                metadata,
+                write_header: true,
+                should_pretty_print: false,
+                use_pretty_px: false,
+                use_pretty_ts: false,
+                with_symbol: false,
+                delimiter: b',',
+            }
+        }
+
+        /// Sets whether the CSV encoder will write a header row automatically.
+        /// Defaults to `true`.
+        ///
+        /// If `false`, a header row can still be written with
+        /// [`DynEncoder::encode_header()`] or [`DynEncoder::encode_header_for_schema()`].
+        pub fn write_header(mut self, write_header: bool) -> Self {
+            self.write_header = write_header;
+            self
+        }
+
+        /// Sets all three pretty options together: `should_pretty_print`,
+        /// `use_pretty_px`, and `use_pretty_ts`. By default all are `false`.
+        pub fn all_pretty(self, all_pretty: bool) -> Self {
+            self.should_pretty_print(all_pretty)
+                .use_pretty_px(all_pretty)
+                .use_pretty_ts(all_pretty)
+        }
+
+        /// Sets whether the encoder should encode nicely-formatted JSON objects with
+        /// indentation if encoding JSON. Defaults to `false` where each JSON object is
+        /// compact with no spacing.
+        pub fn should_pretty_print(mut self, should_pretty_print: bool) -> Self {
+            self.should_pretty_print = should_pretty_print;
+            self
+        }
+
+        /// Sets whether the encoder will serialize price fields as a decimal in CSV
+        /// and JSON encodings. Defaults to `false`.
+        pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
+            self.use_pretty_px = use_pretty_px;
+            self
+        }
+
+        /// Sets whether the encoder will serialize timestamp fields as ISO8601
+        /// datetime strings in CSV and JSON encodings. Defaults to `false`.
+        pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
+            self.use_pretty_ts = use_pretty_ts;
+            self
+        }
+
+        /// Sets whether to add a header field "symbol" if encoding CSV. Defaults to
+        /// `false`.
+        pub fn with_symbol(mut self, with_symbol: bool) -> Self {
+            self.with_symbol = with_symbol;
+            self
+        }
+
+        /// Sets the field delimiter. Defaults to `b','` for comma-separated values
+        /// (CSV).
+        pub fn delimiter(mut self, delimiter: u8) -> Self {
+            self.delimiter = delimiter;
+            self
+        }
+
+        /// Creates the new encoder with the previously specified settings and if
+        /// `write_header` is `true`, encodes the header row.
+        ///
+        /// # Errors
+        /// This function returns an error if it fails to write the CSV header row or
+        /// the DBN metadata, or if `encoding` is Parquet, which isn't supported for
+        /// async encoding.
+        pub async fn build(self) -> Result<DynEncoder<W>> {
+            let writer = DynAsyncWriter::new(self.writer, self.compression);
+            Ok(DynEncoder(match self.encoding {
+                Encoding::Dbn => {
+                    DynEncoderImpl::Dbn(AsyncDbnEncoder::new(writer, self.metadata).await?)
+                }
+                Encoding::Csv => DynEncoderImpl::Csv(
+                    AsyncCsvEncoder::builder(writer)
+                        .version(self.metadata.version)
+                        .use_pretty_px(self.use_pretty_px)
+                        .use_pretty_ts(self.use_pretty_ts)
+                        .delimiter(self.delimiter)
+                        .write_header(self.write_header)
+                        .ts_out(self.metadata.ts_out)
+                        .schema(self.metadata.schema)
+                        .with_symbol(self.with_symbol)
+                        .build()
+                        .await?,
+                ),
+                Encoding::Json => DynEncoderImpl::Json(AsyncJsonEncoder::new(
+                    writer,
+                    self.should_pretty_print,
+                    self.use_pretty_px,
+                    self.use_pretty_ts,
+                )),
+                Encoding::Parquet => {
+                    return Err(Error::BadArgument {
+                        param_name: "encoding".to_owned(),
+                        desc: "Parquet isn't supported for async encoding".to_owned(),
+                    })
+                }
+            }))
+        }
+    }
+
+    impl<W> DynEncoder<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        /// Creates a builder for configuring an async `DynEncoder` object.
+        pub fn builder(
+            writer: W,
+            encoding: Encoding,
+            compression: Compression,
+            metadata: &Metadata,
+        ) -> DynEncoderBuilder<'_, W> {
+            DynEncoderBuilder::new(writer, encoding, compression, metadata)
+        }
+
+        /// Returns a mutable reference to the underlying writer.
+        pub fn get_mut(&mut self) -> &mut W {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.get_mut().get_mut(),
+                DynEncoderImpl::Csv(enc) => enc.get_mut().get_mut(),
+                DynEncoderImpl::Json(enc) => enc.get_mut().get_mut(),
+            }
+        }
+
+        /// Encodes the CSV header for the record type `R`, i.e. the names of each of
+        /// the fields to the output. Does nothing for other encodings.
+        ///
+        /// If `with_symbol` is `true`, will add a header field for "symbol".
+        ///
+        /// # Errors
+        /// This function returns an error if there's an error writing to `writer`.
+        pub async fn encode_header<R: DbnEncodable>(&mut self, with_symbol: bool) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Csv(encoder) => encoder.encode_header::<R>(with_symbol).await,
+                _ => Ok(()),
+            }
+        }
+
+        /// Encodes the CSV header for `schema`, i.e. the names of each of the fields to
+        /// the output. Does nothing for other encodings.
+        ///
+        /// If `ts_out` is `true`, will add a header field "ts_out". If `with_symbol` is
+        /// `true`, will add a header field "symbol".
+        ///
+        /// # Errors
+        /// This function returns an error if there's an error writing to `writer`.
+        pub async fn encode_header_for_schema(
+            &mut self,
+            version: u8,
+            schema: Schema,
+            ts_out: bool,
+            with_symbol: bool,
+        ) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Csv(encoder) => {
+                    encoder
+                        .encode_header_for_schema(version, schema, ts_out, with_symbol)
+                        .await
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl<W> AsyncEncodeRecord for DynEncoder<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        async fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.encode_record(record).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record(record).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record(record).await,
+            }
+        }
+
+        async fn encode_records<R: DbnEncodable>(&mut self, records: &[R]) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.encode_records(records).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_records(records).await,
+                DynEncoderImpl::Json(enc) => enc.encode_records(records).await,
+            }
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.flush().await,
+                DynEncoderImpl::Csv(enc) => enc.flush().await,
+                DynEncoderImpl::Json(enc) => enc.flush().await,
+            }
+        }
+
+        async fn shutdown(&mut self) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.shutdown().await,
+                DynEncoderImpl::Csv(enc) => enc.shutdown().await,
+                DynEncoderImpl::Json(enc) => enc.shutdown().await,
+            }
+        }
+    }
+
+    impl<W> AsyncEncodeRecordRef for DynEncoder<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        async fn encode_record_ref(&mut self, record_ref: RecordRef<'_>) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.encode_record_ref(record_ref).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record_ref(record_ref).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record_ref(record_ref).await,
+            }
+        }
+
+        async fn encode_record_refs(&mut self, record_refs: &[RecordRef<'_>]) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.encode_record_refs(record_refs).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record_refs(record_refs).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record_refs(record_refs).await,
+            }
+        }
+
+        async unsafe fn encode_record_ref_ts_out(
+            &mut self,
+            record_ref: RecordRef<'_>,
+            ts_out: bool,
+        ) -> Result<()> {
+            match &mut self.0 {
+                DynEncoderImpl::Dbn(enc) => enc.encode_record_ref_ts_out(record_ref, ts_out).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record_ref_ts_out(record_ref, ts_out).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record_ref_ts_out(record_ref, ts_out).await,
+            }
+        }
+    }
+
+    impl<W> AsyncEncodeRecordTextExt for DynEncoder<W>
+    where
+        W: io::AsyncWriteExt + Unpin,
+    {
+        async fn encode_record_with_sym<R: DbnEncodable>(
+            &mut self,
+            record: &R,
+            symbol: Option<&str>,
+        ) -> Result<()> {
+            match &mut self.0 {
+                // Not supported for DBN so ignore `symbol`
+                DynEncoderImpl::Dbn(enc) => enc.encode_record(record).await,
+                DynEncoderImpl::Csv(enc) => enc.encode_record_with_sym(record, symbol).await,
+                DynEncoderImpl::Json(enc) => enc.encode_record_with_sym(record, symbol).await,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rstest::*;
+
+        use super::*;
+        use crate::{
+            decode::{
+                tests::TEST_DATA_PATH, DbnDecoder, DbnMetadata, DecodeRecord, DecodeRecordRef,
+            },
+            encode::{DynEncoder as SyncDynEncoder, EncodeRecordRef},
+        };
+
+        #[rstest]
+        #[tokio::test]
+        async fn test_matches_sync(
+            #[values(Encoding::Dbn, Encoding::Csv, Encoding::Json)] encoding: Encoding,
+            #[values(false, true)] pretty: bool,
+        ) {
+            let mut decoder =
+                DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                    .unwrap();
+            let metadata = decoder.metadata().clone();
+            let mut expected = Vec::new();
+            let mut sync_encoder =
+                SyncDynEncoder::builder(&mut expected, encoding, Compression::None, &metadata)
+                    .all_pretty(pretty)
+                    .build()
+                    .unwrap();
+            let mut buf = Vec::new();
+            let mut encoder = DynEncoder::builder(&mut buf, encoding, Compression::None, &metadata)
+                .all_pretty(pretty)
+                .build()
+                .await
+                .unwrap();
+            while let Some(rec) = decoder.decode_record_ref().unwrap() {
+                sync_encoder.encode_record_ref(rec).unwrap();
+                encoder.encode_record_ref(rec).await.unwrap();
+            }
+            drop(sync_encoder);
+            encoder.shutdown().await.unwrap();
+            assert_eq!(buf, expected);
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn test_zstd_round_trip() {
+            let metadata = crate::Metadata::builder()
+                .dataset("XNAS.ITCH")
+                .schema(Some(Schema::Mbo))
+                .start(0)
+                .stype_in(None)
+                .stype_out(crate::SType::InstrumentId)
+                .build();
+            let mut buf = Vec::new();
+            let mut encoder =
+                DynEncoder::builder(&mut buf, Encoding::Dbn, Compression::Zstd, &metadata)
+                    .build()
+                    .await
+                    .unwrap();
+            encoder
+                .encode_record(&crate::MboMsg::default())
+                .await
+                .unwrap();
+            encoder.shutdown().await.unwrap();
+            let decoder = DbnDecoder::with_zstd(buf.as_slice()).unwrap();
+            assert_eq!(decoder.metadata(), &metadata);
+            let records = decoder.decode_records::<crate::MboMsg>().unwrap();
+            assert_eq!(records, vec![crate::MboMsg::default()]);
+        }
+
+        #[rstest]
+        #[tokio::test]
+        async fn test_parquet_unsupported() {
+            let metadata = crate::Metadata::builder()
+                .dataset("XNAS.ITCH")
+                .schema(Some(Schema::Mbo))
+                .start(0)
+                .stype_in(None)
+                .stype_out(crate::SType::InstrumentId)
+                .build();
+            let res =
+                DynEncoder::builder(Vec::new(), Encoding::Parquet, Compression::None, &metadata)
+                    .build()
+                    .await;
+            assert!(matches!(res, Err(Error::BadArgument { .. })));
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::io;
 
-use super::{zstd_encoder, zstd_encoder_with_clevel};
+use super::{zstd_encoder, zstd_encoder_with_clevel, SeekableFrameSize, SeekableZstdWriter};
 use crate::{Compression, Result};
 
 /// Type for runtime polymorphism over whether encoding uncompressed or Zstd-compressed
@@ -15,6 +15,7 @@ where
 {
     Uncompressed(W),
     Zstd(zstd::stream::AutoFinishEncoder<'a, W>),
+    SeekableZstd(SeekableZstdWriter<W>),
 }
 
 impl<W> DynWriter<'_, W>
@@ -42,11 +43,28 @@ where
         )?)))
     }
 
+    /// Creates a new instance with zstd compression of the specified level in the
+    /// Zstandard seekable format, with a new frame every `frame_size`. The output must
+    /// be DBN. See [`SeekableZstdWriter`] for more details.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to initialize the Zstd compressor.
+    pub fn seekable_with_compression_level(
+        writer: W,
+        frame_size: SeekableFrameSize,
+        level: i32,
+    ) -> Result<Self> {
+        Ok(Self(DynWriterImpl::SeekableZstd(
+            SeekableZstdWriter::with_compression_level(writer, frame_size, level)?,
+        )))
+    }
+
     /// Returns a mutable reference to the underlying writer.
     pub fn get_mut(&mut self) -> &mut W {
         match &mut self.0 {
             DynWriterImpl::Uncompressed(w) => w,
             DynWriterImpl::Zstd(enc) => enc.get_mut(),
+            DynWriterImpl::SeekableZstd(enc) => enc.get_mut(),
         }
     }
 }
@@ -59,6 +77,7 @@ where
         match &mut self.0 {
             DynWriterImpl::Uncompressed(writer) => writer.write(buf),
             DynWriterImpl::Zstd(writer) => writer.write(buf),
+            DynWriterImpl::SeekableZstd(writer) => writer.write(buf),
         }
     }
 
@@ -66,6 +85,7 @@ where
         match &mut self.0 {
             DynWriterImpl::Uncompressed(writer) => writer.flush(),
             DynWriterImpl::Zstd(writer) => writer.flush(),
+            DynWriterImpl::SeekableZstd(writer) => writer.flush(),
         }
     }
 
@@ -73,6 +93,7 @@ where
         match &mut self.0 {
             DynWriterImpl::Uncompressed(writer) => writer.write_vectored(bufs),
             DynWriterImpl::Zstd(writer) => writer.write_vectored(bufs),
+            DynWriterImpl::SeekableZstd(writer) => writer.write_vectored(bufs),
         }
     }
 
@@ -80,6 +101,7 @@ where
         match &mut self.0 {
             DynWriterImpl::Uncompressed(writer) => writer.write_all(buf),
             DynWriterImpl::Zstd(writer) => writer.write_all(buf),
+            DynWriterImpl::SeekableZstd(writer) => writer.write_all(buf),
         }
     }
 
@@ -87,6 +109,7 @@ where
         match &mut self.0 {
             DynWriterImpl::Uncompressed(writer) => writer.write_fmt(fmt),
             DynWriterImpl::Zstd(writer) => writer.write_fmt(fmt),
+            DynWriterImpl::SeekableZstd(writer) => writer.write_fmt(fmt),
         }
     }
 }
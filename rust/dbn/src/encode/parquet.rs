@@ -0,0 +1,640 @@
+//! Encoding DBN records into [Apache Parquet](https://parquet.apache.org/).
+
+use std::{
+    any::type_name,
+    io, mem,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression as ParquetCompression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+
+use super::arrow::serialize::{schema_for, write_symbol_field, ArrowSerialize, Columns};
+use crate::{
+    encode::{DbnEncodable, EncodeDbn, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt},
+    rtype_dispatch, rtype_dispatch_base, schema_dispatch, v2, Compression, Error, Record,
+    RecordRef, Result, Schema, WithTsOut, DBN_VERSION,
+};
+
+/// The number of records buffered before they're converted into a record batch.
+const BATCH_SIZE: usize = 8192;
+
+/// Type for encoding files and streams of DBN records in Apache Parquet.
+///
+/// Each column has the same name as the corresponding CSV column. Prices are encoded
+/// as `Decimal128` with a scale of 9 when `use_pretty_px` is `true`, otherwise as the
+/// raw fixed-precision `Int64`. Timestamps are encoded as nanosecond UTC `Timestamp`s
+/// when `use_pretty_ts` is `true`, otherwise as the raw `UInt64` UNIX nanoseconds.
+///
+/// Because Parquet files have a single schema, all records in the file must be of
+/// the same type. Note that encoding [`Metadata`](crate::Metadata) in Parquet is not
+/// supported.
+///
+/// The Parquet footer is written when calling [`finish()`](Self::finish) or when the
+/// encoder is dropped. Errors encountered while dropping are ignored.
+pub struct Encoder<W>
+where
+    W: io::Write,
+{
+    writer: W,
+    buffer: SharedBuffer,
+    props: WriterProperties,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    with_symbol: bool,
+    state: Option<State>,
+    is_finished: bool,
+}
+
+/// Helper for constructing a Parquet [`Encoder`].
+///
+/// No fields are required.
+pub struct EncoderBuilder<W>
+where
+    W: io::Write,
+{
+    writer: W,
+    use_pretty_px: bool,
+    use_pretty_ts: bool,
+    version: u8,
+    schema: Option<Schema>,
+    ts_out: bool,
+    with_symbol: bool,
+    compression: Compression,
+    row_group_size: Option<usize>,
+}
+
+struct State {
+    record_type: &'static str,
+    has_symbol: bool,
+    columns: Columns,
+    arrow_writer: ArrowWriter<SharedBuffer>,
+}
+
+/// [`ArrowWriter`] requires a `Send` writer, which isn't true of all writers like
+/// `StdoutLock`, so the output is buffered here and then copied to the true writer.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl<W> EncoderBuilder<W>
+where
+    W: io::Write,
+{
+    /// Creates a new Parquet encoder builder.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            use_pretty_px: false,
+            use_pretty_ts: false,
+            version: DBN_VERSION,
+            schema: None,
+            ts_out: false,
+            with_symbol: false,
+            compression: Compression::None,
+            row_group_size: None,
+        }
+    }
+
+    /// Sets whether the Parquet encoder will serialize price fields as decimals.
+    /// Defaults to `false`.
+    pub fn use_pretty_px(mut self, use_pretty_px: bool) -> Self {
+        self.use_pretty_px = use_pretty_px;
+        self
+    }
+
+    /// Sets whether the Parquet encoder will serialize timestamp fields as Parquet
+    /// timestamps. Defaults to `false`.
+    pub fn use_pretty_ts(mut self, use_pretty_ts: bool) -> Self {
+        self.use_pretty_ts = use_pretty_ts;
+        self
+    }
+
+    /// Sets the schema that will be encoded, used for determining the Parquet schema
+    /// so a valid file can be written even if no records are encoded.
+    ///
+    /// If schema isn't set, the Parquet schema will be based on the type of the first
+    /// record.
+    pub fn schema(mut self, schema: Option<Schema>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Sets whether to add a "ts_out" column when `schema` is set. Defaults to
+    /// `false`.
+    pub fn ts_out(mut self, ts_out: bool) -> Self {
+        self.ts_out = ts_out;
+        self
+    }
+
+    /// Sets whether to add a "symbol" column. Defaults to `false`.
+    pub fn with_symbol(mut self, with_symbol: bool) -> Self {
+        self.with_symbol = with_symbol;
+        self
+    }
+
+    /// Sets the DBN version which is used for determining which fields to include in
+    /// the Parquet schema when `schema` is set. Currently only relevant to the
+    /// definition schema where fields have changed between versions.
+    ///
+    /// If not specified, defaults to [`DBN_VERSION`].
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the compression applied to the Parquet column chunks. Defaults to
+    /// [`Compression::None`].
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the maximum number of records in each Parquet row group. Defaults to the
+    /// `parquet` crate's default.
+    pub fn row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = Some(row_group_size);
+        self
+    }
+
+    /// Creates the new encoder with the previously specified settings.
+    ///
+    /// # Errors
+    /// This function returns an error if `schema` is set and it fails to initialize
+    /// the Parquet writer.
+    pub fn build(self) -> crate::Result<Encoder<W>> {
+        let mut props = WriterProperties::builder().set_compression(match self.compression {
+            Compression::None => ParquetCompression::UNCOMPRESSED,
+            Compression::Zstd => ParquetCompression::ZSTD(ZstdLevel::default()),
+        });
+        if let Some(row_group_size) = self.row_group_size {
+            props = props.set_max_row_group_size(row_group_size);
+        }
+        let mut encoder = Encoder {
+            writer: self.writer,
+            buffer: SharedBuffer::default(),
+            props: props.build(),
+            use_pretty_px: self.use_pretty_px,
+            use_pretty_ts: self.use_pretty_ts,
+            with_symbol: self.with_symbol,
+            state: None,
+            is_finished: false,
+        };
+        if let Some(schema) = self.schema {
+            let with_symbol = self.with_symbol;
+            // Workaround for definitions fields changing between versions 1/2 and 3
+            if self.version < 3 && schema == Schema::Definition {
+                if self.ts_out {
+                    encoder.init::<WithTsOut<v2::InstrumentDefMsg>>(with_symbol)?;
+                } else {
+                    encoder.init::<v2::InstrumentDefMsg>(with_symbol)?;
+                }
+            } else {
+                schema_dispatch!(schema, ts_out: self.ts_out, encoder.init(with_symbol))?;
+            }
+        }
+        Ok(encoder)
+    }
+}
+
+impl<W> Encoder<W>
+where
+    W: io::Write,
+{
+    /// Creates a builder for configuring an `Encoder` object.
+    pub fn builder(writer: W) -> EncoderBuilder<W> {
+        EncoderBuilder::new(writer)
+    }
+
+    /// Creates a new [`Encoder`] that will write to `writer`.
+    ///
+    /// If `use_pretty_px` is `true`, price fields will be serialized as decimals. If
+    /// `use_pretty_ts` is `true`, timestamp fields will be serialized as Parquet
+    /// timestamps.
+    pub fn new(writer: W, use_pretty_px: bool, use_pretty_ts: bool) -> Self {
+        Self::builder(writer)
+            .use_pretty_px(use_pretty_px)
+            .use_pretty_ts(use_pretty_ts)
+            .build()
+            // Not setting `schema`
+            .unwrap()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Writes any buffered records and the Parquet footer to the output. No records
+    /// can be encoded afterwards. If no records were encoded and `schema` wasn't set,
+    /// nothing will be written.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to write to the underlying writer.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.is_finished {
+            return Ok(());
+        }
+        self.is_finished = true;
+        self.write_batch()?;
+        if let Some(state) = self.state.as_mut() {
+            state
+                .arrow_writer
+                .finish()
+                .map_err(|e| Error::encode(format!("failed to write Parquet footer: {e}")))?;
+        }
+        self.drain_buffer()?;
+        self.writer
+            .flush()
+            .map_err(|e| Error::io(e, "flushing output"))
+    }
+
+    fn init<R: ArrowSerialize>(&mut self, with_symbol: bool) -> Result<()> {
+        let schema = Arc::new(match (self.use_pretty_px, self.use_pretty_ts) {
+            (true, true) => schema_for::<R, true, true>(with_symbol),
+            (true, false) => schema_for::<R, true, false>(with_symbol),
+            (false, true) => schema_for::<R, false, true>(with_symbol),
+            (false, false) => schema_for::<R, false, false>(with_symbol),
+        });
+        let arrow_writer = ArrowWriter::try_new(
+            self.buffer.clone(),
+            schema.clone(),
+            Some(self.props.clone()),
+        )
+        .map_err(|e| Error::encode(format!("failed to initialize Parquet writer: {e}")))?;
+        self.state = Some(State {
+            record_type: type_name::<R>(),
+            has_symbol: with_symbol,
+            columns: Columns::new(schema, BATCH_SIZE),
+            arrow_writer,
+        });
+        Ok(())
+    }
+
+    /// Encodes a record of any type by dispatching to its concrete record type based
+    /// on the `rtype`, because `DbnEncodable` doesn't include `ArrowSerialize`.
+    fn encode_any_record<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+        with_symbol: bool,
+    ) -> Result<()> {
+        // SAFETY: `record` is a valid DBN record: it satisfies `R: Record`.
+        let rec_ref = unsafe { RecordRef::unchecked_from_header(record.header() as *const _) };
+        let rec_len = record.as_ref().len();
+        macro_rules! handler {
+            ($r:ty) => {{
+                // SAFETY: the rtype was checked by the dispatch and the length of
+                // `record` is checked before each conversion.
+                if rec_len >= mem::size_of::<WithTsOut<$r>>() {
+                    self.encode_record_impl(
+                        unsafe { rec_ref.get_unchecked::<WithTsOut<$r>>() },
+                        symbol,
+                        with_symbol,
+                    )
+                } else if rec_len >= mem::size_of::<$r>() {
+                    self.encode_record_impl(
+                        unsafe { rec_ref.get_unchecked::<$r>() },
+                        symbol,
+                        with_symbol,
+                    )
+                } else {
+                    Err(Error::encode(format!(
+                        "{} is too short to encode as {}",
+                        type_name::<R>(),
+                        type_name::<$r>()
+                    )))
+                }
+            }};
+        }
+        rtype_dispatch_base!(rec_ref, handler)?
+    }
+
+    fn encode_record_impl<R: Record + ArrowSerialize>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+        with_symbol: bool,
+    ) -> Result<()> {
+        if self.is_finished {
+            return Err(Error::encode(
+                "can't encode records after finishing the Parquet file",
+            ));
+        }
+        if self.state.is_none() {
+            self.init::<R>(self.with_symbol || with_symbol)?;
+        }
+        let state = self.state.as_mut().unwrap();
+        if state.record_type != type_name::<R>() {
+            return Err(Error::encode(format!(
+                "can't encode {} in a Parquet file of {}. Mixed schemas cannot be encoded in Parquet.",
+                type_name::<R>(),
+                state.record_type
+            )));
+        }
+        match (self.use_pretty_px, self.use_pretty_ts) {
+            (true, true) => record.serialize_to::<true, true>(&mut state.columns),
+            (true, false) => record.serialize_to::<true, false>(&mut state.columns),
+            (false, true) => record.serialize_to::<false, true>(&mut state.columns),
+            (false, false) => record.serialize_to::<false, false>(&mut state.columns),
+        }
+        if state.has_symbol {
+            write_symbol_field(&mut state.columns, symbol);
+        }
+        state.columns.end_row();
+        if state.columns.len() >= BATCH_SIZE {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Converts buffered records to a record batch and passes it to the
+    /// [`ArrowWriter`].
+    fn write_batch(&mut self) -> Result<()> {
+        if let Some(state) = self.state.as_mut() {
+            if !state.columns.is_empty() {
+                let batch = state.columns.finish()?;
+                state
+                    .arrow_writer
+                    .write(&batch)
+                    .map_err(|e| Error::encode(format!("failed to write Parquet rows: {e}")))?;
+            }
+        }
+        self.drain_buffer()
+    }
+
+    fn drain_buffer(&mut self) -> Result<()> {
+        let mut buffer = self.buffer.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if !buffer.is_empty() {
+            self.writer
+                .write_all(&buffer)
+                .map_err(|e| Error::io(e, "writing Parquet output"))?;
+            buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W> Drop for Encoder<W>
+where
+    W: io::Write,
+{
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+impl<W> EncodeRecord for Encoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        self.encode_any_record(record, None, false)
+    }
+
+    fn encode_records<R: DbnEncodable>(&mut self, records: &[R]) -> Result<()> {
+        for record in records {
+            self.encode_record(record)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all buffered records to the output as a Parquet row group. Frequent
+    /// flushing results in small row groups.
+    fn flush(&mut self) -> Result<()> {
+        self.write_batch()?;
+        if let Some(state) = self.state.as_mut() {
+            state
+                .arrow_writer
+                .flush()
+                .map_err(|e| Error::encode(format!("failed to write Parquet row group: {e}")))?;
+        }
+        self.drain_buffer()?;
+        self.writer
+            .flush()
+            .map_err(|e| Error::io(e, "flushing output"))
+    }
+}
+
+impl<W> EncodeRecordRef for Encoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record_ref(&mut self, record: RecordRef) -> Result<()> {
+        rtype_dispatch!(record, self.encode_record_impl(None, false))?
+    }
+
+    unsafe fn encode_record_ref_ts_out(&mut self, record: RecordRef, ts_out: bool) -> Result<()> {
+        rtype_dispatch!(record, ts_out: ts_out, self.encode_record_impl(None, false))?
+    }
+}
+
+impl<W> EncodeDbn for Encoder<W> where W: io::Write {}
+
+impl<W> EncodeRecordTextExt for Encoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> Result<()> {
+        self.encode_any_record(record, symbol, true)
+    }
+}
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use arrow_array::{
+        cast::AsArray,
+        types::{Int64Type, UInt64Type},
+        Array, RecordBatch,
+    };
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        encode::test_data::{BID_ASK, RECORD_HEADER},
+        rtype, Mbp1Msg, RecordHeader, TradeMsg,
+    };
+
+    fn read_batches(buffer: Vec<u8>) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buffer))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    fn mbp1(price: i64) -> Mbp1Msg {
+        Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(
+                rtype::MBP_1,
+                RECORD_HEADER.publisher_id,
+                RECORD_HEADER.instrument_id,
+                RECORD_HEADER.ts_event,
+            ),
+            price,
+            size: 10,
+            action: b'B' as c_char,
+            side: b'B' as c_char,
+            flags: 128.into(),
+            depth: 9,
+            ts_recv: 1658441891000000000,
+            ts_in_delta: 22_000,
+            sequence: 1_002_375,
+            levels: [BID_ASK],
+        }
+    }
+
+    #[rstest]
+    fn test_encode_records(
+        #[values(Compression::None, Compression::Zstd)] compression: Compression,
+    ) {
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::builder(&mut buffer)
+            .compression(compression)
+            .build()
+            .unwrap();
+        encoder
+            .encode_records(&[mbp1(5_500), mbp1(5_600), mbp1(5_700)])
+            .unwrap();
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        let batches = read_batches(buffer);
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+        let batch = &batches[0];
+        assert_eq!(batch.schema().field(0).name(), "ts_recv");
+        assert_eq!(
+            batch
+                .column_by_name("price")
+                .unwrap()
+                .as_primitive::<Int64Type>()
+                .values(),
+            &[5_500, 5_600, 5_700]
+        );
+        assert_eq!(
+            batch
+                .column_by_name("bid_px_00")
+                .unwrap()
+                .as_primitive::<Int64Type>()
+                .value(0),
+            BID_ASK.bid_px
+        );
+    }
+
+    #[test]
+    fn test_encode_with_symbol() {
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::new(&mut buffer, true, true);
+        let record = mbp1(5_500);
+        encoder
+            .encode_record_with_sym(&record, Some("ESZ4"))
+            .unwrap();
+        encoder.encode_record_with_sym(&record, None).unwrap();
+        drop(encoder);
+
+        let batches = read_batches(buffer);
+        let symbol = batches[0].column_by_name("symbol").unwrap();
+        assert_eq!(symbol.as_string::<i32>().value(0), "ESZ4");
+        assert!(symbol.is_null(1));
+    }
+
+    #[test]
+    fn test_mixed_types_fails() {
+        let mut encoder = Encoder::new(Vec::new(), false, false);
+        encoder.encode_record(&mbp1(5_500)).unwrap();
+        assert!(matches!(
+            encoder.encode_record(&TradeMsg::default()),
+            Err(Error::Encode(msg)) if msg.contains("Mixed schemas")
+        ));
+    }
+
+    #[test]
+    fn test_encode_record_ts_out() {
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::new(&mut buffer, false, false);
+        let record = WithTsOut::new(mbp1(5_500), 1_658_441_891_000_000_100);
+        encoder.encode_record(&record).unwrap();
+        drop(encoder);
+
+        let batches = read_batches(buffer);
+        let ts_out = batches[0].column_by_name("ts_out").unwrap();
+        assert_eq!(ts_out.as_primitive::<UInt64Type>().value(0), record.ts_out);
+    }
+
+    #[test]
+    fn test_encode_record_legacy_version() {
+        let mut encoder = Encoder::new(Vec::new(), false, false);
+        encoder
+            .encode_record(&v2::InstrumentDefMsg::default())
+            .unwrap();
+        assert_eq!(
+            encoder.state.as_ref().unwrap().record_type,
+            type_name::<v2::InstrumentDefMsg>()
+        );
+    }
+
+    #[test]
+    fn test_empty_with_schema() {
+        let mut buffer = Vec::new();
+        Encoder::builder(&mut buffer)
+            .schema(Some(Schema::Trades))
+            .ts_out(true)
+            .build()
+            .unwrap()
+            .finish()
+            .unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buffer)).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.fields().last().unwrap().name(), "ts_out");
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 0);
+    }
+
+    #[rstest]
+    fn test_encode_decoded(
+        #[values(
+            "mbo",
+            "mbp-1",
+            "mbp-10",
+            "trades",
+            "ohlcv-1s",
+            "definition",
+            "statistics"
+        )]
+        schema: &str,
+    ) {
+        let decoder =
+            DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.{schema}.v3.dbn.zst"))
+                .unwrap();
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::new(&mut buffer, true, true);
+        encoder.encode_decoded(decoder).unwrap();
+        drop(encoder);
+        let batches = read_batches(buffer);
+        assert!(batches.iter().map(RecordBatch::num_rows).sum::<usize>() > 0);
+    }
+}
@@ -0,0 +1,344 @@
+use std::{io, num::NonZeroU32};
+
+use crate::{
+    decode::zstd::{SeekTable, SeekTableEntry},
+    record::RecordHeader,
+    Result,
+};
+
+use super::ZSTD_COMPRESSION_LEVEL;
+
+/// When a [`SeekableZstdWriter`] ends one frame and begins the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekableFrameSize {
+    /// End each frame after this many records.
+    Records(NonZeroU32),
+    /// End each frame after the first record that brings its uncompressed size to at
+    /// least this many bytes.
+    Bytes(NonZeroU32),
+}
+
+impl Default for SeekableFrameSize {
+    /// 1 MiB frames.
+    fn default() -> Self {
+        Self::Bytes(NonZeroU32::new(1 << 20).unwrap())
+    }
+}
+
+/// Type for writing DBN in the [Zstandard seekable format], where the metadata and
+/// groups of records are compressed in independent frames followed by a seek table.
+/// The output can be read by any Zstandard decoder, and decoders that understand the
+/// seek table can begin decompressing at any frame.
+///
+/// Frames always end on a record boundary, so the input must be a DBN stream beginning
+/// with metadata, such as from a DBN [`Encoder`](super::DbnEncoder). The final frame
+/// and seek table are written when [`finish()`](Self::finish) is called or the writer
+/// is dropped.
+///
+/// [Zstandard seekable format]: https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md
+pub struct SeekableZstdWriter<W>
+where
+    W: io::Write,
+{
+    writer: W,
+    compressor: zstd::bulk::Compressor<'static>,
+    frame_size: SeekableFrameSize,
+    /// Uncompressed data of the current frame.
+    buffer: Vec<u8>,
+    /// End of the last complete record in `buffer`.
+    record_end: usize,
+    record_count: u32,
+    has_metadata: bool,
+    seek_table: SeekTable,
+    is_finished: bool,
+}
+
+impl<W> SeekableZstdWriter<W>
+where
+    W: io::Write,
+{
+    /// Creates a new [`SeekableZstdWriter`] that will write to `writer` with frames of
+    /// `frame_size`.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to initialize the Zstd compressor.
+    pub fn new(writer: W, frame_size: SeekableFrameSize) -> Result<Self> {
+        Self::with_compression_level(writer, frame_size, ZSTD_COMPRESSION_LEVEL)
+    }
+
+    /// Creates a new [`SeekableZstdWriter`] that will write to `writer` with frames of
+    /// `frame_size` compressed at the specified `level`.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to initialize the Zstd compressor.
+    pub fn with_compression_level(
+        writer: W,
+        frame_size: SeekableFrameSize,
+        level: i32,
+    ) -> Result<Self> {
+        let mut compressor = zstd::bulk::Compressor::new(level)
+            .map_err(|e| crate::Error::io(e, "creating zstd compressor"))?;
+        compressor
+            .include_checksum(true)
+            .map_err(|e| crate::Error::io(e, "setting zstd checksum"))?;
+        Ok(Self {
+            writer,
+            compressor,
+            frame_size,
+            buffer: Vec::new(),
+            record_end: 0,
+            record_count: 0,
+            has_metadata: false,
+            seek_table: SeekTable::default(),
+            is_finished: false,
+        })
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the seek table of the frames written so far.
+    pub fn seek_table(&self) -> &SeekTable {
+        &self.seek_table
+    }
+
+    /// Writes any buffered data as the final frame followed by the seek table. Writing
+    /// after finishing will return an error.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to write to the underlying writer.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.is_finished {
+            return Ok(());
+        }
+        self.end_frame(self.buffer.len())?;
+        self.is_finished = true;
+        self.seek_table.write(&mut self.writer)?;
+        self.writer.flush()
+    }
+
+    /// Ends frames at record boundaries according to `frame_size`.
+    fn split_frames(&mut self) -> io::Result<()> {
+        if !self.has_metadata {
+            if self.buffer.len() < 8 {
+                return Ok(());
+            }
+            if &self.buffer[..3] != b"DBN" {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "expected DBN metadata at the start of the stream",
+                ));
+            }
+            let metadata_len =
+                8 + u32::from_le_bytes(self.buffer[4..8].try_into().unwrap()) as usize;
+            if self.buffer.len() < metadata_len {
+                return Ok(());
+            }
+            self.has_metadata = true;
+            // Metadata gets its own frame
+            self.end_frame(metadata_len)?;
+        }
+        while let Some(&length) = self.buffer.get(self.record_end) {
+            let record_len = length as usize * RecordHeader::LENGTH_MULTIPLIER;
+            if record_len == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "encountered record with length 0",
+                ));
+            }
+            if self.buffer.len() < self.record_end + record_len {
+                break;
+            }
+            self.record_end += record_len;
+            self.record_count += 1;
+            let is_full = match self.frame_size {
+                SeekableFrameSize::Records(limit) => self.record_count >= limit.get(),
+                SeekableFrameSize::Bytes(limit) => self.record_end >= limit.get() as usize,
+            };
+            if is_full {
+                self.end_frame(self.record_end)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compresses the first `len` bytes of the buffer as a frame.
+    fn end_frame(&mut self, len: usize) -> io::Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        let decompressed_size = u32::try_from(len).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seekable Zstandard frames can't exceed 4 GiB",
+            )
+        })?;
+        let frame = self.compressor.compress(&self.buffer[..len])?;
+        self.writer.write_all(&frame)?;
+        self.seek_table.push(SeekTableEntry {
+            compressed_size: frame.len() as u32,
+            decompressed_size,
+        });
+        self.buffer.drain(..len);
+        self.record_end = self.record_end.saturating_sub(len);
+        self.record_count = 0;
+        Ok(())
+    }
+}
+
+impl<W> io::Write for SeekableZstdWriter<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_finished {
+            return Err(io::Error::other("writing after finishing Zstandard stream"));
+        }
+        self.buffer.extend_from_slice(buf);
+        self.split_frames()?;
+        Ok(buf.len())
+    }
+
+    /// Ends the current frame at the last complete record and flushes the underlying
+    /// writer.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.has_metadata && !self.is_finished {
+            self.end_frame(self.record_end)?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W> Drop for SeekableZstdWriter<W>
+where
+    W: io::Write,
+{
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{
+            tests::TEST_DATA_PATH, DbnDecoder, DbnMetadata, DecodeRecord, DecodeRecordRef,
+            DynReader, SkipBytes,
+        },
+        encode::{DbnEncoder, EncodeRecordRef},
+        MboMsg, Record, VersionUpgradePolicy,
+    };
+
+    fn encode_seekable(frame_size: SeekableFrameSize) -> (Vec<u8>, Vec<u8>) {
+        let mut decoder =
+            DbnDecoder::from_zstd_file(format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst"))
+                .unwrap();
+        let metadata = decoder.metadata().clone();
+        let mut expected = Vec::new();
+        let mut buf = Vec::new();
+        let mut encoder = DbnEncoder::with_zstd_seekable(&mut buf, &metadata, frame_size).unwrap();
+        let mut plain_encoder = DbnEncoder::new(&mut expected, &metadata).unwrap();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            encoder.encode_record_ref(rec).unwrap();
+            plain_encoder.encode_record_ref(rec).unwrap();
+        }
+        drop(encoder);
+        (buf, expected)
+    }
+
+    #[rstest]
+    #[case::one_record(SeekableFrameSize::Records(NonZeroU32::new(1).unwrap()))]
+    #[case::small(SeekableFrameSize::Bytes(NonZeroU32::new(100).unwrap()))]
+    #[case::default(SeekableFrameSize::default())]
+    fn test_round_trip(#[case] frame_size: SeekableFrameSize) {
+        let (buf, expected) = encode_seekable(frame_size);
+        // Readable by any Zstandard decoder
+        assert_eq!(zstd::decode_all(buf.as_slice()).unwrap(), expected);
+        let seek_table = SeekTable::read(&mut Cursor::new(&buf)).unwrap().unwrap();
+        let total: u64 = seek_table
+            .entries()
+            .iter()
+            .map(|e| e.decompressed_size as u64)
+            .sum();
+        assert_eq!(total, expected.len() as u64);
+        let mut reader = DynReader::inferred_seekable(Cursor::new(&buf)).unwrap();
+        let mut res = Vec::new();
+        reader.read_to_end(&mut res).unwrap();
+        assert_eq!(res, expected);
+    }
+
+    #[rstest]
+    fn test_frames_end_on_records() {
+        let (buf, expected) =
+            encode_seekable(SeekableFrameSize::Records(NonZeroU32::new(1).unwrap()));
+        let seek_table = SeekTable::read(&mut Cursor::new(&buf)).unwrap().unwrap();
+        let decoder = DbnDecoder::new(expected.as_slice()).unwrap();
+        let record_count = decoder.decode_records::<MboMsg>().unwrap().len();
+        // metadata frame plus one frame per record
+        assert_eq!(seek_table.entries().len(), record_count + 1);
+        for entry in seek_table.entries().iter().skip(1) {
+            assert_eq!(
+                entry.decompressed_size as usize,
+                std::mem::size_of::<MboMsg>()
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_skip_bytes_uses_seek_table(#[values(0, 1, 60, 100, 120)] skip: usize) {
+        let (buf, expected) =
+            encode_seekable(SeekableFrameSize::Records(NonZeroU32::new(1).unwrap()));
+        let mut reader = DynReader::inferred_seekable(Cursor::new(&buf)).unwrap();
+        let mut first = [0; 4];
+        reader.read_exact(&mut first).unwrap();
+        reader.skip_bytes(skip).unwrap();
+        let mut res = Vec::new();
+        reader.read_to_end(&mut res).unwrap();
+        assert_eq!(res, expected[4 + skip..]);
+    }
+
+    #[rstest]
+    fn test_seek_with_index() {
+        let (buf, _) = encode_seekable(SeekableFrameSize::Records(NonZeroU32::new(1).unwrap()));
+        let index =
+            crate::index::Index::build(buf.as_slice(), crate::Compression::Zstd, 1).unwrap();
+        let seek_table = SeekTable::read(&mut Cursor::new(&buf)).unwrap().unwrap();
+        // one index frame per seek table entry, plus the seek table itself
+        assert_eq!(index.frames().len(), seek_table.entries().len() + 1);
+        let last = index.entries().last().unwrap();
+        let mut decoder = DbnDecoder::seek_with_index(
+            Cursor::new(&buf),
+            &index,
+            last.ts,
+            VersionUpgradePolicy::AsIs,
+        )
+        .unwrap();
+        let rec = decoder.decode_record_ref().unwrap().unwrap();
+        assert_eq!(rec.raw_index_ts(), last.ts);
+        assert!(rec.has::<MboMsg>());
+    }
+
+    #[rstest]
+    fn test_invalid_input() {
+        let mut writer = SeekableZstdWriter::new(Vec::new(), SeekableFrameSize::default()).unwrap();
+        assert!(writer.write_all(b"NOT DBN DATA").is_err());
+    }
+
+    #[rstest]
+    fn test_no_seek_table() {
+        let buf = zstd::encode_all(b"DBN".as_slice(), 0).unwrap();
+        assert!(SeekTable::read(&mut Cursor::new(&buf)).unwrap().is_none());
+    }
+}
@@ -2,7 +2,18 @@
 //!
 //! This module provides [`SplitEncoder`] which wraps a [`Splitter`] implementation
 //! to route records to different sub-encoders based on various criteria such as time,
-//! symbol, or schema.
+//! symbol, or schema. With the `async` feature, [`AsyncSplitEncoder`] does the same
+//! for async sub-encoders, which it builds asynchronously, optionally limiting how many
+//! are open at once.
+
+#[cfg(feature = "async")]
+mod r#async;
+#[cfg(feature = "async")]
+pub use r#async::{
+    SchemaSplitter as AsyncSchemaSplitter, SplitEncoder as AsyncSplitEncoder,
+    Splitter as AsyncSplitter, SymbolSplitter as AsyncSymbolSplitter,
+    TimeSplitter as AsyncTimeSplitter,
+};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -10,11 +21,11 @@ use std::{
     num::NonZeroU64,
 };
 
-use time::{Time, Weekday};
+use time::{OffsetDateTime, Time, UtcOffset, Weekday};
 
 use crate::{
     encode::{DbnEncodable, EncodeDbn, EncodeRecord, EncodeRecordRef, EncodeRecordTextExt},
-    Metadata, RType, Record, RecordRef, Schema, SymbolIndex,
+    Metadata, RType, Record, RecordRef, SType, Schema, SymbolIndex,
 };
 
 /// A strategy for routing records to different sub-encoders.
@@ -227,6 +238,18 @@ pub enum SplitDuration {
     Month,
 }
 
+impl SplitDuration {
+    /// Returns the first date of the split containing `date`.
+    fn start_date(self, date: time::Date) -> time::Date {
+        match self {
+            SplitDuration::Day => date,
+            SplitDuration::Week if date.weekday() == Weekday::Sunday => date,
+            SplitDuration::Week => date.prev_occurrence(Weekday::Sunday),
+            SplitDuration::Month => date.replace_day(1).unwrap(),
+        }
+    }
+}
+
 /// Splits a stream by time.
 #[derive(Debug)]
 pub struct TimeSplitter<E, F> {
@@ -267,6 +290,42 @@ pub struct SchemaSplitter<E, F> {
     no_schema_behavior: NoSchemaBehavior,
 }
 
+/// Splits a stream into intervals of a fixed length within each day, such as hours or
+/// 15-minute periods.
+///
+/// Days begin at the splitter's origin in its UTC offset, midnight UTC by default.
+/// Setting the origin to the session open aligns the intervals to the session, such as
+/// 09:30 at an offset of -05:00 for the U.S. equities open. The last interval of each
+/// day ends when the next day begins, so intervals longer than a day are shortened to
+/// one day.
+///
+/// The UTC offset is fixed rather than a time zone, so it doesn't follow daylight saving
+/// time. To align to a time zone's session open across a transition, split each side of
+/// the transition separately with its own offset.
+#[derive(Debug)]
+pub struct IntervalSplitter<E, F> {
+    build_encoder: F,
+    interval: NonZeroU64,
+    utc_offset: UtcOffset,
+    origin: Time,
+    encoders: HashMap<OffsetDateTime, E>,
+}
+
+/// Splits a stream by publisher ID.
+#[derive(Debug)]
+pub struct PublisherSplitter<E, F> {
+    build_encoder: F,
+    encoders: HashMap<u16, E>,
+}
+
+/// Splits a stream by instrument ID. Unlike [`SymbolSplitter`], it doesn't require a
+/// symbol map.
+#[derive(Debug)]
+pub struct InstrumentSplitter<E, F> {
+    build_encoder: F,
+    encoders: HashMap<u32, E>,
+}
+
 impl<E, F> TimeSplitter<E, F>
 where
     F: Fn(time::Date, Option<Metadata>) -> crate::Result<E>,
@@ -281,66 +340,6 @@ where
             encoders: HashMap::new(),
         }
     }
-
-    fn split_metadata(
-        split_duration: SplitDuration,
-        mut metadata: Metadata,
-        encoder_date: time::Date,
-    ) -> Metadata {
-        metadata.start = metadata
-            .start()
-            .max(encoder_date.with_time(Time::MIDNIGHT).assume_utc())
-            .unix_timestamp_nanos() as u64;
-        let end = match split_duration {
-            SplitDuration::Day => encoder_date.next_day().unwrap(),
-            SplitDuration::Week => encoder_date + time::Duration::days(7),
-            SplitDuration::Month => {
-                let end_year = if encoder_date.month() == time::Month::December {
-                    encoder_date.year() + 1
-                } else {
-                    encoder_date.year()
-                };
-                encoder_date
-                    .replace_month(encoder_date.month().next())
-                    .unwrap()
-                    .replace_year(end_year)
-                    .unwrap()
-            }
-        }
-        .with_time(Time::MIDNIGHT)
-        .assume_utc();
-        metadata.end = NonZeroU64::new(
-            metadata
-                .end()
-                .map(|old_end| old_end.min(end))
-                .unwrap_or(end)
-                .unix_timestamp_nanos() as u64,
-        );
-        let start_date = metadata.start().date();
-        let end = metadata.end().unwrap();
-        let end_date = if end.time() == time::Time::MIDNIGHT {
-            end.date()
-        } else {
-            end.date().next_day().unwrap()
-        };
-        metadata.mappings.retain_mut(|mapping| {
-            mapping.intervals.retain_mut(|interval| {
-                interval.start_date = interval.start_date.max(start_date);
-                interval.end_date = interval.end_date.min(end_date);
-                interval.start_date < end_date && interval.end_date > start_date
-            });
-            !mapping.intervals.is_empty()
-        });
-        let symbols = metadata
-            .mappings
-            .iter()
-            .map(|m| &m.raw_symbol)
-            .collect::<HashSet<_>>();
-        metadata.symbols.retain(|s| symbols.contains(s));
-        metadata.partial.retain(|s| symbols.contains(s));
-
-        metadata
-    }
 }
 
 impl<E, F> Splitter<E> for TimeSplitter<E, F>
@@ -360,18 +359,13 @@ where
         let index_date = record
             .index_date()
             .ok_or_else(|| crate::Error::encode("record has undefined timestamp"))?;
-        let encoder_date = match self.split_duration {
-            SplitDuration::Day => index_date,
-            SplitDuration::Week if index_date.weekday() == Weekday::Sunday => index_date,
-            SplitDuration::Week => index_date.prev_occurrence(Weekday::Sunday),
-            SplitDuration::Month => index_date.replace_day(1).unwrap(),
-        };
+        let encoder_date = self.split_duration.start_date(index_date);
         let encoder = match self.encoders.entry(encoder_date) {
             Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
                 let split_metadata = metadata
                     .cloned()
-                    .map(|m| Self::split_metadata(self.split_duration, m, encoder_date));
+                    .map(|m| time_split_metadata(self.split_duration, m, encoder_date));
                 entry.insert((self.build_encoder)(encoder_date, split_metadata)?)
             }
         };
@@ -434,13 +428,7 @@ where
         let encoder = match self.encoders.entry(symbol.clone()) {
             Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
-                let split_metadata = metadata.cloned().map(|mut m| {
-                    m.symbols.retain(|s| *s == symbol);
-                    m.partial.retain(|s| *s == symbol);
-                    m.mappings
-                        .retain(|sym_mapping| sym_mapping.raw_symbol == symbol);
-                    m
-                });
+                let split_metadata = metadata.cloned().map(|m| symbol_split_metadata(m, &symbol));
                 entry.insert((self.build_encoder)(&symbol, split_metadata)?)
             }
         };
@@ -511,15 +499,9 @@ where
         let encoder = match self.encoders.entry(schema) {
             Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
-                let split_metadata = metadata.cloned().map(|mut m| {
-                    // Only set schema if not broadcasting (broadcast outputs contain mixed rtypes)
-                    if self.no_schema_behavior != NoSchemaBehavior::Broadcast {
-                        m.schema = Some(schema);
-                    } else {
-                        m.schema = None;
-                    }
-                    m
-                });
+                let split_metadata = metadata
+                    .cloned()
+                    .map(|m| schema_split_metadata(m, schema, self.no_schema_behavior));
                 entry.insert((self.build_encoder)(schema, split_metadata)?)
             }
         };
@@ -534,11 +516,320 @@ where
     }
 }
 
+impl<E, F> IntervalSplitter<E, F>
+where
+    F: Fn(OffsetDateTime, Option<Metadata>) -> crate::Result<E>,
+{
+    /// Creates a new splitter that will split the input stream into intervals of
+    /// `interval` nanoseconds, creating a separate sub-encoder for each interval using
+    /// `build_encoder`, which is passed the start of the interval in the splitter's UTC
+    /// offset.
+    pub fn new(build_encoder: F, interval: NonZeroU64) -> Self {
+        Self {
+            build_encoder,
+            interval,
+            utc_offset: UtcOffset::UTC,
+            origin: Time::MIDNIGHT,
+            encoders: HashMap::new(),
+        }
+    }
+
+    /// Sets the fixed UTC offset the intervals are aligned to. Defaults to UTC.
+    ///
+    /// The offset applies to every day, including those on the other side of a daylight
+    /// saving time transition. For example, -05:00 matches New York time only in
+    /// winter.
+    pub fn utc_offset(mut self, utc_offset: UtcOffset) -> Self {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// Sets the time of day in the splitter's UTC offset when the first interval of
+    /// each day begins, such as the session open. Defaults to midnight.
+    pub fn origin(mut self, origin: Time) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+impl<E, F> Splitter<E> for IntervalSplitter<E, F>
+where
+    F: Fn(OffsetDateTime, Option<Metadata>) -> crate::Result<E>,
+{
+    fn sub_encoder<R>(
+        &mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&mut E>>
+    where
+        R: Record,
+    {
+        use std::collections::hash_map::Entry;
+
+        let index_ts = record
+            .index_ts()
+            .ok_or_else(|| crate::Error::encode("record has undefined timestamp"))?;
+        let (start, end) = interval_bounds(self.interval, self.utc_offset, self.origin, index_ts);
+        let encoder = match self.encoders.entry(start) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let split_metadata = metadata
+                    .cloned()
+                    .map(|m| narrow_metadata_time_range(m, start, end));
+                entry.insert((self.build_encoder)(start, split_metadata)?)
+            }
+        };
+        Ok(Some(encoder))
+    }
+
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a,
+    {
+        self.encoders.values_mut()
+    }
+}
+
+impl<E, F> PublisherSplitter<E, F>
+where
+    F: Fn(u16, Option<Metadata>) -> crate::Result<E>,
+{
+    /// Creates a new splitter that will split the input stream by publisher ID,
+    /// creating a separate sub-encoder for each publisher using `build_encoder`.
+    pub fn new(build_encoder: F) -> Self {
+        Self {
+            build_encoder,
+            encoders: HashMap::new(),
+        }
+    }
+}
+
+impl<E, F> Splitter<E> for PublisherSplitter<E, F>
+where
+    F: Fn(u16, Option<Metadata>) -> crate::Result<E>,
+{
+    fn sub_encoder<R>(
+        &mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&mut E>>
+    where
+        R: Record,
+    {
+        use std::collections::hash_map::Entry;
+
+        let publisher_id = record.header().publisher_id;
+        let encoder = match self.encoders.entry(publisher_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert((self.build_encoder)(publisher_id, metadata.cloned())?)
+            }
+        };
+        Ok(Some(encoder))
+    }
+
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a,
+    {
+        self.encoders.values_mut()
+    }
+}
+
+impl<E, F> InstrumentSplitter<E, F>
+where
+    F: Fn(u32, Option<Metadata>) -> crate::Result<E>,
+{
+    /// Creates a new splitter that will split the input stream by instrument ID,
+    /// creating a separate sub-encoder for each instrument using `build_encoder`.
+    pub fn new(build_encoder: F) -> Self {
+        Self {
+            build_encoder,
+            encoders: HashMap::new(),
+        }
+    }
+}
+
+impl<E, F> Splitter<E> for InstrumentSplitter<E, F>
+where
+    F: Fn(u32, Option<Metadata>) -> crate::Result<E>,
+{
+    fn sub_encoder<R>(
+        &mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&mut E>>
+    where
+        R: Record,
+    {
+        use std::collections::hash_map::Entry;
+
+        let instrument_id = record.header().instrument_id;
+        let encoder = match self.encoders.entry(instrument_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let split_metadata = metadata
+                    .cloned()
+                    .map(|m| instrument_split_metadata(m, instrument_id));
+                entry.insert((self.build_encoder)(instrument_id, split_metadata)?)
+            }
+        };
+        Ok(Some(encoder))
+    }
+
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a,
+    {
+        self.encoders.values_mut()
+    }
+}
+
+/// Returns the start and end of the interval containing `ts`.
+fn interval_bounds(
+    interval: NonZeroU64,
+    utc_offset: UtcOffset,
+    origin: Time,
+    ts: OffsetDateTime,
+) -> (OffsetDateTime, OffsetDateTime) {
+    let ts = ts.to_offset(utc_offset);
+    let mut day_start = ts.date().with_time(origin).assume_offset(utc_offset);
+    if day_start > ts {
+        day_start -= time::Duration::DAY;
+    }
+    let day_end = day_start + time::Duration::DAY;
+    // Less than a day, so it can't overflow
+    let elapsed = (ts - day_start).whole_nanoseconds() as u64;
+    let start =
+        day_start + time::Duration::nanoseconds((elapsed - elapsed % interval.get()) as i64);
+    let end = i64::try_from(interval.get())
+        .ok()
+        .and_then(|interval| start.checked_add(time::Duration::nanoseconds(interval)))
+        .map_or(day_end, |end| end.min(day_end));
+    (start, end)
+}
+
+/// Narrows `metadata` to the split beginning on `encoder_date`.
+fn time_split_metadata(
+    split_duration: SplitDuration,
+    metadata: Metadata,
+    encoder_date: time::Date,
+) -> Metadata {
+    let start = encoder_date.with_time(Time::MIDNIGHT).assume_utc();
+    let end = match split_duration {
+        SplitDuration::Day => encoder_date.next_day().unwrap(),
+        SplitDuration::Week => encoder_date + time::Duration::days(7),
+        SplitDuration::Month => {
+            let end_year = if encoder_date.month() == time::Month::December {
+                encoder_date.year() + 1
+            } else {
+                encoder_date.year()
+            };
+            encoder_date
+                .replace_month(encoder_date.month().next())
+                .unwrap()
+                .replace_year(end_year)
+                .unwrap()
+        }
+    }
+    .with_time(Time::MIDNIGHT)
+    .assume_utc();
+    narrow_metadata_time_range(metadata, start, end)
+}
+
+/// Narrows `metadata` to the time range `[start, end)`.
+fn narrow_metadata_time_range(
+    mut metadata: Metadata,
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+) -> Metadata {
+    metadata.start = metadata.start().max(start).unix_timestamp_nanos() as u64;
+    metadata.end = NonZeroU64::new(
+        metadata
+            .end()
+            .map(|old_end| old_end.min(end))
+            .unwrap_or(end)
+            .unix_timestamp_nanos() as u64,
+    );
+    let start_date = metadata.start().date();
+    let end = metadata.end().unwrap();
+    let end_date = if end.time() == time::Time::MIDNIGHT {
+        end.date()
+    } else {
+        end.date().next_day().unwrap()
+    };
+    metadata.mappings.retain_mut(|mapping| {
+        mapping.intervals.retain_mut(|interval| {
+            interval.start_date = interval.start_date.max(start_date);
+            interval.end_date = interval.end_date.min(end_date);
+            interval.start_date < end_date && interval.end_date > start_date
+        });
+        !mapping.intervals.is_empty()
+    });
+    let symbols = metadata
+        .mappings
+        .iter()
+        .map(|m| &m.raw_symbol)
+        .collect::<HashSet<_>>();
+    metadata.symbols.retain(|s| symbols.contains(s));
+    metadata.partial.retain(|s| symbols.contains(s));
+
+    metadata
+}
+
+/// Narrows `metadata` to `symbol`.
+fn symbol_split_metadata(mut metadata: Metadata, symbol: &str) -> Metadata {
+    metadata.symbols.retain(|s| s == symbol);
+    metadata.partial.retain(|s| s == symbol);
+    metadata
+        .mappings
+        .retain(|sym_mapping| sym_mapping.raw_symbol == symbol);
+    metadata
+}
+
+/// Narrows the symbology of `metadata` to `instrument_id` when the metadata maps to
+/// instrument IDs.
+fn instrument_split_metadata(mut metadata: Metadata, instrument_id: u32) -> Metadata {
+    if metadata.stype_out != SType::InstrumentId {
+        return metadata;
+    }
+    let instrument_id = instrument_id.to_string();
+    metadata.mappings.retain_mut(|mapping| {
+        mapping
+            .intervals
+            .retain(|interval| interval.symbol == instrument_id);
+        !mapping.intervals.is_empty()
+    });
+    let symbols = metadata
+        .mappings
+        .iter()
+        .map(|m| &m.raw_symbol)
+        .collect::<HashSet<_>>();
+    metadata.symbols.retain(|s| symbols.contains(s));
+    metadata.partial.retain(|s| symbols.contains(s));
+    metadata
+}
+
+/// Sets the schema of `metadata` for the split of `schema`.
+fn schema_split_metadata(
+    mut metadata: Metadata,
+    schema: Schema,
+    no_schema_behavior: NoSchemaBehavior,
+) -> Metadata {
+    // Only set schema if not broadcasting (broadcast outputs contain mixed rtypes)
+    if no_schema_behavior != NoSchemaBehavior::Broadcast {
+        metadata.schema = Some(schema);
+    } else {
+        metadata.schema = None;
+    }
+    metadata
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
-    use time::macros::{date, datetime};
+    use time::macros::{date, datetime, offset};
 
     use super::*;
     use crate::{rtype, MboMsg, Mbp1Msg, RecordHeader, TradeMsg, TsSymbolMap, UNDEF_TIMESTAMP};
@@ -604,9 +895,6 @@ mod tests {
         }
     }
 
-    type TestTimeSplitter =
-        TimeSplitter<TestEncoder, fn(time::Date, Option<Metadata>) -> crate::Result<TestEncoder>>;
-
     #[test]
     fn test_time_splitter_by_day_single_day() {
         let build_encoder =
@@ -928,11 +1216,8 @@ mod tests {
             }])
             .build();
 
-        let split_meta = TestTimeSplitter::split_metadata(
-            SplitDuration::Day,
-            metadata.clone(),
-            date!(2023 - 07 - 05),
-        );
+        let split_meta =
+            time_split_metadata(SplitDuration::Day, metadata.clone(), date!(2023 - 07 - 05));
 
         assert_eq!(
             split_meta.start,
@@ -970,7 +1255,7 @@ mod tests {
             .build();
 
         // Test metadata splitting for July
-        let split_meta = TestTimeSplitter::split_metadata(
+        let split_meta = time_split_metadata(
             SplitDuration::Month,
             metadata.clone(),
             date!(2023 - 07 - 01),
@@ -1023,30 +1308,21 @@ mod tests {
             .build();
 
         // both AAPL and TSLA should be present
-        let split_meta = TestTimeSplitter::split_metadata(
-            SplitDuration::Day,
-            metadata.clone(),
-            date!(2023 - 07 - 12),
-        );
+        let split_meta =
+            time_split_metadata(SplitDuration::Day, metadata.clone(), date!(2023 - 07 - 12));
         assert_eq!(split_meta.mappings.len(), 2);
         assert_eq!(split_meta.symbols.len(), 2);
 
         // only AAPL should be present
-        let split_meta = TestTimeSplitter::split_metadata(
-            SplitDuration::Day,
-            metadata.clone(),
-            date!(2023 - 07 - 05),
-        );
+        let split_meta =
+            time_split_metadata(SplitDuration::Day, metadata.clone(), date!(2023 - 07 - 05));
         assert_eq!(split_meta.mappings.len(), 1);
         assert_eq!(split_meta.mappings[0].raw_symbol, "AAPL");
         assert_eq!(split_meta.symbols.len(), 1);
 
         // only TSLA should be present
-        let split_meta = TestTimeSplitter::split_metadata(
-            SplitDuration::Day,
-            metadata.clone(),
-            date!(2023 - 07 - 20),
-        );
+        let split_meta =
+            time_split_metadata(SplitDuration::Day, metadata.clone(), date!(2023 - 07 - 20));
         assert_eq!(split_meta.mappings.len(), 1);
         assert_eq!(split_meta.mappings[0].raw_symbol, "TSLA");
         assert_eq!(split_meta.symbols.len(), 1);
@@ -1061,4 +1337,192 @@ mod tests {
         let rec = mbo_msg(UNDEF_TIMESTAMP, 100);
         splitter.sub_encoder(None, &rec).unwrap_err();
     }
+
+    #[test]
+    fn test_interval_splitter_by_hour() {
+        let build_encoder =
+            |_start: OffsetDateTime, _metadata: Option<Metadata>| Ok(TestEncoder::default());
+        let mut splitter =
+            IntervalSplitter::new(build_encoder, NonZeroU64::new(3_600_000_000_000).unwrap());
+
+        for dt in [
+            datetime!(2023-07-15 10:00 UTC),
+            datetime!(2023-07-15 10:59:59 UTC),
+            datetime!(2023-07-15 11:00 UTC),
+        ] {
+            let rec = mbo_msg(dt.unix_timestamp_nanos() as u64, 100);
+            splitter
+                .sub_encoder(None, &rec)
+                .unwrap()
+                .unwrap()
+                .encode_record(&rec)
+                .unwrap();
+        }
+
+        assert_eq!(splitter.encoders.len(), 2);
+        assert_eq!(
+            splitter.encoders[&datetime!(2023-07-15 10:00 UTC)]
+                .records
+                .len(),
+            2
+        );
+        assert_eq!(
+            splitter.encoders[&datetime!(2023-07-15 11:00 UTC)]
+                .records
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_interval_bounds_session_open() {
+        let fifteen_minutes = NonZeroU64::new(15 * 60 * 1_000_000_000).unwrap();
+        let hour = NonZeroU64::new(60 * 60 * 1_000_000_000).unwrap();
+        let open = Time::from_hms(9, 30, 0).unwrap();
+
+        assert_eq!(
+            interval_bounds(
+                fifteen_minutes,
+                offset!(-5),
+                open,
+                datetime!(2023-07-17 14:40 UTC)
+            ),
+            (
+                datetime!(2023-07-17 09:30 -5),
+                datetime!(2023-07-17 09:45 -5)
+            )
+        );
+        // Before the open belongs to the previous day, whose last interval is shortened
+        assert_eq!(
+            interval_bounds(hour, offset!(-5), open, datetime!(2023-07-17 14:20 UTC)),
+            (
+                datetime!(2023-07-17 08:30 -5),
+                datetime!(2023-07-17 09:30 -5)
+            )
+        );
+        // Intervals longer than a day are shortened to a day
+        assert_eq!(
+            interval_bounds(
+                NonZeroU64::new(u64::MAX).unwrap(),
+                UtcOffset::UTC,
+                Time::MIDNIGHT,
+                datetime!(2023-07-17 14:20 UTC)
+            ),
+            (
+                datetime!(2023-07-17 00:00 UTC),
+                datetime!(2023-07-18 00:00 UTC)
+            )
+        );
+    }
+
+    #[test]
+    fn test_split_metadata_by_interval() {
+        use crate::{MappingInterval, MetadataBuilder, SymbolMapping};
+
+        let metadata = MetadataBuilder::new()
+            .dataset("TEST".to_owned())
+            .schema(Some(Schema::Mbo))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(datetime!(2023-07-01 00:00 UTC).unix_timestamp_nanos() as u64)
+            .end(NonZeroU64::new(
+                datetime!(2023-07-10 00:00 UTC).unix_timestamp_nanos() as u64,
+            ))
+            .symbols(vec!["AAPL".to_owned()])
+            .mappings(vec![SymbolMapping {
+                raw_symbol: "AAPL".to_owned(),
+                intervals: vec![MappingInterval {
+                    start_date: date!(2023 - 07 - 01),
+                    end_date: date!(2023 - 07 - 10),
+                    symbol: "100".to_owned(),
+                }],
+            }])
+            .build();
+
+        let split_meta = narrow_metadata_time_range(
+            metadata,
+            datetime!(2023-07-05 09:30 -5),
+            datetime!(2023-07-05 09:45 -5),
+        );
+
+        assert_eq!(
+            split_meta.start,
+            datetime!(2023-07-05 14:30 UTC).unix_timestamp_nanos() as u64
+        );
+        assert_eq!(
+            split_meta.end.unwrap().get(),
+            datetime!(2023-07-05 14:45 UTC).unix_timestamp_nanos() as u64
+        );
+        assert_eq!(
+            split_meta.mappings[0].intervals[0].start_date,
+            date!(2023 - 07 - 05)
+        );
+        assert_eq!(
+            split_meta.mappings[0].intervals[0].end_date,
+            date!(2023 - 07 - 06)
+        );
+    }
+
+    #[test]
+    fn test_publisher_splitter() {
+        let build_encoder =
+            |_publisher_id: u16, _metadata: Option<Metadata>| Ok(TestEncoder::default());
+        let mut splitter = PublisherSplitter::new(build_encoder);
+
+        let ts = datetime!(2023-07-15 10:00 UTC).unix_timestamp_nanos() as u64;
+        let mut rec = mbo_msg(ts, 100);
+        splitter.sub_encoder(None, &rec).unwrap();
+        rec.hd.publisher_id = 2;
+        splitter.sub_encoder(None, &rec).unwrap();
+        rec.hd.instrument_id = 101;
+        splitter.sub_encoder(None, &rec).unwrap();
+
+        assert_eq!(splitter.encoders.len(), 2);
+        assert!(splitter.encoders.contains_key(&1));
+        assert!(splitter.encoders.contains_key(&2));
+    }
+
+    #[test]
+    fn test_instrument_splitter() {
+        let build_encoder =
+            |_instrument_id: u32, _metadata: Option<Metadata>| Ok(TestEncoder::default());
+        let mut splitter = InstrumentSplitter::new(build_encoder);
+
+        let ts = datetime!(2023-07-15 10:00 UTC).unix_timestamp_nanos() as u64;
+        splitter.sub_encoder(None, &mbo_msg(ts, 100)).unwrap();
+        splitter.sub_encoder(None, &trade_msg(ts, 101)).unwrap();
+        splitter.sub_encoder(None, &mbp1_msg(ts, 100)).unwrap();
+
+        assert_eq!(splitter.encoders.len(), 2);
+        assert!(splitter.encoders.contains_key(&100));
+        assert!(splitter.encoders.contains_key(&101));
+    }
+
+    #[test]
+    fn test_split_metadata_by_instrument() {
+        use crate::{MappingInterval, MetadataBuilder, SymbolMapping};
+
+        let mapping = |raw_symbol: &str, symbol: &str| SymbolMapping {
+            raw_symbol: raw_symbol.to_owned(),
+            intervals: vec![MappingInterval {
+                start_date: date!(2023 - 07 - 01),
+                end_date: date!(2023 - 07 - 10),
+                symbol: symbol.to_owned(),
+            }],
+        };
+        let metadata = MetadataBuilder::new()
+            .dataset("TEST".to_owned())
+            .schema(Some(Schema::Mbo))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(datetime!(2023-07-01 00:00 UTC).unix_timestamp_nanos() as u64)
+            .symbols(vec!["AAPL".to_owned(), "TSLA".to_owned()])
+            .mappings(vec![mapping("AAPL", "100"), mapping("TSLA", "101")])
+            .build();
+
+        let split_meta = instrument_split_metadata(metadata, 101);
+
+        assert_eq!(split_meta.symbols, vec!["TSLA".to_owned()]);
+        assert_eq!(split_meta.mappings, vec![mapping("TSLA", "101")]);
+    }
 }
@@ -0,0 +1,797 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    num::NonZeroUsize,
+};
+
+use crate::{
+    encode::{AsyncEncodeRecord, AsyncEncodeRecordRef, AsyncEncodeRecordTextExt, DbnEncodable},
+    Metadata, RType, Record, RecordRef, Schema, SymbolIndex,
+};
+
+use super::{
+    schema_split_metadata, symbol_split_metadata, time_split_metadata, NoSchemaBehavior,
+    SplitDuration,
+};
+
+/// A strategy for routing records to different async sub-encoders.
+#[allow(async_fn_in_trait)] // the futures can't be Send because self is borrowed mutably
+pub trait Splitter<E> {
+    /// Returns the encoder for the given record, or `None` if the record should be ignored.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to create the sub encoder or to
+    /// close a sub-encoder to make room for it, or if the record belongs to a split
+    /// whose sub-encoder was closed to make room for another.
+    async fn sub_encoder<'a, R>(
+        &'a mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&'a mut E>>
+    where
+        R: Record,
+        E: 'a;
+
+    /// Returns an iterator over all open sub-encoders.
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a;
+
+    /// Shuts down and closes all open sub-encoders. A sub-encoder will be built again
+    /// if a later record belongs to its split.
+    ///
+    /// # Errors
+    /// This function returns the first error encountered while shutting down the
+    /// sub-encoders. Every sub-encoder is shut down regardless.
+    async fn close(&mut self) -> crate::Result<()>;
+}
+
+/// An async encoder that routes records to sub-encoders based on a [`Splitter`]
+/// strategy.
+///
+/// Calling [`shutdown()`](AsyncEncodeRecord::shutdown) shuts down every open
+/// sub-encoder.
+#[derive(Debug)]
+pub struct SplitEncoder<S, E> {
+    splitter: S,
+    metadata: Option<Metadata>,
+    _encoder: PhantomData<E>,
+}
+
+impl<S, E> SplitEncoder<S, E> {
+    /// Creates a new `SplitEncoder` without metadata.
+    ///
+    /// Use this when encoding records without associated metadata, such
+    /// as DBN fragments.
+    pub fn records_only(splitter: S) -> Self {
+        Self {
+            splitter,
+            metadata: None,
+            _encoder: PhantomData,
+        }
+    }
+
+    /// Creates a new `SplitEncoder` with metadata.
+    ///
+    /// The metadata will be passed to the splitter and used to create split-specific
+    /// metadata for each sub-encoder.
+    pub fn with_metadata(splitter: S, metadata: Metadata) -> Self {
+        Self {
+            splitter,
+            metadata: Some(metadata),
+            _encoder: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the splitter.
+    pub fn splitter(&self) -> &S {
+        &self.splitter
+    }
+
+    /// Returns a mutable reference to the splitter.
+    pub fn splitter_mut(&mut self) -> &mut S {
+        &mut self.splitter
+    }
+}
+
+impl<S, E> AsyncEncodeRecord for SplitEncoder<S, E>
+where
+    S: Splitter<E>,
+    E: AsyncEncodeRecord,
+{
+    async fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> crate::Result<()> {
+        if let Some(encoder) = self
+            .splitter
+            .sub_encoder(self.metadata.as_ref(), record)
+            .await?
+        {
+            encoder.encode_record(record).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> crate::Result<()> {
+        for encoder in self.splitter.sub_encoders() {
+            encoder.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> crate::Result<()> {
+        self.splitter.close().await
+    }
+}
+
+impl<S, E> AsyncEncodeRecordRef for SplitEncoder<S, E>
+where
+    S: Splitter<E>,
+    E: AsyncEncodeRecordRef,
+{
+    async fn encode_record_ref(&mut self, record_ref: RecordRef<'_>) -> crate::Result<()> {
+        if let Some(encoder) = self
+            .splitter
+            .sub_encoder(self.metadata.as_ref(), &record_ref)
+            .await?
+        {
+            encoder.encode_record_ref(record_ref).await?;
+        }
+        Ok(())
+    }
+
+    async unsafe fn encode_record_ref_ts_out(
+        &mut self,
+        record_ref: RecordRef<'_>,
+        ts_out: bool,
+    ) -> crate::Result<()> {
+        if let Some(encoder) = self
+            .splitter
+            .sub_encoder(self.metadata.as_ref(), &record_ref)
+            .await?
+        {
+            encoder.encode_record_ref_ts_out(record_ref, ts_out).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<S, E> AsyncEncodeRecordTextExt for SplitEncoder<S, E>
+where
+    S: Splitter<E>,
+    E: AsyncEncodeRecordTextExt,
+{
+    async fn encode_record_with_sym<R: DbnEncodable>(
+        &mut self,
+        record: &R,
+        symbol: Option<&str>,
+    ) -> crate::Result<()> {
+        if let Some(encoder) = self
+            .splitter
+            .sub_encoder(self.metadata.as_ref(), record)
+            .await?
+        {
+            encoder.encode_record_with_sym(record, symbol).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The open sub-encoders of a splitter. When a limit is set, the least recently used
+/// sub-encoder is shut down before opening one past the limit.
+#[derive(Debug)]
+struct OpenEncoders<K, E> {
+    /// Each encoder along with the value of `uses` when it was last used.
+    encoders: HashMap<K, (E, u64)>,
+    /// The keys of the encoders shut down to stay within `max_open`. Building another
+    /// encoder for one of these would write a second header to the same split.
+    evicted: HashSet<K>,
+    max_open: Option<NonZeroUsize>,
+    uses: u64,
+}
+
+impl<K, E> OpenEncoders<K, E> {
+    fn new() -> Self {
+        Self {
+            encoders: HashMap::new(),
+            evicted: HashSet::new(),
+            max_open: None,
+            uses: 0,
+        }
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut E> {
+        self.encoders.values_mut().map(|(encoder, _)| encoder)
+    }
+}
+
+impl<K, E> OpenEncoders<K, E>
+where
+    K: Clone + Debug + Eq + Hash,
+    E: AsyncEncodeRecord,
+{
+    async fn get_or_build<Fut>(
+        &mut self,
+        key: K,
+        build: impl FnOnce(K) -> Fut,
+    ) -> crate::Result<&mut E>
+    where
+        Fut: Future<Output = crate::Result<E>>,
+    {
+        self.uses += 1;
+        if !self.encoders.contains_key(&key) {
+            if self.evicted.contains(&key) {
+                return Err(crate::Error::encode(format!(
+                    "split {key:?} was already closed to stay within `max_open_encoders`"
+                )));
+            }
+            if self
+                .max_open
+                .is_some_and(|max_open| self.encoders.len() >= max_open.get())
+            {
+                self.close_least_recently_used().await?;
+            }
+            let encoder = build(key.clone()).await?;
+            self.encoders.insert(key.clone(), (encoder, self.uses));
+        }
+        let (encoder, last_use) = self.encoders.get_mut(&key).unwrap();
+        *last_use = self.uses;
+        Ok(encoder)
+    }
+
+    async fn close_least_recently_used(&mut self) -> crate::Result<()> {
+        let Some(key) = self
+            .encoders
+            .iter()
+            .min_by_key(|(_, (_, last_use))| *last_use)
+            .map(|(key, _)| key.clone())
+        else {
+            return Ok(());
+        };
+        let (mut encoder, _) = self.encoders.remove(&key).unwrap();
+        self.evicted.insert(key);
+        encoder.shutdown().await
+    }
+
+    async fn close_all(&mut self) -> crate::Result<()> {
+        let mut res = Ok(());
+        for (_, (mut encoder, _)) in self.encoders.drain() {
+            let shutdown_res = encoder.shutdown().await;
+            if res.is_ok() {
+                res = shutdown_res;
+            }
+        }
+        res
+    }
+}
+
+/// Splits a stream by time, building sub-encoders asynchronously.
+#[derive(Debug)]
+pub struct TimeSplitter<E, F> {
+    build_encoder: F,
+    split_duration: SplitDuration,
+    encoders: OpenEncoders<time::Date, E>,
+}
+
+/// Splits a stream by symbol, building sub-encoders asynchronously.
+///
+/// It's generic over [`SymbolIndex`], allowing it to work with both
+/// [`TsSymbolMap`](crate::TsSymbolMap) and [`PitSymbolMap`](crate::PitSymbolMap).
+#[derive(Debug)]
+pub struct SymbolSplitter<E, F, M> {
+    build_encoder: F,
+    encoders: OpenEncoders<String, E>,
+    symbol_map: M,
+}
+
+/// Splits a stream by schema, building sub-encoders asynchronously.
+#[derive(Debug)]
+pub struct SchemaSplitter<E, F> {
+    build_encoder: F,
+    encoders: OpenEncoders<Schema, E>,
+    no_schema_behavior: NoSchemaBehavior,
+}
+
+impl<E, F, Fut> TimeSplitter<E, F>
+where
+    F: Fn(time::Date, Option<Metadata>) -> Fut,
+    Fut: Future<Output = crate::Result<E>>,
+{
+    /// Creates a new splitter that will split the input stream according to
+    /// `split_duration`, creating a separate sub-encoder for each split using
+    /// `build_encoder`.
+    pub fn new(build_encoder: F, split_duration: SplitDuration) -> Self {
+        Self {
+            build_encoder,
+            split_duration,
+            encoders: OpenEncoders::new(),
+        }
+    }
+}
+
+impl<E, F> TimeSplitter<E, F> {
+    /// Sets the maximum number of sub-encoders to keep open at once. When a record
+    /// belongs to a new split and the limit has been reached, the least recently used
+    /// sub-encoder is shut down first.
+    ///
+    /// Because a sub-encoder writes its header when it's built, a split can't be
+    /// reopened once closed: encoding a later record that belongs to it returns an
+    /// error. The limit is therefore only suitable for input that's sorted by time.
+    pub fn max_open_encoders(mut self, max_open_encoders: NonZeroUsize) -> Self {
+        self.encoders.max_open = Some(max_open_encoders);
+        self
+    }
+}
+
+impl<E, F, Fut> Splitter<E> for TimeSplitter<E, F>
+where
+    F: Fn(time::Date, Option<Metadata>) -> Fut,
+    Fut: Future<Output = crate::Result<E>>,
+    E: AsyncEncodeRecord,
+{
+    async fn sub_encoder<'a, R>(
+        &'a mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&'a mut E>>
+    where
+        R: Record,
+        E: 'a,
+    {
+        let index_date = record
+            .index_date()
+            .ok_or_else(|| crate::Error::encode("record has undefined timestamp"))?;
+        let split_duration = self.split_duration;
+        let build_encoder = &self.build_encoder;
+        let encoder = self
+            .encoders
+            .get_or_build(split_duration.start_date(index_date), |encoder_date| {
+                let split_metadata = metadata
+                    .cloned()
+                    .map(|m| time_split_metadata(split_duration, m, encoder_date));
+                build_encoder(encoder_date, split_metadata)
+            })
+            .await?;
+        Ok(Some(encoder))
+    }
+
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a,
+    {
+        self.encoders.values_mut()
+    }
+
+    async fn close(&mut self) -> crate::Result<()> {
+        self.encoders.close_all().await
+    }
+}
+
+impl<E, F, Fut, M> SymbolSplitter<E, F, M>
+where
+    F: Fn(String, Option<Metadata>) -> Fut,
+    Fut: Future<Output = crate::Result<E>>,
+    M: SymbolIndex,
+{
+    /// Creates a new splitter that will split the input stream by symbol,
+    /// creating a separate sub-encoder for each symbol using `build_encoder`.
+    ///
+    /// The `symbol_map` is used to look up the symbol for each record based on
+    /// the instrument ID (and optionally the timestamp for `TsSymbolMap`).
+    pub fn new(build_encoder: F, symbol_map: M) -> Self {
+        Self {
+            build_encoder,
+            encoders: OpenEncoders::new(),
+            symbol_map,
+        }
+    }
+}
+
+impl<E, F, M> SymbolSplitter<E, F, M> {
+    /// Sets the maximum number of sub-encoders to keep open at once. When a record
+    /// belongs to a new symbol and the limit has been reached, the least recently used
+    /// sub-encoder is shut down first.
+    ///
+    /// Because a sub-encoder writes its header when it's built, a symbol's split can't
+    /// be reopened once closed: encoding a later record for that symbol returns an
+    /// error. The limit is therefore only suitable for input where each symbol's
+    /// records are contiguous.
+    pub fn max_open_encoders(mut self, max_open_encoders: NonZeroUsize) -> Self {
+        self.encoders.max_open = Some(max_open_encoders);
+        self
+    }
+}
+
+impl<E, F, Fut, M> Splitter<E> for SymbolSplitter<E, F, M>
+where
+    F: Fn(String, Option<Metadata>) -> Fut,
+    Fut: Future<Output = crate::Result<E>>,
+    M: SymbolIndex,
+    E: AsyncEncodeRecord,
+{
+    async fn sub_encoder<'a, R>(
+        &'a mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&'a mut E>>
+    where
+        R: Record,
+        E: 'a,
+    {
+        let index_ts = record.index_ts();
+        let symbol = self
+            .symbol_map
+            .get_for_rec(record)
+            .ok_or_else(|| {
+                crate::Error::encode(format!(
+                    "no symbol mapping for instrument_id {} at {index_ts:?}",
+                    record.header().instrument_id
+                ))
+            })?
+            .clone();
+        let build_encoder = &self.build_encoder;
+        let encoder = self
+            .encoders
+            .get_or_build(symbol, |symbol| {
+                let split_metadata = metadata.cloned().map(|m| symbol_split_metadata(m, &symbol));
+                build_encoder(symbol, split_metadata)
+            })
+            .await?;
+        Ok(Some(encoder))
+    }
+
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a,
+    {
+        self.encoders.values_mut()
+    }
+
+    async fn close(&mut self) -> crate::Result<()> {
+        self.encoders.close_all().await
+    }
+}
+
+impl<E, F, Fut> SchemaSplitter<E, F>
+where
+    F: Fn(Schema, Option<Metadata>) -> Fut,
+    Fut: Future<Output = crate::Result<E>>,
+{
+    /// Creates a new splitter that will split the input stream by schema,
+    /// creating a separate sub-encoder for each schema using `build_encoder`.
+    ///
+    /// The `no_schema_behavior` determines how records with rtypes that don't map
+    /// to a schema (such as [`ErrorMsg`](crate::ErrorMsg)) are handled.
+    pub fn new(build_encoder: F, no_schema_behavior: NoSchemaBehavior) -> Self {
+        Self {
+            build_encoder,
+            encoders: OpenEncoders::new(),
+            no_schema_behavior,
+        }
+    }
+}
+
+impl<E, F> SchemaSplitter<E, F> {
+    /// Sets the maximum number of sub-encoders to keep open at once. When a record
+    /// belongs to a new schema and the limit has been reached, the least recently used
+    /// sub-encoder is shut down first.
+    ///
+    /// Because a sub-encoder writes its header when it's built, a schema's split can't
+    /// be reopened once closed: encoding a later record of that schema returns an
+    /// error. The limit is therefore only suitable for input where each schema's
+    /// records are contiguous. Records broadcast with [`NoSchemaBehavior::Broadcast`]
+    /// are only sent to open sub-encoders.
+    pub fn max_open_encoders(mut self, max_open_encoders: NonZeroUsize) -> Self {
+        self.encoders.max_open = Some(max_open_encoders);
+        self
+    }
+}
+
+impl<E, F, Fut> Splitter<E> for SchemaSplitter<E, F>
+where
+    F: Fn(Schema, Option<Metadata>) -> Fut,
+    Fut: Future<Output = crate::Result<E>>,
+    E: AsyncEncodeRecord + AsyncEncodeRecordRef,
+{
+    async fn sub_encoder<'a, R>(
+        &'a mut self,
+        metadata: Option<&Metadata>,
+        record: &R,
+    ) -> crate::Result<Option<&'a mut E>>
+    where
+        R: Record,
+        E: 'a,
+    {
+        let Some(schema) = RType::try_into_schema(record.header().rtype) else {
+            return match self.no_schema_behavior {
+                NoSchemaBehavior::Skip => Ok(None),
+                NoSchemaBehavior::Error => Err(crate::Error::encode(format!(
+                    "rtype {} has no corresponding schema",
+                    record.header().rtype
+                ))),
+                NoSchemaBehavior::Broadcast => {
+                    let rec_ref =
+                    // SAFETY: `record` is a valid DBN record: it satisfies `R: Record`.
+                        unsafe { RecordRef::unchecked_from_header(record.header() as *const _) };
+                    for encoder in self.encoders.values_mut() {
+                        // Have to use `encode_record_ref` here because `SplitEncoder` supports
+                        // both `AsyncEncodeRecord` and `AsyncEncodeRecordRef`
+                        encoder.encode_record_ref(rec_ref).await?;
+                    }
+                    Ok(None)
+                }
+            };
+        };
+        let no_schema_behavior = self.no_schema_behavior;
+        let build_encoder = &self.build_encoder;
+        let encoder = self
+            .encoders
+            .get_or_build(schema, |schema| {
+                let split_metadata = metadata
+                    .cloned()
+                    .map(|m| schema_split_metadata(m, schema, no_schema_behavior));
+                build_encoder(schema, split_metadata)
+            })
+            .await?;
+        Ok(Some(encoder))
+    }
+
+    fn sub_encoders<'a>(&'a mut self) -> impl Iterator<Item = &'a mut E>
+    where
+        E: 'a,
+    {
+        self.encoders.values_mut()
+    }
+
+    async fn close(&mut self) -> crate::Result<()> {
+        self.encoders.close_all().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use time::macros::{date, datetime};
+
+    use super::*;
+    use crate::{
+        rtype, ErrorMsg, MboMsg, MetadataBuilder, RecordHeader, SType, TradeMsg, TsSymbolMap,
+    };
+
+    /// Sub-encoders that have been shut down, along with the timestamps of the
+    /// records they encoded.
+    type Closed = Arc<Mutex<Vec<(String, Vec<u64>)>>>;
+
+    struct TestEncoder {
+        key: String,
+        records: Vec<u64>,
+        closed: Closed,
+    }
+
+    impl TestEncoder {
+        fn new(key: impl ToString, closed: &Closed) -> Self {
+            Self {
+                key: key.to_string(),
+                records: Vec::new(),
+                closed: closed.clone(),
+            }
+        }
+    }
+
+    impl AsyncEncodeRecord for TestEncoder {
+        async fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> crate::Result<()> {
+            self.records.push(record.header().ts_event);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn shutdown(&mut self) -> crate::Result<()> {
+            self.closed
+                .lock()
+                .unwrap()
+                .push((self.key.clone(), std::mem::take(&mut self.records)));
+            Ok(())
+        }
+    }
+
+    impl AsyncEncodeRecordRef for TestEncoder {
+        async fn encode_record_ref(&mut self, record_ref: RecordRef<'_>) -> crate::Result<()> {
+            self.records.push(record_ref.header().ts_event);
+            Ok(())
+        }
+
+        async unsafe fn encode_record_ref_ts_out(
+            &mut self,
+            record_ref: RecordRef<'_>,
+            _ts_out: bool,
+        ) -> crate::Result<()> {
+            self.encode_record_ref(record_ref).await
+        }
+    }
+
+    fn sorted_closed(closed: &Closed) -> Vec<(String, Vec<u64>)> {
+        let mut res = closed.lock().unwrap().clone();
+        res.sort();
+        res
+    }
+
+    fn trade_msg(ts: u64, instrument_id: u32) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, ts),
+            ts_recv: ts,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_time_splitter_shutdown_closes_all() {
+        let closed = Closed::default();
+        let build_encoder = |date: time::Date, metadata: Option<Metadata>| {
+            let closed = closed.clone();
+            async move {
+                let metadata = metadata.unwrap();
+                assert_eq!(metadata.start().date(), date);
+                Ok(TestEncoder::new(date, &closed))
+            }
+        };
+        let metadata = MetadataBuilder::new()
+            .dataset("TEST".to_owned())
+            .schema(Some(Schema::Trades))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(datetime!(2023-07-15 00:00 UTC).unix_timestamp_nanos() as u64)
+            .build();
+        let mut encoder: SplitEncoder<_, TestEncoder> = SplitEncoder::with_metadata(
+            TimeSplitter::new(build_encoder, SplitDuration::Day),
+            metadata,
+        );
+        let ts1 = datetime!(2023-07-15 10:00 UTC).unix_timestamp_nanos() as u64;
+        let ts2 = datetime!(2023-07-16 10:00 UTC).unix_timestamp_nanos() as u64;
+        let ts3 = datetime!(2023-07-16 14:00 UTC).unix_timestamp_nanos() as u64;
+        for ts in [ts1, ts2, ts3] {
+            encoder.encode_record(&trade_msg(ts, 1)).await.unwrap();
+        }
+        assert!(closed.lock().unwrap().is_empty());
+        encoder.flush().await.unwrap();
+        encoder.shutdown().await.unwrap();
+        assert_eq!(
+            sorted_closed(&closed),
+            vec![
+                (date!(2023 - 07 - 15).to_string(), vec![ts1]),
+                (date!(2023 - 07 - 16).to_string(), vec![ts2, ts3]),
+            ]
+        );
+        assert_eq!(encoder.splitter_mut().sub_encoders().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_splitter_max_open_encoders() {
+        let mut symbol_map = TsSymbolMap::new();
+        for (instrument_id, symbol) in [(1, "A"), (2, "B"), (3, "C")] {
+            symbol_map
+                .insert(
+                    instrument_id,
+                    date!(2023 - 07 - 01),
+                    date!(2023 - 08 - 01),
+                    Arc::new(symbol.to_owned()),
+                )
+                .unwrap();
+        }
+        let closed = Closed::default();
+        let build_encoder = |symbol: String, _metadata: Option<Metadata>| {
+            let closed = closed.clone();
+            async move { Ok(TestEncoder::new(symbol, &closed)) }
+        };
+        let splitter = SymbolSplitter::new(build_encoder, symbol_map)
+            .max_open_encoders(NonZeroUsize::new(2).unwrap());
+        let mut encoder: SplitEncoder<_, TestEncoder> = SplitEncoder::records_only(splitter);
+        let ts = datetime!(2023-07-15 10:00 UTC).unix_timestamp_nanos() as u64;
+        // A, B, A, C: B is the least recently used when C is opened
+        for (i, instrument_id) in [1, 2, 1, 3].into_iter().enumerate() {
+            encoder
+                .encode_record(&trade_msg(ts + i as u64, instrument_id))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            *closed.lock().unwrap(),
+            vec![("B".to_owned(), vec![ts + 1])]
+        );
+        assert_eq!(encoder.splitter_mut().sub_encoders().count(), 2);
+        encoder.shutdown().await.unwrap();
+        assert_eq!(
+            sorted_closed(&closed),
+            vec![
+                ("A".to_owned(), vec![ts, ts + 2]),
+                ("B".to_owned(), vec![ts + 1]),
+                ("C".to_owned(), vec![ts + 3]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_time_splitter_revisit_closed_split_returns_error() {
+        let built = Arc::new(Mutex::new(Vec::new()));
+        let closed = Closed::default();
+        let build_encoder = |date: time::Date, _metadata: Option<Metadata>| {
+            let closed = closed.clone();
+            built.lock().unwrap().push(date);
+            async move { Ok(TestEncoder::new(date, &closed)) }
+        };
+        let splitter = TimeSplitter::new(build_encoder, SplitDuration::Day)
+            .max_open_encoders(NonZeroUsize::new(1).unwrap());
+        let mut encoder: SplitEncoder<_, TestEncoder> = SplitEncoder::records_only(splitter);
+        let ts1 = datetime!(2023-07-15 10:00 UTC).unix_timestamp_nanos() as u64;
+        let ts2 = datetime!(2023-07-16 10:00 UTC).unix_timestamp_nanos() as u64;
+        encoder.encode_record(&trade_msg(ts1, 1)).await.unwrap();
+        encoder.encode_record(&trade_msg(ts2, 1)).await.unwrap();
+        // 2023-07-15 was closed when 2023-07-16 was opened
+        let res = encoder.encode_record(&trade_msg(ts1 + 1, 1)).await;
+        assert!(
+            matches!(&res, Err(crate::Error::Encode(msg)) if msg.contains("2023-07-15")),
+            "{res:?}"
+        );
+        assert_eq!(
+            *built.lock().unwrap(),
+            vec![date!(2023 - 07 - 15), date!(2023 - 07 - 16)]
+        );
+        // The open split is unaffected
+        encoder.encode_record(&trade_msg(ts2 + 1, 1)).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        assert_eq!(
+            sorted_closed(&closed),
+            vec![
+                (date!(2023 - 07 - 15).to_string(), vec![ts1]),
+                (date!(2023 - 07 - 16).to_string(), vec![ts2, ts2 + 1]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_splitter_broadcast() {
+        let closed = Closed::default();
+        let build_encoder = |schema: Schema, _metadata: Option<Metadata>| {
+            let closed = closed.clone();
+            async move { Ok(TestEncoder::new(schema, &closed)) }
+        };
+        let mut encoder: SplitEncoder<_, TestEncoder> = SplitEncoder::records_only(
+            SchemaSplitter::new(build_encoder, NoSchemaBehavior::Broadcast),
+        );
+        let mbo = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 1, 1),
+            ..Default::default()
+        };
+        let error = ErrorMsg::new(3, None, "test", true);
+        encoder.encode_record(&mbo).await.unwrap();
+        encoder.encode_record(&trade_msg(2, 1)).await.unwrap();
+        encoder.encode_record(&error).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        assert_eq!(
+            sorted_closed(&closed),
+            vec![
+                (Schema::Mbo.to_string(), vec![1, 3]),
+                (Schema::Trades.to_string(), vec![2, 3]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undef_timestamp_returns_error() {
+        let closed = Closed::default();
+        let build_encoder = |date: time::Date, _metadata: Option<Metadata>| {
+            let closed = closed.clone();
+            async move { Ok(TestEncoder::new(date, &closed)) }
+        };
+        let mut encoder: SplitEncoder<_, TestEncoder> =
+            SplitEncoder::records_only(TimeSplitter::new(build_encoder, SplitDuration::Day));
+        let res = encoder
+            .encode_record(&trade_msg(crate::UNDEF_TIMESTAMP, 1))
+            .await;
+        assert!(res.is_err());
+    }
+}
@@ -853,6 +853,9 @@ pub enum Encoding {
     /// JavaScript object notation.
     #[pyo3(name = "JSON")]
     Json = 2,
+    /// Apache Parquet columnar format.
+    #[pyo3(name = "PARQUET")]
+    Parquet = 3,
 }
 
 impl std::str::FromStr for Encoding {
@@ -863,6 +866,7 @@ impl std::str::FromStr for Encoding {
             "dbn" | "dbz" => Ok(Self::Dbn),
             "csv" => Ok(Self::Csv),
             "json" => Ok(Self::Json),
+            "parquet" => Ok(Self::Parquet),
             _ => Err(crate::Error::conversion::<Self>(s.to_owned())),
         }
     }
@@ -881,6 +885,7 @@ impl Encoding {
             Self::Dbn => "dbn",
             Self::Csv => "csv",
             Self::Json => "json",
+            Self::Parquet => "parquet",
         }
     }
 }
@@ -1371,6 +1376,7 @@ impl From<TriState> for char {
     pyo3::pyclass(module = "databento_dbn")
 )]
 #[cfg_attr(not(feature = "python"), derive(MockPyo3))]
+#[repr(u8)]
 pub enum VersionUpgradePolicy {
     /// Decode data from all supported versions (less than or equal to
     /// [`DBN_VERSION`](crate::DBN_VERSION)) as-is.
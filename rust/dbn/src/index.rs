@@ -0,0 +1,744 @@
+//! Timestamp indexes for random access into large DBN files.
+//!
+//! An [`Index`] maps index timestamps to byte offsets in the uncompressed DBN
+//! stream, along with the offsets of the Zstandard frames in compressed files. It's
+//! stored in a small sidecar file next to the DBN file it describes, by convention at
+//! [`Index::sidecar_path()`].
+//!
+//! Indexes can be built from an existing file with [`Index::build_from_file()`] or
+//! while encoding with an [`IndexedEncoder`]. Once built, pass the index to
+//! [`DbnDecoder::seek_with_index()`] to begin decoding at a timestamp without
+//! decoding all the records that precede it.
+//!
+//! Indexes assume the records are sorted by their index timestamp, which is the case
+//! for all DBN files from Databento.
+//!
+//! # Example
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use dbn::{
+//!     decode::{DbnDecoder, DecodeRecordRef},
+//!     index::Index,
+//!     VersionUpgradePolicy,
+//! };
+//!
+//! let path = "20241007.mbo.dbn.zst";
+//! let index = Index::build_from_file(path, Index::DEFAULT_INTERVAL)?;
+//! index.to_file(Index::sidecar_path(path))?;
+//!
+//! let mut decoder = DbnDecoder::seek_with_index(
+//!     File::open(path).unwrap(),
+//!     &index,
+//!     1_728_307_800_000_000_000,
+//!     VersionUpgradePolicy::UpgradeToV3,
+//! )?;
+//! let first = decoder.decode_record_ref()?;
+//! # Ok::<(), dbn::Error>(())
+//! ```
+//!
+//! [`DbnDecoder::seek_with_index()`]: crate::decode::DbnDecoder::seek_with_index
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    decode::{
+        zstd::{starts_with_prefix, FrameReader},
+        DbnDecoder, DecodeRecordRef,
+    },
+    encode::{
+        dbn::{Encoder, MetadataEncoder},
+        DbnEncodable, EncodeDbn, EncodeRecord, EncodeRecordRef,
+    },
+    Compression, Error, Metadata, Record, RecordRef, Result, VersionUpgradePolicy, UNDEF_TIMESTAMP,
+};
+
+const MAGIC: &[u8; 4] = b"DBNI";
+const INDEX_VERSION: u8 = 1;
+/// magic, version, compression, 2 reserved bytes, interval, record_start, file_len,
+/// entry count, and frame count.
+const HEADER_LEN: usize = 4 + 1 + 1 + 2 + 4 + 8 * 4;
+
+/// A timestamp index of a DBN file. See the [module-level documentation](self) for
+/// more information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Index {
+    interval: u32,
+    compression: Compression,
+    record_start: u64,
+    file_len: u64,
+    entries: Vec<IndexEntry>,
+    frames: Vec<FrameEntry>,
+}
+
+/// The location of a record in the uncompressed DBN stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The index timestamp of the record in UNIX nanoseconds.
+    pub ts: u64,
+    /// The offset of the record in bytes from the start of the uncompressed DBN
+    /// stream, including the metadata.
+    pub offset: u64,
+}
+
+/// The start of a Zstandard frame in a compressed DBN file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameEntry {
+    /// The offset of the frame in bytes from the start of the compressed file.
+    pub compressed_offset: u64,
+    /// The offset in bytes of the first byte the frame decompresses to, relative to the
+    /// start of the uncompressed DBN stream.
+    pub offset: u64,
+}
+
+impl Index {
+    /// The default number of records between index entries.
+    pub const DEFAULT_INTERVAL: u32 = 1024;
+
+    /// Builds an index of the DBN stream in `reader` with an entry at most every
+    /// `interval` records. `compression` must match the compression of `reader`.
+    ///
+    /// # Errors
+    /// This function will return an error if `interval` is 0, or it fails to read or
+    /// decode the DBN stream in `reader`.
+    pub fn build(reader: impl Read, compression: Compression, interval: u32) -> Result<Self> {
+        if interval == 0 {
+            return Err(Error::BadArgument {
+                param_name: "interval".to_owned(),
+                desc: "must be greater than 0".to_owned(),
+            });
+        }
+        let mut tracker = FrameTracker::new(BufReader::new(reader), compression)?;
+        // Read the prelude directly to learn the exact length of the metadata, which
+        // may include padding
+        let mut prelude = [0; 8];
+        tracker
+            .read_exact(&mut prelude)
+            .map_err(|e| Error::io(e, "reading DBN prelude"))?;
+        let record_start = 8 + u32::from_le_bytes(prelude[4..].try_into().unwrap()) as u64;
+        let mut decoder = DbnDecoder::with_upgrade_policy(
+            io::Cursor::new(prelude).chain(tracker),
+            VersionUpgradePolicy::AsIs,
+        )?;
+        let mut builder = EntryBuilder::new(interval, record_start);
+        while let Some(rec) = decoder.decode_record_ref()? {
+            builder.push(&rec);
+        }
+        let (file_len, frames) = decoder.into_inner().into_inner().1.finish();
+        Ok(Self {
+            interval,
+            compression,
+            record_start,
+            file_len,
+            entries: builder.entries,
+            frames,
+        })
+    }
+
+    /// Builds an index of the DBN file at `path`, inferring its compression, with an
+    /// entry at most every `interval` records.
+    ///
+    /// # Errors
+    /// This function will return an error if `interval` is 0, or it fails to open,
+    /// read, or decode the file at `path`.
+    pub fn build_from_file(path: impl AsRef<Path>, interval: u32) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader =
+            BufReader::new(File::open(path).map_err(|e| {
+                Error::io(e, format!("opening DBN file at path '{}'", path.display()))
+            })?);
+        let compression = if starts_with_prefix(
+            reader
+                .fill_buf()
+                .map_err(|e| Error::io(e, "reading DBN file"))?,
+        ) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        };
+        Self::build(reader, compression, interval)
+    }
+
+    /// Reads an index previously written with [`Index::write()`] from `reader`.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to read from `reader` or the
+    /// data isn't a valid index.
+    pub fn read(mut reader: impl Read) -> Result<Self> {
+        let io_err = |e| Error::io(e, "reading DBN index");
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header).map_err(io_err)?;
+        if &header[..4] != MAGIC {
+            return Err(Error::decode("invalid DBN index: missing magic"));
+        }
+        if header[4] != INDEX_VERSION {
+            return Err(Error::decode(format!(
+                "unsupported DBN index version {}",
+                header[4]
+            )));
+        }
+        let compression = match header[5] {
+            0 => Compression::None,
+            1 => Compression::Zstd,
+            other => {
+                return Err(Error::decode(format!(
+                    "invalid DBN index: unknown compression {other}"
+                )))
+            }
+        };
+        let interval = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let u64_at = |pos: usize| u64::from_le_bytes(header[pos..pos + 8].try_into().unwrap());
+        let record_start = u64_at(12);
+        let file_len = u64_at(20);
+        let entry_count = u64_at(28);
+        let frame_count = u64_at(36);
+        let mut read_pair = || -> Result<(u64, u64)> {
+            let mut buf = [0; 16];
+            reader.read_exact(&mut buf).map_err(io_err)?;
+            Ok((
+                u64::from_le_bytes(buf[..8].try_into().unwrap()),
+                u64::from_le_bytes(buf[8..].try_into().unwrap()),
+            ))
+        };
+        let entries = (0..entry_count)
+            .map(|_| read_pair().map(|(ts, offset)| IndexEntry { ts, offset }))
+            .collect::<Result<Vec<_>>>()?;
+        let frames = (0..frame_count)
+            .map(|_| {
+                read_pair().map(|(compressed_offset, offset)| FrameEntry {
+                    compressed_offset,
+                    offset,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            interval,
+            compression,
+            record_start,
+            file_len,
+            entries,
+            frames,
+        })
+    }
+
+    /// Reads an index from the file at `path`.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to open or read the file at
+    /// `path` or it doesn't contain a valid index.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| Error::io(e, format!("opening DBN index at path '{}'", path.display())))?;
+        Self::read(BufReader::new(file))
+    }
+
+    /// Writes the index to `writer`.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to write to `writer`.
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        let io_err = |e| Error::io(e, "writing DBN index");
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&[INDEX_VERSION, self.compression as u8, 0, 0]);
+        header.extend_from_slice(&self.interval.to_le_bytes());
+        for val in [
+            self.record_start,
+            self.file_len,
+            self.entries.len() as u64,
+            self.frames.len() as u64,
+        ] {
+            header.extend_from_slice(&val.to_le_bytes());
+        }
+        writer.write_all(&header).map_err(io_err)?;
+        for entry in self.entries.iter() {
+            writer.write_all(&entry.ts.to_le_bytes()).map_err(io_err)?;
+            writer
+                .write_all(&entry.offset.to_le_bytes())
+                .map_err(io_err)?;
+        }
+        for frame in self.frames.iter() {
+            writer
+                .write_all(&frame.compressed_offset.to_le_bytes())
+                .map_err(io_err)?;
+            writer
+                .write_all(&frame.offset.to_le_bytes())
+                .map_err(io_err)?;
+        }
+        writer.flush().map_err(io_err)
+    }
+
+    /// Writes the index to a new file at `path`, overwriting any existing file.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to create or write to the file
+    /// at `path`.
+    pub fn to_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| {
+            Error::io(
+                e,
+                format!("creating DBN index at path '{}'", path.display()),
+            )
+        })?;
+        self.write(BufWriter::new(file))
+    }
+
+    /// Returns the conventional path of the index sidecar for the DBN file at `path`,
+    /// which is `path` with `.idx` appended.
+    pub fn sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+        let mut sidecar = path.as_ref().as_os_str().to_owned();
+        sidecar.push(".idx");
+        PathBuf::from(sidecar)
+    }
+
+    /// Returns the maximum number of records between index entries.
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    /// Returns the compression of the indexed file.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Returns the offset of the first record in the uncompressed DBN stream, i.e.
+    /// the length of the metadata.
+    pub fn record_start(&self) -> u64 {
+        self.record_start
+    }
+
+    /// Returns the length in bytes of the indexed file as stored, i.e. after any
+    /// compression.
+    pub fn file_len(&self) -> u64 {
+        self.file_len
+    }
+
+    /// Returns the index entries, sorted by timestamp.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the Zstandard frames of the indexed file. Empty for uncompressed files.
+    pub fn frames(&self) -> &[FrameEntry] {
+        &self.frames
+    }
+
+    /// Returns the offset in the uncompressed DBN stream from which to begin scanning
+    /// for the first record with an index timestamp greater than or equal to `ts`.
+    /// This is the last entry before `ts`, or the first record if there's none.
+    pub fn scan_start(&self, ts: u64) -> u64 {
+        let pos = self.entries.partition_point(|entry| entry.ts < ts);
+        pos.checked_sub(1)
+            .map_or(self.record_start, |i| self.entries[i].offset)
+    }
+
+    /// Returns the frame containing `offset` in the uncompressed DBN stream.
+    pub fn frame_for(&self, offset: u64) -> Option<&FrameEntry> {
+        let pos = self.frames.partition_point(|frame| frame.offset <= offset);
+        pos.checked_sub(1).map(|i| &self.frames[i])
+    }
+}
+
+/// Type for encoding uncompressed DBN while building an [`Index`] of the output.
+pub struct IndexedEncoder<W>
+where
+    W: io::Write,
+{
+    encoder: Encoder<W>,
+    builder: EntryBuilder,
+}
+
+impl<W> IndexedEncoder<W>
+where
+    W: io::Write,
+{
+    /// Creates a new [`IndexedEncoder`] that will write uncompressed DBN to `writer`
+    /// with an index entry at most every `interval` records.
+    ///
+    /// # Errors
+    /// This function will return an error if `interval` is 0 or it fails to encode
+    /// `metadata` to `writer`.
+    pub fn new(writer: W, metadata: &Metadata, interval: u32) -> Result<Self> {
+        if interval == 0 {
+            return Err(Error::BadArgument {
+                param_name: "interval".to_owned(),
+                desc: "must be greater than 0".to_owned(),
+            });
+        }
+        let (length, _) = MetadataEncoder::<W>::calc_length(metadata);
+        Ok(Self {
+            encoder: Encoder::new(writer, metadata)?,
+            builder: EntryBuilder::new(interval, 8 + length as u64),
+        })
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.encoder.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.encoder.get_mut()
+    }
+
+    /// Flushes the encoder and returns the index of everything encoded.
+    ///
+    /// # Errors
+    /// This function will return an error if it fails to flush the underlying writer.
+    pub fn finish(mut self) -> Result<Index> {
+        self.encoder.flush()?;
+        Ok(Index {
+            interval: self.builder.interval,
+            compression: Compression::None,
+            record_start: self.builder.record_start,
+            file_len: self.builder.offset,
+            entries: self.builder.entries,
+            frames: Vec::new(),
+        })
+    }
+}
+
+impl<W> EncodeRecord for IndexedEncoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record<R: DbnEncodable>(&mut self, record: &R) -> Result<()> {
+        self.encoder.encode_record(record)?;
+        self.builder.push(record);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.encoder.flush()
+    }
+}
+
+impl<W> EncodeRecordRef for IndexedEncoder<W>
+where
+    W: io::Write,
+{
+    fn encode_record_ref(&mut self, record: RecordRef) -> Result<()> {
+        self.encoder.encode_record_ref(record)?;
+        self.builder.push(&record);
+        Ok(())
+    }
+
+    unsafe fn encode_record_ref_ts_out(&mut self, record: RecordRef, ts_out: bool) -> Result<()> {
+        self.encoder.encode_record_ref_ts_out(record, ts_out)?;
+        self.builder.push(&record);
+        Ok(())
+    }
+}
+
+impl<W> EncodeDbn for IndexedEncoder<W> where W: io::Write {}
+
+struct EntryBuilder {
+    interval: u32,
+    record_start: u64,
+    offset: u64,
+    since_entry: u32,
+    entries: Vec<IndexEntry>,
+}
+
+impl EntryBuilder {
+    fn new(interval: u32, record_start: u64) -> Self {
+        Self {
+            interval,
+            record_start,
+            offset: record_start,
+            // so the first record gets an entry
+            since_entry: interval,
+            entries: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, rec: &impl Record) {
+        let ts = rec.raw_index_ts();
+        if self.since_entry >= self.interval
+            && ts != UNDEF_TIMESTAMP
+            // skip out-of-order records to keep entries sorted
+            && self.entries.last().is_none_or(|last| last.ts <= ts)
+        {
+            self.entries.push(IndexEntry {
+                ts,
+                offset: self.offset,
+            });
+            self.since_entry = 0;
+        }
+        self.since_entry = self.since_entry.saturating_add(1);
+        self.offset += rec.record_size() as u64;
+    }
+}
+
+/// Reader that records the boundaries of Zstandard frames while decompressing and
+/// counts the bytes read from the input.
+enum FrameTracker<R> {
+    Uncompressed { reader: R, pos: u64 },
+    Zstd(FrameReader<R>),
+}
+
+impl<R> FrameTracker<R>
+where
+    R: BufRead,
+{
+    fn new(reader: R, compression: Compression) -> Result<Self> {
+        Ok(match compression {
+            Compression::None => Self::Uncompressed { reader, pos: 0 },
+            Compression::Zstd => Self::Zstd(FrameReader::new(reader, None)?.track_frames()),
+        })
+    }
+
+    /// Returns the number of bytes read from the input and the frames.
+    fn finish(self) -> (u64, Vec<FrameEntry>) {
+        match self {
+            Self::Uncompressed { pos, .. } => (pos, Vec::new()),
+            Self::Zstd(reader) => (reader.compressed_pos(), reader.into_frames()),
+        }
+    }
+}
+
+impl<R> Read for FrameTracker<R>
+where
+    R: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Uncompressed { reader, pos } => {
+                let read = reader.read(buf)?;
+                *pos += read as u64;
+                Ok(read)
+            }
+            Self::Zstd(reader) => reader.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use std::io::Cursor;
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnMetadata, DynReader},
+        encode::dbn::RecordEncoder,
+        rtype, MboMsg, MetadataBuilder, RecordHeader, SType, Schema,
+    };
+
+    const RECORD_COUNT: u64 = 100;
+
+    fn mbo(i: u64) -> MboMsg {
+        MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 5482, 1_000 + i * 10),
+            // ts_recv is the index timestamp
+            ts_recv: 1_000 + i * 10,
+            order_id: i,
+            ..Default::default()
+        }
+    }
+
+    fn metadata() -> Metadata {
+        MetadataBuilder::new()
+            .dataset("GLBX.MDP3".to_owned())
+            .schema(Some(Schema::Mbo))
+            .start(1_000)
+            .stype_in(Some(SType::InstrumentId))
+            .stype_out(SType::InstrumentId)
+            .build()
+    }
+
+    fn encode(compression: Compression) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut encoder = Encoder::new(&mut buf, &metadata()).unwrap();
+        for i in 0..RECORD_COUNT {
+            encoder.encode_record(&mbo(i)).unwrap();
+        }
+        match compression {
+            Compression::None => buf,
+            Compression::Zstd => zstd::encode_all(buf.as_slice(), 0).unwrap(),
+        }
+    }
+
+    /// Compresses the metadata and each group of `frame_size` records into separate
+    /// frames.
+    fn encode_multi_frame(frame_size: u64) -> Vec<u8> {
+        let mut metadata_buf = Vec::new();
+        MetadataEncoder::new(&mut metadata_buf)
+            .encode(&metadata())
+            .unwrap();
+        let mut res = zstd::encode_all(metadata_buf.as_slice(), 0).unwrap();
+        for chunk_start in (0..RECORD_COUNT).step_by(frame_size as usize) {
+            let mut record_buf = Vec::new();
+            let mut encoder = RecordEncoder::new(&mut record_buf);
+            for i in chunk_start..(chunk_start + frame_size).min(RECORD_COUNT) {
+                encoder.encode_record(&mbo(i)).unwrap();
+            }
+            res.extend(zstd::encode_all(record_buf.as_slice(), 0).unwrap());
+        }
+        res
+    }
+
+    fn seek(data: &[u8], index: &Index, ts: u64) -> Vec<MboMsg> {
+        let mut decoder =
+            DbnDecoder::seek_with_index(Cursor::new(data), index, ts, VersionUpgradePolicy::AsIs)
+                .unwrap();
+        assert_eq!(*decoder.metadata(), metadata());
+        let mut res = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            res.push(rec.get::<MboMsg>().unwrap().clone());
+        }
+        res
+    }
+
+    #[rstest]
+    fn test_build_matches_encoder(
+        #[values(Compression::None, Compression::Zstd)] compression: Compression,
+    ) {
+        let mut buf = Vec::new();
+        let mut encoder = IndexedEncoder::new(&mut buf, &metadata(), 10).unwrap();
+        for i in 0..RECORD_COUNT {
+            encoder.encode_record(&mbo(i)).unwrap();
+        }
+        let encoder_index = encoder.finish().unwrap();
+        assert_eq!(encoder_index.entries().len(), 10);
+        assert_eq!(encoder_index.file_len(), buf.len() as u64);
+
+        let data = encode(compression);
+        let index = Index::build(data.as_slice(), compression, 10).unwrap();
+        assert_eq!(index.entries(), encoder_index.entries());
+        assert_eq!(index.record_start(), encoder_index.record_start());
+        assert_eq!(index.file_len(), data.len() as u64);
+        if compression == Compression::Zstd {
+            assert_eq!(
+                index.frames(),
+                [FrameEntry {
+                    compressed_offset: 0,
+                    offset: 0
+                }]
+            );
+        } else {
+            assert!(index.frames().is_empty());
+        }
+    }
+
+    #[rstest]
+    fn test_build_multi_frame() {
+        let data = encode_multi_frame(25);
+        let index = Index::build(data.as_slice(), Compression::Zstd, 10).unwrap();
+        // metadata frame plus 4 record frames
+        assert_eq!(index.frames().len(), 5);
+        let record_size = std::mem::size_of::<MboMsg>() as u64;
+        for (i, frame) in index.frames().iter().skip(1).enumerate() {
+            assert_eq!(
+                frame.offset,
+                index.record_start() + i as u64 * 25 * record_size
+            );
+        }
+        assert_eq!(
+            index.frame_for(index.record_start() + 30 * record_size),
+            Some(&index.frames()[2])
+        );
+    }
+
+    #[rstest]
+    fn test_seek(
+        #[values(Compression::None, Compression::Zstd)] compression: Compression,
+        #[values(0, 1_000, 1_005, 1_010, 1_095, 1_100, 1_555, 1_990, 2_000)] ts: u64,
+    ) {
+        let data = encode(compression);
+        let index = Index::build(data.as_slice(), compression, 10).unwrap();
+        let expected = (0..RECORD_COUNT)
+            .map(mbo)
+            .filter(|rec| rec.ts_recv >= ts)
+            .collect::<Vec<_>>();
+        assert_eq!(seek(&data, &index, ts), expected);
+    }
+
+    #[rstest]
+    fn test_seek_multi_frame(#[values(1_000, 1_245, 1_250, 1_600, 1_990)] ts: u64) {
+        let data = encode_multi_frame(25);
+        let index = Index::build(data.as_slice(), Compression::Zstd, 7).unwrap();
+        let expected = (0..RECORD_COUNT)
+            .map(mbo)
+            .filter(|rec| rec.ts_recv >= ts)
+            .collect::<Vec<_>>();
+        assert_eq!(seek(&data, &index, ts), expected);
+    }
+
+    #[rstest]
+    fn test_seek_stale_index() {
+        let data = encode(Compression::None);
+        let index = Index::build(data.as_slice(), Compression::None, 10).unwrap();
+        let res = DbnDecoder::seek_with_index(
+            Cursor::new(&data[..data.len() - 8]),
+            &index,
+            1_500,
+            VersionUpgradePolicy::AsIs,
+        );
+        assert!(matches!(res, Err(Error::BadArgument { .. })));
+    }
+
+    #[rstest]
+    fn test_read_write_round_trip(
+        #[values(Compression::None, Compression::Zstd)] compression: Compression,
+    ) {
+        let data = encode_multi_frame(30);
+        let data = if compression == Compression::None {
+            zstd::decode_all(data.as_slice()).unwrap()
+        } else {
+            data
+        };
+        let index = Index::build(data.as_slice(), compression, 3).unwrap();
+        let mut buf = Vec::new();
+        index.write(&mut buf).unwrap();
+        assert_eq!(Index::read(buf.as_slice()).unwrap(), index);
+    }
+
+    #[rstest]
+    fn test_read_invalid() {
+        assert!(matches!(
+            Index::read([0u8; HEADER_LEN].as_slice()),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[rstest]
+    #[case::uncompressed("test_data.mbo.v3.dbn")]
+    #[case::zstd("test_data.mbo.v3.dbn.zst")]
+    #[case::v1("test_data.mbo.v1.dbn.zst")]
+    fn test_build_from_file(#[case] file_name: &str) {
+        let path = format!("{TEST_DATA_PATH}/{file_name}");
+        let index = Index::build_from_file(&path, 1).unwrap();
+        assert_eq!(index.file_len(), std::fs::metadata(&path).unwrap().len());
+        let mut decoder = DbnDecoder::with_upgrade_policy(
+            DynReader::from_file(&path).unwrap(),
+            VersionUpgradePolicy::AsIs,
+        )
+        .unwrap();
+        let mut ts = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            ts.push(rec.raw_index_ts());
+        }
+        assert!(!index.entries().is_empty());
+        assert!(index.entries().len() <= ts.len());
+        assert_eq!(index.entries()[0].ts, ts[0]);
+    }
+
+    #[rstest]
+    fn test_sidecar_path() {
+        assert_eq!(
+            Index::sidecar_path("data/test.dbn.zst"),
+            PathBuf::from("data/test.dbn.zst.idx")
+        );
+    }
+}
@@ -16,15 +16,27 @@
 //!
 //! This crate provides:
 //! - [Decoders](crate::decode) for DBN and DBZ (the precursor to DBN), both
-//!   sync and async, with the `async` feature flag
+//!   sync and async, with the `async` feature flag, and for CSV and JSON
 //! - [Encoders](crate::encode) for CSV, DBN, and JSON, both sync and async,
-//!   with the `async` feature flag
+//!   with the `async` feature flag, Parquet with the `parquet` feature flag, and
+//!   in-memory [Arrow record batches](crate::encode::arrow) with the `arrow` feature
+//!   flag
 //! - [Normalized market data struct definitions](crate::record) corresponding to the
 //!   different market data schemas offered by Databento
 //! - Wrapper types for dynamically-typed records: [`RecordRef`] (immutable reference),
 //!   [`RecordRefMut`] (mutable reference), and [`RecordBuf`] (owned, stack-allocated)
 //! - [`RecordEnum`] and [`RecordRefEnum`] for exhaustive pattern matching over all
 //!   known record types
+//! - [Order book reconstruction](crate::book) from MBO data
+//! - [Timestamp indexes](crate::index) for seeking within large DBN files
+//! - Fast [concatenation](crate::concat) of DBN files that are already in order
+//! - [Aggregation of trades](crate::resample) into OHLCV bars
+//! - [Sorting](crate::sort) and deduplication of records, including files larger
+//!   than memory
+//! - [Summaries](crate::summary) of the records in a file
+//! - [Record-by-record comparison](crate::diff) of two files
+//! - [Editing the metadata](crate::edit) of existing files
+//! - [Validation](crate::validate) of records against their metadata
 //! - Helper functions and [macros] for common tasks
 //!
 //! # Quick start
@@ -78,12 +90,17 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 #![deny(clippy::missing_errors_doc)]
 
+pub mod book;
 pub mod compat;
+pub mod concat;
 pub mod decode;
+pub mod diff;
+pub mod edit;
 pub mod encode;
 pub mod enums;
 pub mod error;
 pub mod flags;
+pub mod index;
 mod json_writer;
 pub mod macros;
 pub mod metadata;
@@ -95,12 +112,16 @@ pub mod record;
 pub mod record_buf;
 mod record_enum;
 pub mod record_ref;
+pub mod resample;
+pub mod sort;
+pub mod summary;
 pub mod symbol_map;
 #[cfg(test)]
 mod test_utils;
 pub mod v1;
 pub mod v2;
 pub mod v3;
+pub mod validate;
 
 #[doc(inline)]
 pub use crate::{
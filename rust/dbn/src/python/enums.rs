@@ -814,6 +814,7 @@ impl Encoding {
             Self::Dbn => "DBN",
             Self::Csv => "CSV",
             Self::Json => "JSON",
+            Self::Parquet => "PARQUET",
         }
     }
 
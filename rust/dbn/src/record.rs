@@ -18,7 +18,7 @@ use dbn_macros::MockPyo3;
 
 use crate::{
     enums::rtype,
-    macros::{dbn_record, CsvSerialize, JsonSerialize, RecordDebug},
+    macros::{dbn_record, CsvSerialize, JsonSerialize, RecordDebug, TextDeserialize},
     Action, Error, FlagSet, InstrumentClass, MatchAlgorithm, Publisher, RType, Result,
     SecurityUpdateAction, Side, StatUpdateAction, UserDefinedInstrument, ASSET_CSTR_LEN,
     SYMBOL_CSTR_LEN,
@@ -35,7 +35,8 @@ pub use traits::{HasRType, Record, RecordMut};
 /// Common data for all Databento records. Always found at the beginning of a record
 /// struct.
 #[repr(C)]
-#[derive(Clone, Copy, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
 #[cfg_attr(test, derive(type_layout::TypeLayout))]
@@ -65,7 +66,8 @@ pub struct RecordHeader {
 /// A market-by-order (MBO) tick message. The record of the [`Mbo`](crate::Schema::Mbo)
 /// schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -214,7 +216,8 @@ pub struct ConsolidatedBidAskPair {
 
 /// Market-by-price implementation with a book depth of 0. Equivalent to MBP-0. The record of the [`Trades`](crate::Schema::Trades) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -265,7 +268,8 @@ pub struct TradeMsg {
 /// Market-by-price implementation with a known book depth of 1. The record of the
 /// [`Mbp1`](crate::Schema::Mbp1) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -320,7 +324,8 @@ pub struct Mbp1Msg {
 /// Market-by-price implementation with a known book depth of 10. The record of the
 /// [`Mbp10`](crate::Schema::Mbp10) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -375,7 +380,8 @@ pub struct Mbp10Msg {
 /// Subsampled market by price with a known book depth of 1. The record of the
 /// [`Bbo1S`](crate::Schema::Bbo1S) and [`Bbo1M`](crate::Schema::Bbo1M) schemas.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -428,7 +434,8 @@ pub struct BboMsg {
 /// Consolidated market-by-price implementation with a known book depth of 1. The record of
 /// the [`Cmbp1`](crate::Schema::Cmbp1) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -483,7 +490,8 @@ pub struct Cmbp1Msg {
 
 /// Subsampled consolidated market by price with a known book depth of 1. The record of the [`Cbbo1S`](crate::Schema::Cbbo1S) and [`Cbbo1M`](crate::Schema::Cbbo1M) schemas.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -556,7 +564,8 @@ pub type Cbbo1MMsg = CbboMsg;
 /// - [`Ohlcv1D`](crate::enums::Schema::Ohlcv1D)
 /// - [`OhlcvEod`](crate::enums::Schema::OhlcvEod)
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -603,7 +612,8 @@ pub struct OhlcvMsg {
 
 /// A trading status update message. The record of the [`Status`](crate::Schema::Status) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -645,7 +655,8 @@ pub struct StatusMsg {
 /// A definition of an instrument. The record of the
 /// [`Definition`](crate::Schema::Definition) schema.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -903,7 +914,8 @@ pub struct InstrumentDefMsg {
 
 /// An auction imbalance message.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -1021,7 +1033,8 @@ pub struct ImbalanceMsg {
 /// A statistics message. A catchall for various data disseminated by publishers. The
 /// [`stat_type`](Self::stat_type) indicates the statistic contained in the message.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -1079,7 +1092,8 @@ pub struct StatMsg {
 
 /// An error message from the Databento Live Subscription Gateway (LSG).
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -1105,7 +1119,8 @@ pub struct ErrorMsg {
 /// A symbol mapping message from the live API which maps a symbol from one
 /// [`SType`](crate::enums::SType) to another.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
@@ -1142,7 +1157,8 @@ pub struct SymbolMappingMsg {
 /// A non-error message from the Databento Live Subscription Gateway (LSG). Also used
 /// for heartbeating.
 #[repr(C)]
-#[derive(Clone, CsvSerialize, JsonSerialize, PartialEq, Eq, Hash)]
+#[derive(Clone, CsvSerialize, JsonSerialize, TextDeserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arrow", derive(crate::macros::ArrowSerialize))]
 #[cfg_attr(feature = "trivial_copy", derive(Copy))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "python", derive(crate::macros::PyFieldDesc))]
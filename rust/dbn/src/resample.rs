@@ -0,0 +1,1103 @@
+//! Aggregation of trades into open, high, low, close, and volume (OHLCV) bars and
+//! subsampling of top-of-book data.
+//!
+//! A [`BarAggregator`] builds [`OhlcvMsg`] bars per `(instrument_id, publisher_id)`
+//! from the trades in a stream of [`TradeMsg`], [`MboMsg`], [`Mbp1Msg`], [`Mbp10Msg`],
+//! or [`Cmbp1Msg`] records. Bars can cover a fixed interval of time, a fixed number of
+//! trades, or a fixed traded volume. See [`BarSpec`].
+//!
+//! [`BarDecoder`] wraps any [`DecodeRecordRef`] to decode the bars directly.
+//!
+//! A [`BboSubsampler`] samples [`Mbp1Msg`] or [`Cmbp1Msg`] records at regular
+//! intervals to produce the BBO and CBBO schemas, and [`BboDecoder`] wraps any
+//! [`DecodeRecordRef`] to decode the samples directly.
+//!
+//! # Example
+//! ```no_run
+//! use dbn::{
+//!     decode::{DbnDecoder, DecodeRecordRef},
+//!     resample::{BarDecoder, BarSpec},
+//!     Schema,
+//! };
+//!
+//! let decoder = DbnDecoder::from_zstd_file("20241007.trades.dbn.zst")?;
+//! let mut decoder = BarDecoder::new(decoder, BarSpec::from_schema(Schema::Ohlcv1M)?);
+//! while let Some(bar) = decoder.decode_record_ref()? {
+//!     println!("{bar:?}");
+//! }
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::c_char,
+    num::NonZeroU64,
+};
+
+use crate::{
+    decode::{DbnMetadata, DecodeRecord, DecodeRecordRef},
+    rtype, Action, BboMsg, CbboMsg, Cmbp1Msg, Error, HasRType, MboMsg, Mbp10Msg, Mbp1Msg, Metadata,
+    OhlcvMsg, RecordEnum, RecordHeader, RecordRef, Result, Schema, TradeMsg, UNDEF_PRICE,
+    UNDEF_TIMESTAMP,
+};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const TRADE: c_char = Action::Trade as u8 as c_char;
+
+/// How trades are grouped into bars.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BarSpec {
+    /// Bars covering fixed intervals of `ts_event` with this length in nanoseconds.
+    /// Intervals are aligned to the UNIX epoch and each bar's `ts_event` is the start
+    /// of its interval.
+    Time(NonZeroU64),
+    /// Bars of this many trades. Each bar's `ts_event` is the `ts_event` of its first
+    /// trade.
+    Ticks(NonZeroU64),
+    /// Bars that end with the trade that brings their volume to at least this
+    /// quantity. Trades aren't split across bars. Each bar's `ts_event` is the
+    /// `ts_event` of its first trade.
+    Volume(NonZeroU64),
+}
+
+impl BarSpec {
+    /// Creates a bar specification matching one of the interval-based OHLCV schemas:
+    /// [`Schema::Ohlcv1S`], [`Schema::Ohlcv1M`], [`Schema::Ohlcv1H`], or
+    /// [`Schema::Ohlcv1D`].
+    ///
+    /// # Errors
+    /// This function returns an error if `schema` isn't one of the schemas listed
+    /// above.
+    pub fn from_schema(schema: Schema) -> Result<Self> {
+        let secs = match schema {
+            Schema::Ohlcv1S => 1,
+            Schema::Ohlcv1M => 60,
+            Schema::Ohlcv1H => 60 * 60,
+            Schema::Ohlcv1D => 24 * 60 * 60,
+            _ => {
+                return Err(Error::BadArgument {
+                    param_name: "schema".to_owned(),
+                    desc: format!("can't resample to {schema}"),
+                })
+            }
+        };
+        Ok(Self::Time(
+            NonZeroU64::new(secs * NANOS_PER_SECOND).unwrap(),
+        ))
+    }
+
+    /// Returns the schema of the bars, if they correspond to one. Custom intervals,
+    /// tick bars, and volume bars return `None`.
+    pub fn schema(&self) -> Option<Schema> {
+        match self {
+            Self::Time(interval) if interval.get() % NANOS_PER_SECOND == 0 => {
+                match interval.get() / NANOS_PER_SECOND {
+                    1 => Some(Schema::Ohlcv1S),
+                    60 => Some(Schema::Ohlcv1M),
+                    3600 => Some(Schema::Ohlcv1H),
+                    86400 => Some(Schema::Ohlcv1D),
+                    _ => None,
+                }
+            }
+            Self::Time(_) | Self::Ticks(_) | Self::Volume(_) => None,
+        }
+    }
+
+    /// Returns the rtype of the bars. Bars that don't correspond to a schema use the
+    /// OHLCV rtype of unspecified cadence.
+    pub fn rtype(&self) -> u8 {
+        match self.schema() {
+            Some(Schema::Ohlcv1S) => rtype::OHLCV_1S,
+            Some(Schema::Ohlcv1M) => rtype::OHLCV_1M,
+            Some(Schema::Ohlcv1H) => rtype::OHLCV_1H,
+            Some(Schema::Ohlcv1D) => rtype::OHLCV_1D,
+            #[allow(deprecated)]
+            _ => rtype::OHLCV_DEPRECATED,
+        }
+    }
+}
+
+/// Aggregates trades into OHLCV bars for every instrument and publisher in a stream.
+///
+/// Completed bars are queued in the order they close and retrieved with
+/// [`pop()`](Self::pop). Time bars close once a trade from a later interval is
+/// applied for any instrument, so bars are returned in `ts_event` order provided the
+/// input is sorted. A trade with a `ts_event` before the current interval, which can
+/// happen when the input is sorted by `ts_recv`, is included in the bar for the
+/// current interval. Intervals without any trades don't produce a bar.
+///
+/// Call [`finish()`](Self::finish) at the end of the input to close the remaining bars.
+#[derive(Debug, Clone)]
+pub struct BarAggregator {
+    spec: BarSpec,
+    rtype: u8,
+    bars: HashMap<(u32, u16), Bar>,
+    /// The start of the current interval for time bars.
+    interval_start: u64,
+    ready: VecDeque<OhlcvMsg>,
+}
+
+#[derive(Debug, Clone)]
+struct Bar {
+    msg: OhlcvMsg,
+    trade_count: u64,
+}
+
+impl BarAggregator {
+    /// Creates a new aggregator that builds bars according to `spec`.
+    pub fn new(spec: BarSpec) -> Self {
+        Self {
+            spec,
+            rtype: spec.rtype(),
+            bars: HashMap::new(),
+            interval_start: 0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Returns the specification of the bars.
+    pub fn spec(&self) -> BarSpec {
+        self.spec
+    }
+
+    /// Applies `record` if it's a trade, otherwise it's ignored. Trades are
+    /// [`TradeMsg`] records and [`MboMsg`], [`Mbp1Msg`], [`Mbp10Msg`], and [`Cmbp1Msg`]
+    /// records with a trade action. Trades with an undefined price or `ts_event` are
+    /// also ignored.
+    pub fn apply_record(&mut self, record: RecordRef) {
+        let trade = if let Some(trade) = record.get::<TradeMsg>() {
+            Some((&trade.hd, trade.price, trade.size))
+        } else if let Some(mbo) = record.get::<MboMsg>() {
+            (mbo.action == TRADE).then_some((&mbo.hd, mbo.price, mbo.size))
+        } else if let Some(mbp) = record.get::<Mbp1Msg>() {
+            (mbp.action == TRADE).then_some((&mbp.hd, mbp.price, mbp.size))
+        } else if let Some(mbp) = record.get::<Mbp10Msg>() {
+            (mbp.action == TRADE).then_some((&mbp.hd, mbp.price, mbp.size))
+        } else if let Some(cmbp) = record.get::<Cmbp1Msg>() {
+            (cmbp.action == TRADE).then_some((&cmbp.hd, cmbp.price, cmbp.size))
+        } else {
+            None
+        };
+        if let Some((hd, price, size)) = trade {
+            self.apply_trade(hd, price, size);
+        }
+    }
+
+    /// Applies a trade at `price` for `size` to the bar for the instrument and
+    /// publisher in `hd`.
+    pub fn apply_trade(&mut self, hd: &RecordHeader, price: i64, size: u32) {
+        if price == UNDEF_PRICE || hd.ts_event == UNDEF_TIMESTAMP {
+            return;
+        }
+        let key = (hd.instrument_id, hd.publisher_id);
+        let ts_event = match self.spec {
+            BarSpec::Time(interval) => {
+                let start = hd.ts_event - hd.ts_event % interval.get();
+                if start > self.interval_start {
+                    self.close_all();
+                    self.interval_start = start;
+                }
+                self.interval_start
+            }
+            BarSpec::Ticks(_) | BarSpec::Volume(_) => hd.ts_event,
+        };
+        let rtype = self.rtype;
+        let bar = self.bars.entry(key).or_insert_with(|| Bar {
+            msg: OhlcvMsg {
+                hd: RecordHeader::new::<OhlcvMsg>(
+                    rtype,
+                    hd.publisher_id,
+                    hd.instrument_id,
+                    ts_event,
+                ),
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: 0,
+            },
+            trade_count: 0,
+        });
+        bar.msg.high = bar.msg.high.max(price);
+        bar.msg.low = bar.msg.low.min(price);
+        bar.msg.close = price;
+        bar.msg.volume += u64::from(size);
+        bar.trade_count += 1;
+        let is_complete = match self.spec {
+            BarSpec::Time(_) => false,
+            BarSpec::Ticks(ticks) => bar.trade_count >= ticks.get(),
+            BarSpec::Volume(volume) => bar.msg.volume >= volume.get(),
+        };
+        if is_complete {
+            let bar = self.bars.remove(&key).unwrap();
+            self.ready.push_back(bar.msg);
+        }
+    }
+
+    /// Returns the next completed bar, if any.
+    pub fn pop(&mut self) -> Option<OhlcvMsg> {
+        self.ready.pop_front()
+    }
+
+    /// Closes all bars in progress, making them available from [`pop()`](Self::pop).
+    /// Should be called after applying the last record.
+    pub fn finish(&mut self) {
+        self.close_all();
+    }
+
+    fn close_all(&mut self) {
+        let mut bars = self
+            .bars
+            .drain()
+            .map(|(_, bar)| bar.msg)
+            .collect::<Vec<_>>();
+        bars.sort_unstable_by_key(|bar| {
+            (bar.hd.ts_event, bar.hd.instrument_id, bar.hd.publisher_id)
+        });
+        self.ready.extend(bars);
+    }
+
+    /// Applies all remaining records from `decoder`, ignoring those that aren't
+    /// trades.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to decode a record.
+    pub fn replay<D: DecodeRecordRef>(&mut self, decoder: &mut D) -> Result<()> {
+        while let Some(record) = decoder.decode_record_ref()? {
+            self.apply_record(record);
+        }
+        Ok(())
+    }
+}
+
+/// Decodes OHLCV bars aggregated from the trades of another decoder with a
+/// [`BarAggregator`].
+#[derive(Debug)]
+pub struct BarDecoder<D> {
+    decoder: D,
+    aggregator: BarAggregator,
+    bar: Option<OhlcvMsg>,
+    is_done: bool,
+}
+
+impl<D> BarDecoder<D>
+where
+    D: DbnMetadata,
+{
+    /// Creates a new decoder of bars built from the trades in `decoder` according to
+    /// `spec`.
+    ///
+    /// The [`Metadata::schema`] of `decoder` is changed to [`BarSpec::schema()`] and
+    /// [`Metadata::ts_out`] is cleared.
+    pub fn new(mut decoder: D, spec: BarSpec) -> Self {
+        update_metadata(decoder.metadata_mut(), spec.schema());
+        Self::new_no_metadata(decoder, spec)
+    }
+}
+
+impl<D> BarDecoder<D> {
+    /// Creates a new decoder of bars built from the trades in `decoder` according to
+    /// `spec`, without modifying any metadata. Useful for decoders of DBN fragments.
+    pub fn new_no_metadata(decoder: D, spec: BarSpec) -> Self {
+        Self {
+            decoder,
+            aggregator: BarAggregator::new(spec),
+            bar: None,
+            is_done: false,
+        }
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn get_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the inner decoder.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes the decoder and returns the inner decoder.
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+fn update_metadata(metadata: &mut Metadata, schema: Option<Schema>) {
+    metadata.schema = schema;
+    metadata.ts_out = false;
+}
+
+impl<D: DbnMetadata> DbnMetadata for BarDecoder<D> {
+    fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for BarDecoder<D> {
+    fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
+        loop {
+            if let Some(bar) = self.aggregator.pop() {
+                return Ok(Some(RecordRef::from(&*self.bar.insert(bar))));
+            }
+            if self.is_done {
+                return Ok(None);
+            }
+            match self.decoder.decode_record_ref()? {
+                Some(record) => self.aggregator.apply_record(record),
+                None => {
+                    self.aggregator.finish();
+                    self.is_done = true;
+                }
+            }
+        }
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecord for BarDecoder<D> {
+    fn decode_record<T: HasRType>(&mut self) -> Result<Option<&T>> {
+        self.decode_record_ref()?
+            .map(|rec| rec.try_get::<T>())
+            .transpose()
+    }
+}
+
+/// Subsamples top-of-book data into the [`Bbo1S`](Schema::Bbo1S),
+/// [`Bbo1M`](Schema::Bbo1M), [`Cbbo1S`](Schema::Cbbo1S), and
+/// [`Cbbo1M`](Schema::Cbbo1M) schemas for every instrument and publisher in a stream.
+///
+/// BBO schemas are built from [`Mbp1Msg`] records and CBBO schemas from [`Cmbp1Msg`]
+/// records. Intervals are based on `ts_recv` and aligned to the UNIX epoch, with an
+/// update whose `ts_recv` is exactly on a boundary belonging to the interval ending
+/// there. At the end of each interval, a record with the last top of book is produced
+/// for every instrument that had an update during the interval. For BBO schemas, its
+/// `ts_recv` is the end of the interval, while for CBBO schemas it's the `ts_recv` of
+/// the last update. The other fields come from the last update, except for `price`,
+/// `size`, and `side`, which describe the last trade in the session and are undefined
+/// if there hasn't been one.
+///
+/// Completed intervals are queued and retrieved with [`pop()`](Self::pop). Call
+/// [`finish()`](Self::finish) at the end of the input to close the last interval.
+#[derive(Debug, Clone)]
+pub struct BboSubsampler {
+    schema: Schema,
+    interval: u64,
+    quotes: Quotes,
+    /// The end of the current interval.
+    interval_end: u64,
+    ready: VecDeque<RecordEnum>,
+}
+
+#[derive(Debug, Clone)]
+enum Quotes {
+    Bbo(HashMap<(u32, u16), Quote<BboMsg>>),
+    Cbbo(HashMap<(u32, u16), Quote<CbboMsg>>),
+}
+
+#[derive(Debug, Clone)]
+struct Quote<T> {
+    msg: T,
+    is_updated: bool,
+}
+
+impl BboSubsampler {
+    /// Creates a new subsampler for `schema`, which must be one of
+    /// [`Schema::Bbo1S`], [`Schema::Bbo1M`], [`Schema::Cbbo1S`], or
+    /// [`Schema::Cbbo1M`].
+    ///
+    /// # Errors
+    /// This function returns an error if `schema` isn't one of the schemas listed
+    /// above.
+    pub fn new(schema: Schema) -> Result<Self> {
+        let (secs, quotes) = match schema {
+            Schema::Bbo1S => (1, Quotes::Bbo(HashMap::new())),
+            Schema::Bbo1M => (60, Quotes::Bbo(HashMap::new())),
+            Schema::Cbbo1S => (1, Quotes::Cbbo(HashMap::new())),
+            Schema::Cbbo1M => (60, Quotes::Cbbo(HashMap::new())),
+            _ => {
+                return Err(Error::BadArgument {
+                    param_name: "schema".to_owned(),
+                    desc: format!("can't subsample to {schema}"),
+                })
+            }
+        };
+        Ok(Self {
+            schema,
+            interval: secs * NANOS_PER_SECOND,
+            quotes,
+            interval_end: 0,
+            ready: VecDeque::new(),
+        })
+    }
+
+    /// Returns the schema of the subsampled records.
+    pub fn schema(&self) -> Schema {
+        self.schema
+    }
+
+    /// Applies `record` if it's an [`Mbp1Msg`] for BBO schemas or a [`Cmbp1Msg`] for
+    /// CBBO schemas, otherwise it's ignored. Records with an undefined `ts_recv` are
+    /// also ignored.
+    pub fn apply_record(&mut self, record: RecordRef) {
+        match self.quotes {
+            Quotes::Bbo(_) => {
+                if let Some(mbp) = record.get::<Mbp1Msg>() {
+                    self.apply_mbp1(mbp);
+                }
+            }
+            Quotes::Cbbo(_) => {
+                if let Some(cmbp) = record.get::<Cmbp1Msg>() {
+                    self.apply_cmbp1(cmbp);
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::clone_on_copy)]
+    fn apply_mbp1(&mut self, mbp: &Mbp1Msg) {
+        if !self.advance(mbp.ts_recv) {
+            return;
+        }
+        let schema = self.schema;
+        let Quotes::Bbo(quotes) = &mut self.quotes else {
+            return;
+        };
+        let quote = quotes
+            .entry((mbp.hd.instrument_id, mbp.hd.publisher_id))
+            .or_insert_with(|| Quote {
+                msg: BboMsg::default_for_schema(schema),
+                is_updated: false,
+            });
+        let bbo = &mut quote.msg;
+        bbo.hd = RecordHeader::new::<BboMsg>(
+            bbo.hd.rtype,
+            mbp.hd.publisher_id,
+            mbp.hd.instrument_id,
+            mbp.hd.ts_event,
+        );
+        if mbp.action == TRADE {
+            bbo.price = mbp.price;
+            bbo.size = mbp.size;
+            bbo.side = mbp.side;
+        }
+        bbo.flags = mbp.flags;
+        bbo.sequence = mbp.sequence;
+        bbo.levels = mbp.levels.clone();
+        quote.is_updated = true;
+    }
+
+    #[allow(clippy::clone_on_copy)]
+    fn apply_cmbp1(&mut self, cmbp: &Cmbp1Msg) {
+        if !self.advance(cmbp.ts_recv) {
+            return;
+        }
+        let schema = self.schema;
+        let Quotes::Cbbo(quotes) = &mut self.quotes else {
+            return;
+        };
+        let quote = quotes
+            .entry((cmbp.hd.instrument_id, cmbp.hd.publisher_id))
+            .or_insert_with(|| Quote {
+                msg: CbboMsg::default_for_schema(schema),
+                is_updated: false,
+            });
+        let cbbo = &mut quote.msg;
+        cbbo.hd = RecordHeader::new::<CbboMsg>(
+            cbbo.hd.rtype,
+            cmbp.hd.publisher_id,
+            cmbp.hd.instrument_id,
+            cmbp.hd.ts_event,
+        );
+        if cmbp.action == TRADE {
+            cbbo.price = cmbp.price;
+            cbbo.size = cmbp.size;
+            cbbo.side = cmbp.side;
+        }
+        cbbo.flags = cmbp.flags;
+        cbbo.ts_recv = cmbp.ts_recv;
+        cbbo.levels = cmbp.levels.clone();
+        quote.is_updated = true;
+    }
+
+    /// Closes the current interval if `ts_recv` is after its end. Returns `false` if
+    /// `ts_recv` is undefined. Updates from before the current interval are included
+    /// in it.
+    fn advance(&mut self, ts_recv: u64) -> bool {
+        if ts_recv == UNDEF_TIMESTAMP {
+            return false;
+        }
+        let interval_end = ts_recv.div_ceil(self.interval) * self.interval;
+        if interval_end > self.interval_end {
+            self.close_interval();
+            self.interval_end = interval_end;
+        }
+        true
+    }
+
+    fn close_interval(&mut self) {
+        let ts_recv = self.interval_end;
+        match &mut self.quotes {
+            Quotes::Bbo(quotes) => self.ready.extend(
+                take_updated(quotes)
+                    .into_iter()
+                    .map(|bbo| RecordEnum::Bbo(BboMsg { ts_recv, ..bbo })),
+            ),
+            Quotes::Cbbo(quotes) => {
+                self.ready
+                    .extend(take_updated(quotes).into_iter().map(RecordEnum::Cbbo));
+            }
+        }
+    }
+
+    /// Returns the next subsampled record, if any.
+    pub fn pop(&mut self) -> Option<RecordEnum> {
+        self.ready.pop_front()
+    }
+
+    /// Closes the current interval, making its records available from
+    /// [`pop()`](Self::pop). Should be called after applying the last record.
+    pub fn finish(&mut self) {
+        self.close_interval();
+    }
+
+    /// Applies all remaining records from `decoder`, ignoring those that aren't of
+    /// the input type.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to decode a record.
+    pub fn replay<D: DecodeRecordRef>(&mut self, decoder: &mut D) -> Result<()> {
+        while let Some(record) = decoder.decode_record_ref()? {
+            self.apply_record(record);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the updated quotes sorted by instrument ID and publisher ID and marks them
+/// as no longer updated.
+fn take_updated<T: Clone>(quotes: &mut HashMap<(u32, u16), Quote<T>>) -> Vec<T> {
+    let mut updated = quotes
+        .iter_mut()
+        .filter(|(_, quote)| quote.is_updated)
+        .map(|(key, quote)| {
+            quote.is_updated = false;
+            (*key, quote.msg.clone())
+        })
+        .collect::<Vec<_>>();
+    updated.sort_unstable_by_key(|(key, _)| *key);
+    updated.into_iter().map(|(_, msg)| msg).collect()
+}
+
+/// Decodes subsampled BBO or CBBO records built from the top-of-book records of
+/// another decoder with a [`BboSubsampler`].
+#[derive(Debug)]
+pub struct BboDecoder<D> {
+    decoder: D,
+    subsampler: BboSubsampler,
+    record: Option<RecordEnum>,
+    is_done: bool,
+}
+
+impl<D> BboDecoder<D>
+where
+    D: DbnMetadata,
+{
+    /// Creates a new decoder of `schema` records subsampled from `decoder`. See
+    /// [`BboSubsampler::new()`] for the supported schemas.
+    ///
+    /// The [`Metadata::schema`] of `decoder` is changed to `schema` and
+    /// [`Metadata::ts_out`] is cleared.
+    ///
+    /// # Errors
+    /// This function returns an error if `schema` isn't a BBO or CBBO schema.
+    pub fn new(decoder: D, schema: Schema) -> Result<Self> {
+        let mut res = Self::new_no_metadata(decoder, schema)?;
+        update_metadata(res.decoder.metadata_mut(), Some(schema));
+        Ok(res)
+    }
+}
+
+impl<D> BboDecoder<D> {
+    /// Creates a new decoder of `schema` records subsampled from `decoder`, without
+    /// modifying any metadata. Useful for decoders of DBN fragments.
+    ///
+    /// # Errors
+    /// This function returns an error if `schema` isn't a BBO or CBBO schema.
+    pub fn new_no_metadata(decoder: D, schema: Schema) -> Result<Self> {
+        Ok(Self {
+            decoder,
+            subsampler: BboSubsampler::new(schema)?,
+            record: None,
+            is_done: false,
+        })
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn get_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the inner decoder.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes the decoder and returns the inner decoder.
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+impl<D: DbnMetadata> DbnMetadata for BboDecoder<D> {
+    fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.decoder.metadata_mut()
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecordRef for BboDecoder<D> {
+    fn decode_record_ref(&mut self) -> Result<Option<RecordRef<'_>>> {
+        loop {
+            if let Some(record) = self.subsampler.pop() {
+                return Ok(Some(RecordRef::from(&*self.record.insert(record))));
+            }
+            if self.is_done {
+                return Ok(None);
+            }
+            match self.decoder.decode_record_ref()? {
+                Some(record) => self.subsampler.apply_record(record),
+                None => {
+                    self.subsampler.finish();
+                    self.is_done = true;
+                }
+            }
+        }
+    }
+}
+
+impl<D: DecodeRecordRef> DecodeRecord for BboDecoder<D> {
+    fn decode_record<T: HasRType>(&mut self) -> Result<Option<&T>> {
+        self.decode_record_ref()?
+            .map(|rec| rec.try_get::<T>())
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::clone_on_copy)]
+
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        test_utils::VecStream,
+        Record, Side,
+    };
+
+    const PUBLISHER: u16 = 1;
+    const MINUTE: u64 = 60 * NANOS_PER_SECOND;
+
+    fn trade(instrument_id: u32, ts_event: u64, price: i64, size: u32) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, PUBLISHER, instrument_id, ts_event),
+            price,
+            size,
+            action: TRADE,
+            side: Side::Bid as u8 as c_char,
+            ts_recv: ts_event,
+            ..Default::default()
+        }
+    }
+
+    fn bar(
+        rtype: u8,
+        instrument_id: u32,
+        ts_event: u64,
+        [open, high, low, close]: [i64; 4],
+        volume: u64,
+    ) -> OhlcvMsg {
+        OhlcvMsg {
+            hd: RecordHeader::new::<OhlcvMsg>(rtype, PUBLISHER, instrument_id, ts_event),
+            open,
+            high,
+            low,
+            close,
+            volume,
+        }
+    }
+
+    fn aggregate(spec: BarSpec, trades: &[TradeMsg]) -> Vec<OhlcvMsg> {
+        let mut aggregator = BarAggregator::new(spec);
+        let mut res = Vec::new();
+        for trade in trades {
+            aggregator.apply_record(RecordRef::from(trade));
+            res.extend(std::iter::from_fn(|| aggregator.pop()));
+        }
+        aggregator.finish();
+        res.extend(std::iter::from_fn(|| aggregator.pop()));
+        res
+    }
+
+    #[rstest]
+    #[case::second(Schema::Ohlcv1S, rtype::OHLCV_1S)]
+    #[case::minute(Schema::Ohlcv1M, rtype::OHLCV_1M)]
+    #[case::hour(Schema::Ohlcv1H, rtype::OHLCV_1H)]
+    #[case::day(Schema::Ohlcv1D, rtype::OHLCV_1D)]
+    fn test_spec_from_schema(#[case] schema: Schema, #[case] exp_rtype: u8) {
+        let spec = BarSpec::from_schema(schema).unwrap();
+        assert_eq!(spec.schema(), Some(schema));
+        assert_eq!(spec.rtype(), exp_rtype);
+    }
+
+    #[rstest]
+    fn test_spec_from_schema_invalid(
+        #[values(Schema::OhlcvEod, Schema::Trades, Schema::Mbo)] schema: Schema,
+    ) {
+        assert!(BarSpec::from_schema(schema).is_err());
+    }
+
+    #[rstest]
+    #[case::custom_interval(BarSpec::Time(NonZeroU64::new(5 * MINUTE).unwrap()))]
+    #[case::fractional_second(BarSpec::Time(NonZeroU64::new(1_500_000_000).unwrap()))]
+    #[case::ticks(BarSpec::Ticks(NonZeroU64::new(60).unwrap()))]
+    #[case::volume(BarSpec::Volume(NonZeroU64::new(100).unwrap()))]
+    fn test_spec_without_schema(#[case] spec: BarSpec) {
+        assert!(spec.schema().is_none());
+        #[allow(deprecated)]
+        let exp_rtype = rtype::OHLCV_DEPRECATED;
+        assert_eq!(spec.rtype(), exp_rtype);
+    }
+
+    #[rstest]
+    fn test_time_bars() {
+        let bars = aggregate(
+            BarSpec::from_schema(Schema::Ohlcv1M).unwrap(),
+            &[
+                trade(1, MINUTE + 1, 100, 1),
+                trade(2, MINUTE + 2, 50, 5),
+                trade(1, MINUTE + 3, 105, 2),
+                trade(1, MINUTE + 4, 95, 3),
+                trade(1, MINUTE + 5, 101, 4),
+                // skips an interval
+                trade(1, 3 * MINUTE, 99, 1),
+            ],
+        );
+        assert_eq!(
+            bars,
+            [
+                bar(rtype::OHLCV_1M, 1, MINUTE, [100, 105, 95, 101], 10),
+                bar(rtype::OHLCV_1M, 2, MINUTE, [50, 50, 50, 50], 5),
+                bar(rtype::OHLCV_1M, 1, 3 * MINUTE, [99, 99, 99, 99], 1),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_late_trade_included_in_current_interval() {
+        let bars = aggregate(
+            BarSpec::from_schema(Schema::Ohlcv1M).unwrap(),
+            &[
+                trade(1, MINUTE, 100, 1),
+                trade(2, 2 * MINUTE, 50, 1),
+                trade(1, 2 * MINUTE - 1, 101, 1),
+            ],
+        );
+        assert_eq!(
+            bars,
+            [
+                bar(rtype::OHLCV_1M, 1, MINUTE, [100, 100, 100, 100], 1),
+                bar(rtype::OHLCV_1M, 1, 2 * MINUTE, [101, 101, 101, 101], 1),
+                bar(rtype::OHLCV_1M, 2, 2 * MINUTE, [50, 50, 50, 50], 1),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_tick_bars() {
+        #[allow(deprecated)]
+        let rtype = rtype::OHLCV_DEPRECATED;
+        let bars = aggregate(
+            BarSpec::Ticks(NonZeroU64::new(2).unwrap()),
+            &[
+                trade(1, 10, 100, 1),
+                trade(2, 11, 50, 1),
+                trade(1, 12, 102, 1),
+                trade(1, 13, 103, 1),
+            ],
+        );
+        assert_eq!(
+            bars,
+            [
+                bar(rtype, 1, 10, [100, 102, 100, 102], 2),
+                // partial bars are closed by `finish()`
+                bar(rtype, 2, 11, [50, 50, 50, 50], 1),
+                bar(rtype, 1, 13, [103, 103, 103, 103], 1),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_volume_bars() {
+        #[allow(deprecated)]
+        let rtype = rtype::OHLCV_DEPRECATED;
+        let bars = aggregate(
+            BarSpec::Volume(NonZeroU64::new(10).unwrap()),
+            &[
+                trade(1, 10, 100, 4),
+                trade(1, 11, 99, 4),
+                trade(1, 12, 101, 5),
+                trade(1, 13, 98, 10),
+            ],
+        );
+        assert_eq!(
+            bars,
+            [
+                bar(rtype, 1, 10, [100, 101, 99, 101], 13),
+                bar(rtype, 1, 13, [98, 98, 98, 98], 10),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_ignores_non_trades() {
+        let mut aggregator = BarAggregator::new(BarSpec::Ticks(NonZeroU64::new(1).unwrap()));
+        let add = MboMsg {
+            hd: RecordHeader::new::<MboMsg>(rtype::MBO, PUBLISHER, 1, 10),
+            price: 100,
+            size: 1,
+            action: Action::Add as u8 as c_char,
+            ..Default::default()
+        };
+        aggregator.apply_record(RecordRef::from(&add));
+        aggregator.apply_record(RecordRef::from(&trade(1, 11, UNDEF_PRICE, 1)));
+        aggregator.finish();
+        assert!(aggregator.pop().is_none());
+        let fill = MboMsg {
+            action: TRADE,
+            ..add
+        };
+        aggregator.apply_record(RecordRef::from(&fill));
+        #[allow(deprecated)]
+        let exp = bar(rtype::OHLCV_DEPRECATED, 1, 10, [100, 100, 100, 100], 1);
+        assert_eq!(aggregator.pop().unwrap(), exp);
+    }
+
+    #[rstest]
+    fn test_decoder() {
+        let trades = vec![
+            trade(1, MINUTE + 1, 100, 1),
+            trade(1, 2 * MINUTE, 101, 2),
+            trade(1, 2 * MINUTE + 1, 102, 3),
+        ];
+        let decoder = BarDecoder::new_no_metadata(
+            VecStream::new(trades),
+            BarSpec::from_schema(Schema::Ohlcv1M).unwrap(),
+        );
+        let bars = decoder.decode_records::<OhlcvMsg>().unwrap();
+        assert_eq!(
+            bars,
+            [
+                bar(rtype::OHLCV_1M, 1, MINUTE, [100, 100, 100, 100], 1),
+                bar(rtype::OHLCV_1M, 1, 2 * MINUTE, [101, 102, 101, 102], 5),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_decoder_metadata() {
+        let decoder =
+            DbnDecoder::from_file(format!("{TEST_DATA_PATH}/test_data.trades.dbn")).unwrap();
+        let mut decoder = BarDecoder::new(decoder, BarSpec::from_schema(Schema::Ohlcv1S).unwrap());
+        assert_eq!(decoder.metadata().schema, Some(Schema::Ohlcv1S));
+        assert!(!decoder.metadata().ts_out);
+        let mut count = 0;
+        while let Some(rec) = decoder.decode_record_ref().unwrap() {
+            assert!(rec.has::<OhlcvMsg>());
+            assert_eq!(rec.header().rtype, rtype::OHLCV_1S);
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+
+    fn mbp1(instrument_id: u32, ts_recv: u64, action: Action, bid_px: i64) -> Mbp1Msg {
+        Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, PUBLISHER, instrument_id, ts_recv - 10),
+            price: bid_px,
+            size: 1,
+            action: u8::from(action) as c_char,
+            side: Side::Ask as u8 as c_char,
+            ts_recv,
+            sequence: ts_recv as u32,
+            levels: [crate::BidAskPair {
+                bid_px,
+                ask_px: bid_px + 1,
+                bid_sz: 1,
+                ask_sz: 1,
+                bid_ct: 1,
+                ask_ct: 1,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn bbo(update: &Mbp1Msg, ts_recv: u64, last_trade: Option<&Mbp1Msg>) -> BboMsg {
+        let mut bbo = BboMsg {
+            hd: RecordHeader::new::<BboMsg>(
+                rtype::BBO_1S,
+                update.hd.publisher_id,
+                update.hd.instrument_id,
+                update.hd.ts_event,
+            ),
+            flags: update.flags,
+            ts_recv,
+            sequence: update.sequence,
+            levels: update.levels.clone(),
+            ..BboMsg::default_for_schema(Schema::Bbo1S)
+        };
+        if let Some(trade) = last_trade {
+            bbo.price = trade.price;
+            bbo.size = trade.size;
+            bbo.side = trade.side;
+        }
+        bbo
+    }
+
+    #[rstest]
+    fn test_bbo_subsampler_invalid_schema(
+        #[values(Schema::Mbp1, Schema::Cmbp1, Schema::Ohlcv1S)] schema: Schema,
+    ) {
+        assert!(BboSubsampler::new(schema).is_err());
+    }
+
+    #[rstest]
+    fn test_bbo_subsampler() {
+        const SEC: u64 = NANOS_PER_SECOND;
+        let updates = [
+            mbp1(1, SEC / 5, Action::Add, 100),
+            mbp1(1, SEC / 2, Action::Trade, 100),
+            mbp1(2, 7 * SEC / 10, Action::Add, 50),
+            mbp1(1, SEC + SEC / 3, Action::Cancel, 99),
+            // exactly on a boundary is part of the interval ending there
+            mbp1(2, 3 * SEC, Action::Add, 51),
+        ];
+        let mut subsampler = BboSubsampler::new(Schema::Bbo1S).unwrap();
+        for update in updates.iter() {
+            subsampler.apply_record(RecordRef::from(update));
+        }
+        // ignored
+        subsampler.apply_record(RecordRef::from(&trade(1, 3 * SEC, 100, 1)));
+        subsampler.finish();
+        let res = std::iter::from_fn(|| subsampler.pop())
+            .map(|rec| match rec {
+                RecordEnum::Bbo(bbo) => bbo,
+                rec => panic!("unexpected record {rec:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            res,
+            [
+                bbo(&updates[1], SEC, Some(&updates[1])),
+                bbo(&updates[2], SEC, None),
+                bbo(&updates[3], 2 * SEC, Some(&updates[1])),
+                bbo(&updates[4], 3 * SEC, None),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_bbo_decoder_matches_test_data() {
+        let expected = DbnDecoder::from_file(format!("{TEST_DATA_PATH}/test_data.bbo-1s.dbn"))
+            .unwrap()
+            .decode_records::<BboMsg>()
+            .unwrap();
+        // Reconstruct the last update of each interval from the subsampled records
+        let updates = expected
+            .iter()
+            .map(|bbo| Mbp1Msg {
+                hd: RecordHeader::new::<Mbp1Msg>(
+                    rtype::MBP_1,
+                    bbo.hd.publisher_id,
+                    bbo.hd.instrument_id,
+                    bbo.hd.ts_event,
+                ),
+                price: bbo.price,
+                size: bbo.size,
+                action: TRADE,
+                side: bbo.side,
+                flags: bbo.flags,
+                ts_recv: bbo.ts_recv - 1,
+                sequence: bbo.sequence,
+                levels: bbo.levels.clone(),
+                ..Default::default()
+            })
+            .collect();
+        let decoder = BboDecoder::new_no_metadata(VecStream::new(updates), Schema::Bbo1S).unwrap();
+        assert_eq!(decoder.decode_records::<BboMsg>().unwrap(), expected);
+    }
+
+    #[rstest]
+    fn test_cbbo_decoder_matches_test_data() {
+        let decoder =
+            DbnDecoder::from_file(format!("{TEST_DATA_PATH}/test_data.cbbo-1s.dbn")).unwrap();
+        let metadata = decoder.metadata().clone();
+        let expected = decoder.decode_records::<CbboMsg>().unwrap();
+        let updates = expected
+            .iter()
+            .map(|cbbo| Cmbp1Msg {
+                hd: RecordHeader::new::<Cmbp1Msg>(
+                    rtype::CMBP_1,
+                    cbbo.hd.publisher_id,
+                    cbbo.hd.instrument_id,
+                    cbbo.hd.ts_event,
+                ),
+                price: cbbo.price,
+                size: cbbo.size,
+                action: TRADE,
+                side: cbbo.side,
+                flags: cbbo.flags,
+                ts_recv: cbbo.ts_recv,
+                levels: cbbo.levels.clone(),
+                ..Cmbp1Msg::default_for_schema(Schema::Cmbp1)
+            })
+            .collect::<Vec<_>>();
+        let subsample = |updates: &[Cmbp1Msg]| {
+            let mut input = Vec::new();
+            let mut encoder = crate::encode::DbnEncoder::new(
+                &mut input,
+                &Metadata {
+                    schema: Some(Schema::Cmbp1),
+                    ..metadata.clone()
+                },
+            )
+            .unwrap();
+            crate::encode::EncodeRecord::encode_records(&mut encoder, updates).unwrap();
+            let mut decoder =
+                BboDecoder::new(DbnDecoder::new(input.as_slice()).unwrap(), Schema::Cbbo1S)
+                    .unwrap();
+            assert_eq!(decoder.metadata().schema, Some(Schema::Cbbo1S));
+            let mut res = Vec::new();
+            while let Some(cbbo) = decoder.decode_record::<CbboMsg>().unwrap() {
+                res.push(cbbo.clone());
+            }
+            res
+        };
+        // The test data records are from the same interval, so each is the last update
+        // of its own input
+        for (update, exp) in updates.iter().zip(expected.iter()) {
+            assert_eq!(
+                subsample(std::slice::from_ref(update)),
+                std::slice::from_ref(exp)
+            );
+        }
+        assert_eq!(subsample(&updates), [expected.last().unwrap().clone()]);
+    }
+}
@@ -0,0 +1,508 @@
+//! Sorting and deduplication of DBN records, including inputs larger than memory.
+//!
+//! A [`Sorter`] orders records by index timestamp and then by sequence number for the
+//! record types that have one, keeping records with equal keys in their input order.
+//! Once the records read take up more than [`Sorter::max_memory()`], they're sorted
+//! and spilled to a temporary file as a run, and the runs are merged at the end. With
+//! [`Sorter::dedup()`], exact duplicate records are removed.
+//!
+//! # Example
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use dbn::{decode::DynDecoder, sort::Sorter, VersionUpgradePolicy};
+//!
+//! let decoder = DynDecoder::from_file("captures.dbn.zst", VersionUpgradePolicy::AsIs)?;
+//! let output = File::create("sorted.dbn").unwrap();
+//! let summary = Sorter::new().dedup(true).sort(decoder, output)?;
+//! println!("removed {} duplicates", summary.duplicate_count);
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    decode::{DbnMetadata, DbnRecordDecoder, DecodeRecordRef},
+    encode::{DbnEncoder, EncodeRecord, EncodeRecordRef},
+    rtype, v1, BboMsg, HasRType, MboMsg, Mbp10Msg, Mbp1Msg, Metadata, Record, RecordBuf, RecordRef,
+    Result, StatMsg, TradeMsg, VersionUpgradePolicy,
+};
+
+/// The index timestamp and sequence number records are sorted by.
+type SortKey = (u64, u32);
+
+/// Sorts DBN records by index timestamp and sequence number, optionally removing exact
+/// duplicates.
+#[derive(Debug, Clone)]
+pub struct Sorter {
+    dedup: bool,
+    max_memory: usize,
+    temp_dir: PathBuf,
+}
+
+/// Counts from sorting records with a [`Sorter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortSummary {
+    /// The number of records written.
+    pub record_count: u64,
+    /// The number of exact duplicate records removed.
+    pub duplicate_count: u64,
+    /// The number of sorted runs spilled to temporary files. `0` when all records fit
+    /// in memory.
+    pub spilled_run_count: usize,
+}
+
+impl Sorter {
+    /// The default maximum number of bytes of records to hold in memory: 256 MiB.
+    pub const DEFAULT_MAX_MEMORY: usize = 256 << 20;
+
+    /// Creates a new sorter that keeps duplicates, holds up to
+    /// [`DEFAULT_MAX_MEMORY`](Self::DEFAULT_MAX_MEMORY) bytes of records in memory,
+    /// and spills to the system's temporary directory.
+    pub fn new() -> Self {
+        Self {
+            dedup: false,
+            max_memory: Self::DEFAULT_MAX_MEMORY,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Sets whether to remove records that are byte-for-byte identical to an earlier
+    /// record. Only the first occurrence is kept.
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Sets the maximum number of bytes of records to hold in memory before spilling a
+    /// sorted run to a temporary file.
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// Sets the directory where sorted runs are spilled. The files are removed once
+    /// sorting is done.
+    pub fn temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = temp_dir.into();
+        self
+    }
+
+    /// Sorts the records from `decoder` and encodes them along with its metadata to
+    /// `writer` with a DBN [`Encoder`](DbnEncoder).
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to decode a record, to write or read
+    /// a temporary file, or to encode to `writer`.
+    pub fn sort<D, W>(&self, mut decoder: D, writer: W) -> Result<SortSummary>
+    where
+        D: DecodeRecordRef + DbnMetadata,
+        W: io::Write,
+    {
+        let metadata = decoder.metadata().clone();
+        let mut run = Run::default();
+        let mut spilled = Vec::new();
+        while let Some(rec) = decoder.decode_record_ref()? {
+            run.push(rec);
+            if run.memory_usage() >= self.max_memory {
+                spilled.push(run.spill(&self.temp_dir)?);
+            }
+        }
+        let mut output = Output::new(DbnEncoder::new(writer, &metadata)?, self.dedup);
+        if spilled.is_empty() {
+            run.sort();
+            for (key, rec) in run.records() {
+                output.write(key, rec)?;
+            }
+        } else {
+            if !run.is_empty() {
+                spilled.push(run.spill(&self.temp_dir)?);
+            }
+            output.summary.spilled_run_count = spilled.len();
+            merge(&spilled, &metadata, &mut output)?;
+        }
+        output.finish()
+    }
+}
+
+impl Default for Sorter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sorts the records from `decoder` by index timestamp and sequence number with the
+/// default [`Sorter`] settings, encoding them to `writer` as DBN.
+///
+/// # Errors
+/// This function returns an error if it fails to decode a record, to write or read a
+/// temporary file, or to encode to `writer`.
+pub fn sort<D, W>(decoder: D, writer: W, dedup: bool) -> Result<SortSummary>
+where
+    D: DecodeRecordRef + DbnMetadata,
+    W: io::Write,
+{
+    Sorter::new().dedup(dedup).sort(decoder, writer)
+}
+
+/// Returns the key `rec` is sorted by. Records without a sequence number sort as if
+/// it's 0.
+fn sort_key(rec: &RecordRef) -> SortKey {
+    fn sequence<T: HasRType>(rec: &RecordRef, get: impl FnOnce(&T) -> u32) -> Option<u32> {
+        rec.try_get::<T>().ok().map(get)
+    }
+
+    let sequence = match rec.header().rtype {
+        rtype::MBO => sequence(rec, |r: &MboMsg| r.sequence),
+        rtype::MBP_0 => sequence(rec, |r: &TradeMsg| r.sequence),
+        rtype::MBP_1 => sequence(rec, |r: &Mbp1Msg| r.sequence),
+        rtype::MBP_10 => sequence(rec, |r: &Mbp10Msg| r.sequence),
+        rtype::BBO_1S | rtype::BBO_1M => sequence(rec, |r: &BboMsg| r.sequence),
+        // Statistics records were smaller before DBN version 3
+        rtype::STATISTICS => sequence(rec, |r: &StatMsg| r.sequence)
+            .or_else(|| sequence(rec, |r: &v1::StatMsg| r.sequence)),
+        _ => None,
+    };
+    (rec.raw_index_ts(), sequence.unwrap_or(0))
+}
+
+/// Records held in memory to be sorted.
+#[derive(Debug, Default)]
+struct Run {
+    /// Records each padded to a multiple of 8 bytes to keep them aligned.
+    words: Vec<u64>,
+    /// The sort key, offset in `words`, and length in bytes of each record.
+    entries: Vec<(SortKey, usize, usize)>,
+}
+
+impl Run {
+    fn push(&mut self, rec: RecordRef) {
+        let bytes = rec.as_ref();
+        let offset = self.words.len();
+        self.words.resize(offset + bytes.len().div_ceil(8), 0);
+        // SAFETY: the words were just added with room for `bytes` and are valid for
+        // writes as bytes.
+        let dest = unsafe {
+            std::slice::from_raw_parts_mut(
+                self.words.as_mut_ptr().add(offset).cast::<u8>(),
+                bytes.len(),
+            )
+        };
+        dest.copy_from_slice(bytes);
+        self.entries.push((sort_key(&rec), offset, bytes.len()));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.words.len() * mem::size_of::<u64>()
+            + self.entries.len() * mem::size_of::<(SortKey, usize, usize)>()
+    }
+
+    /// Sorts the records, keeping records with equal keys in the order they were
+    /// pushed.
+    fn sort(&mut self) {
+        self.entries.sort_by_key(|(key, _, _)| *key);
+    }
+
+    fn records(&self) -> impl Iterator<Item = (SortKey, RecordRef<'_>)> {
+        self.entries.iter().map(|&(key, offset, len)| {
+            // SAFETY: `offset` and `len` are those of a record copied in `push` to an
+            // 8-byte aligned position.
+            let rec = unsafe {
+                RecordRef::new(std::slice::from_raw_parts(
+                    self.words.as_ptr().add(offset).cast::<u8>(),
+                    len,
+                ))
+            };
+            (key, rec)
+        })
+    }
+
+    /// Sorts the records and writes them to a new temporary file in `temp_dir`,
+    /// leaving the run empty.
+    fn spill(&mut self, temp_dir: &Path) -> Result<SpilledRun> {
+        self.sort();
+        let (spilled, file) = SpilledRun::create(temp_dir)?;
+        let mut writer = BufWriter::new(file);
+        for (_, rec) in self.records() {
+            writer
+                .write_all(rec.as_ref())
+                .map_err(|e| crate::Error::io(e, "writing sorted run"))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| crate::Error::io(e, "flushing sorted run"))?;
+        self.words.clear();
+        self.entries.clear();
+        Ok(spilled)
+    }
+}
+
+/// A sorted run of records in a temporary file, which is removed when dropped.
+#[derive(Debug)]
+struct SpilledRun {
+    path: PathBuf,
+}
+
+impl SpilledRun {
+    fn create(temp_dir: &Path) -> Result<(Self, File)> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = temp_dir.join(format!(
+            "dbn-sort-{}-{}.dbn.frag",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| {
+                crate::Error::io(e, format!("creating temporary file '{}'", path.display()))
+            })?;
+        Ok((Self { path }, file))
+    }
+
+    fn open(&self, metadata: &Metadata) -> Result<DbnRecordDecoder<BufReader<File>>> {
+        let file = File::open(&self.path).map_err(|e| {
+            crate::Error::io(
+                e,
+                format!("opening temporary file '{}'", self.path.display()),
+            )
+        })?;
+        DbnRecordDecoder::with_version(
+            BufReader::new(file),
+            metadata.version,
+            VersionUpgradePolicy::AsIs,
+            metadata.ts_out,
+        )
+    }
+}
+
+impl Drop for SpilledRun {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Merges the sorted `runs` into `output`. Records with equal keys are taken from
+/// earlier runs first to keep them in input order.
+fn merge<W: io::Write>(
+    runs: &[SpilledRun],
+    metadata: &Metadata,
+    output: &mut Output<W>,
+) -> Result<()> {
+    let mut decoders = runs
+        .iter()
+        .map(|run| run.open(metadata))
+        .collect::<Result<Vec<_>>>()?;
+    let mut heads: Vec<Option<RecordBuf>> = (0..runs.len()).map(|_| None).collect();
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (idx, decoder) in decoders.iter_mut().enumerate() {
+        read_head(decoder, idx, &mut heads, &mut heap)?;
+    }
+    while let Some(Reverse((key, idx))) = heap.pop() {
+        let head = heads[idx].take().unwrap();
+        output.write(key, head.as_rec_ref())?;
+        read_head(&mut decoders[idx], idx, &mut heads, &mut heap)?;
+    }
+    Ok(())
+}
+
+/// Reads the next record of run `idx` into `heads`, if any.
+fn read_head<R: io::Read>(
+    decoder: &mut DbnRecordDecoder<R>,
+    idx: usize,
+    heads: &mut [Option<RecordBuf>],
+    heap: &mut BinaryHeap<Reverse<(SortKey, usize)>>,
+) -> Result<()> {
+    if let Some(rec) = decoder.decode_record_ref()? {
+        heap.push(Reverse((sort_key(&rec), idx)));
+        heads[idx] = Some(RecordBuf::try_from(rec)?);
+    }
+    Ok(())
+}
+
+/// Encodes sorted records, removing duplicates if enabled.
+struct Output<W: io::Write> {
+    encoder: DbnEncoder<W>,
+    dedup: bool,
+    /// The key of the last record.
+    key: Option<SortKey>,
+    /// The records with the same key as the last record. Exact duplicates have equal
+    /// keys, so only these need to be checked.
+    seen: HashSet<Vec<u8>>,
+    summary: SortSummary,
+}
+
+impl<W: io::Write> Output<W> {
+    fn new(encoder: DbnEncoder<W>, dedup: bool) -> Self {
+        Self {
+            encoder,
+            dedup,
+            key: None,
+            seen: HashSet::new(),
+            summary: SortSummary::default(),
+        }
+    }
+
+    fn write(&mut self, key: SortKey, rec: RecordRef) -> Result<()> {
+        if self.dedup {
+            if self.key != Some(key) {
+                self.key = Some(key);
+                self.seen.clear();
+            }
+            if !self.seen.insert(rec.as_ref().to_vec()) {
+                self.summary.duplicate_count += 1;
+                return Ok(());
+            }
+        }
+        self.encoder.encode_record_ref(rec)?;
+        self.summary.record_count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<SortSummary> {
+        self.encoder.flush()?;
+        Ok(self.summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{DbnDecoder, DecodeRecord},
+        MetadataBuilder, RecordHeader, SType, Schema,
+    };
+
+    fn trade(ts: u64, sequence: u32, price: i64) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, ts),
+            ts_recv: ts,
+            sequence,
+            price,
+            ..Default::default()
+        }
+    }
+
+    fn encode(records: &[TradeMsg]) -> Vec<u8> {
+        let metadata = MetadataBuilder::new()
+            .dataset("TEST".to_owned())
+            .schema(Some(Schema::Trades))
+            .start(0)
+            .stype_in(None)
+            .stype_out(SType::InstrumentId)
+            .build();
+        let mut buf = Vec::new();
+        let mut encoder = DbnEncoder::new(&mut buf, &metadata).unwrap();
+        encoder.encode_records(records).unwrap();
+        buf
+    }
+
+    fn sort_trades(sorter: &Sorter, records: &[TradeMsg]) -> (SortSummary, Vec<(u64, u32, i64)>) {
+        let input = encode(records);
+        let mut output = Vec::new();
+        let summary = sorter
+            .sort(DbnDecoder::new(input.as_slice()).unwrap(), &mut output)
+            .unwrap();
+        let sorted = DbnDecoder::new(output.as_slice())
+            .unwrap()
+            .decode_records::<TradeMsg>()
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.ts_recv, r.sequence, r.price))
+            .collect();
+        (summary, sorted)
+    }
+
+    fn test_temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dbn-sort-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[rstest]
+    fn test_sort_by_index_ts_then_sequence(#[values(false, true)] spill: bool) {
+        let temp_dir = test_temp_dir(&format!("order-{spill}"));
+        let mut sorter = Sorter::new().temp_dir(&temp_dir);
+        if spill {
+            // Every record is its own run
+            sorter = sorter.max_memory(1);
+        }
+        let (summary, sorted) = sort_trades(
+            &sorter,
+            &[
+                trade(3, 1, 10),
+                trade(1, 2, 11),
+                trade(2, 0, 12),
+                trade(1, 1, 13),
+                // Equal keys stay in input order
+                trade(1, 1, 14),
+            ],
+        );
+        assert_eq!(
+            sorted,
+            vec![(1, 1, 13), (1, 1, 14), (1, 2, 11), (2, 0, 12), (3, 1, 10)]
+        );
+        assert_eq!(summary.record_count, 5);
+        assert_eq!(summary.spilled_run_count, if spill { 5 } else { 0 });
+        // Temporary files are removed
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 0);
+        fs::remove_dir(temp_dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_dedup(#[values(usize::MAX, 1, 150)] max_memory: usize) {
+        let temp_dir = test_temp_dir(&format!("dedup-{max_memory}"));
+        let sorter = Sorter::new()
+            .dedup(true)
+            .max_memory(max_memory)
+            .temp_dir(&temp_dir);
+        let (summary, sorted) = sort_trades(
+            &sorter,
+            &[
+                trade(2, 1, 10),
+                trade(1, 1, 11),
+                trade(2, 1, 12),
+                trade(1, 1, 11),
+                trade(2, 1, 10),
+            ],
+        );
+        assert_eq!(sorted, vec![(1, 1, 11), (2, 1, 10), (2, 1, 12)]);
+        assert_eq!(summary.record_count, 3);
+        assert_eq!(summary.duplicate_count, 2);
+        fs::remove_dir(temp_dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_keeps_duplicates_by_default() {
+        let (summary, sorted) = sort_trades(&Sorter::new(), &[trade(1, 1, 10), trade(1, 1, 10)]);
+        assert_eq!(sorted, vec![(1, 1, 10), (1, 1, 10)]);
+        assert_eq!(summary.duplicate_count, 0);
+    }
+
+    #[rstest]
+    fn test_sort_key_v1_stat() {
+        let stat = v1::StatMsg {
+            hd: RecordHeader::new::<v1::StatMsg>(rtype::STATISTICS, 1, 1, 5),
+            ts_recv: 6,
+            sequence: 7,
+            ..Default::default()
+        };
+        assert_eq!(sort_key(&RecordRef::from(&stat)), (6, 7));
+    }
+}
@@ -0,0 +1,650 @@
+//! Summaries of the contents of DBN files.
+//!
+//! A [`Summarizer`] reads every record of a decoder or file and produces a [`Summary`]
+//! with:
+//! - the number of records per rtype, publisher, instrument, and symbol
+//! - the earliest and latest `ts_event` and `ts_recv`
+//! - gaps between consecutive index timestamps longer than a threshold
+//! - the uncompressed size and, for files, the compressed size
+//! - the DBN version
+//!
+//! A summary can be displayed in a human-readable form or serialized to JSON with
+//! [`Summary::to_json()`].
+//!
+//! # Example
+//! ```no_run
+//! use dbn::summary::Summarizer;
+//!
+//! let summary = Summarizer::new().summarize_file("20241007.mbo.dbn.zst")?;
+//! println!("{summary}");
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::Read,
+    path::Path,
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    decode::{zstd, DbnMetadata, DecodeRecordRef, DynDecoder},
+    encode::dbn::MetadataEncoder,
+    enums::RType,
+    pretty,
+    symbol_map::{PitSymbolMap, SymbolIndex, TsSymbolMap},
+    Metadata, Publisher, Record, RecordRef, Result, Schema, VersionUpgradePolicy, UNDEF_TIMESTAMP,
+};
+
+/// The earliest and latest value of a timestamp field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TsRange {
+    /// The earliest timestamp in UNIX nanoseconds.
+    pub first: u64,
+    /// The latest timestamp in UNIX nanoseconds.
+    pub last: u64,
+}
+
+/// A period between two consecutive records without any records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    /// The position of the record after the gap, starting at 0.
+    pub index: u64,
+    /// The index timestamp of the record before the gap.
+    pub start: u64,
+    /// The index timestamp of the record after the gap.
+    pub end: u64,
+}
+
+impl Gap {
+    /// Returns the length of the gap in nanoseconds.
+    pub fn duration(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// A summary of the records in a DBN file or stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// The DBN version of the input.
+    pub version: u8,
+    /// The dataset code from the metadata.
+    pub dataset: String,
+    /// The schema from the metadata. `None` indicates the input may contain a mix of
+    /// schemas.
+    pub schema: Option<Schema>,
+    /// The total number of records.
+    pub record_count: u64,
+    /// The number of records per rtype.
+    pub rtype_counts: BTreeMap<u8, u64>,
+    /// The number of records per publisher ID.
+    pub publisher_counts: BTreeMap<u16, u64>,
+    /// The number of records per instrument ID.
+    pub instrument_counts: BTreeMap<u32, u64>,
+    /// The number of records per symbol. Records whose instrument couldn't be resolved
+    /// to a symbol through the metadata or symbol mapping records aren't counted.
+    pub symbol_counts: BTreeMap<String, u64>,
+    /// The range of `ts_event` across all records. `None` if there were no records
+    /// with a `ts_event`.
+    pub ts_event: Option<TsRange>,
+    /// The range of `ts_recv` across the records that have one. `None` if there were
+    /// no records with a `ts_recv`.
+    pub ts_recv: Option<TsRange>,
+    /// The minimum length in nanoseconds of a reported gap.
+    pub gap_threshold: u64,
+    /// The gaps longer than `gap_threshold`, up to the maximum set with
+    /// [`Summarizer::max_gaps()`].
+    pub gaps: Vec<Gap>,
+    /// The total number of gaps, including those past the maximum.
+    pub gap_count: u64,
+    /// The size in bytes of the input as uncompressed DBN, including the metadata.
+    pub uncompressed_size: u64,
+    /// The size in bytes of the file on disk if it's Zstd-compressed. Only set by
+    /// [`Summarizer::summarize_file()`].
+    pub compressed_size: Option<u64>,
+}
+
+/// Summarizes the records in DBN files and streams. See the
+/// [module-level documentation](crate::summary) for the contents of a summary.
+#[derive(Debug, Clone)]
+pub struct Summarizer {
+    gap_threshold: u64,
+    max_gaps: usize,
+}
+
+impl Summarizer {
+    /// The default minimum length of a reported gap: one minute.
+    pub const DEFAULT_GAP_THRESHOLD: u64 = 60_000_000_000;
+    /// The default maximum number of gaps retained in the summary.
+    pub const DEFAULT_MAX_GAPS: usize = 100;
+
+    /// Creates a new summarizer with the default gap threshold and maximum number of
+    /// gaps.
+    pub fn new() -> Self {
+        Self {
+            gap_threshold: Self::DEFAULT_GAP_THRESHOLD,
+            max_gaps: Self::DEFAULT_MAX_GAPS,
+        }
+    }
+
+    /// Sets the length in nanoseconds a gap between consecutive index timestamps must
+    /// exceed to be reported. Defaults to
+    /// [`DEFAULT_GAP_THRESHOLD`](Self::DEFAULT_GAP_THRESHOLD).
+    pub fn gap_threshold(mut self, gap_threshold: u64) -> Self {
+        self.gap_threshold = gap_threshold;
+        self
+    }
+
+    /// Sets the maximum number of gaps retained in the summary. Gaps past the maximum
+    /// are still counted. Defaults to [`DEFAULT_MAX_GAPS`](Self::DEFAULT_MAX_GAPS).
+    pub fn max_gaps(mut self, max_gaps: usize) -> Self {
+        self.max_gaps = max_gaps;
+        self
+    }
+
+    /// Summarizes all remaining records from `decoder`.
+    ///
+    /// # Errors
+    /// This function returns an error if the symbology mappings in the metadata can't
+    /// be parsed or it fails to decode a record.
+    pub fn summarize<D>(&self, mut decoder: D) -> Result<Summary>
+    where
+        D: DecodeRecordRef + DbnMetadata,
+    {
+        let mut state = State::new(self, decoder.metadata())?;
+        while let Some(record) = decoder.decode_record_ref()? {
+            state.on_record(record);
+        }
+        Ok(state.summary)
+    }
+
+    /// Summarizes the DBN file at `path`, Zstd-compressed or not, including its
+    /// compressed size.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to open or read the file, the
+    /// symbology mappings in the metadata can't be parsed, or it fails to decode a
+    /// record.
+    pub fn summarize_file(&self, path: impl AsRef<Path>) -> Result<Summary> {
+        let path = path.as_ref();
+        let open_err = |e| {
+            crate::Error::io(
+                e,
+                format!("opening file to summarize at path '{}'", path.display()),
+            )
+        };
+        let mut file = File::open(path).map_err(open_err)?;
+        let file_size = file.metadata().map_err(open_err)?.len();
+        let mut prefix = Vec::with_capacity(4);
+        file.by_ref()
+            .take(4)
+            .read_to_end(&mut prefix)
+            .map_err(open_err)?;
+        // Summarize the records as they're stored
+        let mut summary =
+            self.summarize(DynDecoder::from_file(path, VersionUpgradePolicy::AsIs)?)?;
+        if zstd::starts_with_prefix(&prefix) {
+            summary.compressed_size = Some(file_size);
+        }
+        Ok(summary)
+    }
+}
+
+impl Default for Summarizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Summarizes all records from `decoder` with the default [`Summarizer`] settings.
+///
+/// # Errors
+/// This function returns an error if the symbology mappings in the metadata can't be
+/// parsed or it fails to decode a record.
+pub fn summarize<D>(decoder: D) -> Result<Summary>
+where
+    D: DecodeRecordRef + DbnMetadata,
+{
+    Summarizer::new().summarize(decoder)
+}
+
+/// The state of a summary in progress.
+struct State {
+    max_gaps: usize,
+    ts_symbol_map: TsSymbolMap,
+    pit_symbol_map: PitSymbolMap,
+    prev_index_ts: Option<u64>,
+    summary: Summary,
+}
+
+impl State {
+    fn new(summarizer: &Summarizer, metadata: &Metadata) -> Result<Self> {
+        let (metadata_len, _) = MetadataEncoder::<Vec<u8>>::calc_length(metadata);
+        Ok(Self {
+            max_gaps: summarizer.max_gaps,
+            ts_symbol_map: metadata.symbol_map()?,
+            pit_symbol_map: PitSymbolMap::new(),
+            prev_index_ts: None,
+            summary: Summary {
+                version: metadata.version,
+                dataset: metadata.dataset.clone(),
+                schema: metadata.schema,
+                gap_threshold: summarizer.gap_threshold,
+                // "DBN", the version, and the metadata length precede the metadata
+                uncompressed_size: 8 + u64::from(metadata_len),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn on_record(&mut self, record: RecordRef) {
+        let summary = &mut self.summary;
+        let index = summary.record_count;
+        summary.record_count += 1;
+        summary.uncompressed_size += record.record_size() as u64;
+        let hd = record.header();
+        *summary.rtype_counts.entry(hd.rtype).or_default() += 1;
+        *summary.publisher_counts.entry(hd.publisher_id).or_default() += 1;
+        *summary
+            .instrument_counts
+            .entry(hd.instrument_id)
+            .or_default() += 1;
+        if hd.ts_event != UNDEF_TIMESTAMP {
+            extend_range(&mut summary.ts_event, hd.ts_event);
+        }
+        if let Some(ts_recv) = ts_recv(&record).filter(|ts| *ts != UNDEF_TIMESTAMP) {
+            extend_range(&mut summary.ts_recv, ts_recv);
+        }
+        self.on_index_ts(index, record.raw_index_ts());
+        self.count_symbol(record);
+    }
+
+    fn on_index_ts(&mut self, index: u64, index_ts: u64) {
+        if index_ts == UNDEF_TIMESTAMP {
+            return;
+        }
+        if let Some(prev) = self.prev_index_ts {
+            // Out-of-order records aren't gaps
+            if index_ts.saturating_sub(prev) > self.summary.gap_threshold {
+                self.summary.gap_count += 1;
+                if self.summary.gaps.len() < self.max_gaps {
+                    self.summary.gaps.push(Gap {
+                        index,
+                        start: prev,
+                        end: index_ts,
+                    });
+                }
+            }
+        }
+        self.prev_index_ts = Some(self.prev_index_ts.map_or(index_ts, |p| p.max(index_ts)));
+    }
+
+    fn count_symbol(&mut self, record: RecordRef) {
+        if matches!(record.rtype(), Ok(RType::Error | RType::System)) {
+            return;
+        }
+        // Malformed symbol mapping records are still counted, just not applied
+        let _ = self.pit_symbol_map.on_record(record);
+        let symbol = self
+            .pit_symbol_map
+            .get(record.header().instrument_id)
+            .or_else(|| self.ts_symbol_map.get_for_rec(&record));
+        if let Some(symbol) = symbol {
+            if let Some(count) = self.summary.symbol_counts.get_mut(symbol) {
+                *count += 1;
+            } else {
+                self.summary.symbol_counts.insert(symbol.clone(), 1);
+            }
+        }
+    }
+}
+
+fn extend_range(range: &mut Option<TsRange>, ts: u64) {
+    match range {
+        Some(range) => {
+            range.first = range.first.min(ts);
+            range.last = range.last.max(ts);
+        }
+        None => {
+            *range = Some(TsRange {
+                first: ts,
+                last: ts,
+            })
+        }
+    }
+}
+
+/// Returns the `ts_recv` of `record` if its type has one, which is then also its index
+/// timestamp.
+fn ts_recv(record: &RecordRef) -> Option<u64> {
+    match record.rtype().ok()? {
+        #[allow(deprecated)]
+        RType::OhlcvDeprecated
+        | RType::Ohlcv1S
+        | RType::Ohlcv1M
+        | RType::Ohlcv1H
+        | RType::Ohlcv1D
+        | RType::OhlcvEod
+        | RType::Error
+        | RType::SymbolMapping
+        | RType::System => None,
+        _ => Some(record.raw_index_ts()),
+    }
+}
+
+fn rtype_name(rtype: u8) -> String {
+    RType::try_from(rtype)
+        .map(|rtype| rtype.as_str().to_owned())
+        .unwrap_or_else(|_| format!("{rtype:#04x}"))
+}
+
+fn publisher_name(publisher_id: u16) -> String {
+    Publisher::try_from(publisher_id)
+        .map(|publisher| format!("{publisher_id} ({publisher})"))
+        .unwrap_or_else(|_| publisher_id.to_string())
+}
+
+fn ts_range_to_json(range: Option<TsRange>) -> Value {
+    range.map_or(
+        Value::Null,
+        |range| json!({ "first": range.first, "last": range.last }),
+    )
+}
+
+fn counts_to_json<K: ToString>(counts: &BTreeMap<K, u64>) -> Value {
+    Value::Object(
+        counts
+            .iter()
+            .map(|(key, count)| (key.to_string(), Value::from(*count)))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+impl Summary {
+    /// Serializes the summary to JSON, optionally with indentation.
+    pub fn to_json(&self, pretty: bool) -> String {
+        let rtype_counts = Value::Object(
+            self.rtype_counts
+                .iter()
+                .map(|(rtype, count)| (rtype_name(*rtype), Value::from(*count)))
+                .collect(),
+        );
+        let gaps = self
+            .gaps
+            .iter()
+            .map(|gap| {
+                json!({
+                    "index": gap.index,
+                    "start": gap.start,
+                    "end": gap.end,
+                    "duration": gap.duration(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let summary = json!({
+            "version": self.version,
+            "dataset": self.dataset,
+            "schema": self.schema.map(|schema| schema.as_str()),
+            "record_count": self.record_count,
+            "rtype_counts": rtype_counts,
+            "publisher_counts": counts_to_json(&self.publisher_counts),
+            "instrument_counts": counts_to_json(&self.instrument_counts),
+            "symbol_counts": counts_to_json(&self.symbol_counts),
+            "ts_event": ts_range_to_json(self.ts_event),
+            "ts_recv": ts_range_to_json(self.ts_recv),
+            "gap_threshold": self.gap_threshold,
+            "gap_count": self.gap_count,
+            "gaps": gaps,
+            "uncompressed_size": self.uncompressed_size,
+            "compressed_size": self.compressed_size,
+        });
+        let res = if pretty {
+            serde_json::to_string_pretty(&summary)
+        } else {
+            serde_json::to_string(&summary)
+        };
+        // Serializing a `Value` can't fail
+        res.unwrap()
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fn write_range(f: &mut Formatter<'_>, name: &str, range: Option<TsRange>) -> fmt::Result {
+            match range {
+                Some(range) => writeln!(
+                    f,
+                    "{name}: {} to {}",
+                    pretty::Ts(range.first),
+                    pretty::Ts(range.last)
+                ),
+                None => writeln!(f, "{name}: none"),
+            }
+        }
+        fn write_counts(
+            f: &mut Formatter<'_>,
+            name: &str,
+            counts: impl Iterator<Item = (String, u64)>,
+        ) -> fmt::Result {
+            writeln!(f, "Records by {name}:")?;
+            for (key, count) in counts {
+                writeln!(f, "  {key}: {count}")?;
+            }
+            Ok(())
+        }
+
+        writeln!(f, "DBN version: {}", self.version)?;
+        writeln!(f, "Dataset: {}", self.dataset)?;
+        match self.schema {
+            Some(schema) => writeln!(f, "Schema: {schema}")?,
+            None => writeln!(f, "Schema: mixed")?,
+        }
+        match self.compressed_size {
+            Some(compressed_size) => writeln!(
+                f,
+                "Size: {compressed_size} bytes compressed, {} bytes uncompressed",
+                self.uncompressed_size
+            )?,
+            None => writeln!(f, "Size: {} bytes", self.uncompressed_size)?,
+        }
+        writeln!(f, "Records: {}", self.record_count)?;
+        write_range(f, "ts_event", self.ts_event)?;
+        write_range(f, "ts_recv", self.ts_recv)?;
+        write_counts(
+            f,
+            "rtype",
+            self.rtype_counts
+                .iter()
+                .map(|(rtype, count)| (rtype_name(*rtype), *count)),
+        )?;
+        write_counts(
+            f,
+            "publisher",
+            self.publisher_counts
+                .iter()
+                .map(|(publisher_id, count)| (publisher_name(*publisher_id), *count)),
+        )?;
+        write_counts(
+            f,
+            "instrument ID",
+            self.instrument_counts
+                .iter()
+                .map(|(instrument_id, count)| (instrument_id.to_string(), *count)),
+        )?;
+        write_counts(
+            f,
+            "symbol",
+            self.symbol_counts
+                .iter()
+                .map(|(symbol, count)| (symbol.clone(), *count)),
+        )?;
+        write!(
+            f,
+            "Gaps longer than {}ns: {}",
+            self.gap_threshold, self.gap_count
+        )?;
+        for gap in self.gaps.iter() {
+            write!(
+                f,
+                "\n  {} to {} ({}ns)",
+                pretty::Ts(gap.start),
+                pretty::Ts(gap.end),
+                gap.duration()
+            )?;
+        }
+        if self.gap_count > self.gaps.len() as u64 {
+            write!(f, "\n  ...")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{tests::TEST_DATA_PATH, DbnDecoder},
+        rtype,
+        symbol_map::tests::metadata_w_mappings,
+        Action, Dataset, RecordHeader, SType, TradeMsg,
+    };
+
+    // 2023-07-03 00:00 UTC
+    const TS: u64 = 1_688_342_400_000_000_000;
+
+    fn trade(instrument_id: u32, ts_recv: u64) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, ts_recv - 10),
+            action: Action::Trade as std::ffi::c_char,
+            ts_recv,
+            ..Default::default()
+        }
+    }
+
+    fn summarize_records(
+        summarizer: Summarizer,
+        metadata: &Metadata,
+        records: &[TradeMsg],
+    ) -> Summary {
+        let mut state = State::new(&summarizer, metadata).unwrap();
+        for rec in records {
+            state.on_record(RecordRef::from(rec));
+        }
+        state.summary
+    }
+
+    #[rstest]
+    #[case::uncompressed("test_data.mbo.v3.dbn", false)]
+    #[case::zstd("test_data.mbo.v3.dbn.zst", true)]
+    fn test_summarize_file(#[case] file_name: &str, #[case] is_compressed: bool) {
+        let path = format!("{TEST_DATA_PATH}/{file_name}");
+        let summary = Summarizer::new().summarize_file(&path).unwrap();
+        assert_eq!(summary.version, 3);
+        assert_eq!(summary.schema, Some(Schema::Mbo));
+        assert_eq!(summary.record_count, 2);
+        assert_eq!(summary.rtype_counts, BTreeMap::from([(rtype::MBO, 2)]));
+        assert_eq!(summary.publisher_counts, BTreeMap::from([(1, 2)]));
+        assert_eq!(summary.instrument_counts, BTreeMap::from([(5482, 2)]));
+        assert_eq!(summary.ts_event.unwrap().first, 1_609_160_400_000_429_831);
+        assert_eq!(summary.ts_event.unwrap().last, 1_609_160_400_000_431_665);
+        assert_eq!(summary.ts_recv.unwrap().first, 1_609_160_400_000_704_060);
+        assert_eq!(summary.ts_recv.unwrap().last, 1_609_160_400_000_711_344);
+        assert_eq!(summary.gap_count, 0);
+        let uncompressed_size = std::fs::metadata(format!(
+            "{TEST_DATA_PATH}/{}",
+            file_name.trim_end_matches(".zst")
+        ))
+        .unwrap()
+        .len();
+        assert_eq!(summary.uncompressed_size, uncompressed_size);
+        assert_eq!(summary.compressed_size.is_some(), is_compressed);
+    }
+
+    #[test]
+    fn test_summarize_matches_summarize_file() {
+        let path = format!("{TEST_DATA_PATH}/test_data.mbo.v3.dbn.zst");
+        let summary = summarize(DbnDecoder::from_zstd_file(&path).unwrap()).unwrap();
+        let file_summary = Summarizer::new().summarize_file(&path).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                compressed_size: None,
+                ..file_summary
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::all(Summarizer::DEFAULT_MAX_GAPS, 2)]
+    #[case::truncated(1, 1)]
+    fn test_gaps(#[case] max_gaps: usize, #[case] exp_len: usize) {
+        let metadata = Metadata::builder()
+            .dataset(Dataset::XnasItch.as_str().to_owned())
+            .schema(Some(Schema::Trades))
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .start(TS)
+            .build();
+        let summary = summarize_records(
+            Summarizer::new().gap_threshold(1_000).max_gaps(max_gaps),
+            &metadata,
+            &[
+                trade(1, TS + 1_000),
+                trade(1, TS + 1_500),
+                trade(2, TS + 5_000),
+                // out of order, not a gap
+                trade(2, TS + 4_000),
+                trade(1, TS + 9_000),
+            ],
+        );
+        assert_eq!(summary.gap_count, 2);
+        assert_eq!(summary.gaps.len(), exp_len);
+        assert_eq!(
+            summary.gaps[0],
+            Gap {
+                index: 2,
+                start: TS + 1_500,
+                end: TS + 5_000
+            }
+        );
+        assert_eq!(
+            summary.ts_recv,
+            Some(TsRange {
+                first: TS + 1_000,
+                last: TS + 9_000
+            })
+        );
+        assert_eq!(
+            summary.ts_event,
+            Some(TsRange {
+                first: TS + 990,
+                last: TS + 8_990
+            })
+        );
+        assert!(summary.to_string().contains("Gaps longer than 1000ns: 2"));
+    }
+
+    #[test]
+    fn test_symbol_counts() {
+        // AAPL is mapped to instrument ID 32 for all of July 2023
+        let summary = summarize_records(
+            Summarizer::new(),
+            &metadata_w_mappings(),
+            &[trade(32, TS + 100), trade(32, TS + 200), trade(1, TS + 300)],
+        );
+        assert_eq!(
+            summary.symbol_counts,
+            BTreeMap::from([("AAPL".to_owned(), 2)])
+        );
+        assert_eq!(summary.instrument_counts, BTreeMap::from([(1, 1), (32, 2)]));
+        let json = summary.to_json(false);
+        assert!(json.contains(r#""rtype_counts":{"mbp-0":3}"#), "{json}");
+        assert!(json.contains(r#""symbol_counts":{"AAPL":2}"#), "{json}");
+        assert!(json.contains(r#""compressed_size":null"#), "{json}");
+    }
+}
@@ -0,0 +1,709 @@
+//! Validation of DBN records against their metadata and the DBN specification.
+//!
+//! A [`Validator`] checks each record it's given and collects [`Diagnostic`]s into a
+//! [`Report`], which can be serialized to JSON with [`Report::to_json()`]. The checks
+//! are:
+//! - index timestamps are non-decreasing
+//! - the length in each record's header matches the size of its rtype for the DBN
+//!   version and `ts_out` in the metadata
+//! - enum fields like `action` and `side` hold known values
+//! - index timestamps are within [`Metadata::start`] and [`Metadata::end`]
+//! - every instrument has a symbol mapping, either from [`Metadata::mappings`] or a
+//!   [`SymbolMappingMsg`](crate::SymbolMappingMsg) record
+//!
+//! The report also counts records with the [`BAD_TS_RECV`](crate::flags::BAD_TS_RECV)
+//! and [`MAYBE_BAD_BOOK`](crate::flags::MAYBE_BAD_BOOK) flags set.
+//!
+//! To check the records as they're stored, decode them with
+//! [`VersionUpgradePolicy::AsIs`](crate::VersionUpgradePolicy::AsIs).
+//!
+//! # Example
+//! ```no_run
+//! use dbn::{decode::DynDecoder, validate::validate, VersionUpgradePolicy};
+//!
+//! let decoder = DynDecoder::from_file("20241007.mbo.dbn.zst", VersionUpgradePolicy::AsIs)?;
+//! let report = validate(decoder)?;
+//! println!("{}", report.to_json(true));
+//! # Ok::<(), dbn::Error>(())
+//! ```
+
+use std::{collections::HashSet, mem, num::NonZeroU64};
+
+use serde_json::{json, Value};
+
+use crate::{
+    decode::{DbnMetadata, DecodeRecordRef},
+    enums::RType,
+    symbol_map::{PitSymbolMap, SymbolIndex, TsSymbolMap},
+    v1, v2, v3, BboMsg, CbboMsg, Cmbp1Msg, FlagSet, HasRType, ImbalanceMsg, MboMsg, Mbp10Msg,
+    Mbp1Msg, Metadata, OhlcvMsg, Record, RecordRef, Result, StatusMsg, TradeMsg, UNDEF_TIMESTAMP,
+};
+
+/// A problem found with a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The position of the record in the input, starting at 0.
+    pub index: u64,
+    /// The kind of problem.
+    pub kind: DiagnosticKind,
+}
+
+/// The kinds of problems found by a [`Validator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// The record's index timestamp is before that of the previous record.
+    NonMonotonicIndexTs {
+        /// The index timestamp of the record.
+        index_ts: u64,
+        /// The index timestamp of the previous record.
+        previous: u64,
+    },
+    /// The length in the record's header doesn't match the size of its rtype.
+    LengthMismatch {
+        /// The rtype of the record.
+        rtype: u8,
+        /// The length of the record in bytes.
+        length: usize,
+        /// The expected length of the record in bytes.
+        expected: usize,
+    },
+    /// The record's rtype isn't a known [`RType`].
+    UnknownRType {
+        /// The rtype of the record.
+        rtype: u8,
+    },
+    /// An enum field of the record holds an unknown value.
+    InvalidEnum {
+        /// The name of the field.
+        field: &'static str,
+        /// The raw value of the field.
+        value: u16,
+    },
+    /// The record's index timestamp is outside the time range of the metadata.
+    OutsideRange {
+        /// The index timestamp of the record.
+        index_ts: u64,
+    },
+    /// No symbol mapping exists for the record's instrument. Only reported for the
+    /// first record of each instrument.
+    UnmappedInstrument {
+        /// The instrument ID of the record.
+        instrument_id: u32,
+    },
+    /// The input couldn't be decoded. No records are checked after this.
+    DecodeError {
+        /// A description of the error.
+        desc: String,
+    },
+}
+
+impl DiagnosticKind {
+    /// Returns the name of the kind of diagnostic in snake case, as used in the JSON
+    /// report.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NonMonotonicIndexTs { .. } => "non_monotonic_index_ts",
+            Self::LengthMismatch { .. } => "length_mismatch",
+            Self::UnknownRType { .. } => "unknown_rtype",
+            Self::InvalidEnum { .. } => "invalid_enum",
+            Self::OutsideRange { .. } => "outside_range",
+            Self::UnmappedInstrument { .. } => "unmapped_instrument",
+            Self::DecodeError { .. } => "decode_error",
+        }
+    }
+}
+
+/// The results of validating a stream of records.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of records checked.
+    pub record_count: u64,
+    /// The problems found, up to the maximum set with
+    /// [`Validator::max_diagnostics()`].
+    pub diagnostics: Vec<Diagnostic>,
+    /// The total number of problems found, including those past the maximum.
+    pub diagnostic_count: u64,
+    /// The number of records with the [`BAD_TS_RECV`](crate::flags::BAD_TS_RECV)
+    /// flag set.
+    pub bad_ts_recv_count: u64,
+    /// The number of records with the [`MAYBE_BAD_BOOK`](crate::flags::MAYBE_BAD_BOOK)
+    /// flag set.
+    pub maybe_bad_book_count: u64,
+}
+
+impl Report {
+    /// Returns `true` if no problems were found. Flag counts don't affect validity.
+    pub fn is_valid(&self) -> bool {
+        self.diagnostic_count == 0
+    }
+
+    /// Serializes the report to JSON, optionally with indentation.
+    pub fn to_json(&self, pretty: bool) -> String {
+        let diagnostics = self
+            .diagnostics
+            .iter()
+            .map(diagnostic_to_json)
+            .collect::<Vec<_>>();
+        let report = json!({
+            "valid": self.is_valid(),
+            "record_count": self.record_count,
+            "diagnostic_count": self.diagnostic_count,
+            "bad_ts_recv_count": self.bad_ts_recv_count,
+            "maybe_bad_book_count": self.maybe_bad_book_count,
+            "diagnostics": diagnostics,
+        });
+        let res = if pretty {
+            serde_json::to_string_pretty(&report)
+        } else {
+            serde_json::to_string(&report)
+        };
+        // Serializing a `Value` can't fail
+        res.unwrap()
+    }
+}
+
+fn diagnostic_to_json(diagnostic: &Diagnostic) -> Value {
+    let mut res = match &diagnostic.kind {
+        DiagnosticKind::NonMonotonicIndexTs { index_ts, previous } => {
+            json!({ "index_ts": index_ts, "previous": previous })
+        }
+        DiagnosticKind::LengthMismatch {
+            rtype,
+            length,
+            expected,
+        } => json!({ "rtype": rtype, "length": length, "expected": expected }),
+        DiagnosticKind::UnknownRType { rtype } => json!({ "rtype": rtype }),
+        DiagnosticKind::InvalidEnum { field, value } => {
+            json!({ "field": field, "value": value })
+        }
+        DiagnosticKind::OutsideRange { index_ts } => json!({ "index_ts": index_ts }),
+        DiagnosticKind::UnmappedInstrument { instrument_id } => {
+            json!({ "instrument_id": instrument_id })
+        }
+        DiagnosticKind::DecodeError { desc } => json!({ "desc": desc }),
+    };
+    res["index"] = json!(diagnostic.index);
+    res["kind"] = json!(diagnostic.kind.name());
+    res
+}
+
+/// Checks records against their metadata and the DBN specification. See the
+/// [module-level documentation](crate::validate) for the checks performed.
+#[derive(Debug)]
+pub struct Validator {
+    version: u8,
+    ts_out: bool,
+    start: u64,
+    end: Option<NonZeroU64>,
+    ts_symbol_map: TsSymbolMap,
+    pit_symbol_map: PitSymbolMap,
+    unmapped: HashSet<u32>,
+    prev_index_ts: u64,
+    max_diagnostics: usize,
+    report: Report,
+}
+
+impl Validator {
+    /// The default maximum number of diagnostics retained in the report.
+    pub const DEFAULT_MAX_DIAGNOSTICS: usize = 1000;
+
+    /// Creates a new validator for records described by `metadata`.
+    ///
+    /// # Errors
+    /// This function returns an error if the symbology mappings in `metadata` can't be
+    /// parsed.
+    pub fn new(metadata: &Metadata) -> Result<Self> {
+        Ok(Self {
+            version: metadata.version,
+            ts_out: metadata.ts_out,
+            start: metadata.start,
+            end: metadata.end,
+            ts_symbol_map: metadata.symbol_map()?,
+            pit_symbol_map: PitSymbolMap::new(),
+            unmapped: HashSet::new(),
+            prev_index_ts: 0,
+            max_diagnostics: Self::DEFAULT_MAX_DIAGNOSTICS,
+            report: Report::default(),
+        })
+    }
+
+    /// Sets the maximum number of diagnostics retained in the report. Problems past
+    /// the maximum are still counted. Defaults to
+    /// [`DEFAULT_MAX_DIAGNOSTICS`](Self::DEFAULT_MAX_DIAGNOSTICS).
+    pub fn max_diagnostics(mut self, max_diagnostics: usize) -> Self {
+        self.max_diagnostics = max_diagnostics;
+        self
+    }
+
+    /// Returns the report of the records checked so far.
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
+    /// Consumes the validator and returns the report.
+    pub fn finish(self) -> Report {
+        self.report
+    }
+
+    /// Checks `record`, the next record in the input.
+    pub fn validate_record(&mut self, record: RecordRef) {
+        let index = self.report.record_count;
+        self.report.record_count += 1;
+        let hd = record.header();
+        let Ok(rtype) = record.rtype() else {
+            self.push(index, DiagnosticKind::UnknownRType { rtype: hd.rtype });
+            return;
+        };
+        let expected = self.expected_len(rtype);
+        let length = record.record_size();
+        if length != expected {
+            self.push(
+                index,
+                DiagnosticKind::LengthMismatch {
+                    rtype: hd.rtype,
+                    length,
+                    expected,
+                },
+            );
+            // The other checks can't safely read a short record
+            if length < expected {
+                return;
+            }
+        }
+        self.validate_index_ts(index, record.raw_index_ts());
+        self.validate_enums(index, rtype, record);
+        self.validate_mapping(index, rtype, record);
+        if let Some(flags) = flags(record) {
+            if flags.is_bad_ts_recv() {
+                self.report.bad_ts_recv_count += 1;
+            }
+            if flags.is_maybe_bad_book() {
+                self.report.maybe_bad_book_count += 1;
+            }
+        }
+    }
+
+    /// Checks all remaining records from `decoder`. A decoding error is added to the
+    /// report as a [`DiagnosticKind::DecodeError`] and ends validation.
+    pub fn validate_decoder<D: DecodeRecordRef>(&mut self, decoder: &mut D) {
+        loop {
+            match decoder.decode_record_ref() {
+                Ok(Some(record)) => self.validate_record(record),
+                Ok(None) => break,
+                Err(e) => {
+                    let index = self.report.record_count;
+                    self.push(
+                        index,
+                        DiagnosticKind::DecodeError {
+                            desc: e.to_string(),
+                        },
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, index: u64, kind: DiagnosticKind) {
+        self.report.diagnostic_count += 1;
+        if self.report.diagnostics.len() < self.max_diagnostics {
+            self.report.diagnostics.push(Diagnostic { index, kind });
+        }
+    }
+
+    /// Returns the expected length in bytes of a record of `rtype`.
+    fn expected_len(&self, rtype: RType) -> usize {
+        let size = match rtype {
+            RType::Mbp0 => mem::size_of::<TradeMsg>(),
+            RType::Mbp1 => mem::size_of::<Mbp1Msg>(),
+            RType::Mbp10 => mem::size_of::<Mbp10Msg>(),
+            #[allow(deprecated)]
+            RType::OhlcvDeprecated
+            | RType::Ohlcv1S
+            | RType::Ohlcv1M
+            | RType::Ohlcv1H
+            | RType::Ohlcv1D
+            | RType::OhlcvEod => mem::size_of::<OhlcvMsg>(),
+            RType::Status => mem::size_of::<StatusMsg>(),
+            RType::InstrumentDef => match self.version {
+                1 => mem::size_of::<v1::InstrumentDefMsg>(),
+                2 => mem::size_of::<v2::InstrumentDefMsg>(),
+                _ => mem::size_of::<v3::InstrumentDefMsg>(),
+            },
+            RType::Imbalance => mem::size_of::<ImbalanceMsg>(),
+            RType::Error => match self.version {
+                1 => mem::size_of::<v1::ErrorMsg>(),
+                _ => mem::size_of::<v3::ErrorMsg>(),
+            },
+            RType::SymbolMapping => match self.version {
+                1 => mem::size_of::<v1::SymbolMappingMsg>(),
+                _ => mem::size_of::<v3::SymbolMappingMsg>(),
+            },
+            RType::System => match self.version {
+                1 => mem::size_of::<v1::SystemMsg>(),
+                _ => mem::size_of::<v3::SystemMsg>(),
+            },
+            RType::Statistics => match self.version {
+                1 | 2 => mem::size_of::<v2::StatMsg>(),
+                _ => mem::size_of::<v3::StatMsg>(),
+            },
+            RType::Mbo => mem::size_of::<MboMsg>(),
+            RType::Cmbp1 | RType::Tcbbo => mem::size_of::<Cmbp1Msg>(),
+            RType::Bbo1S | RType::Bbo1M => mem::size_of::<BboMsg>(),
+            RType::Cbbo1S | RType::Cbbo1M => mem::size_of::<CbboMsg>(),
+        };
+        if self.ts_out {
+            // `ts_out` is appended to the end of each record
+            size + mem::size_of::<u64>()
+        } else {
+            size
+        }
+    }
+
+    fn validate_index_ts(&mut self, index: u64, index_ts: u64) {
+        if index_ts == UNDEF_TIMESTAMP {
+            return;
+        }
+        if index_ts < self.prev_index_ts {
+            self.push(
+                index,
+                DiagnosticKind::NonMonotonicIndexTs {
+                    index_ts,
+                    previous: self.prev_index_ts,
+                },
+            );
+        }
+        self.prev_index_ts = self.prev_index_ts.max(index_ts);
+        if index_ts < self.start || self.end.is_some_and(|end| index_ts >= end.get()) {
+            self.push(index, DiagnosticKind::OutsideRange { index_ts });
+        }
+    }
+
+    fn validate_enums(&mut self, index: u64, rtype: RType, record: RecordRef) {
+        let mut invalid = Vec::new();
+        let mut check_char = |field: &'static str, value: std::ffi::c_char, is_valid: bool| {
+            if !is_valid {
+                invalid.push(DiagnosticKind::InvalidEnum {
+                    field,
+                    value: u16::from(value as u8),
+                });
+            }
+        };
+        match rtype {
+            RType::Mbo => {
+                let mbo = record.get::<MboMsg>().unwrap();
+                check_char("action", mbo.action, mbo.action().is_ok());
+                check_char("side", mbo.side, mbo.side().is_ok());
+            }
+            RType::Mbp0 => {
+                let trade = record.get::<TradeMsg>().unwrap();
+                check_char("action", trade.action, trade.action().is_ok());
+                check_char("side", trade.side, trade.side().is_ok());
+            }
+            RType::Mbp1 => {
+                let mbp = record.get::<Mbp1Msg>().unwrap();
+                check_char("action", mbp.action, mbp.action().is_ok());
+                check_char("side", mbp.side, mbp.side().is_ok());
+            }
+            RType::Mbp10 => {
+                let mbp = record.get::<Mbp10Msg>().unwrap();
+                check_char("action", mbp.action, mbp.action().is_ok());
+                check_char("side", mbp.side, mbp.side().is_ok());
+            }
+            RType::Cmbp1 | RType::Tcbbo => {
+                let cmbp = record.get::<Cmbp1Msg>().unwrap();
+                check_char("action", cmbp.action, cmbp.action().is_ok());
+                check_char("side", cmbp.side, cmbp.side().is_ok());
+            }
+            RType::Bbo1S | RType::Bbo1M => {
+                let bbo = record.get::<BboMsg>().unwrap();
+                check_char("side", bbo.side, bbo.side().is_ok());
+            }
+            RType::Cbbo1S | RType::Cbbo1M => {
+                let cbbo = record.get::<CbboMsg>().unwrap();
+                check_char("side", cbbo.side, cbbo.side().is_ok());
+            }
+            RType::Imbalance => {
+                let imbalance = record.get::<ImbalanceMsg>().unwrap();
+                check_char("side", imbalance.side, imbalance.side().is_ok());
+            }
+            RType::Status => {
+                let status = record.get::<StatusMsg>().unwrap();
+                for (field, value, is_valid) in [
+                    ("action", status.action, status.action().is_ok()),
+                    ("reason", status.reason, status.reason().is_ok()),
+                    (
+                        "trading_event",
+                        status.trading_event,
+                        status.trading_event().is_ok(),
+                    ),
+                ] {
+                    if !is_valid {
+                        invalid.push(DiagnosticKind::InvalidEnum { field, value });
+                    }
+                }
+            }
+            _ => {}
+        }
+        for kind in invalid {
+            self.push(index, kind);
+        }
+    }
+
+    fn validate_mapping(&mut self, index: u64, rtype: RType, record: RecordRef) {
+        if matches!(rtype, RType::Error | RType::System) {
+            return;
+        }
+        // Symbol mapping records can't fail to parse here because their length was
+        // already checked
+        let _ = self.pit_symbol_map.on_record(record);
+        // Without any mappings there's nothing to check against
+        if self.ts_symbol_map.is_empty() && self.pit_symbol_map.is_empty() {
+            return;
+        }
+        let instrument_id = record.header().instrument_id;
+        if self.unmapped.contains(&instrument_id)
+            || self.pit_symbol_map.get(instrument_id).is_some()
+            || self.ts_symbol_map.get_for_rec(&record).is_some()
+        {
+            return;
+        }
+        self.unmapped.insert(instrument_id);
+        self.push(index, DiagnosticKind::UnmappedInstrument { instrument_id });
+    }
+}
+
+/// Returns the flags of `record` if its type has them.
+fn flags(record: RecordRef) -> Option<FlagSet> {
+    fn get<T: HasRType>(record: RecordRef, flags: impl Fn(&T) -> FlagSet) -> Option<FlagSet> {
+        record.try_get::<T>().ok().map(flags)
+    }
+    get::<MboMsg>(record, |r| r.flags)
+        .or_else(|| get::<TradeMsg>(record, |r| r.flags))
+        .or_else(|| get::<Mbp1Msg>(record, |r| r.flags))
+        .or_else(|| get::<Mbp10Msg>(record, |r| r.flags))
+        .or_else(|| get::<Cmbp1Msg>(record, |r| r.flags))
+        .or_else(|| get::<BboMsg>(record, |r| r.flags))
+        .or_else(|| get::<CbboMsg>(record, |r| r.flags))
+}
+
+/// Validates all records from `decoder` against its metadata. See [`Validator`].
+///
+/// # Errors
+/// This function returns an error if the symbology mappings in the metadata can't be
+/// parsed. Errors decoding records are included in the report.
+pub fn validate<D: DecodeRecordRef + DbnMetadata>(mut decoder: D) -> Result<Report> {
+    let mut validator = Validator::new(decoder.metadata())?;
+    validator.validate_decoder(&mut decoder);
+    Ok(validator.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        decode::{dbn::RecordDecoder, tests::TEST_DATA_PATH, DynDecoder},
+        symbol_map::tests::metadata_w_mappings,
+        Action, RecordHeader, VersionUpgradePolicy,
+    };
+
+    // 2023-07-03 00:00 UTC
+    const TS: u64 = 1_688_342_400_000_000_000;
+
+    fn trade(instrument_id: u32, ts_recv: u64) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(crate::rtype::MBP_0, 1, instrument_id, ts_recv),
+            action: Action::Trade as std::ffi::c_char,
+            ts_recv,
+            ..Default::default()
+        }
+    }
+
+    fn kinds(report: &Report) -> Vec<&'static str> {
+        report.diagnostics.iter().map(|d| d.kind.name()).collect()
+    }
+
+    #[rstest]
+    fn test_validate_test_data(
+        #[values(
+            "mbo.v3.dbn.zst",
+            "mbp-1.v1.dbn.zst",
+            "mbp-10.v2.dbn.zst",
+            "trades.v1.dbn.zst",
+            "trades.v3.dbn.zst",
+            "ohlcv-1h.v2.dbn.zst",
+            "definition.v1.dbn.zst",
+            "definition.v2.dbn.zst",
+            "definition.v3.dbn.zst",
+            "statistics.v1.dbn.zst",
+            "statistics.v3.dbn.zst",
+            "status.v3.dbn.zst",
+            "cbbo-1s.v3.dbn.zst"
+        )]
+        file: &str,
+    ) {
+        let decoder = DynDecoder::from_file(
+            format!("{TEST_DATA_PATH}/test_data.{file}"),
+            VersionUpgradePolicy::AsIs,
+        )
+        .unwrap();
+        let report = validate(decoder).unwrap();
+        assert!(report.record_count > 0);
+        for diagnostic in report.diagnostics.iter() {
+            assert!(
+                matches!(
+                    diagnostic.kind,
+                    DiagnosticKind::NonMonotonicIndexTs { .. }
+                        | DiagnosticKind::OutsideRange { .. }
+                        | DiagnosticKind::UnmappedInstrument { .. }
+                ),
+                "{diagnostic:?}"
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_validate_records() {
+        let mut validator = Validator::new(&metadata_w_mappings()).unwrap();
+        let mut bad_ts_recv = trade(32, TS + 10);
+        bad_ts_recv.flags = FlagSet::empty().set_bad_ts_recv();
+        let mut bad_action = trade(32, TS + 20);
+        bad_action.action = b'X' as std::ffi::c_char;
+        for rec in [
+            trade(32, TS),
+            bad_ts_recv,
+            // non-monotonic
+            trade(32, TS + 5),
+            trade(99, TS + 20),
+            bad_action,
+            // only reported once
+            trade(99, TS + 30),
+            // before start
+            trade(99, 1),
+        ] {
+            validator.validate_record(RecordRef::from(&rec));
+        }
+        let report = validator.finish();
+        assert_eq!(report.record_count, 7);
+        assert_eq!(report.bad_ts_recv_count, 1);
+        assert_eq!(report.maybe_bad_book_count, 0);
+        assert_eq!(
+            report.diagnostics,
+            vec![
+                Diagnostic {
+                    index: 2,
+                    kind: DiagnosticKind::NonMonotonicIndexTs {
+                        index_ts: TS + 5,
+                        previous: TS + 10
+                    }
+                },
+                Diagnostic {
+                    index: 3,
+                    kind: DiagnosticKind::UnmappedInstrument { instrument_id: 99 }
+                },
+                Diagnostic {
+                    index: 4,
+                    kind: DiagnosticKind::InvalidEnum {
+                        field: "action",
+                        value: u16::from(b'X')
+                    }
+                },
+                Diagnostic {
+                    index: 6,
+                    kind: DiagnosticKind::NonMonotonicIndexTs {
+                        index_ts: 1,
+                        previous: TS + 30
+                    }
+                },
+                Diagnostic {
+                    index: 6,
+                    kind: DiagnosticKind::OutsideRange { index_ts: 1 }
+                },
+            ]
+        );
+        assert!(!report.is_valid());
+    }
+
+    #[rstest]
+    #[case::short(-1)]
+    #[case::long(1)]
+    fn test_validate_length_mismatch(#[case] delta: i8) {
+        let mut rec = trade(32, TS);
+        rec.hd.length = rec.hd.length.wrapping_add_signed(delta);
+        let mut validator = Validator::new(&metadata_w_mappings()).unwrap();
+        validator.validate_record(RecordRef::from(&rec));
+        let report = validator.finish();
+        assert_eq!(
+            report.diagnostics,
+            vec![Diagnostic {
+                index: 0,
+                kind: DiagnosticKind::LengthMismatch {
+                    rtype: crate::rtype::MBP_0,
+                    length: rec.record_size(),
+                    expected: mem::size_of::<TradeMsg>(),
+                }
+            }]
+        );
+    }
+
+    #[rstest]
+    fn test_validate_unknown_rtype() {
+        let mut rec = trade(32, TS);
+        rec.hd.rtype = 0xFE;
+        let mut validator = Validator::new(&metadata_w_mappings()).unwrap();
+        validator.validate_record(RecordRef::from(&rec));
+        assert_eq!(kinds(validator.report()), ["unknown_rtype"]);
+    }
+
+    #[rstest]
+    fn test_validate_decode_error() {
+        let mut buf = trade(32, TS).as_ref().to_vec();
+        // Impossible length
+        buf.extend([3u8, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let mut validator = Validator::new(&metadata_w_mappings()).unwrap();
+        validator.validate_decoder(&mut RecordDecoder::new(buf.as_slice()));
+        let report = validator.finish();
+        assert_eq!(report.record_count, 1);
+        assert_eq!(kinds(&report), ["decode_error"]);
+        assert_eq!(report.diagnostics[0].index, 1);
+    }
+
+    #[rstest]
+    fn test_max_diagnostics() {
+        let mut validator = Validator::new(&metadata_w_mappings())
+            .unwrap()
+            .max_diagnostics(2);
+        for ts in (0..5).rev() {
+            validator.validate_record(RecordRef::from(&trade(32, TS + ts)));
+        }
+        let report = validator.finish();
+        assert_eq!(report.diagnostics.len(), 2);
+        assert_eq!(report.diagnostic_count, 4);
+    }
+
+    #[rstest]
+    fn test_report_to_json(#[values(false, true)] pretty: bool) {
+        let mut validator = Validator::new(&metadata_w_mappings()).unwrap();
+        let mut rec = trade(99, TS);
+        rec.flags = FlagSet::empty().set_maybe_bad_book();
+        validator.validate_record(RecordRef::from(&rec));
+        let json = validator.finish().to_json(pretty);
+        assert_eq!(json.contains('\n'), pretty);
+        let res: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            res,
+            json!({
+                "valid": false,
+                "record_count": 1,
+                "diagnostic_count": 1,
+                "bad_ts_recv_count": 0,
+                "maybe_bad_book_count": 1,
+                "diagnostics": [
+                    { "index": 0, "kind": "unmapped_instrument", "instrument_id": 99 }
+                ],
+            })
+        );
+    }
+}